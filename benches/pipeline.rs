@@ -0,0 +1,93 @@
+//! Benchmarks for the per-message hot path: parsing Binance messages and
+//! processing closed candles through history and indicators.
+
+use chrono::{TimeZone, Utc};
+use criterion::{criterion_group, criterion_main, Criterion};
+use crypto_kline_tracker::config::Config;
+use crypto_kline_tracker::history::CandleHistory;
+use crypto_kline_tracker::indicators::{IndicatorPipeline, IndicatorRegistry};
+use crypto_kline_tracker::{KlineData, SeriesKey};
+use std::hint::black_box;
+use std::sync::Arc;
+
+const MESSAGE: &str = r#"{"e":"kline","E":1700000060000,"s":"BTCUSDT","k":{"t":1700000000000,"T":1700000059999,"s":"BTCUSDT","i":"1m","f":100,"L":200,"o":"37000.10","c":"37012.55","h":"37020.00","l":"36990.42","v":"12.345","n":100,"x":true,"q":"456789.01","V":"6.1","Q":"225000.5","B":"0"}}"#;
+
+const COMBINED: &str = r#"{"stream":"btcusdt@kline_1m","data":{"e":"kline","E":1700000060000,"s":"BTCUSDT","k":{"t":1700000000000,"T":1700000059999,"s":"BTCUSDT","i":"1m","f":100,"L":200,"o":"37000.10","c":"37012.55","h":"37020.00","l":"36990.42","v":"12.345","n":100,"x":true,"q":"456789.01","V":"6.1","Q":"225000.5","B":"0"}}}"#;
+
+/// The indicators a typical configuration runs.
+const INDICATORS: &str = r#"
+[[indicators]]
+kind = "sma"
+period = 20
+[[indicators]]
+kind = "ema"
+period = 50
+[[indicators]]
+kind = "rsi"
+period = 14
+[[indicators]]
+kind = "macd"
+[[indicators]]
+kind = "bollinger"
+[[indicators]]
+kind = "atr"
+[[indicators]]
+kind = "supertrend"
+"#;
+
+fn key() -> SeriesKey {
+    (Arc::from("btcusdt"), Arc::from("1m"))
+}
+
+/// A closed one-minute candle following a gentle sine wave.
+fn candle(minute: i64) -> KlineData {
+    let (symbol, interval) = key();
+    let close = 37000.0 + (minute as f64 / 10.0).sin() * 50.0;
+    KlineData {
+        symbol,
+        interval,
+        interval_start: Utc.timestamp_opt(1_700_000_000 + minute * 60, 0).unwrap(),
+        open: close - 2.0,
+        high: close + 5.0,
+        low: close - 7.0,
+        close,
+        volume: 10.0 + (minute % 7) as f64,
+        closed: true,
+        timing: None,
+    }
+}
+
+fn parse(c: &mut Criterion) {
+    let (symbol, interval) = key();
+    c.bench_function("parse", |b| {
+        b.iter(|| KlineData::parse(&symbol, &interval, black_box(MESSAGE)).unwrap())
+    });
+    c.bench_function("parse_combined", |b| {
+        b.iter(|| KlineData::parse_combined(black_box(COMBINED), |_| Some(key())).unwrap())
+    });
+}
+
+fn process(c: &mut Criterion) {
+    let candles: Vec<KlineData> = (0..1000).map(candle).collect();
+
+    c.bench_function("history_update", |b| {
+        let mut history = CandleHistory::new(500);
+        let mut next = candles.iter().cycle();
+        b.iter(|| history.update(next.next().unwrap().clone()))
+    });
+
+    let config: Config = toml::from_str(INDICATORS).unwrap();
+    let pipeline =
+        IndicatorPipeline::new(IndicatorRegistry::with_builtins(), config.indicators).unwrap();
+    c.bench_function("indicators_update", |b| {
+        let mut pipeline = pipeline.fork();
+        let mut next = candles.iter().cycle();
+        b.iter(|| {
+            let (values, events) = pipeline.update(next.next().unwrap());
+            black_box((values.len(), events.len()))
+        })
+    });
+}
+
+criterion_group!(benches, parse, process);
+criterion_main!(benches);
@@ -0,0 +1,16 @@
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    println!("cargo:rerun-if-changed=proto/tracker.proto");
+    #[cfg(feature = "server")]
+    compile_proto()?;
+    Ok(())
+}
+
+#[cfg(feature = "server")]
+fn compile_proto() -> Result<(), Box<dyn std::error::Error>> {
+    // protox compiles the proto in pure Rust, so building needs no protoc.
+    let descriptors = protox::compile(["proto/tracker.proto"], ["proto"])?;
+    tonic_prost_build::configure()
+        .build_client(false)
+        .compile_fds(descriptors)?;
+    Ok(())
+}
@@ -0,0 +1,23 @@
+//! Messages of a combined-stream connection, as `KlineData::parse_combined`
+//! reads them, for streams named however the message names them.
+
+#![no_main]
+
+use crypto_kline_tracker::KlineData;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|message: &str| {
+    let parsed = KlineData::parse_combined(message, |stream| {
+        let (symbol, interval) = stream.split_once("@kline_")?;
+        Some((symbol.into(), interval.into()))
+    });
+    if let Ok(Some(kline)) = parsed {
+        for value in [kline.open, kline.high, kline.low, kline.close, kline.volume] {
+            assert!(
+                value.is_finite(),
+                "parsed a non-finite value from {:?}",
+                message
+            );
+        }
+    }
+});
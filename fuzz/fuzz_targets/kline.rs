@@ -0,0 +1,20 @@
+//! Messages of a single-stream connection, as `KlineData::parse` reads them.
+
+#![no_main]
+
+use crypto_kline_tracker::KlineData;
+use libfuzzer_sys::fuzz_target;
+use std::sync::Arc;
+
+fuzz_target!(|message: &str| {
+    let (symbol, interval) = (Arc::from("btcusdt"), Arc::from("1m"));
+    if let Ok(Some(kline)) = KlineData::parse(&symbol, &interval, message) {
+        for value in [kline.open, kline.high, kline.low, kline.close, kline.volume] {
+            assert!(
+                value.is_finite(),
+                "parsed a non-finite value from {:?}",
+                message
+            );
+        }
+    }
+});
@@ -0,0 +1,264 @@
+//! Long-running components run as actors: each owns its state, takes its
+//! input from a typed, bounded mailbox, and can be restarted or started
+//! afresh with new settings while the tracker runs.
+
+use crate::metrics::Metrics;
+use crate::ops::{OpsEvent, Severity};
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use futures_util::future::BoxFuture;
+use futures_util::FutureExt;
+use serde_json::{json, Value};
+use std::any::Any;
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::panic::AssertUnwindSafe;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tracing::{error, info};
+
+/// The receiving end of an actor's input. It outlives the actor, so messages
+/// queued while an actor restarts are handled by the next one.
+pub struct Mailbox<T> {
+    rx: Arc<tokio::sync::Mutex<mpsc::Receiver<T>>>,
+    /// For counting queued messages; the receiver is locked while waiting.
+    tx: mpsc::WeakSender<T>,
+}
+
+impl<T> Clone for Mailbox<T> {
+    fn clone(&self) -> Self {
+        Self {
+            rx: self.rx.clone(),
+            tx: self.tx.clone(),
+        }
+    }
+}
+
+/// A mailbox holding up to `capacity` messages, and the sender feeding it.
+pub fn mailbox<T>(capacity: usize) -> (mpsc::Sender<T>, Mailbox<T>) {
+    let (tx, rx) = mpsc::channel(capacity);
+    let mailbox = Mailbox {
+        rx: Arc::new(tokio::sync::Mutex::new(rx)),
+        tx: tx.downgrade(),
+    };
+    (tx, mailbox)
+}
+
+impl<T> Mailbox<T> {
+    /// The next message, or `None` once every sender is gone.
+    pub async fn recv(&self) -> Option<T> {
+        self.rx.lock().await.recv().await
+    }
+
+    /// Messages waiting to be taken.
+    pub fn len(&self) -> usize {
+        self.tx
+            .upgrade()
+            .map_or(0, |tx| tx.max_capacity() - tx.capacity())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Counts work an actor has taken from its mailbox but not yet finished,
+/// such as deliveries it spawned, so shutdown can wait for it.
+#[derive(Clone, Default)]
+pub struct InFlight(Arc<AtomicUsize>);
+
+/// Counts as in flight until dropped.
+pub struct InFlightGuard(Arc<AtomicUsize>);
+
+impl InFlight {
+    pub fn start(&self) -> InFlightGuard {
+        self.0.fetch_add(1, Ordering::AcqRel);
+        InFlightGuard(self.0.clone())
+    }
+
+    pub fn is_idle(&self) -> bool {
+        self.0.load(Ordering::Acquire) == 0
+    }
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+/// How long a panicked actor waits before starting again, so one that
+/// panics on every message does not spin.
+const PANIC_RESTART_DELAY: Duration = Duration::from_secs(1);
+
+type Start = Arc<dyn Fn() -> BoxFuture<'static, ()> + Send + Sync>;
+
+struct Actor {
+    start: Start,
+    task: JoinHandle<()>,
+    started: DateTime<Utc>,
+    restarts: u32,
+}
+
+/// Reports panics caught in long-running tasks, which are then restarted
+/// rather than left dead: logs each, counts it per component in the
+/// metrics and, when enabled, raises an incident.
+#[derive(Clone)]
+pub struct PanicReporter {
+    metrics: Metrics,
+    ops_tx: mpsc::Sender<OpsEvent>,
+    incidents: bool,
+}
+
+impl PanicReporter {
+    pub fn new(metrics: Metrics, ops_tx: mpsc::Sender<OpsEvent>, incidents: bool) -> Self {
+        Self {
+            metrics,
+            ops_tx,
+            incidents,
+        }
+    }
+
+    /// The same reporter, but never raising incidents, for the pagers that
+    /// would deliver them.
+    pub fn without_incidents(&self) -> Self {
+        Self {
+            incidents: false,
+            ..self.clone()
+        }
+    }
+
+    /// Reports the panic `panic` caught in `component`.
+    pub fn report(&self, component: &str, panic: Box<dyn Any + Send>) {
+        let message = panic
+            .downcast_ref::<&str>()
+            .map(|message| message.to_string())
+            .or_else(|| panic.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "unknown cause".to_string());
+        error!("{} panicked: {}", component, message);
+        self.metrics.record_panic(component);
+        if self.incidents {
+            let _ = self.ops_tx.try_send(OpsEvent::trigger(
+                format!("panic:{}", component),
+                format!("{} panicked: {}", component, message),
+                Severity::Error,
+            ));
+        }
+    }
+
+    /// Runs `task`, reporting it as `component` if it panics. Returns
+    /// whether it ran to completion.
+    pub async fn catch(&self, component: &str, task: impl Future<Output = ()>) -> bool {
+        match AssertUnwindSafe(task).catch_unwind().await {
+            Ok(()) => true,
+            Err(panic) => {
+                self.report(component, panic);
+                false
+            }
+        }
+    }
+}
+
+/// Every running actor by name, so each can be inspected and restarted on
+/// its own. An actor that panics is reported and started again.
+#[derive(Clone)]
+pub struct Supervisor {
+    actors: Arc<Mutex<BTreeMap<String, Actor>>>,
+    panics: PanicReporter,
+}
+
+impl Supervisor {
+    pub fn new(panics: PanicReporter) -> Self {
+        Self {
+            actors: Arc::default(),
+            panics,
+        }
+    }
+
+    pub fn panics(&self) -> &PanicReporter {
+        &self.panics
+    }
+
+    fn lock(&self) -> MutexGuard<'_, BTreeMap<String, Actor>> {
+        self.actors.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Runs the actor `name` until it returns, starting it again a moment
+    /// after each panic.
+    fn run(&self, name: &str, start: Start) -> JoinHandle<()> {
+        let (this, name) = (self.clone(), name.to_string());
+        tokio::spawn(async move {
+            while !this.panics.catch(&name, start()).await {
+                tokio::time::sleep(PANIC_RESTART_DELAY).await;
+                if let Some(actor) = this.lock().get_mut(&name) {
+                    actor.started = Utc::now();
+                    actor.restarts += 1;
+                }
+                info!("Restarted component {} after a panic", name);
+            }
+        })
+    }
+
+    /// Runs `start` as the actor `name`, stopping any actor already running
+    /// under that name; this is how an actor is reconfigured. `start` is
+    /// called again on every restart.
+    pub fn spawn<F, Fut>(&self, name: &str, start: F)
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let start: Start = Arc::new(move || Box::pin(start()));
+        let mut actors = self.lock();
+        let restarts = match actors.get(name) {
+            Some(previous) => {
+                previous.task.abort();
+                info!("Reconfigured component {}", name);
+                previous.restarts + 1
+            }
+            None => 0,
+        };
+        let task = self.run(name, start.clone());
+        actors.insert(
+            name.to_string(),
+            Actor {
+                start,
+                task,
+                started: Utc::now(),
+                restarts,
+            },
+        );
+    }
+
+    /// Stops the actor `name` and starts it again with the same settings.
+    pub fn restart(&self, name: &str) -> Result<()> {
+        let mut actors = self.lock();
+        let actor = actors
+            .get_mut(name)
+            .ok_or_else(|| anyhow!("Unknown component '{}'", name))?;
+        actor.task.abort();
+        actor.task = self.run(name, actor.start.clone());
+        actor.started = Utc::now();
+        actor.restarts += 1;
+        info!("Restarted component {}", name);
+        Ok(())
+    }
+
+    /// Every actor's name, whether it is running, when it last started and
+    /// how often it has been restarted.
+    pub fn status(&self) -> Value {
+        self.lock()
+            .iter()
+            .map(|(name, actor)| {
+                json!({
+                    "name": name,
+                    "running": !actor.task.is_finished(),
+                    "started": actor.started.to_rfc3339(),
+                    "restarts": actor.restarts,
+                })
+            })
+            .collect()
+    }
+}
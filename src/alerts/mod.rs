@@ -0,0 +1,378 @@
+use crate::arbitrage;
+use crate::config::{AlertCondition, AlertConfig, CrossDirection};
+use crate::depeg;
+use crate::expr::{Expr, KlineContext};
+use crate::history::CandleHistory;
+use crate::indicators::IndicatorEvent;
+use crate::interval::interval_duration;
+#[cfg(feature = "scripting")]
+use crate::script::{self, Script};
+use crate::KlineData;
+#[cfg(not(feature = "scripting"))]
+use anyhow::bail;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, Utc};
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+/// A fired alert rule.
+#[derive(Debug, Clone)]
+pub struct Alert {
+    pub rule: String,
+    pub symbol: String,
+    pub interval: String,
+    pub price: f64,
+    /// Percent change of the candle that triggered the alert.
+    pub change_percent: f64,
+    pub message: String,
+    pub triggered_at: DateTime<Utc>,
+}
+
+impl Alert {
+    pub fn to_json(&self) -> Value {
+        json!({
+            "rule": self.rule,
+            "symbol": self.symbol,
+            "interval": self.interval,
+            "price": self.price,
+            "change_percent": self.change_percent,
+            "message": self.message,
+            "triggered_at": self.triggered_at.to_rfc3339(),
+        })
+    }
+}
+
+/// The indicator values alert rules can refer to for one series.
+#[derive(Clone, Copy, Default)]
+pub struct SeriesIndicators<'a> {
+    pub latest: Option<&'a BTreeMap<String, Value>>,
+    pub previous: Option<&'a BTreeMap<String, Value>>,
+}
+
+/// A rule's index, symbol and interval scope (`None` for every interval).
+type ArmKey = (usize, Arc<str>, Option<Arc<str>>);
+
+/// Evaluates the configured alert rules against incoming candles.
+///
+/// Each rule is armed per symbol (and interval, for expression rules): it
+/// fires once when its condition becomes true, then stays quiet until its
+/// re-arm condition holds. Firings are further rate limited per dedup key by
+/// the rule's cooldown.
+pub struct AlertEngine {
+    rules: Vec<AlertConfig>,
+    /// Compiled `when` and `rearm_when` for each expression rule, by index.
+    expressions: Vec<Option<(Expr, Option<Expr>)>>,
+    /// Loaded scripts of script rules, by index.
+    #[cfg(feature = "scripting")]
+    scripts: Vec<Option<Script>>,
+    /// Tracked intervals, shortest first.
+    intervals: Vec<String>,
+    /// Whether each rule may fire, per (rule index, symbol, interval scope).
+    armed: HashMap<ArmKey, bool>,
+    /// When each dedup key last fired.
+    last_fired: HashMap<String, DateTime<Utc>>,
+}
+
+impl AlertEngine {
+    /// Compiles every expression and script rule, failing on the first
+    /// invalid one.
+    pub fn new(rules: Vec<AlertConfig>, intervals: &[String]) -> Result<Self> {
+        #[cfg(feature = "scripting")]
+        let scripts = rules
+            .iter()
+            .map(|rule| match &rule.condition {
+                AlertCondition::Script { script } => Script::load(script)
+                    .with_context(|| format!("Invalid script for alert '{}'", rule.name()))
+                    .map(Some),
+                _ => Ok(None),
+            })
+            .collect::<Result<_>>()?;
+        #[cfg(not(feature = "scripting"))]
+        if let Some(rule) = rules
+            .iter()
+            .find(|rule| matches!(rule.condition, AlertCondition::Script { .. }))
+        {
+            bail!("Alert '{}' needs the `scripting` feature", rule.name());
+        }
+        let expressions = rules
+            .iter()
+            .map(|rule| match &rule.condition {
+                AlertCondition::Rule { when, rearm_when } => {
+                    let compile = |source: &str| {
+                        Expr::parse(source).with_context(|| {
+                            format!("Invalid condition for alert '{}'", rule.name())
+                        })
+                    };
+                    let rearm = rearm_when.as_deref().map(compile).transpose()?;
+                    Ok(Some((compile(when)?, rearm)))
+                }
+                _ => Ok(None),
+            })
+            .collect::<Result<_>>()?;
+        let mut intervals = intervals.to_vec();
+        intervals.sort_by_key(|interval| interval_duration(interval));
+        Ok(Self {
+            rules,
+            expressions,
+            #[cfg(feature = "scripting")]
+            scripts,
+            intervals,
+            armed: HashMap::new(),
+            last_fired: HashMap::new(),
+        })
+    }
+
+    /// Evaluates every rule scoped to `kline`'s series at `now`. `history`
+    /// is that series' candle history, already including `kline`;
+    /// `indicators` holds its latest indicator values and those as of the
+    /// previous candle.
+    pub fn evaluate(
+        &mut self,
+        kline: &KlineData,
+        history: &CandleHistory,
+        indicators: SeriesIndicators<'_>,
+        now: DateTime<Utc>,
+    ) -> Vec<Alert> {
+        let previous = history.previous().map(|previous| KlineContext {
+            kline: previous,
+            indicators: indicators.previous,
+            previous: None,
+        });
+        let context = KlineContext {
+            kline,
+            indicators: indicators.latest,
+            previous: previous.as_ref(),
+        };
+        let mut alerts = Vec::new();
+
+        for (index, rule) in self.rules.iter().enumerate() {
+            if !rule.applies_to(&kline.symbol, &kline.interval) {
+                continue;
+            }
+            let hysteresis = rule.rearm_pct / 100.0;
+            // (condition holds, re-arm condition holds, armed initially,
+            // interval scope of the armed state, message)
+            let (active, rearm, initially_armed, scope, message) = match &rule.condition {
+                AlertCondition::Price { direction, price } => {
+                    let (active, rearm) = match direction {
+                        CrossDirection::Above => (
+                            kline.close > *price,
+                            kline.close <= price * (1.0 - hysteresis),
+                        ),
+                        CrossDirection::Below => (
+                            kline.close < *price,
+                            kline.close >= price * (1.0 + hysteresis),
+                        ),
+                    };
+                    // A price rule fires on a cross, not because the price
+                    // is already past the threshold at startup.
+                    let message = format!("{} close crossed {} {}", kline.symbol, direction, price);
+                    (active, rearm, !active, None, message)
+                }
+                AlertCondition::PercentChange { window, threshold } => {
+                    // Use the finest interval in scope so the window is
+                    // measured as precisely as possible, and only once.
+                    let finest = self
+                        .intervals
+                        .iter()
+                        .find(|i| rule.applies_to(&kline.symbol, i));
+                    if finest.map(String::as_str) != Some(&*kline.interval) {
+                        continue;
+                    }
+                    let Some(change) = window_change(kline, history, window) else {
+                        continue;
+                    };
+                    let active = change.abs() >= *threshold;
+                    let rearm = change.abs() < threshold - rule.rearm_pct;
+                    let message =
+                        format!("{} moved {:+.2}% within {}", kline.symbol, change, window);
+                    (active, rearm, true, None, message)
+                }
+                AlertCondition::Depeg { threshold_bps } => {
+                    // Like a percent change, watched once, on the finest
+                    // interval in scope.
+                    let finest = self
+                        .intervals
+                        .iter()
+                        .find(|i| rule.applies_to(&kline.symbol, i));
+                    if finest.map(String::as_str) != Some(&*kline.interval) {
+                        continue;
+                    }
+                    let deviation = depeg::deviation_bps(kline.close);
+                    let active = deviation.abs() > *threshold_bps;
+                    let rearm = deviation.abs() <= threshold_bps - rule.rearm_pct * 100.0;
+                    let message = format!(
+                        "{} is {:.1} bps {} its peg at {:.4}",
+                        kline.symbol,
+                        deviation.abs(),
+                        if deviation < 0.0 { "below" } else { "above" },
+                        kline.close
+                    );
+                    (active, rearm, true, None, message)
+                }
+                AlertCondition::Spread { threshold_pct } => {
+                    let finest = self
+                        .intervals
+                        .iter()
+                        .find(|i| rule.applies_to(&kline.symbol, i));
+                    if finest.map(String::as_str) != Some(&*kline.interval) {
+                        continue;
+                    }
+                    let spread = kline.close;
+                    let active = spread.abs() > *threshold_pct;
+                    let rearm = spread.abs() <= threshold_pct - rule.rearm_pct;
+                    let Some((first, second, symbol)) = arbitrage::split_spread(&kline.symbol)
+                    else {
+                        continue;
+                    };
+                    let (cheap, dear) = if spread < 0.0 {
+                        (second, first)
+                    } else {
+                        (first, second)
+                    };
+                    let message = format!(
+                        "{} is {:.2}% dearer on {} than on {}, past the {:.2}% of fees and threshold",
+                        symbol,
+                        spread.abs(),
+                        dear,
+                        cheap,
+                        threshold_pct
+                    );
+                    (active, rearm, true, None, message)
+                }
+                AlertCondition::IndicatorEvent { .. } => continue,
+                AlertCondition::Rule { when, .. } => {
+                    let Some((condition, rearm_when)) = &self.expressions[index] else {
+                        continue;
+                    };
+                    let active = condition.matches(&context);
+                    let rearm = match rearm_when {
+                        Some(rearm_when) => rearm_when.matches(&context),
+                        None => !active,
+                    };
+                    let message = format!("{} {}: {}", kline.symbol, kline.interval, when);
+                    (active, rearm, true, Some(kline.interval.clone()), message)
+                }
+                #[cfg(feature = "scripting")]
+                AlertCondition::Script { .. } => {
+                    let Some(compiled) = &mut self.scripts[index] else {
+                        continue;
+                    };
+                    let mut scope = script::candle_scope(kline, indicators.latest);
+                    let previous = history.previous().map_or(rhai::Dynamic::UNIT, |previous| {
+                        script::candle_map(previous, indicators.previous).into()
+                    });
+                    scope.push_dynamic("previous", previous);
+                    let active = compiled.matches(&mut scope);
+                    let message = format!(
+                        "{} {}: {}",
+                        kline.symbol,
+                        kline.interval,
+                        compiled.path().display()
+                    );
+                    (active, !active, true, Some(kline.interval.clone()), message)
+                }
+                #[cfg(not(feature = "scripting"))]
+                AlertCondition::Script { .. } => continue,
+            };
+
+            let armed = self
+                .armed
+                .entry((index, kline.symbol.clone(), scope))
+                .or_insert(initially_armed);
+            if !*armed {
+                *armed = rearm;
+                continue;
+            }
+            if !active {
+                continue;
+            }
+            *armed = false;
+
+            alerts.extend(fire(rule, &mut self.last_fired, kline, message, now));
+        }
+        alerts
+    }
+
+    /// Raises an alert at `now` for each indicator event matched by an
+    /// `indicator_event` rule. `kline` is the candle that produced them.
+    pub fn evaluate_events(
+        &mut self,
+        kline: &KlineData,
+        events: &[IndicatorEvent],
+        now: DateTime<Utc>,
+    ) -> Vec<Alert> {
+        let mut alerts = Vec::new();
+        for rule in &self.rules {
+            let AlertCondition::IndicatorEvent { indicator, event } = &rule.condition else {
+                continue;
+            };
+            if !rule.applies_to(&kline.symbol, &kline.interval) {
+                continue;
+            }
+            for raised in events {
+                let matches = indicator.as_ref().is_none_or(|i| *i == raised.indicator)
+                    && event.as_ref().is_none_or(|e| *e == raised.event);
+                if matches {
+                    let message = format!(
+                        "{} {}: {} {}",
+                        raised.symbol, raised.interval, raised.indicator, raised.event
+                    );
+                    alerts.extend(fire(rule, &mut self.last_fired, kline, message, now));
+                }
+            }
+        }
+        alerts
+    }
+}
+
+/// Builds the alert for `rule`, unless its dedup key is still cooling down.
+fn fire(
+    rule: &AlertConfig,
+    last_fired: &mut HashMap<String, DateTime<Utc>>,
+    kline: &KlineData,
+    message: String,
+    now: DateTime<Utc>,
+) -> Option<Alert> {
+    let dedup_key = rule.dedup_key(&kline.symbol, &kline.interval);
+    let cooldown = Duration::seconds(rule.cooldown_secs as i64);
+    if last_fired
+        .get(&dedup_key)
+        .is_some_and(|last| now - *last < cooldown)
+    {
+        return None;
+    }
+    last_fired.insert(dedup_key, now);
+
+    Some(Alert {
+        rule: rule.name(),
+        symbol: kline.symbol.to_string(),
+        interval: kline.interval.to_string(),
+        price: kline.close,
+        change_percent: kline.price_change_percent(),
+        message,
+        triggered_at: now,
+    })
+}
+
+/// The biggest move within `window` to the latest close, in percent: up
+/// from the lowest low of the candles inside it, or down from their highest
+/// high, whichever is larger. A spike that retraces within the window still
+/// counts, as the move back down from its high.
+fn window_change(kline: &KlineData, history: &CandleHistory, window: &str) -> Option<f64> {
+    let window = interval_duration(window)?;
+    let step = interval_duration(&kline.interval)?;
+    let candles = (window.num_seconds() / step.num_seconds().max(1)).max(1) as usize;
+    let (high, low) = history
+        .last_n(candles)
+        .fold((f64::MIN, f64::MAX), |(high, low), candle| {
+            (high.max(candle.high), low.min(candle.low))
+        });
+    if low <= 0.0 {
+        return None;
+    }
+    let rise = (kline.close - low) / low * 100.0;
+    let fall = (kline.close - high) / high * 100.0;
+    Some(if rise >= -fall { rise } else { fall })
+}
@@ -0,0 +1,105 @@
+//! Prices of the same symbol on several venues: the Binance streams tracked
+//! as usual, and any other endpoint speaking Binance's stream protocol, such
+//! as Binance.US. A venue's series are named `venue:symbol`, e.g.
+//! `binanceus:btcusdt`, and the spread between two venues is a synthetic
+//! series of its own, `binance-binanceus:btcusdt`, in percent.
+
+use crate::config::{ArbitrageConfig, VenueConfig};
+use crate::KlineData;
+
+/// The venue of the plain, unqualified symbols.
+pub const PRIMARY_VENUE: &str = "binance";
+
+/// Splits a series' symbol into its venue, `None` for the primary one, and
+/// the symbol as the venue names it.
+pub fn split_venue(symbol: &str) -> (Option<&str>, &str) {
+    match symbol.split_once(':') {
+        Some((venue, symbol)) => (Some(venue), symbol),
+        None => (None, symbol),
+    }
+}
+
+/// The series name of `symbol` on `venue`.
+pub fn venue_symbol(venue: &str, symbol: &str) -> String {
+    if venue == PRIMARY_VENUE {
+        symbol.to_string()
+    } else {
+        format!("{}:{}", venue, symbol)
+    }
+}
+
+/// The series name of the spread of `symbol` on `second` over `first`.
+pub fn spread_name(first: &str, second: &str, symbol: &str) -> String {
+    format!("{}-{}:{}", first, second, symbol)
+}
+
+/// Splits a spread series' name into its two venues and the symbol, or
+/// returns `None` if it is not one.
+pub fn split_spread(name: &str) -> Option<(&str, &str, &str)> {
+    let (venues, symbol) = name.split_once(':')?;
+    let (first, second) = venues.split_once('-')?;
+    Some((first, second, symbol))
+}
+
+/// Every pair of venues, the primary one first, each with the taker fee
+/// paid on both sides of a round trip between them, in percent.
+pub fn venue_pairs<'a>(
+    config: &'a ArbitrageConfig,
+    venues: &'a [VenueConfig],
+) -> Vec<(&'a str, &'a str, f64)> {
+    let venues: Vec<(&str, f64)> = std::iter::once((PRIMARY_VENUE, config.fee_pct))
+        .chain(
+            venues
+                .iter()
+                .map(|venue| (venue.name.as_str(), venue.fee_pct)),
+        )
+        .collect();
+    let mut pairs = Vec::new();
+    for (index, (first, first_fee)) in venues.iter().enumerate() {
+        for (second, second_fee) in &venues[index + 1..] {
+            pairs.push((*first, *second, first_fee + second_fee));
+        }
+    }
+    pairs
+}
+
+/// Builds the candle of how much dearer `second` is than `first`, in
+/// percent, from the latest candle on each venue. Returns `None` until both
+/// are on the same interval start.
+///
+/// As for ratios, the high and low are the extremes observed across updates
+/// of the current candle (`previous` is the last spread candle emitted).
+pub fn compute_spread(
+    name: &str,
+    first: &KlineData,
+    second: &KlineData,
+    previous: Option<&KlineData>,
+) -> Option<KlineData> {
+    if first.interval != second.interval || first.interval_start != second.interval_start {
+        return None;
+    }
+    if first.open == 0.0 || first.close == 0.0 {
+        return None;
+    }
+
+    let open = (second.open - first.open) / first.open * 100.0;
+    let close = (second.close - first.close) / first.close * 100.0;
+    let (mut high, mut low) = (open.max(close), open.min(close));
+    if let Some(prev) = previous.filter(|p| p.interval_start == first.interval_start) {
+        high = high.max(prev.high);
+        low = low.min(prev.low);
+    }
+
+    Some(KlineData {
+        symbol: name.into(),
+        interval: first.interval.clone(),
+        interval_start: first.interval_start,
+        open,
+        high,
+        low,
+        close,
+        volume: 0.0,
+        closed: first.closed && second.closed,
+        timing: None,
+    })
+}
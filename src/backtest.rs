@@ -0,0 +1,277 @@
+//! Replays stored candles through the indicators, alert rules, strategies
+//! and paper trading orders used live, and reports how the simulated account
+//! fared.
+
+use crate::alerts::{AlertEngine, SeriesIndicators};
+use crate::config::{Config, Side};
+use crate::history::CandleHistory;
+use crate::indicators::IndicatorPipeline;
+use crate::interval::interval_duration;
+use crate::paper::{Fill, PaperAccount, Placed};
+use crate::strategy::StrategySet;
+use crate::{KlineData, SeriesKey};
+use chrono::{DateTime, Utc};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use tracing::debug;
+
+/// A round trip: quantity bought, then sold.
+#[derive(Debug, Clone)]
+pub struct Trade {
+    pub symbol: String,
+    pub quantity: f64,
+    /// Average price the quantity was bought at.
+    pub entry_price: f64,
+    pub exit_price: f64,
+    /// When the position the quantity was sold from was opened.
+    pub opened: DateTime<Utc>,
+    pub closed: DateTime<Utc>,
+    /// After the fees of both sides.
+    pub pnl: f64,
+    pub reason: String,
+}
+
+impl Trade {
+    pub fn to_json(&self) -> Value {
+        json!({
+            "symbol": self.symbol,
+            "quantity": self.quantity,
+            "entry_price": self.entry_price,
+            "exit_price": self.exit_price,
+            "opened": self.opened.to_rfc3339(),
+            "closed": self.closed.to_rfc3339(),
+            "pnl": self.pnl,
+            "reason": self.reason,
+        })
+    }
+}
+
+/// The outcome of a backtest.
+#[derive(Debug, Clone)]
+pub struct Report {
+    pub starting_balance: f64,
+    /// Cash plus open positions at their last close.
+    pub final_equity: f64,
+    pub trades: Vec<Trade>,
+    /// Equity after each candle close, one point per candle start.
+    pub equity: Vec<(DateTime<Utc>, f64)>,
+    /// Largest fall of the equity from an earlier peak, in percent.
+    pub max_drawdown_percent: f64,
+    /// Quantity still held per symbol at the end.
+    pub open_positions: Vec<(String, f64)>,
+    pub candles: usize,
+}
+
+impl Report {
+    pub fn pnl(&self) -> f64 {
+        self.final_equity - self.starting_balance
+    }
+
+    /// Percent of trades that made money, or `None` without trades.
+    pub fn win_rate(&self) -> Option<f64> {
+        if self.trades.is_empty() {
+            return None;
+        }
+        let wins = self.trades.iter().filter(|trade| trade.pnl > 0.0).count();
+        Some(wins as f64 / self.trades.len() as f64 * 100.0)
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "candles": self.candles,
+            "starting_balance": self.starting_balance,
+            "final_equity": self.final_equity,
+            "pnl": self.pnl(),
+            "max_drawdown_percent": self.max_drawdown_percent,
+            "win_rate": self.win_rate(),
+            "trades": self.trades.iter().map(Trade::to_json).collect::<Vec<_>>(),
+            "equity": self
+                .equity
+                .iter()
+                .map(|(time, equity)| json!({ "time": time.to_rfc3339(), "equity": equity }))
+                .collect::<Vec<_>>(),
+            "open_positions": self
+                .open_positions
+                .iter()
+                .map(|(symbol, quantity)| json!({ "symbol": symbol, "quantity": quantity }))
+                .collect::<Vec<_>>(),
+        })
+    }
+}
+
+/// What is held of one symbol, for pricing round trips.
+struct Holding {
+    opened: DateTime<Utc>,
+    quantity: f64,
+    /// Buy fees not yet charged to a trade.
+    fees: f64,
+}
+
+/// Runs `candles`, closed and oldest first, through `pipeline`, `alerts`
+/// with `config`'s paper trading orders, and `strategies`.
+pub fn run(
+    config: &Config,
+    candles: &[KlineData],
+    pipeline: IndicatorPipeline,
+    alerts: AlertEngine,
+    strategies: StrategySet,
+) -> Report {
+    run_warmed(config, candles, 0, pipeline, alerts, strategies)
+}
+
+/// Like [`run`], but the first `warmup` candles only warm up the
+/// indicators, alert rules and strategies: nothing is traded on them, and
+/// the report starts after them.
+pub fn run_warmed(
+    config: &Config,
+    candles: &[KlineData],
+    warmup: usize,
+    mut pipeline: IndicatorPipeline,
+    mut alerts: AlertEngine,
+    mut strategies: StrategySet,
+) -> Report {
+    let mut account = PaperAccount::new(&config.paper);
+    let mut history: HashMap<SeriesKey, CandleHistory> = HashMap::new();
+    let mut holdings: HashMap<String, Holding> = HashMap::new();
+    let mut trades = Vec::new();
+    let mut equity: Vec<(DateTime<Utc>, f64)> = Vec::new();
+
+    let warmup = warmup.min(candles.len());
+    for (index, kline) in candles.iter().enumerate() {
+        if index == warmup && warmup > 0 {
+            // Signals raised while warming up were never traded.
+            strategies.flatten();
+        }
+        let series = history
+            .entry(kline.key())
+            .or_insert_with(|| CandleHistory::new(config.history_size));
+        series.update(kline.clone());
+        // Alerts and signals happen when the candle ends, on the candles'
+        // clock, so cooldowns span candles as they would live.
+        let closed_at = kline.interval_start
+            + interval_duration(&kline.interval).unwrap_or_else(chrono::Duration::zero);
+        let (_, events) = pipeline.update(kline);
+        let mut fired = alerts.evaluate_events(kline, &events, closed_at);
+        let indicators = SeriesIndicators {
+            latest: pipeline.values(&kline.symbol, &kline.interval),
+            previous: pipeline.previous_values(&kline.symbol, &kline.interval),
+        };
+        fired.extend(alerts.evaluate(kline, series, indicators, closed_at));
+        if index < warmup {
+            let latest = pipeline.values(&kline.symbol, &kline.interval);
+            strategies.update(kline, latest, closed_at);
+            continue;
+        }
+
+        // Fills orders placed on earlier candles before this one places any,
+        // so those can only fill on a later one. The close is seen when the
+        // candle ends, which delayed orders wait from.
+        let mut fills = account.on_kline(kline, closed_at);
+        for alert in &fired {
+            for placed in account.on_alert(&config.paper.orders, alert) {
+                match placed {
+                    Ok(Placed::Filled(fill)) => fills.push(fill),
+                    Ok(Placed::Resting(_) | Placed::Delayed(_)) => {}
+                    Err(e) => debug!("Rejected order at {}: {}", kline.interval_start, e),
+                }
+            }
+        }
+        let latest = pipeline.values(&kline.symbol, &kline.interval);
+        for signal in strategies.update(kline, latest, closed_at) {
+            let Some(strategy) = config
+                .strategies
+                .iter()
+                .find(|s| s.name() == signal.strategy)
+            else {
+                continue;
+            };
+            match account.on_signal(strategy, &signal) {
+                Ok(Placed::Filled(fill)) => fills.push(fill),
+                Ok(Placed::Resting(_) | Placed::Delayed(_)) => {}
+                Err(e) => debug!("Rejected order at {}: {}", kline.interval_start, e),
+            }
+        }
+        for fill in &fills {
+            record(fill, kline.interval_start, &mut holdings, &mut trades);
+        }
+
+        let point = (kline.interval_start, account.equity());
+        match equity.last_mut() {
+            Some(last) if last.0 == point.0 => *last = point,
+            _ => equity.push(point),
+        }
+    }
+
+    let mut open_positions: Vec<(String, f64)> = holdings
+        .into_iter()
+        .map(|(symbol, holding)| (symbol, holding.quantity))
+        .collect();
+    open_positions.sort_by(|a, b| a.0.cmp(&b.0));
+    Report {
+        starting_balance: config.paper.starting_balance,
+        final_equity: account.equity(),
+        trades,
+        max_drawdown_percent: max_drawdown(&equity),
+        equity,
+        open_positions,
+        candles: candles.len() - warmup,
+    }
+}
+
+/// Updates `holdings` with `fill`, closing a trade when it sells.
+fn record(
+    fill: &Fill,
+    at: DateTime<Utc>,
+    holdings: &mut HashMap<String, Holding>,
+    trades: &mut Vec<Trade>,
+) {
+    let holding = holdings
+        .entry(fill.symbol.clone())
+        .or_insert_with(|| Holding {
+            opened: at,
+            quantity: 0.0,
+            fees: 0.0,
+        });
+    match fill.side {
+        Side::Buy => {
+            if holding.quantity == 0.0 {
+                holding.opened = at;
+            }
+            holding.quantity += fill.quantity;
+            holding.fees += fill.fee;
+        }
+        Side::Sell => {
+            let entry_price = fill.entry_price.unwrap_or(fill.price);
+            let share = (fill.quantity / holding.quantity).min(1.0);
+            let entry_fees = holding.fees * share;
+            trades.push(Trade {
+                symbol: fill.symbol.clone(),
+                quantity: fill.quantity,
+                entry_price,
+                exit_price: fill.price,
+                opened: holding.opened,
+                closed: at,
+                pnl: (fill.price - entry_price) * fill.quantity - fill.fee - entry_fees,
+                reason: fill.reason.clone(),
+            });
+            holding.quantity = (holding.quantity - fill.quantity).max(0.0);
+            holding.fees -= entry_fees;
+            if holding.quantity < 1e-12 {
+                holdings.remove(&fill.symbol);
+            }
+        }
+    }
+}
+
+/// Largest fall from a running peak of `equity`, in percent.
+fn max_drawdown(equity: &[(DateTime<Utc>, f64)]) -> f64 {
+    let mut peak = f64::MIN;
+    let mut worst: f64 = 0.0;
+    for (_, value) in equity {
+        peak = peak.max(*value);
+        if peak > 0.0 {
+            worst = worst.max((peak - value) / peak * 100.0);
+        }
+    }
+    worst
+}
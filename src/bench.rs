@@ -0,0 +1,191 @@
+//! Measures how fast the tracker ingests klines, by replaying a capture of
+//! Binance messages through the processing pipeline as fast as it will go.
+
+use crate::actor;
+use crate::alerts::AlertEngine;
+use crate::clock;
+use crate::config::Config;
+use crate::health::Health;
+use crate::indicators::IndicatorPipeline;
+use crate::metrics::Metrics;
+use crate::multiplex::stream_name;
+use crate::processor::{process_kline_stream, OutputFormat, Outputs};
+use crate::queue::KlineSender;
+use crate::shard::{Router, Shards};
+use crate::state::SharedState;
+use crate::store::Store;
+use crate::strategy::StrategySet;
+use crate::{KlineData, SeriesKey};
+use anyhow::{Context, Result};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
+
+static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+static ALLOCATED_BYTES: AtomicU64 = AtomicU64::new(0);
+
+/// The system allocator, counting allocations so [`ingest`] can report
+/// them. Install it with `#[global_allocator]` to have them counted.
+pub struct CountingAlloc;
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        ALLOCATED_BYTES.fetch_add(layout.size() as u64, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        ALLOCATED_BYTES.fetch_add(new_size as u64, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+fn allocations() -> (u64, u64) {
+    (
+        ALLOCATIONS.load(Ordering::Relaxed),
+        ALLOCATED_BYTES.load(Ordering::Relaxed),
+    )
+}
+
+/// What an ingest run measured.
+#[derive(Debug)]
+pub struct IngestReport {
+    pub messages: usize,
+    pub candles: usize,
+    pub skipped: usize,
+    pub elapsed: Duration,
+    /// Zero unless [`CountingAlloc`] is the global allocator.
+    pub allocations: u64,
+    pub allocated_bytes: u64,
+}
+
+impl IngestReport {
+    pub fn candles_per_sec(&self) -> f64 {
+        self.candles as f64 / self.elapsed.as_secs_f64().max(f64::EPSILON)
+    }
+}
+
+impl fmt::Display for IngestReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let candles = self.candles.max(1) as f64;
+        writeln!(
+            f,
+            "Ingested {} candles from {} messages ({} skipped) in {:.3}s",
+            self.candles,
+            self.messages,
+            self.skipped,
+            self.elapsed.as_secs_f64()
+        )?;
+        writeln!(f, "Throughput: {:.0} candles/sec", self.candles_per_sec())?;
+        if self.allocations > 0 {
+            write!(
+                f,
+                "Allocations: {} ({:.1} per candle), {} bytes ({:.0} per candle)",
+                self.allocations,
+                self.allocations as f64 / candles,
+                self.allocated_bytes,
+                self.allocated_bytes as f64 / candles
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Replays `capture`, a file of messages from Binance's combined stream
+/// endpoint one per line, through the full pipeline configured by `config`:
+/// parsing, the worker queues, indicators, alerts, strategies and state.
+/// Candles are stored in memory only and no sink is started. Timing starts
+/// once the capture is loaded and ends when the last candle has been
+/// processed.
+pub async fn ingest(
+    capture: &Path,
+    config: Config,
+    pipeline: IndicatorPipeline,
+    alert_engine: AlertEngine,
+    strategies: StrategySet,
+) -> Result<IngestReport> {
+    let text = std::fs::read_to_string(capture)
+        .with_context(|| format!("Failed to read capture {}", capture.display()))?;
+    let messages: Vec<&str> = text.lines().filter(|line| !line.is_empty()).collect();
+
+    let metrics = Metrics::default();
+    let shards = Shards::new(config.workers, &config.ratios);
+    let (router, receivers) = Router::new(&config.queue, shards.clone(), metrics.clone());
+    // Every configured series, named as in the messages, with the queue its
+    // stream would feed.
+    let mut series: HashMap<String, (SeriesKey, KlineSender)> = HashMap::new();
+    for symbol in &config.symbols {
+        for interval in &config.intervals {
+            let key = (symbol.as_str().into(), interval.as_str().into());
+            series.insert(stream_name(symbol, interval), (key, router.sender(symbol)));
+        }
+    }
+    drop(router);
+    let (notify_tx, _notifications) = actor::mailbox(100);
+    let (ops_tx, _incidents) = actor::mailbox(100);
+    let (events_tx, _) = broadcast::channel(1);
+    let outputs = Outputs {
+        state: SharedState::default(),
+        store: Arc::new(Store::open(Path::new(":memory:"))?),
+        notify_tx,
+        ops_tx,
+        events_tx,
+        format: OutputFormat::Text,
+        filter: None,
+        health: Health::new(Duration::from_secs(config.ops.stream_down_secs.max(1))),
+        metrics,
+        clock: clock::system(),
+    };
+
+    let (allocations_before, bytes_before) = allocations();
+    let started = Instant::now();
+    let processor = tokio::spawn(process_kline_stream(
+        receivers,
+        shards,
+        config,
+        pipeline,
+        alert_engine,
+        strategies,
+        outputs,
+    ));
+    let (mut candles, mut skipped) = (0, 0);
+    for message in &messages {
+        let mut route = None;
+        let parsed = KlineData::parse_combined(message, |stream| {
+            route = series.get(stream);
+            route.map(|(key, _)| key.clone())
+        });
+        match (parsed, route) {
+            (Ok(Some(kline)), Some((_, tx))) => {
+                tx.send(kline).await?;
+                candles += 1;
+            }
+            _ => skipped += 1,
+        }
+    }
+    // Closing the queues lets the workers finish.
+    drop(series);
+    processor.await?;
+    let elapsed = started.elapsed();
+    let (allocations_after, bytes_after) = allocations();
+
+    Ok(IngestReport {
+        messages: messages.len(),
+        candles,
+        skipped,
+        elapsed,
+        allocations: allocations_after - allocations_before,
+        allocated_bytes: bytes_after - bytes_before,
+    })
+}
@@ -0,0 +1,66 @@
+use crate::KlineData;
+
+const BODY: char = '█';
+const WICK: char = '│';
+/// Rows between two price labels on the axis.
+const LABEL_EVERY: usize = 5;
+
+/// One character cell of a chart.
+#[derive(Debug, Clone, Copy)]
+pub struct Mark {
+    pub glyph: char,
+    /// Whether the candle closed at or above its open.
+    pub rising: bool,
+}
+
+/// Candlesticks laid out on a character grid, one column per candle.
+pub struct Chart {
+    /// Rows from the highest price to the lowest.
+    pub rows: Vec<Vec<Option<Mark>>>,
+    /// The price at the middle of each row, for rows that get an axis label.
+    pub labels: Vec<Option<f64>>,
+}
+
+/// Renders `candles`, oldest first, as a chart `height` rows tall scaled
+/// between their overall high and low.
+pub fn render(candles: &[KlineData], height: usize) -> Chart {
+    let height = height.max(2);
+    let high = candles
+        .iter()
+        .map(|c| c.high)
+        .fold(f64::NEG_INFINITY, f64::max);
+    let low = candles.iter().map(|c| c.low).fold(f64::INFINITY, f64::min);
+    let step = (high - low).max(f64::EPSILON) / height as f64;
+
+    let rows = (0..height)
+        .map(|row| {
+            let top = high - row as f64 * step;
+            let bottom = top - step;
+            let overlaps = |from: f64, to: f64| from <= top && to >= bottom;
+            candles
+                .iter()
+                .map(|candle| {
+                    let rising = candle.close >= candle.open;
+                    let glyph =
+                        if overlaps(candle.open.min(candle.close), candle.open.max(candle.close)) {
+                            BODY
+                        } else if overlaps(candle.low, candle.high) {
+                            WICK
+                        } else {
+                            return None;
+                        };
+                    Some(Mark { glyph, rising })
+                })
+                .collect()
+        })
+        .collect();
+
+    let labels = (0..height)
+        .map(|row| {
+            (row % LABEL_EVERY == 0 || row == height - 1)
+                .then_some(high - (row as f64 + 0.5) * step)
+        })
+        .collect();
+
+    Chart { rows, labels }
+}
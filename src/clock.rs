@@ -0,0 +1,71 @@
+//! Where the pipeline reads the time from: the system clock when streaming,
+//! or a virtual one that follows the replayed data, so a replay stamps
+//! alerts, signals and sessions, and measures cooldowns, the same way every
+//! run. Tests can drive risk limits from a virtual clock too.
+
+use chrono::{DateTime, Utc};
+use std::fmt::Debug;
+use std::sync::{Arc, Mutex};
+
+/// A source of the current time.
+pub trait Clock: Debug + Send + Sync {
+    fn now(&self) -> DateTime<Utc>;
+
+    /// Told the exchange's event time of each update as it is processed.
+    /// The system clock ignores it.
+    fn follow(&self, _event_time: DateTime<Utc>) {}
+}
+
+/// A clock shared by the workers.
+pub type SharedClock = Arc<dyn Clock>;
+
+/// The system's clock.
+#[derive(Debug)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// The system's clock, shared.
+pub fn system() -> SharedClock {
+    Arc::new(SystemClock)
+}
+
+/// A clock that only moves when told to: forward to each event time it
+/// follows, or wherever [`VirtualClock::set`] puts it.
+#[derive(Debug)]
+pub struct VirtualClock {
+    now: Mutex<DateTime<Utc>>,
+}
+
+impl VirtualClock {
+    pub fn new(start: DateTime<Utc>) -> Self {
+        Self {
+            now: Mutex::new(start),
+        }
+    }
+
+    pub fn set(&self, now: DateTime<Utc>) {
+        *self.now.lock().unwrap_or_else(|e| e.into_inner()) = now;
+    }
+
+    pub fn advance(&self, by: chrono::Duration) {
+        *self.now.lock().unwrap_or_else(|e| e.into_inner()) += by;
+    }
+}
+
+impl Clock for VirtualClock {
+    fn now(&self) -> DateTime<Utc> {
+        *self.now.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Moves forward to `event_time`, never back, so updates arriving out
+    /// of order do not rewind it.
+    fn follow(&self, event_time: DateTime<Utc>) {
+        let mut now = self.now.lock().unwrap_or_else(|e| e.into_inner());
+        *now = (*now).max(event_time);
+    }
+}
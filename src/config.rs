@@ -0,0 +1,1913 @@
+use crate::interval::interval_duration;
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Base of the WebSocket URLs streamed from: Binance's, or a local
+    /// [`MockServer`](crate::mock::MockServer)'s to test without it.
+    pub stream_url: String,
+    pub symbols: Vec<String>,
+    pub intervals: Vec<String>,
+    pub history_size: usize,
+    pub cache: CacheConfig,
+    /// Seconds between periodic session summaries.
+    pub summary_interval_secs: u64,
+    pub digest: DigestConfig,
+    /// Workers the symbols are sharded across for processing.
+    pub workers: usize,
+    /// Seconds each shutdown step may take: closing the streams, then
+    /// delivering what the sinks still hold.
+    pub shutdown_grace_secs: u64,
+    pub ratios: Vec<RatioConfig>,
+    pub depeg: DepegConfig,
+    pub open_interest: OpenInterestConfig,
+    /// Other venues serving Binance's stream protocol, compared by
+    /// `arbitrage` and combined by `index`.
+    pub venues: Vec<VenueConfig>,
+    pub arbitrage: ArbitrageConfig,
+    pub index: IndexConfig,
+    pub listings: ListingsConfig,
+    pub halts: HaltsConfig,
+    pub precision: PrecisionConfig,
+    pub indicators: Vec<IndicatorConfig>,
+    pub alerts: Vec<AlertConfig>,
+    pub strategies: Vec<StrategyConfig>,
+    pub notifiers: Vec<NotifierConfig>,
+    pub push: Vec<PushConfig>,
+    pub queue: QueueConfig,
+    pub restart: RestartConfig,
+    pub multiplex: MultiplexConfig,
+    pub ops: OpsConfig,
+    pub store: StoreConfig,
+    pub snapshot: SnapshotConfig,
+    pub paper: PaperConfig,
+    pub holdings: Vec<HoldingConfig>,
+    pub portfolio: PortfolioConfig,
+    pub live: LiveConfig,
+    pub risk: RiskConfig,
+    pub server: ServerConfig,
+    pub grpc: GrpcConfig,
+    pub control: ControlConfig,
+    pub auth: AuthConfig,
+    pub runtime: RuntimeConfig,
+    pub synthetic: SyntheticConfig,
+}
+
+/// A synthetic pair whose price is `base / quote`, e.g. ETHUSDT/BTCUSDT.
+#[derive(Debug, Clone, Deserialize)]
+pub struct RatioConfig {
+    pub base: String,
+    pub quote: String,
+}
+
+/// The built-in stablecoin monitor: an alert rule named `depeg` for every
+/// pair in `symbols`, which are streamed whether listed in `symbols` or not,
+/// and a summary line of how far each is from its peg.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct DepegConfig {
+    pub enabled: bool,
+    /// Stablecoins quoted in another stablecoin, so both should be 1.0.
+    pub symbols: Vec<String>,
+    /// Basis points from 1.0 a price may deviate before the alert fires.
+    pub threshold_bps: f64,
+    /// Basis points the price must come back inside the threshold before
+    /// the alert can fire again.
+    pub rearm_bps: f64,
+    pub cooldown_secs: u64,
+}
+
+impl Default for DepegConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            symbols: ["usdcusdt", "fdusdusdt", "tusdusdt", "usdpusdt"]
+                .iter()
+                .map(|s| s.to_string())
+                .collect(),
+            threshold_bps: 50.0,
+            rearm_bps: 10.0,
+            cooldown_secs: 300,
+        }
+    }
+}
+
+/// Polling the open interest of perpetual futures named like the tracked
+/// spot symbols, e.g. BTCUSDT for `btcusdt`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct OpenInterestConfig {
+    pub enabled: bool,
+    /// Symbols to poll; empty means every tracked symbol.
+    pub symbols: Vec<String>,
+    pub interval_secs: u64,
+    /// Base URL of the futures REST API.
+    pub url: String,
+}
+
+impl Default for OpenInterestConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            symbols: Vec::new(),
+            interval_secs: 60,
+            url: "https://fapi.binance.com".to_string(),
+        }
+    }
+}
+
+/// The cross-venue spread monitor: `symbols` streamed on every
+/// [venue](VenueConfig) as well as Binance, with the spread
+/// between each pair of venues tracked as a series of its own and an alert
+/// rule named `arbitrage` firing when it pays more than both venues' fees.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ArbitrageConfig {
+    pub enabled: bool,
+    /// Symbols to compare; empty means every tracked symbol.
+    pub symbols: Vec<String>,
+    /// Binance's taker fee, in percent.
+    pub fee_pct: f64,
+    /// Percent the spread must pay beyond both venues' fees to alert.
+    pub threshold_pct: f64,
+    pub cooldown_secs: u64,
+}
+
+impl Default for ArbitrageConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            symbols: Vec::new(),
+            fee_pct: 0.1,
+            threshold_pct: 0.1,
+            cooldown_secs: 300,
+        }
+    }
+}
+
+/// A composite price per symbol across Binance and every [venue](VenueConfig),
+/// tracked as the series `index:symbol`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct IndexConfig {
+    pub enabled: bool,
+    /// Symbols to price; empty means every tracked symbol.
+    pub symbols: Vec<String>,
+    pub method: IndexMethod,
+    /// Venues that must be quoting the current candle for the index to be
+    /// computed.
+    pub min_venues: usize,
+}
+
+impl Default for IndexConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            symbols: Vec::new(),
+            method: IndexMethod::Median,
+            min_venues: 2,
+        }
+    }
+}
+
+/// How venues' prices are combined into an index.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IndexMethod {
+    /// The median across venues, robust to one venue going astray.
+    #[default]
+    Median,
+    /// The mean weighted by each venue's volume in the candle.
+    VolumeWeighted,
+}
+
+/// Watching Binance's exchange info for new trading pairs.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ListingsConfig {
+    pub enabled: bool,
+    /// Only pairs quoted in one of these assets are reported; empty reports
+    /// every pair.
+    pub quote_assets: Vec<String>,
+    pub interval_secs: u64,
+    /// Whether to start streaming a new pair on every tracked interval.
+    pub subscribe: bool,
+    /// Base URL of the spot REST API.
+    pub url: String,
+}
+
+impl Default for ListingsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            quote_assets: vec!["USDT".to_string()],
+            interval_secs: 300,
+            subscribe: false,
+            url: "https://api.binance.com".to_string(),
+        }
+    }
+}
+
+/// Watching the streamed symbols' trading status in Binance's exchange
+/// info, and their streams for silence.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct HaltsConfig {
+    pub enabled: bool,
+    pub interval_secs: u64,
+    /// A symbol none of whose streams has sent anything for this many
+    /// seconds is reported as possibly halted; 0 turns this off.
+    pub silence_secs: u64,
+    /// Base URL of the spot REST API.
+    pub url: String,
+}
+
+impl Default for HaltsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval_secs: 60,
+            silence_secs: 1800,
+            url: "https://api.binance.com".to_string(),
+        }
+    }
+}
+
+/// A digest of the whole market logged and sent to the notifiers at a
+/// fixed period, for watching many symbols at once.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct DigestConfig {
+    pub enabled: bool,
+    pub interval_secs: u64,
+    /// Symbols listed among the gainers, the losers and the volume spikes.
+    pub top: usize,
+    /// Whether every candle update is still logged; off leaves the digest
+    /// in its place.
+    pub log_updates: bool,
+}
+
+impl Default for DigestConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval_secs: 300,
+            top: 5,
+            log_updates: true,
+        }
+    }
+}
+
+/// Fetching each symbol's tick size from Binance's exchange info at
+/// startup, to show its prices with.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct PrecisionConfig {
+    pub enabled: bool,
+    /// Base URL of the spot REST API.
+    pub url: String,
+}
+
+impl Default for PrecisionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            url: "https://api.binance.com".to_string(),
+        }
+    }
+}
+
+/// A venue serving Binance's WebSocket stream protocol. Its symbols are
+/// streamed on the shortest tracked interval.
+#[derive(Debug, Clone, Deserialize)]
+pub struct VenueConfig {
+    /// Prefixes the venue's series, e.g. `binanceus` for `binanceus:btcusdt`.
+    pub name: String,
+    pub stream_url: String,
+    /// Taker fee, in percent.
+    #[serde(default = "default_venue_fee_pct")]
+    pub fee_pct: f64,
+}
+
+fn default_venue_fee_pct() -> f64 {
+    0.1
+}
+
+/// One indicator instance to run, scoped to a set of symbols and intervals.
+///
+/// Any key other than the ones below is passed to the indicator as a numeric
+/// parameter, e.g. `period = 20`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct IndicatorConfig {
+    pub kind: String,
+    /// Label for the output; defaults to the kind followed by its parameters.
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Symbols to run on; empty means every symbol, including ratios.
+    #[serde(default)]
+    pub symbols: Vec<String>,
+    /// Intervals to run on; empty means every interval.
+    #[serde(default)]
+    pub intervals: Vec<String>,
+    #[serde(flatten)]
+    pub params: HashMap<String, f64>,
+}
+
+/// A trading strategy, run on every closed candle of its symbols and
+/// intervals by paper trading and backtests. Its signals buy the amount set
+/// here and sell the whole position.
+///
+/// Like indicators, any other key is passed to the strategy as a numeric
+/// parameter, e.g. `fast = 10`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct StrategyConfig {
+    pub kind: String,
+    /// Label for its orders; defaults to the kind.
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub symbols: Vec<String>,
+    #[serde(default)]
+    pub intervals: Vec<String>,
+    /// Base quantity each buy trades.
+    #[serde(default)]
+    pub quantity: Option<f64>,
+    /// Quote value each buy trades, converted at the candle's close.
+    #[serde(default)]
+    pub quote_amount: Option<f64>,
+    /// Place limit orders this many percent better than the close instead of
+    /// market orders.
+    #[serde(default)]
+    pub limit_offset_pct: Option<f64>,
+    /// Exits attached to each buy; declared before `params` so that their
+    /// keys are not taken for parameters.
+    #[serde(flatten)]
+    pub exits: ExitConfig,
+    /// The Rhai script a `script` strategy runs.
+    #[serde(default)]
+    pub script: Option<PathBuf>,
+    #[serde(flatten)]
+    pub params: HashMap<String, f64>,
+}
+
+/// An alert rule, scoped like indicators to a set of symbols and intervals.
+#[derive(Debug, Clone, Deserialize)]
+pub struct AlertConfig {
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub symbols: Vec<String>,
+    #[serde(default)]
+    pub intervals: Vec<String>,
+    /// Minimum seconds between two firings with the same dedup key.
+    #[serde(default)]
+    pub cooldown_secs: u64,
+    /// Hysteresis before a fired rule can fire again: the price must move
+    /// back this many percent beyond a `price` threshold, or a
+    /// `percent_change` move fall this many points inside its threshold.
+    #[serde(default)]
+    pub rearm_pct: f64,
+    /// Template for the key that cooldowns are tracked by, using `{rule}`,
+    /// `{symbol}` and `{interval}`. Defaults to `{rule}:{symbol}:{interval}`.
+    #[serde(default)]
+    pub dedup_key: Option<String>,
+    #[serde(flatten)]
+    pub condition: AlertCondition,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum AlertCondition {
+    /// Fires when the close crosses `price` in the given direction.
+    Price {
+        direction: CrossDirection,
+        price: f64,
+    },
+    /// Fires when the price moves more than `threshold` percent, in either
+    /// direction, within `window` (an interval string such as `15m`).
+    PercentChange { window: String, threshold: f64 },
+    /// Fires when the expression in `when` starts to hold, e.g.
+    /// `close > 100000 and (change_pct > 2 or rsi_14 < 30)`.
+    /// `rearm_when` optionally replaces "`when` no longer holds" as the
+    /// condition for firing again.
+    Rule {
+        when: String,
+        #[serde(default)]
+        rearm_when: Option<String>,
+    },
+    /// Fires for every event an indicator raises, optionally narrowed to one
+    /// indicator (by name) and one event, e.g. `volume_spike`.
+    IndicatorEvent {
+        #[serde(default)]
+        indicator: Option<String>,
+        #[serde(default)]
+        event: Option<String>,
+    },
+    /// Fires when the Rhai script at `script` starts to return `true`; it is
+    /// read again whenever the file changes. Needs the `scripting` feature.
+    Script { script: PathBuf },
+    /// Fires when a stablecoin pair's close is more than `threshold_bps`
+    /// basis points away from 1.0. `rearm_pct` is taken in points of
+    /// percent, as for `percent_change`.
+    Depeg { threshold_bps: f64 },
+    /// Fires when a spread series' close, in percent, is more than
+    /// `threshold_pct` either way.
+    Spread { threshold_pct: f64 },
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CrossDirection {
+    Above,
+    Below,
+}
+
+/// A channel fired alerts are delivered to.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum NotifierConfig {
+    Webhook(WebhookConfig),
+    Telegram(TelegramConfig),
+    Discord(DiscordConfig),
+    Slack(SlackConfig),
+    Email(EmailConfig),
+    Desktop(DesktopConfig),
+    Sound(SoundConfig),
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct WebhookConfig {
+    /// What the webhook is called in logs, health and delivery records,
+    /// rather than its URL, which may carry a secret.
+    #[serde(default = "default_webhook_name")]
+    pub name: String,
+    pub url: String,
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+    #[serde(default = "default_retries")]
+    pub retries: u32,
+    /// Also deliver periodic summaries, not just alerts.
+    #[serde(default)]
+    pub summaries: bool,
+    /// Also deliver strategy signals.
+    #[serde(default)]
+    pub signals: bool,
+}
+
+fn default_webhook_name() -> String {
+    "webhook".to_string()
+}
+
+/// A downstream endpoint that candles are POSTed to as they arrive.
+#[derive(Debug, Clone, Deserialize)]
+pub struct PushConfig {
+    pub url: String,
+    /// Symbols to push; empty means every symbol, including ratios.
+    #[serde(default)]
+    pub symbols: Vec<String>,
+    /// Intervals to push; empty means every interval.
+    #[serde(default)]
+    pub intervals: Vec<String>,
+    /// Collect candles for this many seconds and send them together; 0
+    /// sends each one as it comes.
+    #[serde(default)]
+    pub batch_secs: u64,
+    /// Also push updates to candles still in progress, not just closed ones.
+    #[serde(default)]
+    pub include_open: bool,
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+    #[serde(default = "default_retries")]
+    pub retries: u32,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct TelegramConfig {
+    pub bot_token: String,
+    pub chat_id: String,
+    /// The Bot API server, for a self-hosted one; Telegram's by default.
+    #[serde(default = "default_telegram_api_url")]
+    pub api_url: String,
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+    #[serde(default = "default_retries")]
+    pub retries: u32,
+    #[serde(default = "default_true")]
+    pub summaries: bool,
+    /// Also deliver strategy signals.
+    #[serde(default)]
+    pub signals: bool,
+}
+
+fn default_telegram_api_url() -> String {
+    "https://api.telegram.org".to_string()
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct DiscordConfig {
+    pub webhook_url: String,
+    /// Overrides the webhook's default display name.
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+    #[serde(default = "default_retries")]
+    pub retries: u32,
+    #[serde(default = "default_true")]
+    pub summaries: bool,
+    /// Also deliver strategy signals.
+    #[serde(default)]
+    pub signals: bool,
+}
+
+/// Either `webhook_url`, or `bot_token` and `channel` for the Web API.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SlackConfig {
+    #[serde(default)]
+    pub webhook_url: Option<String>,
+    #[serde(default)]
+    pub bot_token: Option<String>,
+    #[serde(default)]
+    pub channel: Option<String>,
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+    #[serde(default = "default_retries")]
+    pub retries: u32,
+    #[serde(default = "default_true")]
+    pub summaries: bool,
+    /// Also deliver strategy signals.
+    #[serde(default)]
+    pub signals: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct EmailConfig {
+    pub host: String,
+    /// Defaults to the standard port for the TLS mode.
+    #[serde(default)]
+    pub port: Option<u16>,
+    #[serde(default)]
+    pub tls: EmailTls,
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub password: Option<String>,
+    pub from: String,
+    pub to: Vec<String>,
+    #[serde(default = "default_email_subject")]
+    pub subject: String,
+    #[serde(default = "default_email_body")]
+    pub body: String,
+    /// Collects alerts into one digest mail per this many seconds; 0 sends
+    /// each alert immediately.
+    #[serde(default = "default_batch_secs")]
+    pub batch_secs: u64,
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+    #[serde(default)]
+    pub summaries: bool,
+    /// Also deliver strategy signals.
+    #[serde(default)]
+    pub signals: bool,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EmailTls {
+    /// Implicit TLS, usually port 465.
+    Tls,
+    /// Upgrade a plain connection with STARTTLS, usually port 587.
+    #[default]
+    Starttls,
+    /// Plain text, for local relays only.
+    None,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct DesktopConfig {
+    #[serde(default = "default_desktop_timeout_ms")]
+    pub timeout_ms: u32,
+    #[serde(default)]
+    pub summaries: bool,
+    /// Also deliver strategy signals.
+    #[serde(default)]
+    pub signals: bool,
+}
+
+fn default_desktop_timeout_ms() -> u32 {
+    10_000
+}
+
+/// Rings the terminal bell, or plays `file` through `player` when a sound
+/// file is configured.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SoundConfig {
+    pub file: Option<PathBuf>,
+    /// Command used to play `file`; defaults to the platform's player.
+    pub player: Option<String>,
+    #[serde(default)]
+    pub summaries: bool,
+    /// Also deliver strategy signals.
+    #[serde(default)]
+    pub signals: bool,
+}
+
+fn default_email_subject() -> String {
+    "[{rule}] {symbol} {message}".to_string()
+}
+
+fn default_email_body() -> String {
+    "{message}\n\nSymbol: {symbol} ({interval})\nPrice: {price}\n\
+     Change: {change_percent}%\nTriggered at: {triggered_at}"
+        .to_string()
+}
+
+fn default_batch_secs() -> u64 {
+    300
+}
+
+/// The queue between the stream tasks and the kline processor.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct QueueConfig {
+    /// Updates held before the backpressure policy applies.
+    pub capacity: usize,
+    pub backpressure: Backpressure,
+}
+
+impl Default for QueueConfig {
+    fn default() -> Self {
+        Self {
+            capacity: 100,
+            backpressure: Backpressure::Block,
+        }
+    }
+}
+
+/// What the stream tasks do when the processor falls behind and the queue
+/// is full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Backpressure {
+    /// Wait for room, which stalls every stream until the processor catches
+    /// up. Nothing is lost.
+    Block,
+    /// Discard the oldest queued update to make room.
+    DropOldest,
+    /// Replace a queued update for the same candle with the newer one, and
+    /// only wait when the queue is full of distinct candles.
+    Coalesce,
+}
+
+/// The local database fired alerts are recorded in.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct StoreConfig {
+    pub path: PathBuf,
+}
+
+impl Default for StoreConfig {
+    fn default() -> Self {
+        Self {
+            path: PathBuf::from("kline_tracker.db"),
+        }
+    }
+}
+
+/// Periodic snapshots of the in-memory state, restored on startup; off
+/// unless enabled.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct SnapshotConfig {
+    pub enabled: bool,
+    pub path: PathBuf,
+    /// Seconds between snapshots. One is also taken on shutdown.
+    pub interval_secs: u64,
+}
+
+impl Default for SnapshotConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            path: PathBuf::from("kline_tracker.snapshot.json"),
+            interval_secs: 60,
+        }
+    }
+}
+
+/// The random-walk market `--synthetic` streams instead of Binance: every
+/// configured symbol on every configured interval, the longer intervals
+/// aggregating the same price path as the shortest.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct SyntheticConfig {
+    /// Starting price by symbol; any other symbol starts at 100.
+    pub prices: HashMap<String, f64>,
+    /// Standard deviation of a shortest-interval candle's return, in percent.
+    pub volatility_pct: f64,
+    /// Mean return of a shortest-interval candle, in percent.
+    pub drift_pct: f64,
+    /// Mean volume of a shortest-interval candle.
+    pub volume: f64,
+    /// Updates sent per shortest-interval candle, the last one closing it.
+    pub updates_per_candle: usize,
+    /// How many times faster than real time candles are generated; 0 for as
+    /// fast as they are processed.
+    pub speed: f64,
+    /// Shortest-interval candles to generate before ending; unlimited if
+    /// unset.
+    pub candles: Option<u64>,
+    /// Seeds the random walk, so a run can be repeated; random if unset.
+    pub seed: Option<u64>,
+}
+
+impl Default for SyntheticConfig {
+    fn default() -> Self {
+        Self {
+            prices: HashMap::new(),
+            volatility_pct: 0.5,
+            drift_pct: 0.0,
+            volume: 100.0,
+            updates_per_candle: 4,
+            speed: 1.0,
+            candles: None,
+            seed: None,
+        }
+    }
+}
+
+/// An amount of a symbol's base asset already held, marked to market in the
+/// summaries.
+#[derive(Debug, Clone, Deserialize)]
+pub struct HoldingConfig {
+    pub symbol: String,
+    pub quantity: f64,
+    /// Average price paid per unit, for unrealized PnL.
+    #[serde(default)]
+    pub cost_basis: Option<f64>,
+}
+
+/// How the holdings' risk is measured.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct PortfolioConfig {
+    /// Interval whose closed candles the holdings' volatility and
+    /// correlations are measured on; the first of `intervals` if unset.
+    pub interval: Option<String>,
+    /// How many of the latest returns to measure them over.
+    pub lookback: usize,
+}
+
+impl Default for PortfolioConfig {
+    fn default() -> Self {
+        Self {
+            interval: None,
+            lookback: 100,
+        }
+    }
+}
+
+/// Simulated trading against live prices, driven by alerts; off unless
+/// enabled.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct PaperConfig {
+    pub enabled: bool,
+    /// Quote currency the account starts with.
+    pub starting_balance: f64,
+    /// Percent of the traded value charged for market orders.
+    pub taker_fee_pct: f64,
+    /// Percent of the traded value charged for limit orders.
+    pub maker_fee_pct: f64,
+    pub fills: FillModelConfig,
+    pub orders: Vec<PaperOrderConfig>,
+    pub grids: Vec<GridConfig>,
+    pub dca: Vec<DcaConfig>,
+}
+
+impl Default for PaperConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            starting_balance: 10_000.0,
+            taker_fee_pct: 0.1,
+            maker_fee_pct: 0.1,
+            fills: FillModelConfig::default(),
+            orders: Vec::new(),
+            grids: Vec::new(),
+            dca: Vec::new(),
+        }
+    }
+}
+
+/// How simulated market orders fill, in paper trading, replays and
+/// backtests; each cost is off unless set. Limit orders fill at their price.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct FillModelConfig {
+    /// Bid-ask spread in basis points, half of which each market order pays.
+    pub spread_bps: f64,
+    /// Slippage every market order pays, in basis points.
+    pub slippage_bps: f64,
+    /// Further slippage in basis points per percent of the candle's volume
+    /// an order takes.
+    pub volume_impact_bps: f64,
+    /// Milliseconds between placing a market order and its fill, at the
+    /// first price after.
+    pub latency_ms: u64,
+}
+
+/// Real orders on Binance's spot market, signed with an API key; off unless
+/// enabled, and only in builds with the `live` feature.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct LiveConfig {
+    pub enabled: bool,
+    /// Read from `BINANCE_API_KEY` when empty.
+    pub api_key: String,
+    /// Read from `BINANCE_API_SECRET` when empty.
+    pub api_secret: String,
+    /// Point both URLs at the spot testnet to trade without real funds.
+    pub rest_url: String,
+    /// Base of the user-data stream's WebSocket URL.
+    pub stream_url: String,
+    /// Milliseconds a signed request stays valid after it is made.
+    pub recv_window_ms: u64,
+    pub timeout_secs: u64,
+    /// Names of the strategies whose signals place real orders.
+    pub strategies: Vec<String>,
+    /// Follow order updates over the user-data stream.
+    pub user_data_stream: bool,
+}
+
+impl Default for LiveConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            api_key: String::new(),
+            api_secret: String::new(),
+            rest_url: "https://api.binance.com".to_string(),
+            stream_url: "wss://stream.binance.com:9443/ws".to_string(),
+            recv_window_ms: 5000,
+            timeout_secs: default_timeout_secs(),
+            strategies: Vec::new(),
+            user_data_stream: true,
+        }
+    }
+}
+
+/// Limits every paper and live order is checked against before it is
+/// placed; each is off unless set.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct RiskConfig {
+    /// Refuse every new order from the start; `halt` and `resume` on the
+    /// control socket switch it at runtime.
+    pub kill_switch: bool,
+    /// Most a position in one symbol may be worth after a buy, in quote
+    /// currency.
+    pub max_position_value: Option<f64>,
+    /// Per-symbol overrides of `max_position_value`.
+    pub position_limits: HashMap<String, f64>,
+    /// Stop buying once the realized loss since midnight UTC reaches this
+    /// much quote currency.
+    pub max_daily_loss: Option<f64>,
+    /// Most orders placed in any 60 seconds.
+    pub max_orders_per_minute: Option<u32>,
+}
+
+/// An order placed whenever the alert rule `rule` fires, for the alert's
+/// symbol.
+#[derive(Debug, Clone, Deserialize)]
+pub struct PaperOrderConfig {
+    pub rule: String,
+    pub side: Side,
+    /// Base quantity to trade.
+    #[serde(default)]
+    pub quantity: Option<f64>,
+    /// Quote value to trade, converted at the alert's price. A sell with
+    /// neither this nor `quantity` closes the whole position.
+    #[serde(default)]
+    pub quote_amount: Option<f64>,
+    /// Place a limit order this many percent better than the alert's price
+    /// instead of a market order.
+    #[serde(default)]
+    pub limit_offset_pct: Option<f64>,
+    #[serde(flatten)]
+    pub exits: ExitConfig,
+}
+
+/// A grid bot trading one symbol on the paper account: a buy limit order
+/// rests at every level below the price, and each one that fills is sold
+/// at the level above, which then buys again at the level below.
+#[derive(Debug, Clone, Deserialize)]
+pub struct GridConfig {
+    /// Defaults to `grid <symbol>`.
+    #[serde(default)]
+    pub name: Option<String>,
+    pub symbol: String,
+    /// Price of the lowest level.
+    pub lower: f64,
+    /// Price of the highest level.
+    pub upper: f64,
+    /// Price levels, evenly spaced from `lower` to `upper` inclusive.
+    pub levels: usize,
+    /// Base quantity each level buys and sells.
+    pub quantity: f64,
+}
+
+impl GridConfig {
+    pub fn name(&self) -> String {
+        self.name
+            .clone()
+            .unwrap_or_else(|| format!("grid {}", self.symbol))
+    }
+}
+
+/// Dollar-cost averaging on the paper account: a market buy of a fixed
+/// quote amount of one symbol at its first price, then every
+/// `every_secs`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct DcaConfig {
+    /// Defaults to `dca <symbol>`.
+    #[serde(default)]
+    pub name: Option<String>,
+    pub symbol: String,
+    /// Quote value of each buy.
+    pub quote_amount: f64,
+    /// Seconds between buys, by the time of the prices.
+    pub every_secs: u64,
+}
+
+impl DcaConfig {
+    pub fn name(&self) -> String {
+        self.name
+            .clone()
+            .unwrap_or_else(|| format!("dca {}", self.symbol))
+    }
+}
+
+/// Exits attached to what a paper buy fills, each off unless set: whichever
+/// is reached first sells the quantity bought.
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+pub struct ExitConfig {
+    /// Sell once the price falls this many percent below the fill.
+    #[serde(default)]
+    pub stop_loss_pct: Option<f64>,
+    /// Sell once the price rises this many percent above the fill.
+    #[serde(default)]
+    pub take_profit_pct: Option<f64>,
+    /// Sell once the price falls this many percent below its highest since
+    /// the fill.
+    #[serde(default)]
+    pub trailing_stop_pct: Option<f64>,
+}
+
+impl ExitConfig {
+    pub fn is_empty(&self) -> bool {
+        self.stop_loss_pct.is_none()
+            && self.take_profit_pct.is_none()
+            && self.trailing_stop_pct.is_none()
+    }
+
+    /// Checks the percentages, naming the order as `owner` in errors.
+    fn validate(&self, owner: &str) -> Result<()> {
+        for (key, pct) in [
+            ("stop_loss_pct", self.stop_loss_pct),
+            ("trailing_stop_pct", self.trailing_stop_pct),
+        ] {
+            if pct.is_some_and(|pct| !(pct > 0.0 && pct < 100.0)) {
+                bail!("{} has a {} outside 0 to 100", owner, key);
+            }
+        }
+        if self
+            .take_profit_pct
+            .is_some_and(|pct| !(pct.is_finite() && pct > 0.0))
+        {
+            bail!("{} needs a positive take_profit_pct", owner);
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Side {
+    Buy,
+    Sell,
+}
+
+/// The HTTP API, off unless enabled.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ServerConfig {
+    pub enabled: bool,
+    pub listen: SocketAddr,
+    /// Serve HTTPS with this certificate instead of plain HTTP.
+    pub tls: Option<TlsConfig>,
+    /// Origins allowed to call the API from a browser, or `*` for any.
+    pub cors_origins: Vec<String>,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            listen: SocketAddr::from(([127, 0, 0, 1], 8080)),
+            tls: None,
+            cors_origins: Vec::new(),
+        }
+    }
+}
+
+/// PEM files for serving HTTPS.
+#[derive(Debug, Clone, Deserialize)]
+pub struct TlsConfig {
+    /// The certificate chain, leaf first.
+    pub cert: PathBuf,
+    pub key: PathBuf,
+}
+
+/// The gRPC streaming API, off unless enabled.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct GrpcConfig {
+    pub enabled: bool,
+    pub listen: SocketAddr,
+}
+
+impl Default for GrpcConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            listen: SocketAddr::from(([127, 0, 0, 1], 50051)),
+        }
+    }
+}
+
+/// The local control socket, off unless enabled.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ControlConfig {
+    pub enabled: bool,
+    pub path: PathBuf,
+}
+
+impl Default for ControlConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            path: PathBuf::from("kline_tracker.sock"),
+        }
+    }
+}
+
+/// API tokens for the HTTP and gRPC APIs. Without any, both are open to
+/// anyone who can reach them.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct AuthConfig {
+    pub tokens: Vec<ApiTokenConfig>,
+}
+
+/// One client allowed to use the APIs.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ApiTokenConfig {
+    /// Identifies the client in logs.
+    pub name: String,
+    /// Sent as a bearer token, an `X-API-Key` header or a `token` query
+    /// parameter.
+    pub token: String,
+    /// Most live subscriptions the client may hold at once, across
+    /// WebSocket, SSE, GraphQL and gRPC; unlimited if unset.
+    #[serde(default)]
+    pub max_subscriptions: Option<usize>,
+}
+
+/// How streams that fail or are closed by Binance are restarted.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct RestartConfig {
+    /// Seconds before the first restart, doubled with each consecutive
+    /// failure up to `max_backoff_secs`.
+    pub backoff_secs: u64,
+    pub max_backoff_secs: u64,
+    /// A connection that stays up this long is healthy again, which resets
+    /// the backoff.
+    pub stable_secs: u64,
+    /// Consecutive failures of one stream before escalating.
+    pub max_failures: u32,
+    pub escalation: Escalation,
+}
+
+impl Default for RestartConfig {
+    fn default() -> Self {
+        Self {
+            backoff_secs: 1,
+            max_backoff_secs: 60,
+            stable_secs: 60,
+            max_failures: 5,
+            escalation: Escalation::Alert,
+        }
+    }
+}
+
+/// What happens once a stream keeps failing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Escalation {
+    /// Open an operational incident, resolved once the stream is stable,
+    /// and keep restarting it.
+    Alert,
+    /// Stop the tracker with an error, for a service manager to restart.
+    Exit,
+}
+
+/// Bounds the memory taken by candle history.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct CacheConfig {
+    /// Megabytes the history buffers of all series may take up together
+    /// before the least recently updated series are evicted; unbounded if
+    /// unset.
+    pub budget_mb: Option<usize>,
+}
+
+impl CacheConfig {
+    pub fn budget_bytes(&self) -> Option<usize> {
+        self.budget_mb.map(|mb| mb.saturating_mul(1024 * 1024))
+    }
+}
+
+/// Packs streams into shared connections instead of opening one per
+/// stream, within Binance's limits.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct MultiplexConfig {
+    pub enabled: bool,
+    /// Streams carried by one connection; Binance allows up to 1024.
+    pub streams_per_connection: usize,
+    /// Subscription requests sent per connection per second; Binance
+    /// disconnects clients sending more than 5 messages a second.
+    pub messages_per_sec: u32,
+}
+
+impl Default for MultiplexConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            streams_per_connection: 200,
+            messages_per_sec: 4,
+        }
+    }
+}
+
+/// The tokio runtime the tracker runs on. Only read at startup.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct RuntimeConfig {
+    pub flavor: RuntimeFlavor,
+    /// Threads running tasks under `multi_thread`; one per CPU core if
+    /// unset.
+    pub worker_threads: Option<usize>,
+    /// Most threads kept for blocking work such as writing to the store;
+    /// tokio's default of 512 if unset.
+    pub max_blocking_threads: Option<usize>,
+}
+
+impl Default for RuntimeConfig {
+    fn default() -> Self {
+        Self {
+            flavor: RuntimeFlavor::MultiThread,
+            worker_threads: None,
+            max_blocking_threads: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RuntimeFlavor {
+    /// Every task on the main thread, for small devices and few streams.
+    CurrentThread,
+    /// Tasks spread over a pool of worker threads.
+    MultiThread,
+}
+
+/// Operational incidents about the tracker itself.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct OpsConfig {
+    /// A stream with no message for this many seconds is reported as down.
+    pub stream_down_secs: u64,
+    /// Report missing candles between consecutive updates of a stream.
+    pub detect_gaps: bool,
+    /// Raise an incident for every panic caught in a stream or sink task.
+    pub panic_incidents: bool,
+    pub pagers: Vec<PagerConfig>,
+}
+
+impl Default for OpsConfig {
+    fn default() -> Self {
+        Self {
+            stream_down_secs: 300,
+            detect_gaps: true,
+            panic_incidents: true,
+            pagers: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum PagerConfig {
+    Pagerduty(PagerDutyConfig),
+    Opsgenie(OpsgenieConfig),
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PagerDutyConfig {
+    /// Integration key of an Events API v2 service.
+    pub routing_key: String,
+    #[serde(default = "default_pager_source")]
+    pub source: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct OpsgenieConfig {
+    pub api_key: String,
+    /// Use the EU instance of the API.
+    #[serde(default)]
+    pub eu: bool,
+}
+
+fn default_pager_source() -> String {
+    "crypto_kline_tracker".to_string()
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_timeout_secs() -> u64 {
+    10
+}
+
+fn default_retries() -> u32 {
+    3
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            stream_url: crate::STREAM_URL.to_string(),
+            symbols: ["btcusdt", "ethusdt", "bnbusdt", "adausdt", "dogeusdt"]
+                .iter()
+                .map(|s| s.to_string())
+                .collect(),
+            intervals: ["1m", "5m", "15m"].iter().map(|s| s.to_string()).collect(),
+            history_size: 500,
+            cache: CacheConfig::default(),
+            summary_interval_secs: 60,
+            digest: DigestConfig::default(),
+            workers: 1,
+            shutdown_grace_secs: 10,
+            ratios: Vec::new(),
+            depeg: DepegConfig::default(),
+            open_interest: OpenInterestConfig::default(),
+            venues: Vec::new(),
+            arbitrage: ArbitrageConfig::default(),
+            index: IndexConfig::default(),
+            listings: ListingsConfig::default(),
+            halts: HaltsConfig::default(),
+            precision: PrecisionConfig::default(),
+            indicators: Vec::new(),
+            alerts: Vec::new(),
+            strategies: Vec::new(),
+            notifiers: Vec::new(),
+            push: Vec::new(),
+            queue: QueueConfig::default(),
+            restart: RestartConfig::default(),
+            multiplex: MultiplexConfig::default(),
+            ops: OpsConfig::default(),
+            store: StoreConfig::default(),
+            snapshot: SnapshotConfig::default(),
+            paper: PaperConfig::default(),
+            holdings: Vec::new(),
+            portfolio: PortfolioConfig::default(),
+            live: LiveConfig::default(),
+            risk: RiskConfig::default(),
+            server: ServerConfig::default(),
+            grpc: GrpcConfig::default(),
+            control: ControlConfig::default(),
+            auth: AuthConfig::default(),
+            runtime: RuntimeConfig::default(),
+            synthetic: SyntheticConfig::default(),
+        }
+    }
+}
+
+impl Config {
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        let Some(path) = path else {
+            return Ok(Self::default());
+        };
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config file {}", path.display()))?;
+        let mut config: Config = toml::from_str(&contents)
+            .with_context(|| format!("Failed to parse config file {}", path.display()))?;
+        config.normalize();
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn validate(&self) -> Result<()> {
+        if !(self.stream_url.starts_with("ws://") || self.stream_url.starts_with("wss://")) {
+            bail!("stream_url must be a ws:// or wss:// URL");
+        }
+        for alert in &self.alerts {
+            if let AlertCondition::PercentChange { window, .. } = &alert.condition {
+                if interval_duration(window).is_none() {
+                    bail!(
+                        "Alert '{}' has an invalid window '{}'",
+                        alert.name(),
+                        window
+                    );
+                }
+            }
+            if let AlertCondition::Script { .. } = &alert.condition {
+                if !cfg!(feature = "scripting") {
+                    bail!("Script alerts need the `scripting` feature");
+                }
+            }
+            if let AlertCondition::Depeg { threshold_bps } = &alert.condition {
+                if *threshold_bps <= 0.0 {
+                    bail!("Alert '{}' needs a positive threshold_bps", alert.name());
+                }
+            }
+            if let AlertCondition::Spread { threshold_pct } = &alert.condition {
+                if *threshold_pct <= 0.0 {
+                    bail!("Alert '{}' needs a positive threshold_pct", alert.name());
+                }
+            }
+        }
+        if self.depeg.rearm_bps < 0.0 {
+            bail!("depeg.rearm_bps cannot be negative");
+        }
+        if self.open_interest.interval_secs == 0 {
+            bail!("open_interest.interval_secs must be at least 1");
+        }
+        if self.digest.interval_secs == 0 {
+            bail!("digest.interval_secs must be at least 1");
+        }
+        if self.listings.interval_secs == 0 {
+            bail!("listings.interval_secs must be at least 1");
+        }
+        if self.halts.interval_secs == 0 {
+            bail!("halts.interval_secs must be at least 1");
+        }
+        for notifier in &self.notifiers {
+            if let NotifierConfig::Email(email) = notifier {
+                if email.to.is_empty() {
+                    bail!("The email notifier needs at least one address in `to`");
+                }
+                if email.username.is_some() != email.password.is_some() {
+                    bail!("The email notifier needs both a username and a password, or neither");
+                }
+            }
+        }
+        let mut venues = HashSet::from([crate::arbitrage::PRIMARY_VENUE]);
+        for venue in &self.venues {
+            let named = !venue.name.is_empty()
+                && venue
+                    .name
+                    .chars()
+                    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit());
+            if !named {
+                bail!(
+                    "Venue name '{}' must be lowercase letters and digits",
+                    venue.name
+                );
+            }
+            if venue.name == crate::index::INDEX_PREFIX {
+                bail!("Venue name '{}' is reserved", venue.name);
+            }
+            if !venues.insert(&venue.name) {
+                bail!("Venue '{}' is configured more than once", venue.name);
+            }
+            if !(venue.stream_url.starts_with("ws://") || venue.stream_url.starts_with("wss://")) {
+                bail!(
+                    "The stream_url of venue '{}' must be a ws:// or wss:// URL",
+                    venue.name
+                );
+            }
+        }
+        if self.arbitrage.enabled && self.venues.is_empty() {
+            bail!("arbitrage needs at least one venue besides Binance");
+        }
+        if self.index.enabled && !(1..=self.venues.len() + 1).contains(&self.index.min_venues) {
+            bail!(
+                "index.min_venues must be between 1 and the {} venues, Binance included",
+                self.venues.len() + 1
+            );
+        }
+        for strategy in &self.strategies {
+            if strategy.kind == "script" && !cfg!(feature = "scripting") {
+                bail!("Script strategies need the `scripting` feature");
+            }
+        }
+        if self.workers == 0 {
+            bail!("At least one worker is needed");
+        }
+        if self.queue.capacity == 0 {
+            bail!("The queue capacity must be at least 1");
+        }
+        if !(1..=1024).contains(&self.multiplex.streams_per_connection) {
+            bail!("multiplex.streams_per_connection must be between 1 and 1024");
+        }
+        if !(1..=5).contains(&self.multiplex.messages_per_sec) {
+            bail!("multiplex.messages_per_sec must be between 1 and 5");
+        }
+        if self.restart.max_failures == 0 {
+            bail!("restart.max_failures must be at least 1");
+        }
+        if self.snapshot.interval_secs == 0 {
+            bail!("snapshot.interval_secs must be at least 1");
+        }
+        let synthetic = &self.synthetic;
+        if synthetic.volatility_pct < 0.0 || synthetic.volume < 0.0 || synthetic.speed < 0.0 {
+            bail!("synthetic.volatility_pct, volume and speed cannot be negative");
+        }
+        if synthetic.updates_per_candle == 0 {
+            bail!("synthetic.updates_per_candle must be at least 1");
+        }
+        if let Some((symbol, _)) = synthetic.prices.iter().find(|(_, price)| **price <= 0.0) {
+            bail!("synthetic.prices.{} must be positive", symbol);
+        }
+        if self.paper.starting_balance < 0.0
+            || self.paper.taker_fee_pct < 0.0
+            || self.paper.maker_fee_pct < 0.0
+        {
+            bail!("paper.starting_balance and the paper fees cannot be negative");
+        }
+        let fills = &self.paper.fills;
+        if [
+            fills.spread_bps,
+            fills.slippage_bps,
+            fills.volume_impact_bps,
+        ]
+        .iter()
+        .any(|cost| !cost.is_finite() || *cost < 0.0)
+        {
+            bail!("paper.fills costs cannot be negative");
+        }
+        for order in &self.paper.orders {
+            if order.quantity.is_some() && order.quote_amount.is_some() {
+                bail!(
+                    "Paper order for '{}' sets both quantity and quote_amount",
+                    order.rule
+                );
+            }
+            if order
+                .quantity
+                .or(order.quote_amount)
+                .is_some_and(|n| n <= 0.0)
+            {
+                bail!(
+                    "Paper order for '{}' must trade a positive amount",
+                    order.rule
+                );
+            }
+            if order.side == Side::Buy && order.quantity.or(order.quote_amount).is_none() {
+                bail!(
+                    "Paper buy order for '{}' needs a quantity or quote_amount",
+                    order.rule
+                );
+            }
+            if order
+                .limit_offset_pct
+                .is_some_and(|pct| !(0.0..100.0).contains(&pct))
+            {
+                bail!(
+                    "Paper order for '{}' has a limit_offset_pct outside 0 to 100",
+                    order.rule
+                );
+            }
+            if order.side == Side::Sell && !order.exits.is_empty() {
+                bail!(
+                    "Paper order for '{}' sells, so it cannot take exits",
+                    order.rule
+                );
+            }
+            order
+                .exits
+                .validate(&format!("Paper order for '{}'", order.rule))?;
+        }
+        let mut grids = HashSet::new();
+        for grid in &self.paper.grids {
+            let name = grid.name();
+            if !grids.insert(name.clone()) {
+                bail!("Grid name '{}' is used more than once", name);
+            }
+            if grid.symbol.is_empty() {
+                bail!("Grid '{}' needs a symbol", name);
+            }
+            if !(grid.lower > 0.0 && grid.upper > grid.lower && grid.upper.is_finite()) {
+                bail!("Grid '{}' needs 0 < lower < upper", name);
+            }
+            if grid.levels < 2 {
+                bail!("Grid '{}' needs at least 2 levels", name);
+            }
+            if !(grid.quantity > 0.0 && grid.quantity.is_finite()) {
+                bail!("Grid '{}' must trade a positive quantity", name);
+            }
+        }
+        let mut plans = HashSet::new();
+        for dca in &self.paper.dca {
+            let name = dca.name();
+            if !plans.insert(name.clone()) {
+                bail!("DCA name '{}' is used more than once", name);
+            }
+            if dca.symbol.is_empty() {
+                bail!("DCA '{}' needs a symbol", name);
+            }
+            if !(dca.quote_amount > 0.0 && dca.quote_amount.is_finite()) {
+                bail!("DCA '{}' must buy a positive quote_amount", name);
+            }
+            if dca.every_secs == 0 {
+                bail!("DCA '{}' needs every_secs above zero", name);
+            }
+        }
+        let mut held = HashSet::new();
+        for holding in &self.holdings {
+            if !held.insert(&holding.symbol) {
+                bail!("Holding of {} is declared more than once", holding.symbol);
+            }
+            if !holding.quantity.is_finite() || holding.quantity <= 0.0 {
+                bail!(
+                    "Holding of {} must have a positive quantity",
+                    holding.symbol
+                );
+            }
+            if holding.cost_basis.is_some_and(|price| price < 0.0) {
+                bail!("Holding of {} has a negative cost_basis", holding.symbol);
+            }
+        }
+        if let Some(interval) = &self.portfolio.interval {
+            if !self.intervals.contains(interval) {
+                bail!("portfolio.interval '{}' is not in intervals", interval);
+            }
+        }
+        if self.portfolio.lookback < 2 {
+            bail!("portfolio.lookback must be at least 2");
+        }
+        let mut strategy_names = HashSet::new();
+        for strategy in &self.strategies {
+            if !strategy_names.insert(strategy.name()) {
+                bail!(
+                    "Strategy name '{}' is used more than once; give each a unique name",
+                    strategy.name()
+                );
+            }
+            if strategy.quantity.is_some() == strategy.quote_amount.is_some() {
+                bail!(
+                    "Strategy '{}' needs exactly one of quantity and quote_amount",
+                    strategy.name()
+                );
+            }
+            if strategy
+                .quantity
+                .or(strategy.quote_amount)
+                .is_some_and(|n| n <= 0.0)
+            {
+                bail!(
+                    "Strategy '{}' must trade a positive amount",
+                    strategy.name()
+                );
+            }
+            if strategy
+                .limit_offset_pct
+                .is_some_and(|pct| !(0.0..100.0).contains(&pct))
+            {
+                bail!(
+                    "Strategy '{}' has a limit_offset_pct outside 0 to 100",
+                    strategy.name()
+                );
+            }
+            strategy
+                .exits
+                .validate(&format!("Strategy '{}'", strategy.name()))?;
+        }
+        if self.live.enabled && !cfg!(feature = "live") {
+            bail!("Live trading needs the `live` feature");
+        }
+        if !(1..=60_000).contains(&self.live.recv_window_ms) {
+            bail!("live.recv_window_ms must be between 1 and 60000");
+        }
+        for name in &self.live.strategies {
+            if !self
+                .strategies
+                .iter()
+                .any(|strategy| &strategy.name() == name)
+            {
+                bail!("live.strategies names unknown strategy '{}'", name);
+            }
+        }
+        let risk = &self.risk;
+        if risk
+            .max_position_value
+            .iter()
+            .chain(risk.position_limits.values())
+            .chain(&risk.max_daily_loss)
+            .any(|limit| *limit <= 0.0)
+        {
+            bail!("Risk limits must be positive");
+        }
+        if risk.max_orders_per_minute == Some(0) {
+            bail!("risk.max_orders_per_minute must be at least 1");
+        }
+        if self.runtime.worker_threads == Some(0) {
+            bail!("runtime.worker_threads must be at least 1");
+        }
+        if self.runtime.max_blocking_threads == Some(0) {
+            bail!("runtime.max_blocking_threads must be at least 1");
+        }
+        if self.server.enabled && !cfg!(feature = "server") {
+            bail!("The HTTP API needs the `server` feature");
+        }
+        if self.grpc.enabled && !cfg!(feature = "server") {
+            bail!("The gRPC API needs the `server` feature");
+        }
+        let mut tokens = HashSet::new();
+        for token in &self.auth.tokens {
+            if token.token.is_empty() {
+                bail!("API token '{}' is empty", token.name);
+            }
+            if !tokens.insert(&token.token) {
+                bail!("API token '{}' is used by more than one client", token.name);
+            }
+        }
+        Ok(())
+    }
+
+    fn normalize(&mut self) {
+        for symbol in &mut self.symbols {
+            *symbol = symbol.to_lowercase();
+        }
+        for ratio in &mut self.ratios {
+            ratio.base = ratio.base.to_lowercase();
+            ratio.quote = ratio.quote.to_lowercase();
+            // Both legs must be streamed for the ratio to be computed.
+            for leg in [&ratio.base, &ratio.quote] {
+                if !self.symbols.contains(leg) {
+                    self.symbols.push(leg.clone());
+                }
+            }
+        }
+        if self.depeg.enabled {
+            for symbol in &mut self.depeg.symbols {
+                *symbol = symbol.to_lowercase();
+                if !self.symbols.contains(symbol) {
+                    self.symbols.push(symbol.clone());
+                }
+            }
+            self.alerts.push(AlertConfig {
+                name: Some("depeg".to_string()),
+                symbols: self.depeg.symbols.clone(),
+                intervals: Vec::new(),
+                cooldown_secs: self.depeg.cooldown_secs,
+                rearm_pct: self.depeg.rearm_bps / 100.0,
+                dedup_key: None,
+                condition: AlertCondition::Depeg {
+                    threshold_bps: self.depeg.threshold_bps,
+                },
+            });
+        }
+        for symbol in &mut self.open_interest.symbols {
+            *symbol = symbol.to_lowercase();
+        }
+        for asset in &mut self.listings.quote_assets {
+            *asset = asset.to_uppercase();
+        }
+        for venue in &mut self.venues {
+            venue.name = venue.name.to_lowercase();
+        }
+        if self.arbitrage.enabled {
+            let arbitrage = &mut self.arbitrage;
+            if arbitrage.symbols.is_empty() {
+                arbitrage.symbols = self.symbols.clone();
+            }
+            for symbol in &mut arbitrage.symbols {
+                *symbol = symbol.to_lowercase();
+                if !self.symbols.contains(symbol) {
+                    self.symbols.push(symbol.clone());
+                }
+            }
+            for (first, second, fees) in crate::arbitrage::venue_pairs(arbitrage, &self.venues) {
+                self.alerts.push(AlertConfig {
+                    name: Some("arbitrage".to_string()),
+                    symbols: arbitrage
+                        .symbols
+                        .iter()
+                        .map(|symbol| crate::arbitrage::spread_name(first, second, symbol))
+                        .collect(),
+                    intervals: Vec::new(),
+                    cooldown_secs: arbitrage.cooldown_secs,
+                    rearm_pct: 0.0,
+                    dedup_key: None,
+                    condition: AlertCondition::Spread {
+                        threshold_pct: fees + arbitrage.threshold_pct,
+                    },
+                });
+            }
+        }
+        if self.index.enabled {
+            let index = &mut self.index;
+            if index.symbols.is_empty() {
+                index.symbols = self.symbols.clone();
+            }
+            for symbol in &mut index.symbols {
+                *symbol = symbol.to_lowercase();
+                if !self.symbols.contains(symbol) {
+                    self.symbols.push(symbol.clone());
+                }
+            }
+        }
+        self.risk.position_limits = std::mem::take(&mut self.risk.position_limits)
+            .into_iter()
+            .map(|(symbol, limit)| (symbol.to_lowercase(), limit))
+            .collect();
+        self.synthetic.prices = std::mem::take(&mut self.synthetic.prices)
+            .into_iter()
+            .map(|(symbol, price)| (symbol.to_lowercase(), price))
+            .collect();
+        for holding in &mut self.holdings {
+            holding.symbol = holding.symbol.to_lowercase();
+            // A holding is marked with its symbol's latest price.
+            if !self.symbols.contains(&holding.symbol) {
+                self.symbols.push(holding.symbol.clone());
+            }
+        }
+        for alert in &mut self.alerts {
+            for symbol in &mut alert.symbols {
+                *symbol = symbol.to_lowercase();
+            }
+        }
+        for strategy in &mut self.strategies {
+            strategy.kind = strategy.kind.to_lowercase();
+            for symbol in &mut strategy.symbols {
+                *symbol = symbol.to_lowercase();
+            }
+        }
+        for push in &mut self.push {
+            for symbol in &mut push.symbols {
+                *symbol = symbol.to_lowercase();
+            }
+        }
+        for indicator in &mut self.indicators {
+            indicator.kind = indicator.kind.to_lowercase();
+            for symbol in &mut indicator.symbols {
+                *symbol = symbol.to_lowercase();
+            }
+        }
+    }
+}
+
+impl RatioConfig {
+    pub fn name(&self) -> String {
+        format!("{}/{}", self.base, self.quote)
+    }
+}
+
+impl IndicatorConfig {
+    pub fn name(&self) -> String {
+        if let Some(name) = &self.name {
+            return name.clone();
+        }
+        let mut params: Vec<_> = self.params.iter().collect();
+        params.sort_by(|a, b| a.0.cmp(b.0));
+        params
+            .into_iter()
+            .fold(self.kind.clone(), |name, (_, value)| {
+                format!("{}_{}", name, value)
+            })
+    }
+
+    /// Reads a numeric parameter, falling back to `default` when unset.
+    pub fn param(&self, key: &str, default: f64) -> f64 {
+        self.params.get(key).copied().unwrap_or(default)
+    }
+
+    /// Reads a period-like parameter as a count of at least one.
+    pub fn period(&self, key: &str, default: usize) -> usize {
+        (self.param(key, default as f64) as usize).max(1)
+    }
+
+    pub fn applies_to(&self, symbol: &str, interval: &str) -> bool {
+        (self.symbols.is_empty() || self.symbols.iter().any(|s| s == symbol))
+            && (self.intervals.is_empty() || self.intervals.iter().any(|i| i == interval))
+    }
+}
+
+impl StrategyConfig {
+    pub fn name(&self) -> String {
+        self.name.clone().unwrap_or_else(|| self.kind.clone())
+    }
+
+    /// Reads a numeric parameter, falling back to `default` when unset.
+    pub fn param(&self, key: &str, default: f64) -> f64 {
+        self.params.get(key).copied().unwrap_or(default)
+    }
+
+    /// Reads a period-like parameter as a count of at least one.
+    pub fn period(&self, key: &str, default: usize) -> usize {
+        (self.param(key, default as f64) as usize).max(1)
+    }
+
+    pub fn applies_to(&self, symbol: &str, interval: &str) -> bool {
+        (self.symbols.is_empty() || self.symbols.iter().any(|s| s == symbol))
+            && (self.intervals.is_empty() || self.intervals.iter().any(|i| i == interval))
+    }
+
+    /// The order a signal to `side` places: a buy of the configured amount
+    /// with the configured exits, or a sale of the whole position.
+    pub fn order(&self, side: Side) -> PaperOrderConfig {
+        let buying = side == Side::Buy;
+        PaperOrderConfig {
+            rule: self.name(),
+            side,
+            quantity: self.quantity.filter(|_| buying),
+            quote_amount: self.quote_amount.filter(|_| buying),
+            limit_offset_pct: self.limit_offset_pct,
+            exits: if buying {
+                self.exits
+            } else {
+                ExitConfig::default()
+            },
+        }
+    }
+}
+
+impl AlertConfig {
+    pub fn name(&self) -> String {
+        if let Some(name) = &self.name {
+            return name.clone();
+        }
+        match &self.condition {
+            AlertCondition::Price { direction, price } => format!("price {} {}", direction, price),
+            AlertCondition::PercentChange { window, threshold } => {
+                format!("move {}% in {}", threshold, window)
+            }
+            AlertCondition::Rule { when, .. } => when.clone(),
+            AlertCondition::Script { script } => script.display().to_string(),
+            AlertCondition::Depeg { threshold_bps } => format!("depeg {} bps", threshold_bps),
+            AlertCondition::Spread { threshold_pct } => format!("spread {}%", threshold_pct),
+            AlertCondition::IndicatorEvent { indicator, event } => format!(
+                "{} {}",
+                indicator.as_deref().unwrap_or("any indicator"),
+                event.as_deref().unwrap_or("event")
+            ),
+        }
+    }
+
+    pub fn applies_to(&self, symbol: &str, interval: &str) -> bool {
+        (self.symbols.is_empty() || self.symbols.iter().any(|s| s == symbol))
+            && (self.intervals.is_empty() || self.intervals.iter().any(|i| i == interval))
+    }
+
+    pub fn dedup_key(&self, symbol: &str, interval: &str) -> String {
+        self.dedup_key
+            .as_deref()
+            .unwrap_or("{rule}:{symbol}:{interval}")
+            .replace("{rule}", &self.name())
+            .replace("{symbol}", symbol)
+            .replace("{interval}", interval)
+    }
+}
+
+impl std::fmt::Display for Side {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Side::Buy => "buy",
+            Side::Sell => "sell",
+        })
+    }
+}
+
+impl std::fmt::Display for CrossDirection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            CrossDirection::Above => "above",
+            CrossDirection::Below => "below",
+        })
+    }
+}
@@ -0,0 +1,269 @@
+//! A local Unix socket for administering a running tracker. Each line sent is
+//! one command; each reply is one line of JSON.
+
+use crate::actor::Supervisor;
+use crate::config::Config;
+use crate::config::{ControlConfig, Side};
+use crate::health::Health;
+use crate::interval::interval_duration;
+use crate::live::{self, Amount, Live};
+use crate::metrics::Metrics;
+use crate::paper::Paper;
+use crate::risk::KillSwitch;
+use crate::sinks::Sinks;
+use crate::state::SharedState;
+use crate::streams::Streams;
+use anyhow::{anyhow, bail, Result};
+use futures_util::future::join_all;
+use serde_json::{json, Value};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::sync::Notify;
+use tracing::{debug, info, warn};
+
+/// What control commands act on.
+#[derive(Clone)]
+pub struct Control {
+    pub streams: Streams,
+    pub state: SharedState,
+    pub health: Health,
+    pub metrics: Metrics,
+    pub supervisor: Supervisor,
+    pub sinks: Sinks,
+    pub paper: Paper,
+    /// The live trading account, if live trading is enabled.
+    pub live: Option<Live>,
+    /// Stops paper and live trading from placing new orders.
+    pub kill_switch: KillSwitch,
+    /// The config file `reload` reads, if the tracker was started with one.
+    pub config_path: Option<PathBuf>,
+    /// Notified when a client asks the tracker to shut down.
+    pub shutdown: Arc<Notify>,
+}
+
+/// Serves the control socket until the listener fails.
+#[cfg(unix)]
+pub async fn serve(config: &ControlConfig, control: Control) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::UnixListener;
+
+    // A socket file left behind by an earlier run would make binding fail.
+    if config.path.exists() {
+        std::fs::remove_file(&config.path)?;
+    }
+    let listener = UnixListener::bind(&config.path)?;
+    // Anyone who can connect can place live orders.
+    std::fs::set_permissions(&config.path, std::fs::Permissions::from_mode(0o600))?;
+    info!("Control socket listening on {}", config.path.display());
+    loop {
+        let (socket, _) = listener.accept().await?;
+        let control = control.clone();
+        tokio::spawn(async move {
+            let (read, mut write) = socket.into_split();
+            let mut lines = BufReader::new(read).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let reply = match control.execute(&line).await {
+                    Ok(reply) => reply,
+                    Err(e) => json!({ "ok": false, "error": e.to_string() }),
+                };
+                if write
+                    .write_all(format!("{}\n", reply).as_bytes())
+                    .await
+                    .is_err()
+                {
+                    break;
+                }
+            }
+            debug!("Control client disconnected");
+        });
+    }
+}
+
+#[cfg(not(unix))]
+pub async fn serve(_config: &ControlConfig, _control: Control) -> Result<()> {
+    bail!("The control socket needs Unix domain sockets")
+}
+
+/// Sends one command to a running tracker and returns its reply, failing if
+/// the command did.
+#[cfg(unix)]
+pub async fn request(config: &ControlConfig, command: &str) -> Result<Value> {
+    use anyhow::Context;
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::UnixStream;
+
+    let mut socket = UnixStream::connect(&config.path).await.with_context(|| {
+        format!(
+            "Failed to connect to the control socket {}; is the tracker running with control enabled?",
+            config.path.display()
+        )
+    })?;
+    socket
+        .write_all(format!("{}\n", command).as_bytes())
+        .await?;
+    let mut reply = String::new();
+    BufReader::new(socket).read_line(&mut reply).await?;
+    let reply: Value = serde_json::from_str(&reply)?;
+    if reply["ok"] != json!(true) {
+        bail!(
+            "{}",
+            reply["error"].as_str().unwrap_or("The command failed")
+        );
+    }
+    Ok(reply)
+}
+
+#[cfg(not(unix))]
+pub async fn request(_config: &ControlConfig, _command: &str) -> Result<Value> {
+    bail!("The control socket needs Unix domain sockets")
+}
+
+impl Control {
+    fn live(&self) -> Result<&Live> {
+        self.live
+            .as_ref()
+            .ok_or_else(|| anyhow!("Live trading is not enabled"))
+    }
+
+    async fn execute(&self, line: &str) -> Result<Value> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        Ok(match words.as_slice() {
+            ["subscribe", symbol, interval] => {
+                if interval_duration(interval).is_none() {
+                    bail!("Invalid interval '{}'", interval);
+                }
+                let added = self.streams.add(&symbol.to_lowercase(), interval);
+                json!({ "ok": true, "added": added })
+            }
+            ["unsubscribe", symbol, interval] => {
+                let removed = self.streams.remove(&symbol.to_lowercase(), interval);
+                json!({ "ok": true, "removed": removed })
+            }
+            ["streams"] => {
+                let streams: Vec<Value> = self
+                    .streams
+                    .list()
+                    .into_iter()
+                    .map(|(symbol, interval)| {
+                        let state = self.health.stream_state(&symbol, &interval);
+                        json!({
+                            "symbol": symbol,
+                            "interval": interval,
+                            "state": state.map(|state| state.as_str()),
+                        })
+                    })
+                    .collect();
+                json!({ "ok": true, "streams": streams })
+            }
+            ["status"] => json!({
+                "ok": true,
+                "streams": self.metrics.to_json(),
+                "channels": self.metrics.channels_json(),
+                "latency": self.metrics.latency_json(),
+                "panics": self.metrics.panics_json(),
+            }),
+            ["halt"] => {
+                self.kill_switch.engage();
+                warn!("Kill switch engaged over the control socket; new orders are refused");
+                json!({ "ok": true })
+            }
+            ["resume"] => {
+                self.kill_switch.release();
+                info!("Kill switch released over the control socket");
+                json!({ "ok": true })
+            }
+            ["paper"] => json!({ "ok": true, "paper": self.paper.lock().to_json() }),
+            ["orders"] => json!({ "ok": true, "live": self.live()?.to_json() }),
+            [side @ ("buy" | "sell"), symbol, quantity, price @ ..] if price.len() <= 1 => {
+                let side = if *side == "buy" {
+                    Side::Buy
+                } else {
+                    Side::Sell
+                };
+                let quantity: f64 = quantity
+                    .parse()
+                    .map_err(|_| anyhow!("Invalid quantity '{}'", quantity))?;
+                let limit = match price {
+                    [price] => Some(
+                        price
+                            .parse::<f64>()
+                            .map_err(|_| anyhow!("Invalid price '{}'", price))?,
+                    ),
+                    _ => None,
+                };
+                let symbol = symbol.to_lowercase();
+                let last_price = self
+                    .state
+                    .sessions
+                    .get(symbol.as_str())
+                    .map(|session| session.last_price);
+                let order = self
+                    .live()?
+                    .place(
+                        &symbol,
+                        side,
+                        Amount::Quantity(quantity),
+                        limit,
+                        last_price,
+                        "control socket",
+                    )
+                    .await?;
+                live::log_order(&order, &self.state);
+                json!({ "ok": true, "order": order.to_json() })
+            }
+            ["cancel", symbol, id] => {
+                let id: i64 = id
+                    .parse()
+                    .map_err(|_| anyhow!("Invalid order id '{}'", id))?;
+                let order = self.live()?.cancel(&symbol.to_lowercase(), id).await?;
+                live::log_order(&order, &self.state);
+                json!({ "ok": true, "order": order.to_json() })
+            }
+            ["dump"] => json!({ "ok": true, "snapshot": self.state.snapshot() }),
+            ["flush"] => {
+                let notifiers = self.sinks.notifiers();
+                let results = join_all(
+                    notifiers
+                        .iter()
+                        .map(|notifier| async { (notifier.name(), notifier.flush().await) }),
+                )
+                .await;
+                let errors: Vec<String> = results
+                    .into_iter()
+                    .filter_map(|(name, result)| result.err().map(|e| format!("{}: {}", name, e)))
+                    .collect();
+                json!({ "ok": errors.is_empty(), "errors": errors })
+            }
+            ["components"] => json!({ "ok": true, "components": self.supervisor.status() }),
+            ["restart", component] => {
+                self.supervisor.restart(component)?;
+                json!({ "ok": true })
+            }
+            ["reload"] => {
+                let Some(path) = &self.config_path else {
+                    bail!("The tracker was started without a config file");
+                };
+                let config = Config::load(Some(path))?;
+                self.sinks.start(&config)?;
+                info!("Reloaded sink settings from {}", path.display());
+                json!({ "ok": true })
+            }
+            ["shutdown"] => {
+                warn!("Shutdown requested over the control socket");
+                self.shutdown.notify_one();
+                json!({ "ok": true })
+            }
+            _ => bail!(
+                "Unknown command '{}'; expected subscribe <symbol> <interval>, \
+                 unsubscribe <symbol> <interval>, streams, status, paper, orders, \
+                 buy|sell <symbol> <quantity> [price], cancel <symbol> <order id>, halt, resume, dump, flush, \
+                 components, restart <component>, reload or shutdown",
+                line.trim()
+            ),
+        })
+    }
+}
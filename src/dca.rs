@@ -0,0 +1,153 @@
+//! Dollar-cost averaging: buys of a fixed quote amount of one symbol on a
+//! schedule on the paper account, compared with having spent the same total
+//! at once on the first buy.
+
+use crate::config::{DcaConfig, ExitConfig, Side};
+use crate::paper::{Fill, PaperAccount};
+use crate::state::MarketState;
+use crate::KlineData;
+use chrono::{DateTime, Duration, Utc};
+use serde_json::{json, Value};
+use tracing::warn;
+
+/// A running DCA plan and what it has bought.
+#[derive(Debug, Clone)]
+pub struct Dca {
+    pub name: String,
+    pub symbol: String,
+    pub quote_amount: f64,
+    pub every: Duration,
+    /// When the next buy is due; the first price buys at once.
+    next: Option<DateTime<Utc>>,
+    pub buys: usize,
+    pub quantity: f64,
+    /// Quote spent on the quantity, before fees.
+    pub cost: f64,
+    pub fees: f64,
+    /// What one unit of the first buy cost, fee included.
+    first_price: Option<f64>,
+    mark_price: f64,
+}
+
+impl Dca {
+    pub fn new(config: &DcaConfig) -> Self {
+        Self {
+            name: config.name(),
+            symbol: config.symbol.clone(),
+            quote_amount: config.quote_amount,
+            every: Duration::seconds(config.every_secs as i64),
+            next: None,
+            buys: 0,
+            quantity: 0.0,
+            cost: 0.0,
+            fees: 0.0,
+            first_price: None,
+            mark_price: 0.0,
+        }
+    }
+
+    /// Quote spent, fees included.
+    pub fn invested(&self) -> f64 {
+        self.cost + self.fees
+    }
+
+    /// Average price paid per unit, before fees; `None` before a buy.
+    pub fn average_price(&self) -> Option<f64> {
+        (self.quantity > 0.0).then(|| self.cost / self.quantity)
+    }
+
+    pub fn value(&self) -> f64 {
+        self.quantity * self.mark_price
+    }
+
+    pub fn pnl(&self) -> f64 {
+        self.value() - self.invested()
+    }
+
+    /// What spending the whole amount invested on the first buy, at its
+    /// price, would have made by now.
+    pub fn lump_sum_pnl(&self) -> f64 {
+        self.first_price.map_or(0.0, |price| {
+            self.invested() / price * self.mark_price - self.invested()
+        })
+    }
+
+    /// Buys if `kline` is of the plan's symbol and a buy is due at `now`,
+    /// the time of its price.
+    pub fn on_kline(
+        &mut self,
+        account: &mut PaperAccount,
+        kline: &KlineData,
+        now: DateTime<Utc>,
+    ) -> Option<Fill> {
+        if *kline.symbol != *self.symbol {
+            return None;
+        }
+        self.mark_price = kline.close;
+        if self.next.is_some_and(|next| now < next) {
+            return None;
+        }
+        // A missed buy is skipped rather than made up for.
+        let next = self.next.unwrap_or(now);
+        let every = self.every.num_milliseconds();
+        let missed = (now - next).num_milliseconds() / every;
+        self.next = Some(next + Duration::milliseconds(every * (missed + 1)));
+
+        let reason = format!("{}: scheduled buy {}", self.name, self.buys + 1);
+        let fill = match account.market(
+            &self.symbol,
+            Side::Buy,
+            self.quote_amount / kline.close,
+            kline.close,
+            reason,
+            ExitConfig::default(),
+        ) {
+            Ok(fill) => fill,
+            Err(e) => {
+                warn!("{} could not buy: {}", self.name, e);
+                return None;
+            }
+        };
+        self.buys += 1;
+        self.quantity += fill.quantity;
+        self.cost += fill.price * fill.quantity;
+        self.fees += fill.fee;
+        self.first_price
+            .get_or_insert((fill.price * fill.quantity + fill.fee) / fill.quantity);
+        Some(fill)
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "name": self.name,
+            "symbol": self.symbol,
+            "quote_amount": self.quote_amount,
+            "every_secs": self.every.num_seconds(),
+            "next_buy": self.next.map(|next| next.to_rfc3339()),
+            "buys": self.buys,
+            "quantity": self.quantity,
+            "invested": self.invested(),
+            "average_price": self.average_price(),
+            "value": self.value(),
+            "pnl": self.pnl(),
+            "lump_sum_pnl": self.lump_sum_pnl(),
+        })
+    }
+
+    /// One line for the log, the average price shown with the precision
+    /// `state` has for the symbol.
+    pub fn summary_line(&self, state: &MarketState) -> String {
+        let average_price = self.average_price().unwrap_or_default();
+        format!(
+            "  {}: {} buy(s), {} @ {:.*}, invested {:.2}, PnL {:+.2} vs {:+.2} as a lump sum",
+            self.name,
+            self.buys,
+            self.quantity,
+            state.decimals(&self.symbol, average_price),
+            average_price,
+            self.invested(),
+            self.pnl(),
+            self.lump_sum_pnl()
+        )
+    }
+}
@@ -0,0 +1,36 @@
+//! How far stablecoin pairs trade from their peg of 1.0, for the `depeg`
+//! alert and the periodic stablecoin summary.
+
+use crate::config::DepegConfig;
+use crate::state::MarketState;
+
+/// Signed distance of `price` from 1.0, in basis points.
+pub fn deviation_bps(price: f64) -> f64 {
+    (price - 1.0) * 10_000.0
+}
+
+/// Every monitored pair's last price this session and its deviation, those
+/// past the alert threshold marked, or `None` with the monitor off.
+pub fn summary_line(config: &DepegConfig, state: &MarketState) -> Option<String> {
+    if !config.enabled || config.symbols.is_empty() {
+        return None;
+    }
+    let pairs: Vec<String> = config
+        .symbols
+        .iter()
+        .map(|symbol| {
+            let Some(session) = state.sessions.get(symbol.as_str()) else {
+                return format!("{} no price yet", symbol);
+            };
+            let price = session.last_price;
+            let deviation = deviation_bps(price);
+            let flag = if deviation.abs() > config.threshold_bps {
+                ", DEPEGGED"
+            } else {
+                ""
+            };
+            format!("{} {:.4} ({:+.1} bps{})", symbol, price, deviation, flag)
+        })
+        .collect();
+    Some(pairs.join(" | "))
+}
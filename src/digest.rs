@@ -0,0 +1,263 @@
+//! A periodic digest of the whole market: the symbols that gained and lost
+//! the most since the previous digest, the volume traded in each quote
+//! asset, the candles whose volume spiked, and how the streams are doing.
+//! Easier to follow than every update's log line once many symbols are
+//! tracked.
+
+use crate::arbitrage;
+use crate::config::{Config, DigestConfig, IndicatorConfig};
+use crate::health::{Health, StreamState};
+use crate::index::INDEX_PREFIX;
+use crate::indicators::{Indicator, VolumeSpike};
+use crate::interval::interval_duration;
+use crate::state::MarketState;
+use crate::KlineData;
+use chrono::{DateTime, Local, Utc};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use std::time::Instant;
+
+/// A candle the volume spike indicator raised an event for.
+struct Spike {
+    kline: KlineData,
+    /// Its volume over the average of the candles before it.
+    ratio: f64,
+}
+
+/// Builds each digest from the market state, remembering the prices of the
+/// previous one to measure changes from.
+#[derive(Debug)]
+pub struct Digest {
+    config: DigestConfig,
+    /// The `volume_spike` indicator's config, if any, which spikes are
+    /// found with; else its defaults.
+    spike: Option<IndicatorConfig>,
+    since: DateTime<Utc>,
+    /// When the previous digest was built, on the same clock as the
+    /// streams' last messages.
+    built: Instant,
+    /// Each symbol's price as of the previous digest.
+    prices: HashMap<Arc<str>, f64>,
+}
+
+impl Digest {
+    /// The first digest covers `since` onwards, measuring changes from each
+    /// session's first price.
+    pub fn new(config: &Config, since: DateTime<Utc>) -> Self {
+        Self {
+            config: config.digest.clone(),
+            spike: config
+                .indicators
+                .iter()
+                .find(|indicator| indicator.kind == "volume_spike")
+                .cloned(),
+            since,
+            built: Instant::now(),
+            prices: HashMap::new(),
+        }
+    }
+
+    /// The lines of the digest of everything since the previous one, as of
+    /// `now`.
+    pub fn summarize(
+        &mut self,
+        state: &MarketState,
+        health: &Health,
+        now: DateTime<Utc>,
+    ) -> Vec<String> {
+        let mut changes: Vec<(Arc<str>, f64, f64)> = Vec::new();
+        let mut prices = HashMap::new();
+        for (symbol, session) in state.sorted_sessions() {
+            if !is_market(&symbol) {
+                continue;
+            }
+            let from = self
+                .prices
+                .get(&symbol)
+                .copied()
+                .unwrap_or(session.first_price);
+            if from > 0.0 {
+                let change = (session.last_price - from) / from * 100.0;
+                changes.push((symbol.clone(), session.last_price, change));
+            }
+            prices.insert(symbol, session.last_price);
+        }
+        let (volume, spikes) = self.volume(state, now);
+
+        let top = self.config.top;
+        let mover = |(symbol, price, change): &(Arc<str>, f64, f64)| {
+            format!(
+                "{} {:+.2}% ({:.*})",
+                symbol.to_uppercase(),
+                change,
+                state.decimals(symbol, *price),
+                price
+            )
+        };
+        changes.sort_by(|a, b| b.2.total_cmp(&a.2));
+        let gainers: Vec<String> = changes
+            .iter()
+            .filter(|(_, _, change)| *change > 0.0)
+            .take(top)
+            .map(mover)
+            .collect();
+        let losers: Vec<String> = changes
+            .iter()
+            .rev()
+            .filter(|(_, _, change)| *change < 0.0)
+            .take(top)
+            .map(mover)
+            .collect();
+        let spikes: Vec<String> = spikes
+            .iter()
+            .take(top)
+            .map(|spike| {
+                format!(
+                    "{} {} {:.1}x at {}",
+                    spike.kline.symbol.to_uppercase(),
+                    spike.kline.interval,
+                    spike.ratio,
+                    spike
+                        .kline
+                        .interval_start
+                        .with_timezone(&Local)
+                        .format("%H:%M")
+                )
+            })
+            .collect();
+        let volume: Vec<String> = volume
+            .iter()
+            .map(|(asset, volume)| format!("{:.2} {}", volume, asset))
+            .collect();
+        let lines = vec![
+            format!(
+                "Since {}: {} symbol(s), traded {}",
+                self.since.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S"),
+                changes.len(),
+                if volume.is_empty() {
+                    "nothing".to_string()
+                } else {
+                    volume.join(", ")
+                }
+            ),
+            format!("Top gainers: {}", list(gainers)),
+            format!("Top losers: {}", list(losers)),
+            format!("Volume spikes: {}", list(spikes)),
+            format!("Streams: {}", streams_line(health, self.built)),
+        ];
+
+        self.prices = prices;
+        self.since = now;
+        self.built = Instant::now();
+        lines
+    }
+
+    /// The volume of every candle of a market symbol that closed since the
+    /// previous digest, totalled by quote asset with each symbol counted on
+    /// its shortest interval, and those candles whose volume spiked, the
+    /// biggest first. Symbols whose quote asset is unknown are left out of
+    /// the totals.
+    fn volume(
+        &self,
+        state: &MarketState,
+        now: DateTime<Utc>,
+    ) -> (BTreeMap<Arc<str>, f64>, Vec<Spike>) {
+        let history = state.history();
+        let mut shortest: HashMap<&str, &str> = HashMap::new();
+        for (symbol, interval) in history.keys() {
+            if !is_market(symbol) || interval_duration(interval).is_none() {
+                continue;
+            }
+            let current = shortest.entry(symbol).or_insert(interval);
+            if interval_duration(interval) < interval_duration(current) {
+                *current = interval;
+            }
+        }
+
+        let mut volume = BTreeMap::new();
+        let mut spikes = Vec::new();
+        for (symbol, interval) in shortest {
+            let Some(series) = history.get(&(symbol.into(), interval.into())) else {
+                continue;
+            };
+            let Some(step) = interval_duration(interval) else {
+                continue;
+            };
+            let asset = state.quote_asset(symbol);
+            // Fed every closed candle in the history, so that the first
+            // ones of the period are compared with those before it.
+            let mut indicator = VolumeSpike::from_config(self.spike.as_ref());
+            for kline in series.iter() {
+                let closes = kline.interval_start + step;
+                if closes > now {
+                    break;
+                }
+                let output = indicator.update(kline);
+                if closes <= self.since {
+                    continue;
+                }
+                if let Some(asset) = &asset {
+                    *volume.entry(asset.clone()).or_insert(0.0) += kline.volume * kline.close;
+                }
+                if let Some(output) = output.filter(|output| output.get("event").is_some()) {
+                    spikes.push(Spike {
+                        kline: kline.clone(),
+                        ratio: output["ratio"].as_f64().unwrap_or_default(),
+                    });
+                }
+            }
+        }
+        spikes.sort_by(|a, b| b.ratio.total_cmp(&a.ratio));
+        (volume, spikes)
+    }
+}
+
+/// Whether `symbol` trades, rather than being a ratio, spread or index
+/// derived from symbols that do.
+fn is_market(symbol: &str) -> bool {
+    !symbol.contains('/')
+        && arbitrage::split_spread(symbol).is_none()
+        && arbitrage::split_venue(symbol).0 != Some(INDEX_PREFIX)
+}
+
+fn list(items: Vec<String>) -> String {
+    if items.is_empty() {
+        "none".to_string()
+    } else {
+        items.join(", ")
+    }
+}
+
+/// How many streams are in each state, and which connected ones have sent
+/// nothing since `since`.
+fn streams_line(health: &Health, since: Instant) -> String {
+    let window = since.elapsed();
+    let mut keys = health.stream_keys();
+    keys.sort();
+    let (mut connected, mut connecting, mut disconnected) = (0, 0, 0);
+    let mut silent = Vec::new();
+    for (symbol, interval) in &keys {
+        match health.stream_state(symbol, interval) {
+            Some(StreamState::Connected) => {
+                connected += 1;
+                if health
+                    .last_message_age(symbol, interval)
+                    .is_none_or(|age| age > window)
+                {
+                    silent.push(format!("{} {}", symbol, interval));
+                }
+            }
+            Some(StreamState::Connecting) => connecting += 1,
+            Some(StreamState::Disconnected) => disconnected += 1,
+            None => {}
+        }
+    }
+    let mut line = format!(
+        "{} connected, {} connecting, {} disconnected",
+        connected, connecting, disconnected
+    );
+    if !silent.is_empty() {
+        line.push_str(&format!("; silent: {}", silent.join(", ")));
+    }
+    line
+}
@@ -0,0 +1,39 @@
+use crate::alerts::Alert;
+use crate::strategy::TradeSignal;
+use crate::KlineData;
+use serde_json::{json, Value};
+
+/// Something the tracker publishes to live subscribers.
+#[derive(Debug, Clone)]
+pub enum Event {
+    Kline(KlineData),
+    Alert(Alert),
+    Signal(TradeSignal),
+}
+
+impl Event {
+    pub fn symbol(&self) -> &str {
+        match self {
+            Event::Kline(kline) => &kline.symbol,
+            Event::Alert(alert) => &alert.symbol,
+            Event::Signal(signal) => &signal.symbol,
+        }
+    }
+
+    pub fn interval(&self) -> &str {
+        match self {
+            Event::Kline(kline) => &kline.interval,
+            Event::Alert(alert) => &alert.interval,
+            Event::Signal(signal) => &signal.interval,
+        }
+    }
+
+    /// The event tagged with its type, as the WebSocket server sends klines.
+    pub fn to_json(&self) -> Value {
+        match self {
+            Event::Kline(kline) => json!({ "type": "kline", "data": kline.to_json() }),
+            Event::Alert(alert) => json!({ "type": "alert", "data": alert.to_json() }),
+            Event::Signal(signal) => json!({ "type": "signal", "data": signal.to_json() }),
+        }
+    }
+}
@@ -0,0 +1,504 @@
+//! A small expression language used for alert rules.
+//!
+//! ```text
+//! close > 100000 and (change_pct > 2 or rsi_14 < 30)
+//! symbol == 'btcusdt' && change_pct.abs() > 1
+//! macd.histogram > 0 and not bollinger.upper < close
+//! ```
+//!
+//! Identifiers are resolved through a [`Context`]; dotted paths reach into
+//! object-valued variables such as multi-output indicators. An expression
+//! whose variables are not all available (e.g. an indicator still warming up)
+//! evaluates to `None`, which conditions treat as false.
+
+use crate::KlineData;
+use anyhow::{anyhow, bail, Result};
+use serde_json::Value as Json;
+use std::collections::BTreeMap;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Val {
+    Num(f64),
+    Bool(bool),
+    Str(String),
+}
+
+impl Val {
+    fn as_num(&self) -> Option<f64> {
+        match self {
+            Val::Num(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    fn truthy(&self) -> bool {
+        match self {
+            Val::Bool(b) => *b,
+            Val::Num(n) => *n != 0.0,
+            Val::Str(s) => !s.is_empty(),
+        }
+    }
+
+    /// Converts a JSON value, such as an indicator output, into a `Val`.
+    pub fn from_json(value: &Json) -> Option<Self> {
+        match value {
+            Json::Number(n) => n.as_f64().map(Val::Num),
+            Json::Bool(b) => Some(Val::Bool(*b)),
+            Json::String(s) => Some(Val::Str(s.clone())),
+            _ => None,
+        }
+    }
+}
+
+/// Resolves variables referenced by an expression.
+pub trait Context {
+    /// Looks up a variable by its dotted path, e.g. `["macd", "histogram"]`.
+    fn get(&self, path: &[String]) -> Option<Val>;
+
+    /// The same variables one step earlier (the previous closed candle), used
+    /// by the crossover functions.
+    fn previous(&self) -> Option<&dyn Context> {
+        None
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Eq,
+    Ne,
+    And,
+    Or,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Lit(Val),
+    Var(Vec<String>),
+    Neg(Box<Expr>),
+    Not(Box<Expr>),
+    Bin(BinOp, Box<Expr>, Box<Expr>),
+    Call(String, Vec<Expr>),
+}
+
+impl Expr {
+    pub fn parse(source: &str) -> Result<Self> {
+        let tokens = tokenize(source)?;
+        let mut parser = Parser { tokens, pos: 0 };
+        let expr = parser.or()?;
+        match parser.peek() {
+            None => Ok(expr),
+            Some(token) => bail!("Unexpected '{}' in expression '{}'", token, source),
+        }
+    }
+
+    pub fn eval(&self, ctx: &dyn Context) -> Option<Val> {
+        match self {
+            Expr::Lit(v) => Some(v.clone()),
+            Expr::Var(path) => ctx.get(path),
+            Expr::Neg(e) => Some(Val::Num(-e.eval(ctx)?.as_num()?)),
+            Expr::Not(e) => Some(Val::Bool(!e.eval(ctx)?.truthy())),
+            Expr::Bin(BinOp::And, l, r) => {
+                Some(Val::Bool(l.eval(ctx)?.truthy() && r.eval(ctx)?.truthy()))
+            }
+            Expr::Bin(BinOp::Or, l, r) => {
+                // An unavailable side shouldn't stop the other from matching.
+                let left = l.eval(ctx).is_some_and(|v| v.truthy());
+                Some(Val::Bool(left || r.eval(ctx).is_some_and(|v| v.truthy())))
+            }
+            Expr::Bin(op, l, r) => binary(*op, l.eval(ctx)?, r.eval(ctx)?),
+            Expr::Call(name, args) if is_crossover(name) => {
+                let [a, b] = args.as_slice() else {
+                    return None;
+                };
+                let previous = ctx.previous()?;
+                let before = eval_num(a, previous)? - eval_num(b, previous)?;
+                let now = eval_num(a, ctx)? - eval_num(b, ctx)?;
+                let crossed = match name.as_str() {
+                    "crosses_above" => before <= 0.0 && now > 0.0,
+                    "crosses_below" => before >= 0.0 && now < 0.0,
+                    _ => (before <= 0.0 && now > 0.0) || (before >= 0.0 && now < 0.0),
+                };
+                Some(Val::Bool(crossed))
+            }
+            Expr::Call(name, args) => {
+                let args: Option<Vec<f64>> = args.iter().map(|a| a.eval(ctx)?.as_num()).collect();
+                call(name, &args?)
+            }
+        }
+    }
+
+    /// Evaluates the expression as a condition; unavailable means false.
+    pub fn matches(&self, ctx: &dyn Context) -> bool {
+        self.eval(ctx).is_some_and(|v| v.truthy())
+    }
+}
+
+fn eval_num(expr: &Expr, ctx: &dyn Context) -> Option<f64> {
+    expr.eval(ctx)?.as_num()
+}
+
+/// `crosses_above(a, b)`, `crosses_below(a, b)` and `crosses(a, b)` compare
+/// `a - b` against its value on the previous step.
+fn is_crossover(name: &str) -> bool {
+    matches!(name, "crosses_above" | "crosses_below" | "crosses")
+}
+
+fn binary(op: BinOp, l: Val, r: Val) -> Option<Val> {
+    if let (Val::Str(l), Val::Str(r)) = (&l, &r) {
+        return match op {
+            BinOp::Eq => Some(Val::Bool(l.eq_ignore_ascii_case(r))),
+            BinOp::Ne => Some(Val::Bool(!l.eq_ignore_ascii_case(r))),
+            _ => None,
+        };
+    }
+    if let (Val::Bool(l), Val::Bool(r)) = (&l, &r) {
+        return match op {
+            BinOp::Eq => Some(Val::Bool(l == r)),
+            BinOp::Ne => Some(Val::Bool(l != r)),
+            _ => None,
+        };
+    }
+    let (l, r) = (l.as_num()?, r.as_num()?);
+    Some(match op {
+        BinOp::Add => Val::Num(l + r),
+        BinOp::Sub => Val::Num(l - r),
+        BinOp::Mul => Val::Num(l * r),
+        BinOp::Div if r == 0.0 => return None,
+        BinOp::Div => Val::Num(l / r),
+        BinOp::Lt => Val::Bool(l < r),
+        BinOp::Le => Val::Bool(l <= r),
+        BinOp::Gt => Val::Bool(l > r),
+        BinOp::Ge => Val::Bool(l >= r),
+        BinOp::Eq => Val::Bool(l == r),
+        BinOp::Ne => Val::Bool(l != r),
+        BinOp::And | BinOp::Or => unreachable!("logical operators are short-circuited"),
+    })
+}
+
+fn call(name: &str, args: &[f64]) -> Option<Val> {
+    Some(Val::Num(match (name, args) {
+        ("abs", [x]) => x.abs(),
+        ("min", [a, b]) => a.min(*b),
+        ("max", [a, b]) => a.max(*b),
+        _ => return None,
+    }))
+}
+
+fn check_call(name: &str, arity: usize) -> Result<()> {
+    let expected = match name {
+        "abs" => 1,
+        "min" | "max" => 2,
+        name if is_crossover(name) => 2,
+        _ => bail!("Unknown function '{}'", name),
+    };
+    if arity != expected {
+        bail!("Function '{}' does not take {} arguments", name, arity);
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Num(f64),
+    Str(String),
+    Ident(String),
+    Op(&'static str),
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Num(n) => write!(f, "{}", n),
+            Token::Str(s) => write!(f, "'{}'", s),
+            Token::Ident(s) => f.write_str(s),
+            Token::Op(op) => f.write_str(op),
+        }
+    }
+}
+
+const OPERATORS: &[&str] = &[
+    "&&", "||", "==", "!=", "<=", ">=", "<", ">", "!", "+", "-", "*", "/", "(", ")", ",", ".",
+];
+
+fn tokenize(source: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = source.char_indices().peekable();
+    while let Some(&(start, c)) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c.is_ascii_digit() {
+            let mut end = start;
+            while let Some(&(i, c)) = chars.peek() {
+                if !(c.is_ascii_digit() || c == '.' || c == '_') {
+                    break;
+                }
+                end = i + c.len_utf8();
+                chars.next();
+            }
+            let text = source[start..end].replace('_', "");
+            let number = text
+                .parse()
+                .map_err(|_| anyhow!("Invalid number '{}'", text))?;
+            tokens.push(Token::Num(number));
+        } else if c.is_alphabetic() || c == '_' {
+            let mut end = start;
+            while let Some(&(i, c)) = chars.peek() {
+                if !(c.is_alphanumeric() || c == '_') {
+                    break;
+                }
+                end = i + c.len_utf8();
+                chars.next();
+            }
+            tokens.push(match &source[start..end] {
+                "and" => Token::Op("&&"),
+                "or" => Token::Op("||"),
+                "not" => Token::Op("!"),
+                ident => Token::Ident(ident.to_string()),
+            });
+        } else if c == '\'' || c == '"' {
+            chars.next();
+            let mut text = String::new();
+            loop {
+                match chars.next() {
+                    Some((_, q)) if q == c => break,
+                    Some((_, ch)) => text.push(ch),
+                    None => bail!("Unterminated string in expression '{}'", source),
+                }
+            }
+            tokens.push(Token::Str(text));
+        } else {
+            let rest = &source[start..];
+            let Some(op) = OPERATORS.iter().find(|op| rest.starts_with(**op)) else {
+                bail!("Unexpected character '{}' in expression '{}'", c, source);
+            };
+            for _ in 0..op.len() {
+                chars.next();
+            }
+            tokens.push(Token::Op(op));
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn eat(&mut self, op: &'static str) -> bool {
+        if self.peek() == Some(&Token::Op(op)) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, op: &'static str) -> Result<()> {
+        if self.eat(op) {
+            Ok(())
+        } else {
+            match self.peek() {
+                Some(token) => bail!("Expected '{}' but found '{}'", op, token),
+                None => bail!("Expected '{}' at end of expression", op),
+            }
+        }
+    }
+
+    fn or(&mut self) -> Result<Expr> {
+        let mut left = self.and()?;
+        while self.eat("||") {
+            left = Expr::Bin(BinOp::Or, Box::new(left), Box::new(self.and()?));
+        }
+        Ok(left)
+    }
+
+    fn and(&mut self) -> Result<Expr> {
+        let mut left = self.not()?;
+        while self.eat("&&") {
+            left = Expr::Bin(BinOp::And, Box::new(left), Box::new(self.not()?));
+        }
+        Ok(left)
+    }
+
+    fn not(&mut self) -> Result<Expr> {
+        if self.eat("!") {
+            return Ok(Expr::Not(Box::new(self.not()?)));
+        }
+        self.comparison()
+    }
+
+    fn comparison(&mut self) -> Result<Expr> {
+        let left = self.sum()?;
+        let op = match self.peek() {
+            Some(Token::Op("<")) => BinOp::Lt,
+            Some(Token::Op("<=")) => BinOp::Le,
+            Some(Token::Op(">")) => BinOp::Gt,
+            Some(Token::Op(">=")) => BinOp::Ge,
+            Some(Token::Op("==")) => BinOp::Eq,
+            Some(Token::Op("!=")) => BinOp::Ne,
+            _ => return Ok(left),
+        };
+        self.pos += 1;
+        Ok(Expr::Bin(op, Box::new(left), Box::new(self.sum()?)))
+    }
+
+    fn sum(&mut self) -> Result<Expr> {
+        let mut left = self.product()?;
+        loop {
+            let op = if self.eat("+") {
+                BinOp::Add
+            } else if self.eat("-") {
+                BinOp::Sub
+            } else {
+                return Ok(left);
+            };
+            left = Expr::Bin(op, Box::new(left), Box::new(self.product()?));
+        }
+    }
+
+    fn product(&mut self) -> Result<Expr> {
+        let mut left = self.unary()?;
+        loop {
+            let op = if self.eat("*") {
+                BinOp::Mul
+            } else if self.eat("/") {
+                BinOp::Div
+            } else {
+                return Ok(left);
+            };
+            left = Expr::Bin(op, Box::new(left), Box::new(self.unary()?));
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expr> {
+        if self.eat("-") {
+            return Ok(Expr::Neg(Box::new(self.unary()?)));
+        }
+        let mut expr = self.primary()?;
+        // Method-call sugar: `x.abs()` is `abs(x)`.
+        while self.eat(".") {
+            let Some(Token::Ident(method)) = self.next() else {
+                bail!("Expected a method name after '.'");
+            };
+            self.expect("(")?;
+            self.expect(")")?;
+            check_call(&method, 1)?;
+            expr = Expr::Call(method, vec![expr]);
+        }
+        Ok(expr)
+    }
+
+    fn primary(&mut self) -> Result<Expr> {
+        match self.next() {
+            Some(Token::Num(n)) => Ok(Expr::Lit(Val::Num(n))),
+            Some(Token::Str(s)) => Ok(Expr::Lit(Val::Str(s))),
+            Some(Token::Op("(")) => {
+                let expr = self.or()?;
+                self.expect(")")?;
+                Ok(expr)
+            }
+            Some(Token::Ident(name)) if name == "true" => Ok(Expr::Lit(Val::Bool(true))),
+            Some(Token::Ident(name)) if name == "false" => Ok(Expr::Lit(Val::Bool(false))),
+            Some(Token::Ident(name)) => {
+                if self.eat("(") {
+                    let mut args = Vec::new();
+                    if !self.eat(")") {
+                        loop {
+                            args.push(self.or()?);
+                            if self.eat(")") {
+                                break;
+                            }
+                            self.expect(",")?;
+                        }
+                    }
+                    check_call(&name, args.len())?;
+                    return Ok(Expr::Call(name, args));
+                }
+                let mut path = vec![name];
+                // A dotted segment is a field unless it is followed by `(`.
+                while let (Some(Token::Op(".")), Some(Token::Ident(field)), next) = (
+                    self.tokens.get(self.pos),
+                    self.tokens.get(self.pos + 1),
+                    self.tokens.get(self.pos + 2),
+                ) {
+                    if next == Some(&Token::Op("(")) {
+                        break;
+                    }
+                    path.push(field.clone());
+                    self.pos += 2;
+                }
+                Ok(Expr::Var(path))
+            }
+            Some(token) => bail!("Unexpected '{}'", token),
+            None => bail!("Unexpected end of expression"),
+        }
+    }
+}
+
+/// The variables available for a candle: `symbol`, `interval`, `open`,
+/// `high`, `low`, `close`, `volume`, `quote_volume`, `change`, `change_pct`,
+/// `closed`, plus every indicator output by name.
+pub struct KlineContext<'a> {
+    pub kline: &'a KlineData,
+    pub indicators: Option<&'a BTreeMap<String, Json>>,
+    /// The previous closed candle's context, if known.
+    pub previous: Option<&'a KlineContext<'a>>,
+}
+
+impl Context for KlineContext<'_> {
+    fn previous(&self) -> Option<&dyn Context> {
+        self.previous.map(|previous| previous as &dyn Context)
+    }
+
+    fn get(&self, path: &[String]) -> Option<Val> {
+        let kline = self.kline;
+        let value = match path {
+            [name] => match name.as_str() {
+                "symbol" => return Some(Val::Str(kline.symbol.to_string())),
+                "interval" => return Some(Val::Str(kline.interval.to_string())),
+                "closed" => return Some(Val::Bool(kline.closed)),
+                "open" => Some(kline.open),
+                "high" => Some(kline.high),
+                "low" => Some(kline.low),
+                "close" | "price" => Some(kline.close),
+                "volume" => Some(kline.volume),
+                "quote_volume" => Some(kline.volume * kline.close),
+                "change" => Some(kline.price_change()),
+                "change_pct" => Some(kline.price_change_percent()),
+                _ => None,
+            },
+            _ => None,
+        };
+        if let Some(value) = value {
+            return Some(Val::Num(value));
+        }
+
+        let (name, fields) = path.split_first()?;
+        let mut value = self.indicators?.get(name)?;
+        for field in fields {
+            value = value.get(field)?;
+        }
+        Val::from_json(value)
+    }
+}
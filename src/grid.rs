@@ -0,0 +1,162 @@
+//! Grid bots: limit orders resting at evenly spaced price levels of one
+//! symbol on the paper account, each buy sold one level up and bought back
+//! one level down, so the bot profits from the price swinging in its range.
+
+use crate::config::{ExitConfig, GridConfig, Side};
+use crate::paper::{Fill, PaperAccount};
+use crate::precision;
+use crate::KlineData;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use tracing::warn;
+
+/// A running grid and what it has traded.
+#[derive(Debug, Clone)]
+pub struct Grid {
+    pub name: String,
+    pub symbol: String,
+    pub quantity: f64,
+    /// Level prices, lowest first.
+    pub prices: Vec<f64>,
+    /// Level and side of each resting order, by order id.
+    orders: HashMap<u64, (usize, Side)>,
+    /// What the quantity held for each level cost, fee included.
+    costs: Vec<Option<f64>>,
+    started: bool,
+    pub buys: usize,
+    pub sells: usize,
+    /// From sells, after the fees of both sides.
+    pub realized_pnl: f64,
+    mark_price: f64,
+}
+
+impl Grid {
+    pub fn new(config: &GridConfig) -> Self {
+        let step = (config.upper - config.lower) / (config.levels - 1) as f64;
+        Self {
+            name: config.name(),
+            symbol: config.symbol.clone(),
+            quantity: config.quantity,
+            prices: (0..config.levels)
+                .map(|level| config.lower + step * level as f64)
+                .collect(),
+            orders: HashMap::new(),
+            costs: vec![None; config.levels],
+            started: false,
+            buys: 0,
+            sells: 0,
+            realized_pnl: 0.0,
+            mark_price: 0.0,
+        }
+    }
+
+    /// Quantity bought and not yet sold.
+    pub fn held(&self) -> f64 {
+        self.costs.iter().flatten().count() as f64 * self.quantity
+    }
+
+    /// The held quantity at the latest price, less what it cost.
+    pub fn unrealized_pnl(&self) -> f64 {
+        let cost: f64 = self.costs.iter().flatten().sum();
+        self.held() * self.mark_price - cost
+    }
+
+    pub fn pnl(&self) -> f64 {
+        self.realized_pnl + self.unrealized_pnl()
+    }
+
+    /// Reacts to `kline` after `account` has seen it: `fills` are the
+    /// account's fills for it, among which the grid's own are answered one
+    /// level over. On the first price of its symbol, the grid places a buy at
+    /// every level below it but the top one.
+    pub fn on_kline(&mut self, account: &mut PaperAccount, kline: &KlineData, fills: &[Fill]) {
+        if *kline.symbol != *self.symbol {
+            return;
+        }
+        self.mark_price = kline.close;
+        for fill in fills {
+            let Some((level, side)) = self.orders.remove(&fill.order_id) else {
+                continue;
+            };
+            match side {
+                Side::Buy => {
+                    self.buys += 1;
+                    self.costs[level] = Some(fill.price * fill.quantity + fill.fee);
+                    self.place(account, level + 1, Side::Sell);
+                }
+                Side::Sell => {
+                    self.sells += 1;
+                    let cost = self.costs[level - 1].take().unwrap_or_default();
+                    self.realized_pnl += fill.price * fill.quantity - fill.fee - cost;
+                    self.place(account, level - 1, Side::Buy);
+                }
+            }
+        }
+        // Orders the account cancelled are gone for good.
+        let open = account.open_orders();
+        self.orders
+            .retain(|id, _| open.iter().any(|order| order.id == *id));
+        if !self.started {
+            self.started = true;
+            for level in 0..self.prices.len() - 1 {
+                if self.prices[level] < kline.close {
+                    self.place(account, level, Side::Buy);
+                }
+            }
+        }
+    }
+
+    fn place(&mut self, account: &mut PaperAccount, level: usize, side: Side) {
+        let price = self.prices[level];
+        let reason = format!(
+            "{}: {} level {} at {:.*}",
+            self.name,
+            side,
+            level,
+            precision::fallback_decimals(price),
+            price
+        );
+        match account.limit(
+            &self.symbol,
+            side,
+            self.quantity,
+            price,
+            reason,
+            ExitConfig::default(),
+        ) {
+            Ok(order) => {
+                self.orders.insert(order.id, (level, side));
+            }
+            Err(e) => warn!("{} could not {} at level {}: {}", self.name, side, level, e),
+        }
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "name": self.name,
+            "symbol": self.symbol,
+            "levels": self.prices,
+            "quantity": self.quantity,
+            "open_orders": self.orders.len(),
+            "buys": self.buys,
+            "sells": self.sells,
+            "held": self.held(),
+            "realized_pnl": self.realized_pnl,
+            "unrealized_pnl": self.unrealized_pnl(),
+            "pnl": self.pnl(),
+        })
+    }
+
+    /// One line for the log.
+    pub fn summary_line(&self) -> String {
+        format!(
+            "  {}: {} buy(s), {} sell(s), holding {}, PnL {:+.2} (realized {:+.2})",
+            self.name,
+            self.buys,
+            self.sells,
+            self.held(),
+            self.pnl(),
+            self.realized_pnl
+        )
+    }
+}
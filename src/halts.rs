@@ -0,0 +1,232 @@
+//! Symbols Binance has stopped trading. Their status in the exchange info is
+//! polled, and a symbol in a break, halted or delisted has its streams
+//! stopped rather than left reconnecting to a market that sends nothing,
+//! until it trades again. A symbol whose streams have all gone silent is
+//! flagged as possibly halted, without being stopped.
+
+use crate::alerts::Alert;
+use crate::arbitrage::split_venue;
+use crate::clock::SharedClock;
+use crate::config::HaltsConfig;
+use crate::events::Event;
+use crate::health::Health;
+use crate::listings::{self, SymbolInfo};
+use crate::notify::Notification;
+use crate::state::SharedState;
+use crate::streams::Streams;
+use chrono::{DateTime, Utc};
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc};
+use tracing::{info, warn};
+
+/// The rule name halt alerts are reported under.
+pub const HALT_RULE: &str = "halt";
+
+/// The status of a symbol missing from the exchange info.
+pub const DELISTED: &str = "DELISTED";
+
+/// The status of a symbol whose streams have gone silent.
+pub const SILENT: &str = "SILENT";
+
+/// Statuses a symbol can be in while it trades or is about to.
+const OPEN: [&str; 3] = ["TRADING", "PRE_TRADING", "AUCTION_MATCH"];
+
+/// Why a symbol is not trading, and since when.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TradingStatus {
+    /// As in the exchange info, e.g. `BREAK` or `HALT`, or [`DELISTED`] or
+    /// [`SILENT`].
+    pub status: String,
+    pub since: DateTime<Utc>,
+}
+
+impl TradingStatus {
+    pub fn to_json(&self) -> Value {
+        json!({
+            "status": self.status,
+            "since": self.since.to_rfc3339(),
+        })
+    }
+}
+
+/// The status that stops a symbol with the exchange info `info`, or `None`
+/// if it is open for trading.
+pub fn halted_status(info: Option<&SymbolInfo>) -> Option<&str> {
+    match info {
+        None => Some(DELISTED),
+        Some(info) if OPEN.contains(&info.status.as_str()) => None,
+        Some(info) => Some(&info.status),
+    }
+}
+
+/// Polls the exchange info every `interval_secs` for as long as the tracker
+/// runs, stopping the streams of every streamed symbol that is not trading
+/// and restarting them once it is, and marking it in `state` meanwhile.
+/// Every change is reported as an alert to `events_tx` and `notify_tx`.
+pub async fn run(
+    config: HaltsConfig,
+    state: SharedState,
+    streams: Streams,
+    health: Health,
+    events_tx: broadcast::Sender<Event>,
+    notify_tx: mpsc::Sender<Notification>,
+    clock: SharedClock,
+) {
+    let client = match reqwest::Client::builder()
+        .timeout(Duration::from_secs(30))
+        .build()
+    {
+        Ok(client) => client,
+        Err(e) => {
+            warn!("Halt detection is disabled: {}", e);
+            return;
+        }
+    };
+    let report = |symbol: &str, intervals: &[String], message: String| {
+        let interval = intervals
+            .iter()
+            .min_by_key(|interval| crate::interval::interval_duration(interval))
+            .cloned()
+            .unwrap_or_default();
+        let price = state
+            .sessions
+            .get(symbol)
+            .map_or(0.0, |session| session.last_price);
+        warn!("HALT {}: {}", symbol, message);
+        let alert = Alert {
+            rule: HALT_RULE.to_string(),
+            symbol: symbol.to_string(),
+            interval,
+            price,
+            change_percent: 0.0,
+            message,
+            triggered_at: clock.now(),
+        };
+        listings::publish(alert, &events_tx, &notify_tx);
+    };
+    let silence = Duration::from_secs(config.silence_secs);
+    // The intervals each stopped symbol was streamed on.
+    let mut stopped: HashMap<String, Vec<String>> = HashMap::new();
+    let mut timer = tokio::time::interval(Duration::from_secs(config.interval_secs));
+    loop {
+        timer.tick().await;
+        let mut streamed: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for (symbol, interval) in streams.list() {
+            // Other venues have statuses of their own.
+            if split_venue(&symbol).0.is_none() {
+                streamed.entry(symbol).or_default().push(interval);
+            }
+        }
+
+        match listings::fetch(&client, &config.url).await {
+            Ok(pairs) => {
+                let pairs: HashMap<String, SymbolInfo> = pairs
+                    .into_iter()
+                    .map(|info| (info.symbol.to_lowercase(), info))
+                    .collect();
+                for (symbol, intervals) in &streamed {
+                    let Some(status) = halted_status(pairs.get(symbol)) else {
+                        continue;
+                    };
+                    for interval in intervals {
+                        streams.remove(symbol, interval);
+                    }
+                    stopped.insert(symbol.clone(), intervals.clone());
+                    mark(&state, symbol, status, clock.now());
+                    report(
+                        symbol,
+                        intervals,
+                        format!(
+                            "Binance reports {} as {}; stopped streaming it",
+                            symbol, status
+                        ),
+                    );
+                }
+                stopped.retain(|symbol, intervals| match halted_status(pairs.get(symbol)) {
+                    Some(status) => {
+                        if mark(&state, symbol, status, clock.now()) {
+                            report(
+                                symbol,
+                                intervals,
+                                format!("Binance now reports {} as {}", symbol, status),
+                            );
+                        }
+                        true
+                    }
+                    None => {
+                        for interval in intervals.iter() {
+                            streams.add(symbol, interval);
+                        }
+                        state.trading_status.remove(symbol.as_str());
+                        report(
+                            symbol,
+                            intervals,
+                            format!(
+                                "Binance reports {} trading again; streaming it again",
+                                symbol
+                            ),
+                        );
+                        false
+                    }
+                });
+            }
+            Err(e) => warn!("Failed to poll the exchange info: {}", e),
+        }
+
+        if silence.is_zero() {
+            continue;
+        }
+        for (symbol, intervals) in &streamed {
+            if stopped.contains_key(symbol) {
+                continue;
+            }
+            let silent = intervals.iter().all(|interval| {
+                health
+                    .last_message_age(symbol, interval)
+                    .is_some_and(|age| age >= silence)
+            });
+            let marked = state
+                .trading_status
+                .get(symbol.as_str())
+                .is_some_and(|status| status.status == SILENT);
+            if silent && !marked {
+                mark(&state, symbol, SILENT, clock.now());
+                report(
+                    symbol,
+                    intervals,
+                    format!(
+                        "{} has sent nothing for {}s; it may be halted",
+                        symbol, config.silence_secs
+                    ),
+                );
+            } else if !silent && marked {
+                state.trading_status.remove(symbol.as_str());
+                info!("{} is sending data again", symbol);
+            }
+        }
+    }
+}
+
+/// Marks `symbol` as in `status` from `now`, unless it already was. Returns
+/// whether it changed.
+fn mark(state: &SharedState, symbol: &str, status: &str, now: DateTime<Utc>) -> bool {
+    let symbol: Arc<str> = symbol.into();
+    if state
+        .trading_status
+        .get(&symbol)
+        .is_some_and(|current| current.status == status)
+    {
+        return false;
+    }
+    state.trading_status.insert(
+        symbol,
+        TradingStatus {
+            status: status.to_string(),
+            since: now,
+        },
+    );
+    true
+}
@@ -0,0 +1,210 @@
+use crate::SeriesKey;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamState {
+    Connecting,
+    Connected,
+    Disconnected,
+}
+
+impl StreamState {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            StreamState::Connecting => "connecting",
+            StreamState::Connected => "connected",
+            StreamState::Disconnected => "disconnected",
+        }
+    }
+}
+
+#[derive(Debug)]
+struct StreamHealth {
+    state: StreamState,
+    last_message: Option<Instant>,
+}
+
+/// The outcome of the latest delivery through one sink, such as a notifier.
+#[derive(Debug)]
+struct SinkHealth {
+    last_error: Option<String>,
+    at: Instant,
+}
+
+#[derive(Debug, Default)]
+struct HealthState {
+    streams: HashMap<SeriesKey, StreamHealth>,
+    sinks: HashMap<String, SinkHealth>,
+}
+
+/// Connection state of every stream and the health of every sink, shared
+/// between the tasks that update it and the health endpoints.
+#[derive(Debug, Clone)]
+pub struct Health {
+    state: Arc<Mutex<HealthState>>,
+    /// A connected stream with no message for this long counts as dead.
+    stale_after: Duration,
+}
+
+/// The result of a health check: whether it passed, and the details behind
+/// it.
+pub struct Report {
+    pub ok: bool,
+    pub body: Value,
+}
+
+impl Health {
+    pub fn new(stale_after: Duration) -> Self {
+        Self {
+            state: Arc::default(),
+            stale_after,
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HealthState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn set_stream_state(&self, symbol: &str, interval: &str, state: StreamState) {
+        self.lock()
+            .streams
+            .entry((symbol.into(), interval.into()))
+            .and_modify(|stream| stream.state = state)
+            .or_insert(StreamHealth {
+                state,
+                last_message: None,
+            });
+    }
+
+    pub fn remove_stream(&self, symbol: &str, interval: &str) {
+        self.lock()
+            .streams
+            .remove(&(symbol.into(), interval.into()));
+    }
+
+    pub fn stream_state(&self, symbol: &str, interval: &str) -> Option<StreamState> {
+        self.lock()
+            .streams
+            .get(&(symbol.into(), interval.into()))
+            .map(|stream| stream.state)
+    }
+
+    /// How long since the stream last delivered a message, if it ever has.
+    pub fn last_message_age(&self, symbol: &str, interval: &str) -> Option<Duration> {
+        self.lock()
+            .streams
+            .get(&(symbol.into(), interval.into()))
+            .and_then(|stream| stream.last_message)
+            .map(|seen| seen.elapsed())
+    }
+
+    /// Every stream being tracked, whatever its state.
+    pub fn stream_keys(&self) -> Vec<SeriesKey> {
+        self.lock().streams.keys().cloned().collect()
+    }
+
+    pub fn record_message(&self, key: &SeriesKey) {
+        if let Some(stream) = self.lock().streams.get_mut(key) {
+            stream.last_message = Some(Instant::now());
+        }
+    }
+
+    pub fn record_delivery(&self, sink: &str, error: Option<String>) {
+        self.lock().sinks.insert(
+            sink.to_string(),
+            SinkHealth {
+                last_error: error,
+                at: Instant::now(),
+            },
+        );
+    }
+
+    /// Liveness: fails once any stream has disconnected or gone quiet for
+    /// longer than the stale threshold, so an orchestrator restarts the
+    /// tracker.
+    pub fn liveness(&self) -> Report {
+        self.report(|stream| match stream.state {
+            StreamState::Connecting => true,
+            StreamState::Connected => stream
+                .last_message
+                .is_none_or(|seen| seen.elapsed() < self.stale_after),
+            StreamState::Disconnected => false,
+        })
+    }
+
+    /// Readiness: passes once every stream is connected and has delivered
+    /// data recently.
+    pub fn readiness(&self) -> Report {
+        self.report(|stream| {
+            stream.state == StreamState::Connected
+                && stream
+                    .last_message
+                    .is_some_and(|seen| seen.elapsed() < self.stale_after)
+        })
+    }
+
+    fn report(&self, healthy: impl Fn(&StreamHealth) -> bool) -> Report {
+        let state = self.lock();
+        let age = |at: Instant| at.elapsed().as_secs_f64();
+
+        let mut streams: Vec<_> = state.streams.iter().collect();
+        streams.sort_by(|a, b| a.0.cmp(b.0));
+        let ok = streams.iter().all(|(_, stream)| healthy(stream));
+        let streams: Vec<Value> = streams
+            .into_iter()
+            .map(|((symbol, interval), stream)| {
+                json!({
+                    "symbol": symbol,
+                    "interval": interval,
+                    "state": stream.state.as_str(),
+                    "last_message_age_secs": stream.last_message.map(age),
+                    "healthy": healthy(stream),
+                })
+            })
+            .collect();
+
+        // Probes need no token, so sink errors are left to `sinks`.
+        let mut sinks: Vec<_> = state.sinks.iter().collect();
+        sinks.sort_by(|a, b| a.0.cmp(b.0));
+        let sinks: Vec<Value> = sinks
+            .into_iter()
+            .map(|(name, sink)| {
+                json!({
+                    "name": name,
+                    "healthy": sink.last_error.is_none(),
+                })
+            })
+            .collect();
+
+        Report {
+            ok,
+            body: json!({
+                "status": if ok { "ok" } else { "unavailable" },
+                "streams": streams,
+                "sinks": sinks,
+            }),
+        }
+    }
+
+    /// Every sink's latest delivery: whether it failed and why, and how long
+    /// ago it was, sorted by sink.
+    pub fn sinks(&self) -> Vec<Value> {
+        let state = self.lock();
+        let mut sinks: Vec<_> = state.sinks.iter().collect();
+        sinks.sort_by(|a, b| a.0.cmp(b.0));
+        sinks
+            .into_iter()
+            .map(|(name, sink)| {
+                json!({
+                    "name": name,
+                    "healthy": sink.last_error.is_none(),
+                    "last_error": sink.last_error,
+                    "last_delivery_age_secs": sink.at.elapsed().as_secs_f64(),
+                })
+            })
+            .collect()
+    }
+}
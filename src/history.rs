@@ -0,0 +1,140 @@
+use crate::{KlineData, SeriesKey};
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::mem::size_of;
+use tracing::debug;
+
+/// Rolling buffer of the most recent candles for one symbol/interval.
+///
+/// Updates for the candle currently in progress replace the last entry, so
+/// the buffer always holds one entry per interval start.
+#[derive(Debug, Clone)]
+pub struct CandleHistory {
+    candles: VecDeque<KlineData>,
+    capacity: usize,
+}
+
+impl CandleHistory {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            candles: VecDeque::with_capacity(capacity.min(1024)),
+            capacity: capacity.max(1),
+        }
+    }
+
+    pub fn update(&mut self, kline: KlineData) {
+        match self.candles.back_mut() {
+            Some(last) if last.interval_start == kline.interval_start => *last = kline,
+            _ => {
+                if self.candles.len() == self.capacity {
+                    self.candles.pop_front();
+                }
+                self.candles.push_back(kline);
+            }
+        }
+    }
+
+    pub fn latest(&self) -> Option<&KlineData> {
+        self.candles.back()
+    }
+
+    /// The candle before the latest one.
+    pub fn previous(&self) -> Option<&KlineData> {
+        self.candles.iter().rev().nth(1)
+    }
+
+    /// Every candle held, oldest first.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &KlineData> {
+        self.candles.iter()
+    }
+
+    /// The most recent `n` candles, oldest first.
+    pub fn last_n(&self, n: usize) -> impl Iterator<Item = &KlineData> {
+        self.candles
+            .iter()
+            .skip(self.candles.len().saturating_sub(n))
+    }
+
+    /// Roughly the memory the buffer takes up. Symbol and interval names are
+    /// shared by every candle of a series, so only the candles count.
+    pub fn bytes(&self) -> usize {
+        size_of::<Self>() + self.candles.capacity() * size_of::<KlineData>()
+    }
+}
+
+/// The history buffers of every series, evicting the least recently updated
+/// series once they take up more than a memory budget.
+#[derive(Debug, Default)]
+pub struct HistoryCache {
+    /// Each series' buffer and when it was last updated.
+    series: HashMap<SeriesKey, (CandleHistory, u64)>,
+    /// Series by when they were last updated, least recent first.
+    by_update: BTreeMap<u64, SeriesKey>,
+    next_update: u64,
+    bytes: usize,
+    evictions: u64,
+}
+
+impl HistoryCache {
+    pub fn get(&self, key: &SeriesKey) -> Option<&CandleHistory> {
+        self.series.get(key).map(|(history, _)| history)
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &SeriesKey> {
+        self.series.keys()
+    }
+
+    /// Adds `kline` to its series' buffer, creating one holding up to
+    /// `capacity` candles if needed, then evicts the least recently updated
+    /// other series until the buffers fit in `budget` bytes, if any.
+    pub fn update(
+        &mut self,
+        kline: KlineData,
+        capacity: usize,
+        budget: Option<usize>,
+    ) -> &CandleHistory {
+        let key = kline.key();
+        let update = self.next_update;
+        self.next_update += 1;
+        let (history, updated) = match self.series.entry(key.clone()) {
+            Entry::Occupied(entry) => {
+                let (history, updated) = entry.into_mut();
+                self.by_update.remove(updated);
+                self.bytes -= history.bytes();
+                (history, updated)
+            }
+            Entry::Vacant(entry) => {
+                let (history, updated) = entry.insert((CandleHistory::new(capacity), update));
+                (history, updated)
+            }
+        };
+        history.update(kline);
+        self.bytes += history.bytes();
+        *updated = update;
+        self.by_update.insert(update, key.clone());
+
+        if let Some(budget) = budget {
+            while self.bytes > budget && self.by_update.len() > 1 {
+                let Some((_, evicted)) = self.by_update.pop_first() else {
+                    break;
+                };
+                if let Some((history, _)) = self.series.remove(&evicted) {
+                    self.bytes -= history.bytes();
+                    self.evictions += 1;
+                    debug!("Evicted the history of {} {}", evicted.0, evicted.1);
+                }
+            }
+        }
+        &self.series[&key].0
+    }
+
+    /// Roughly the memory every buffer takes up together.
+    pub fn bytes(&self) -> usize {
+        self.bytes
+    }
+
+    /// Series evicted to stay within the budget so far.
+    pub fn evictions(&self) -> u64 {
+        self.evictions
+    }
+}
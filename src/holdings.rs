@@ -0,0 +1,112 @@
+//! Holdings declared in the config, marked to market with the latest prices
+//! streamed for their symbols.
+
+use crate::config::HoldingConfig;
+use crate::state::MarketState;
+use serde_json::{json, Value};
+
+/// A holding valued at its symbol's latest price.
+#[derive(Debug, Clone)]
+pub struct Mark {
+    pub symbol: String,
+    pub quantity: f64,
+    pub cost_basis: Option<f64>,
+    /// `None` until a price has arrived for the symbol.
+    pub price: Option<f64>,
+}
+
+impl Mark {
+    pub fn value(&self) -> Option<f64> {
+        self.price.map(|price| price * self.quantity)
+    }
+
+    pub fn cost(&self) -> Option<f64> {
+        self.cost_basis.map(|price| price * self.quantity)
+    }
+
+    /// Needs both a price and a cost basis.
+    pub fn unrealized_pnl(&self) -> Option<f64> {
+        Some(self.value()? - self.cost()?)
+    }
+
+    pub fn unrealized_pnl_percent(&self) -> Option<f64> {
+        let cost = self.cost().filter(|cost| *cost > 0.0)?;
+        Some(self.unrealized_pnl()? / cost * 100.0)
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "symbol": self.symbol,
+            "quantity": self.quantity,
+            "cost_basis": self.cost_basis,
+            "price": self.price,
+            "value": self.value(),
+            "unrealized_pnl": self.unrealized_pnl(),
+            "unrealized_pnl_percent": self.unrealized_pnl_percent(),
+        })
+    }
+}
+
+/// Every holding marked at its symbol's last price this session.
+pub fn mark(holdings: &[HoldingConfig], state: &MarketState) -> Vec<Mark> {
+    holdings
+        .iter()
+        .map(|holding| Mark {
+            symbol: holding.symbol.clone(),
+            quantity: holding.quantity,
+            cost_basis: holding.cost_basis,
+            price: state
+                .sessions
+                .get(holding.symbol.as_str())
+                .map(|session| session.last_price),
+        })
+        .collect()
+}
+
+/// Value and unrealized PnL of every priced holding together.
+pub fn totals(marks: &[Mark]) -> (f64, f64) {
+    let value = marks.iter().filter_map(Mark::value).sum();
+    let pnl = marks.iter().filter_map(Mark::unrealized_pnl).sum();
+    (value, pnl)
+}
+
+/// One line per holding, then the portfolio's totals. Prices are shown with
+/// the precision `state` has for their symbol.
+pub fn summary_lines(marks: &[Mark], state: &MarketState) -> Vec<String> {
+    if marks.is_empty() {
+        return Vec::new();
+    }
+    let mut lines: Vec<String> = marks
+        .iter()
+        .map(|mark| {
+            let Some(price) = mark.price else {
+                return format!("{} {} | No price yet", mark.symbol, mark.quantity);
+            };
+            let decimals = state.decimals(&mark.symbol, price);
+            let mut line = format!(
+                "{} {} at {:.*} = {:.2}",
+                mark.symbol,
+                mark.quantity,
+                decimals,
+                price,
+                mark.value().unwrap_or_default()
+            );
+            if let (Some(cost_basis), Some(pnl)) = (mark.cost_basis, mark.unrealized_pnl()) {
+                line.push_str(&format!(
+                    " | Cost basis: {:.*} | Unrealized PnL: {:+.2} ({:+.2}%)",
+                    decimals,
+                    cost_basis,
+                    pnl,
+                    mark.unrealized_pnl_percent().unwrap_or_default()
+                ));
+            }
+            line
+        })
+        .collect();
+    let (value, pnl) = totals(marks);
+    lines.push(format!(
+        "Portfolio value: {:.2} | Unrealized PnL: {:+.2}",
+        value, pnl
+    ));
+    lines
+}
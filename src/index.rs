@@ -0,0 +1,84 @@
+//! Composite prices: one candle per symbol combining its candles on Binance
+//! and every other [venue](crate::arbitrage), tracked as a synthetic series
+//! named `index:symbol`, e.g. `index:btcusdt`.
+
+use crate::config::IndexMethod;
+use crate::KlineData;
+
+/// Prefixes the series of an index, in place of a venue.
+pub const INDEX_PREFIX: &str = "index";
+
+/// The series name of `symbol`'s index.
+pub fn index_symbol(symbol: &str) -> String {
+    format!("{}:{}", INDEX_PREFIX, symbol)
+}
+
+/// Combines the venues' candles on the same interval start as `legs[0]`,
+/// ignoring the others, which are behind. Returns `None` while fewer than
+/// `min_venues` are on it.
+///
+/// Open, high, low and close are each combined by `method`, and the high and
+/// low then widened to the extremes observed across updates of the current
+/// candle (`previous` is the last index candle emitted), as for ratios.
+/// Volume is the venues' total, and the candle is closed once every venue's
+/// is.
+pub fn compute_index(
+    name: &str,
+    legs: &[KlineData],
+    method: IndexMethod,
+    min_venues: usize,
+    previous: Option<&KlineData>,
+) -> Option<KlineData> {
+    let first = legs.first()?;
+    let legs: Vec<&KlineData> = legs
+        .iter()
+        .filter(|leg| leg.interval == first.interval && leg.interval_start == first.interval_start)
+        .collect();
+    if legs.len() < min_venues.max(1) {
+        return None;
+    }
+
+    let combine = |field: fn(&KlineData) -> f64| match method {
+        IndexMethod::Median => median(legs.iter().map(|leg| field(leg)).collect()),
+        IndexMethod::VolumeWeighted => {
+            let volume: f64 = legs.iter().map(|leg| leg.volume).sum();
+            if volume > 0.0 {
+                legs.iter().map(|leg| field(leg) * leg.volume).sum::<f64>() / volume
+            } else {
+                // Nothing traded yet: every venue counts the same.
+                legs.iter().map(|leg| field(leg)).sum::<f64>() / legs.len() as f64
+            }
+        }
+    };
+    let open = combine(|leg| leg.open);
+    let close = combine(|leg| leg.close);
+    let mut high = combine(|leg| leg.high).max(open).max(close);
+    let mut low = combine(|leg| leg.low).min(open).min(close);
+    if let Some(prev) = previous.filter(|p| p.interval_start == first.interval_start) {
+        high = high.max(prev.high);
+        low = low.min(prev.low);
+    }
+
+    Some(KlineData {
+        symbol: name.into(),
+        interval: first.interval.clone(),
+        interval_start: first.interval_start,
+        open,
+        high,
+        low,
+        close,
+        volume: legs.iter().map(|leg| leg.volume).sum(),
+        closed: legs.iter().all(|leg| leg.closed),
+        timing: None,
+    })
+}
+
+fn median(mut values: Vec<f64>) -> f64 {
+    values.sort_by(f64::total_cmp);
+    let middle = values.len() / 2;
+    if values.len().is_multiple_of(2) {
+        (values[middle - 1] + values[middle]) / 2.0
+    } else {
+        values[middle]
+    }
+}
@@ -0,0 +1,88 @@
+use super::Indicator;
+use crate::KlineData;
+use serde_json::{json, Value};
+
+/// Wilder's average directional index with the +DI and −DI lines.
+pub struct Adx {
+    period: usize,
+    prev: Option<(f64, f64, f64)>,
+    seen: usize,
+    tr: f64,
+    plus_dm: f64,
+    minus_dm: f64,
+    dx_seen: usize,
+    adx: f64,
+}
+
+impl Adx {
+    pub fn new(period: usize) -> Self {
+        Self {
+            period,
+            prev: None,
+            seen: 0,
+            tr: 0.0,
+            plus_dm: 0.0,
+            minus_dm: 0.0,
+            dx_seen: 0,
+            adx: 0.0,
+        }
+    }
+}
+
+impl Indicator for Adx {
+    fn update(&mut self, candle: &KlineData) -> Option<Value> {
+        let (high, low, close) = (candle.high, candle.low, candle.close);
+        let (prev_high, prev_low, prev_close) = self.prev.replace((high, low, close))?;
+
+        let up = high - prev_high;
+        let down = prev_low - low;
+        let plus_dm = if up > down && up > 0.0 { up } else { 0.0 };
+        let minus_dm = if down > up && down > 0.0 { down } else { 0.0 };
+        let tr = (high - low)
+            .max((high - prev_close).abs())
+            .max((low - prev_close).abs());
+
+        let period = self.period as f64;
+        self.seen += 1;
+        if self.seen <= self.period {
+            self.tr += tr;
+            self.plus_dm += plus_dm;
+            self.minus_dm += minus_dm;
+            if self.seen < self.period {
+                return None;
+            }
+        } else {
+            self.tr = self.tr - self.tr / period + tr;
+            self.plus_dm = self.plus_dm - self.plus_dm / period + plus_dm;
+            self.minus_dm = self.minus_dm - self.minus_dm / period + minus_dm;
+        }
+
+        if self.tr == 0.0 {
+            return None;
+        }
+        let plus_di = 100.0 * self.plus_dm / self.tr;
+        let minus_di = 100.0 * self.minus_dm / self.tr;
+        let di_sum = plus_di + minus_di;
+        let dx = if di_sum == 0.0 {
+            0.0
+        } else {
+            100.0 * (plus_di - minus_di).abs() / di_sum
+        };
+
+        self.dx_seen += 1;
+        if self.dx_seen <= self.period {
+            self.adx += dx / period;
+            if self.dx_seen < self.period {
+                return None;
+            }
+        } else {
+            self.adx = (self.adx * (period - 1.0) + dx) / period;
+        }
+
+        Some(json!({
+            "adx": self.adx,
+            "plus_di": plus_di,
+            "minus_di": minus_di,
+        }))
+    }
+}
@@ -0,0 +1,51 @@
+use super::Indicator;
+use crate::KlineData;
+use serde_json::Value;
+
+/// Wilder's average true range.
+pub struct Atr {
+    period: usize,
+    prev_close: Option<f64>,
+    seed_sum: f64,
+    seen: usize,
+    value: Option<f64>,
+}
+
+impl Atr {
+    pub fn new(period: usize) -> Self {
+        Self {
+            period,
+            prev_close: None,
+            seed_sum: 0.0,
+            seen: 0,
+            value: None,
+        }
+    }
+
+    pub fn next(&mut self, candle: &KlineData) -> Option<f64> {
+        let true_range = match self.prev_close {
+            Some(prev) => (candle.high - candle.low)
+                .max((candle.high - prev).abs())
+                .max((candle.low - prev).abs()),
+            None => candle.high - candle.low,
+        };
+        self.prev_close = Some(candle.close);
+
+        let period = self.period as f64;
+        self.value = match self.value {
+            Some(prev) => Some((prev * (period - 1.0) + true_range) / period),
+            None => {
+                self.seed_sum += true_range;
+                self.seen += 1;
+                (self.seen == self.period).then(|| self.seed_sum / period)
+            }
+        };
+        self.value
+    }
+}
+
+impl Indicator for Atr {
+    fn update(&mut self, candle: &KlineData) -> Option<Value> {
+        self.next(candle).map(Value::from)
+    }
+}
@@ -0,0 +1,59 @@
+use super::Indicator;
+use crate::KlineData;
+use serde_json::{json, Value};
+use std::collections::VecDeque;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Bands {
+    pub middle: f64,
+    pub upper: f64,
+    pub lower: f64,
+}
+
+/// Bollinger bands: SMA of closes ± `k` standard deviations.
+pub struct Bollinger {
+    period: usize,
+    k: f64,
+    window: VecDeque<f64>,
+}
+
+impl Bollinger {
+    pub fn new(period: usize, k: f64) -> Self {
+        Self {
+            period,
+            k,
+            window: VecDeque::with_capacity(period + 1),
+        }
+    }
+
+    pub fn next(&mut self, close: f64) -> Option<Bands> {
+        self.window.push_back(close);
+        if self.window.len() > self.period {
+            self.window.pop_front();
+        }
+        if self.window.len() < self.period {
+            return None;
+        }
+
+        let n = self.period as f64;
+        let mean = self.window.iter().sum::<f64>() / n;
+        let variance = self.window.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / n;
+        let deviation = self.k * variance.sqrt();
+        Some(Bands {
+            middle: mean,
+            upper: mean + deviation,
+            lower: mean - deviation,
+        })
+    }
+}
+
+impl Indicator for Bollinger {
+    fn update(&mut self, candle: &KlineData) -> Option<Value> {
+        let bands = self.next(candle.close)?;
+        Some(json!({
+            "middle": bands.middle,
+            "upper": bands.upper,
+            "lower": bands.lower,
+        }))
+    }
+}
@@ -0,0 +1,40 @@
+use super::Indicator;
+use crate::KlineData;
+use serde_json::Value;
+use std::collections::VecDeque;
+
+/// Commodity channel index of the typical price `(high + low + close) / 3`.
+pub struct Cci {
+    period: usize,
+    window: VecDeque<f64>,
+}
+
+impl Cci {
+    pub fn new(period: usize) -> Self {
+        Self {
+            period,
+            window: VecDeque::with_capacity(period + 1),
+        }
+    }
+}
+
+impl Indicator for Cci {
+    fn update(&mut self, candle: &KlineData) -> Option<Value> {
+        let typical = (candle.high + candle.low + candle.close) / 3.0;
+        self.window.push_back(typical);
+        if self.window.len() > self.period {
+            self.window.pop_front();
+        }
+        if self.window.len() < self.period {
+            return None;
+        }
+
+        let n = self.period as f64;
+        let mean = self.window.iter().sum::<f64>() / n;
+        let mean_deviation = self.window.iter().map(|v| (v - mean).abs()).sum::<f64>() / n;
+        if mean_deviation == 0.0 {
+            return Some(Value::from(0.0));
+        }
+        Some(Value::from((typical - mean) / (0.015 * mean_deviation)))
+    }
+}
@@ -0,0 +1,64 @@
+use super::{Atr, Indicator};
+use crate::KlineData;
+use serde_json::{json, Value};
+use std::collections::VecDeque;
+
+/// Chandelier exit levels: the highest high of the window minus
+/// `multiplier` × ATR for longs, and the lowest low plus the same for shorts.
+///
+/// Emits `long_exit` when the close drops below the long level and
+/// `short_exit` when it rises above the short level.
+pub struct Chandelier {
+    period: usize,
+    multiplier: f64,
+    atr: Atr,
+    window: VecDeque<(f64, f64)>,
+    above_long: Option<bool>,
+    below_short: Option<bool>,
+}
+
+impl Chandelier {
+    pub fn new(period: usize, multiplier: f64) -> Self {
+        Self {
+            period,
+            multiplier,
+            atr: Atr::new(period),
+            window: VecDeque::with_capacity(period + 1),
+            above_long: None,
+            below_short: None,
+        }
+    }
+}
+
+impl Indicator for Chandelier {
+    fn update(&mut self, candle: &KlineData) -> Option<Value> {
+        self.window.push_back((candle.high, candle.low));
+        if self.window.len() > self.period {
+            self.window.pop_front();
+        }
+        let atr = self.atr.next(candle)?;
+
+        let highest = self.window.iter().map(|w| w.0).fold(f64::MIN, f64::max);
+        let lowest = self.window.iter().map(|w| w.1).fold(f64::MAX, f64::min);
+        let long = highest - self.multiplier * atr;
+        let short = lowest + self.multiplier * atr;
+
+        let above_long = candle.close >= long;
+        let below_short = candle.close <= short;
+        let event = if self.above_long == Some(true) && !above_long {
+            Some("long_exit")
+        } else if self.below_short == Some(true) && !below_short {
+            Some("short_exit")
+        } else {
+            None
+        };
+        self.above_long = Some(above_long);
+        self.below_short = Some(below_short);
+
+        let mut output = json!({ "long": long, "short": short });
+        if let Some(event) = event {
+            output["event"] = json!(event);
+        }
+        Some(output)
+    }
+}
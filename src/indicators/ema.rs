@@ -0,0 +1,43 @@
+use super::Indicator;
+use crate::KlineData;
+use serde_json::Value;
+
+/// Exponential moving average of closes, seeded with the SMA of the first
+/// `period` values.
+pub struct Ema {
+    period: usize,
+    alpha: f64,
+    seed_sum: f64,
+    seen: usize,
+    value: Option<f64>,
+}
+
+impl Ema {
+    pub fn new(period: usize) -> Self {
+        Self {
+            period,
+            alpha: 2.0 / (period as f64 + 1.0),
+            seed_sum: 0.0,
+            seen: 0,
+            value: None,
+        }
+    }
+
+    pub fn next(&mut self, value: f64) -> Option<f64> {
+        self.value = match self.value {
+            Some(prev) => Some(prev + self.alpha * (value - prev)),
+            None => {
+                self.seed_sum += value;
+                self.seen += 1;
+                (self.seen == self.period).then(|| self.seed_sum / self.period as f64)
+            }
+        };
+        self.value
+    }
+}
+
+impl Indicator for Ema {
+    fn update(&mut self, candle: &KlineData) -> Option<Value> {
+        self.next(candle.close).map(Value::from)
+    }
+}
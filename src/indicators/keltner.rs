@@ -0,0 +1,58 @@
+use super::{Atr, Bollinger, Ema, Indicator};
+use crate::KlineData;
+use serde_json::{json, Value};
+
+/// Keltner channels (EMA of closes ± `multiplier` × ATR).
+///
+/// Besides the channel itself it reports where the close sits within it
+/// (0 at the lower line, 1 at the upper) and whether the Bollinger bands have
+/// contracted inside the channel, the classic volatility "squeeze".
+pub struct Keltner {
+    ema: Ema,
+    atr: Atr,
+    multiplier: f64,
+    bollinger: Bollinger,
+}
+
+impl Keltner {
+    pub fn new(
+        period: usize,
+        atr_period: usize,
+        multiplier: f64,
+        bollinger_period: usize,
+        bollinger_k: f64,
+    ) -> Self {
+        Self {
+            ema: Ema::new(period),
+            atr: Atr::new(atr_period),
+            multiplier,
+            bollinger: Bollinger::new(bollinger_period, bollinger_k),
+        }
+    }
+}
+
+impl Indicator for Keltner {
+    fn update(&mut self, candle: &KlineData) -> Option<Value> {
+        let middle = self.ema.next(candle.close);
+        let atr = self.atr.next(candle);
+        let bands = self.bollinger.next(candle.close);
+        let (middle, atr) = (middle?, atr?);
+
+        let upper = middle + self.multiplier * atr;
+        let lower = middle - self.multiplier * atr;
+        let position = if upper > lower {
+            (candle.close - lower) / (upper - lower)
+        } else {
+            0.5
+        };
+        let squeeze = bands.map(|b| b.upper < upper && b.lower > lower);
+
+        Some(json!({
+            "middle": middle,
+            "upper": upper,
+            "lower": lower,
+            "position": position,
+            "squeeze": squeeze,
+        }))
+    }
+}
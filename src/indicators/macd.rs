@@ -0,0 +1,34 @@
+use super::{Ema, Indicator};
+use crate::KlineData;
+use serde_json::{json, Value};
+
+/// MACD line (fast EMA − slow EMA), its signal EMA and the histogram.
+pub struct Macd {
+    fast: Ema,
+    slow: Ema,
+    signal: Ema,
+}
+
+impl Macd {
+    pub fn new(fast: usize, slow: usize, signal: usize) -> Self {
+        Self {
+            fast: Ema::new(fast),
+            slow: Ema::new(slow),
+            signal: Ema::new(signal),
+        }
+    }
+}
+
+impl Indicator for Macd {
+    fn update(&mut self, candle: &KlineData) -> Option<Value> {
+        let fast = self.fast.next(candle.close);
+        let slow = self.slow.next(candle.close);
+        let macd = fast? - slow?;
+        let signal = self.signal.next(macd)?;
+        Some(json!({
+            "macd": macd,
+            "signal": signal,
+            "histogram": macd - signal,
+        }))
+    }
+}
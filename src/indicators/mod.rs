@@ -0,0 +1,281 @@
+#[cfg(feature = "indicators")]
+mod adx;
+#[cfg(feature = "indicators")]
+mod atr;
+#[cfg(feature = "indicators")]
+mod bollinger;
+#[cfg(feature = "indicators")]
+mod cci;
+#[cfg(feature = "indicators")]
+mod chandelier;
+#[cfg(feature = "indicators")]
+mod ema;
+#[cfg(feature = "indicators")]
+mod keltner;
+#[cfg(feature = "indicators")]
+mod macd;
+#[cfg(feature = "indicators")]
+mod psar;
+#[cfg(feature = "indicators")]
+mod roc;
+#[cfg(feature = "indicators")]
+mod rsi;
+#[cfg(feature = "indicators")]
+mod sma;
+#[cfg(feature = "indicators")]
+mod supertrend;
+mod volume_spike;
+
+#[cfg(feature = "indicators")]
+use {
+    adx::Adx, atr::Atr, bollinger::Bollinger, cci::Cci, chandelier::Chandelier, ema::Ema,
+    keltner::Keltner, macd::Macd, psar::ParabolicSar, roc::Roc, supertrend::SuperTrend,
+};
+// Strategies build on these.
+#[cfg(feature = "indicators")]
+pub(crate) use {rsi::Rsi, sma::Sma};
+// The digest looks for spikes the same way, with or without the feature.
+pub(crate) use volume_spike::VolumeSpike;
+
+use crate::config::IndicatorConfig;
+use crate::{KlineData, SeriesKey};
+use anyhow::{bail, Result};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+/// An incremental computation fed one closed candle at a time.
+///
+/// Indicators that detect discrete occurrences (a trend flip, a level being
+/// crossed) report them by including an `"event"` string in their output
+/// object; the pipeline lifts these into [`IndicatorEvent`]s.
+///
+/// Built-in indicators implement this trait, and so can custom ones: register
+/// a factory for a new `kind` with [`IndicatorRegistry::register`] and it can
+/// be declared in the config exactly like the built-ins.
+///
+/// Returns `None` while the indicator is still warming up.
+pub trait Indicator: Send {
+    fn update(&mut self, candle: &KlineData) -> Option<Value>;
+}
+
+/// Creates an indicator instance from its config entry.
+pub type IndicatorFactory =
+    Box<dyn Fn(&IndicatorConfig) -> Result<Box<dyn Indicator>> + Send + Sync>;
+
+/// Maps indicator kinds, as written in the config, to their factories.
+pub struct IndicatorRegistry {
+    factories: HashMap<String, IndicatorFactory>,
+}
+
+impl IndicatorRegistry {
+    /// A registry with no indicators registered, for custom kinds only. See
+    /// [`with_builtins`](Self::with_builtins), which `default` also gives.
+    pub fn empty() -> Self {
+        Self {
+            factories: HashMap::new(),
+        }
+    }
+
+    /// A registry with every built-in indicator registered, or none without
+    /// the `indicators` feature.
+    pub fn with_builtins() -> Self {
+        #[allow(unused_mut)]
+        let mut registry = Self::empty();
+        #[cfg(feature = "indicators")]
+        registry.register_builtins();
+        registry
+    }
+
+    #[cfg(feature = "indicators")]
+    fn register_builtins(&mut self) {
+        self.register("sma", |c| Ok(Box::new(Sma::new(c.period("period", 20)))));
+        self.register("ema", |c| Ok(Box::new(Ema::new(c.period("period", 20)))));
+        self.register("rsi", |c| Ok(Box::new(Rsi::new(c.period("period", 14)))));
+        self.register("atr", |c| Ok(Box::new(Atr::new(c.period("period", 14)))));
+        self.register("bollinger", |c| {
+            Ok(Box::new(Bollinger::new(
+                c.period("period", 20),
+                c.param("k", 2.0),
+            )))
+        });
+        self.register("macd", |c| {
+            Ok(Box::new(Macd::new(
+                c.period("fast", 12),
+                c.period("slow", 26),
+                c.period("signal", 9),
+            )))
+        });
+        self.register("keltner", |c| {
+            Ok(Box::new(Keltner::new(
+                c.period("period", 20),
+                c.period("atr_period", 10),
+                c.param("multiplier", 2.0),
+                c.period("bollinger_period", 20),
+                c.param("bollinger_k", 2.0),
+            )))
+        });
+        self.register("roc", |c| {
+            Ok(Box::new(Roc::rate_of_change(c.period("period", 10))))
+        });
+        self.register("momentum", |c| {
+            Ok(Box::new(Roc::momentum(c.period("period", 10))))
+        });
+        self.register("supertrend", |c| {
+            Ok(Box::new(SuperTrend::new(
+                c.period("atr_period", 10),
+                c.param("multiplier", 3.0),
+            )))
+        });
+        self.register("psar", |c| {
+            Ok(Box::new(ParabolicSar::new(
+                c.param("step", 0.02),
+                c.param("max_step", 0.2),
+            )))
+        });
+        self.register("adx", |c| Ok(Box::new(Adx::new(c.period("period", 14)))));
+        self.register("cci", |c| Ok(Box::new(Cci::new(c.period("period", 20)))));
+        self.register("chandelier", |c| {
+            Ok(Box::new(Chandelier::new(
+                c.period("period", 22),
+                c.param("multiplier", 3.0),
+            )))
+        });
+        self.register("volume_spike", |c| {
+            Ok(Box::new(VolumeSpike::from_config(Some(c))))
+        });
+    }
+
+    /// Registers (or replaces) the factory for `kind`.
+    pub fn register<F>(&mut self, kind: &str, factory: F)
+    where
+        F: Fn(&IndicatorConfig) -> Result<Box<dyn Indicator>> + Send + Sync + 'static,
+    {
+        self.factories
+            .insert(kind.to_lowercase(), Box::new(factory));
+    }
+
+    fn build(&self, config: &IndicatorConfig) -> Result<Box<dyn Indicator>> {
+        match self.factories.get(&config.kind) {
+            Some(factory) => factory(config),
+            None => bail!("Unknown indicator kind '{}'", config.kind),
+        }
+    }
+}
+
+impl Default for IndicatorRegistry {
+    fn default() -> Self {
+        Self::with_builtins()
+    }
+}
+
+/// A discrete occurrence reported by an indicator, e.g. a trend flip.
+#[derive(Debug, Clone)]
+pub struct IndicatorEvent {
+    pub symbol: String,
+    pub interval: String,
+    pub indicator: String,
+    pub event: String,
+    pub value: Value,
+}
+
+/// The indicator instances and latest outputs for one symbol/interval.
+struct SeriesIndicators {
+    instances: Vec<(String, Box<dyn Indicator>)>,
+    values: BTreeMap<String, Value>,
+    /// The values as of the previous closed candle, for crossover checks.
+    previous: BTreeMap<String, Value>,
+}
+
+/// Runs the configured indicators against every matching series.
+///
+/// The set of indicators for a series is resolved from the config the first
+/// time a candle for it is seen, so adding a combination only needs a config
+/// entry.
+pub struct IndicatorPipeline {
+    registry: Arc<IndicatorRegistry>,
+    specs: Vec<IndicatorConfig>,
+    series: HashMap<SeriesKey, SeriesIndicators>,
+}
+
+impl IndicatorPipeline {
+    /// Validates every configured indicator up front so typos fail at startup.
+    pub fn new(registry: IndicatorRegistry, specs: Vec<IndicatorConfig>) -> Result<Self> {
+        for spec in &specs {
+            registry.build(spec)?;
+        }
+        Ok(Self {
+            registry: Arc::new(registry),
+            specs,
+            series: HashMap::new(),
+        })
+    }
+
+    /// A pipeline running the same indicators with no series computed yet,
+    /// for another worker.
+    pub fn fork(&self) -> Self {
+        Self {
+            registry: self.registry.clone(),
+            specs: self.specs.clone(),
+            series: HashMap::new(),
+        }
+    }
+
+    /// The latest indicator values for a series, if any have been computed.
+    pub fn values(&self, symbol: &str, interval: &str) -> Option<&BTreeMap<String, Value>> {
+        self.series
+            .get(&(symbol.into(), interval.into()))
+            .map(|series| &series.values)
+    }
+
+    /// The indicator values for a series as of the closed candle before the
+    /// latest one.
+    pub fn previous_values(
+        &self,
+        symbol: &str,
+        interval: &str,
+    ) -> Option<&BTreeMap<String, Value>> {
+        self.series
+            .get(&(symbol.into(), interval.into()))
+            .map(|series| &series.previous)
+    }
+
+    /// Feeds a closed candle to every indicator scoped to its series and
+    /// returns the series' latest values along with any events raised.
+    pub fn update(
+        &mut self,
+        candle: &KlineData,
+    ) -> (&BTreeMap<String, Value>, Vec<IndicatorEvent>) {
+        let (registry, specs) = (&self.registry, &self.specs);
+        let series = self
+            .series
+            .entry(candle.key())
+            .or_insert_with(|| SeriesIndicators {
+                instances: specs
+                    .iter()
+                    .filter(|spec| spec.applies_to(&candle.symbol, &candle.interval))
+                    .filter_map(|spec| Some((spec.name(), registry.build(spec).ok()?)))
+                    .collect(),
+                values: BTreeMap::new(),
+                previous: BTreeMap::new(),
+            });
+        series.previous = series.values.clone();
+
+        let mut events = Vec::new();
+        for (name, indicator) in &mut series.instances {
+            if let Some(value) = indicator.update(candle) {
+                if let Some(event) = value.get("event").and_then(Value::as_str) {
+                    events.push(IndicatorEvent {
+                        symbol: candle.symbol.to_string(),
+                        interval: candle.interval.to_string(),
+                        indicator: name.clone(),
+                        event: event.to_string(),
+                        value: value.clone(),
+                    });
+                }
+                series.values.insert(name.clone(), value);
+            }
+        }
+        (&series.values, events)
+    }
+}
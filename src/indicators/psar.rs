@@ -0,0 +1,103 @@
+use super::Indicator;
+use crate::KlineData;
+use serde_json::{json, Value};
+
+/// Wilder's parabolic SAR.
+///
+/// The stop accelerates towards price by `step` each time the trend makes a
+/// new extreme, up to `max_step`, and emits `bullish_flip` / `bearish_flip`
+/// when price crosses it and the trend reverses.
+pub struct ParabolicSar {
+    step: f64,
+    max_step: f64,
+    state: Option<SarState>,
+    prev: Option<(f64, f64)>,
+    prev2: Option<(f64, f64)>,
+}
+
+struct SarState {
+    rising: bool,
+    sar: f64,
+    extreme: f64,
+    acceleration: f64,
+}
+
+impl ParabolicSar {
+    pub fn new(step: f64, max_step: f64) -> Self {
+        Self {
+            step,
+            max_step,
+            state: None,
+            prev: None,
+            prev2: None,
+        }
+    }
+}
+
+impl Indicator for ParabolicSar {
+    fn update(&mut self, candle: &KlineData) -> Option<Value> {
+        let (high, low) = (candle.high, candle.low);
+        let prev = self.prev.replace((high, low));
+        let prev2 = std::mem::replace(&mut self.prev2, prev);
+        let (prev_high, prev_low) = prev?;
+
+        let Some(state) = &mut self.state else {
+            let rising = high + low >= prev_high + prev_low;
+            self.state = Some(SarState {
+                rising,
+                sar: if rising { prev_low } else { prev_high },
+                extreme: if rising { high } else { low },
+                acceleration: self.step,
+            });
+            return None;
+        };
+
+        let mut sar = state.sar + state.acceleration * (state.extreme - state.sar);
+        // The SAR may never move inside the previous two candles' range.
+        let (prev2_high, prev2_low) = prev2.unwrap_or((prev_high, prev_low));
+        sar = if state.rising {
+            sar.min(prev_low).min(prev2_low)
+        } else {
+            sar.max(prev_high).max(prev2_high)
+        };
+
+        let mut event = None;
+        if state.rising && low < sar {
+            *state = SarState {
+                rising: false,
+                sar: state.extreme,
+                extreme: low,
+                acceleration: self.step,
+            };
+            event = Some("bearish_flip");
+        } else if !state.rising && high > sar {
+            *state = SarState {
+                rising: true,
+                sar: state.extreme,
+                extreme: high,
+                acceleration: self.step,
+            };
+            event = Some("bullish_flip");
+        } else {
+            state.sar = sar;
+            let new_extreme = if state.rising {
+                high > state.extreme
+            } else {
+                low < state.extreme
+            };
+            if new_extreme {
+                state.extreme = if state.rising { high } else { low };
+                state.acceleration = (state.acceleration + self.step).min(self.max_step);
+            }
+        }
+
+        let mut output = json!({
+            "value": state.sar,
+            "direction": if state.rising { "up" } else { "down" },
+        });
+        if let Some(event) = event {
+            output["event"] = json!(event);
+        }
+        Some(output)
+    }
+}
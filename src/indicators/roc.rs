@@ -0,0 +1,54 @@
+use super::Indicator;
+use crate::KlineData;
+use serde_json::Value;
+use std::collections::VecDeque;
+
+/// Change of the close over the last `period` candles, either as a
+/// percentage (rate of change) or in price units (momentum).
+pub struct Roc {
+    period: usize,
+    percent: bool,
+    window: VecDeque<f64>,
+}
+
+impl Roc {
+    pub fn rate_of_change(period: usize) -> Self {
+        Self::new(period, true)
+    }
+
+    pub fn momentum(period: usize) -> Self {
+        Self::new(period, false)
+    }
+
+    fn new(period: usize, percent: bool) -> Self {
+        Self {
+            period,
+            percent,
+            window: VecDeque::with_capacity(period + 2),
+        }
+    }
+
+    pub fn next(&mut self, close: f64) -> Option<f64> {
+        self.window.push_back(close);
+        if self.window.len() > self.period + 1 {
+            self.window.pop_front();
+        }
+        if self.window.len() <= self.period {
+            return None;
+        }
+        let past = *self.window.front()?;
+        if !self.percent {
+            Some(close - past)
+        } else if past != 0.0 {
+            Some((close - past) / past * 100.0)
+        } else {
+            None
+        }
+    }
+}
+
+impl Indicator for Roc {
+    fn update(&mut self, candle: &KlineData) -> Option<Value> {
+        self.next(candle.close).map(Value::from)
+    }
+}
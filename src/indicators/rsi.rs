@@ -0,0 +1,55 @@
+use super::Indicator;
+use crate::KlineData;
+use serde_json::Value;
+
+/// Wilder's relative strength index of closes.
+pub struct Rsi {
+    period: usize,
+    prev_close: Option<f64>,
+    avg_gain: f64,
+    avg_loss: f64,
+    seen: usize,
+}
+
+impl Rsi {
+    pub fn new(period: usize) -> Self {
+        Self {
+            period,
+            prev_close: None,
+            avg_gain: 0.0,
+            avg_loss: 0.0,
+            seen: 0,
+        }
+    }
+
+    pub fn next(&mut self, close: f64) -> Option<f64> {
+        let prev = self.prev_close.replace(close)?;
+        let change = close - prev;
+        let (gain, loss) = (change.max(0.0), (-change).max(0.0));
+        let period = self.period as f64;
+
+        self.seen += 1;
+        if self.seen <= self.period {
+            self.avg_gain += gain / period;
+            self.avg_loss += loss / period;
+            if self.seen < self.period {
+                return None;
+            }
+        } else {
+            self.avg_gain = (self.avg_gain * (period - 1.0) + gain) / period;
+            self.avg_loss = (self.avg_loss * (period - 1.0) + loss) / period;
+        }
+
+        Some(if self.avg_loss == 0.0 {
+            100.0
+        } else {
+            100.0 - 100.0 / (1.0 + self.avg_gain / self.avg_loss)
+        })
+    }
+}
+
+impl Indicator for Rsi {
+    fn update(&mut self, candle: &KlineData) -> Option<Value> {
+        self.next(candle.close).map(Value::from)
+    }
+}
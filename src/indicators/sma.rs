@@ -0,0 +1,36 @@
+use super::Indicator;
+use crate::KlineData;
+use serde_json::Value;
+use std::collections::VecDeque;
+
+/// Simple moving average of closes.
+pub struct Sma {
+    period: usize,
+    window: VecDeque<f64>,
+    sum: f64,
+}
+
+impl Sma {
+    pub fn new(period: usize) -> Self {
+        Self {
+            period,
+            window: VecDeque::with_capacity(period + 1),
+            sum: 0.0,
+        }
+    }
+
+    pub fn next(&mut self, value: f64) -> Option<f64> {
+        self.window.push_back(value);
+        self.sum += value;
+        if self.window.len() > self.period {
+            self.sum -= self.window.pop_front().unwrap_or_default();
+        }
+        (self.window.len() == self.period).then(|| self.sum / self.period as f64)
+    }
+}
+
+impl Indicator for Sma {
+    fn update(&mut self, candle: &KlineData) -> Option<Value> {
+        self.next(candle.close).map(Value::from)
+    }
+}
@@ -0,0 +1,80 @@
+use super::{Atr, Indicator};
+use crate::KlineData;
+use serde_json::{json, Value};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Trend {
+    Up,
+    Down,
+}
+
+/// SuperTrend: an ATR band around the candle midpoint that trails price and
+/// flips side when the close breaks through it.
+///
+/// Emits a `bullish_flip` or `bearish_flip` event on the candle where the
+/// trend changes.
+pub struct SuperTrend {
+    atr: Atr,
+    multiplier: f64,
+    upper: f64,
+    lower: f64,
+    prev_close: f64,
+    trend: Option<Trend>,
+}
+
+impl SuperTrend {
+    pub fn new(atr_period: usize, multiplier: f64) -> Self {
+        Self {
+            atr: Atr::new(atr_period),
+            multiplier,
+            upper: f64::INFINITY,
+            lower: f64::NEG_INFINITY,
+            prev_close: 0.0,
+            trend: None,
+        }
+    }
+}
+
+impl Indicator for SuperTrend {
+    fn update(&mut self, candle: &KlineData) -> Option<Value> {
+        let atr = self.atr.next(candle)?;
+        let mid = (candle.high + candle.low) / 2.0;
+        let basic_upper = mid + self.multiplier * atr;
+        let basic_lower = mid - self.multiplier * atr;
+
+        // The bands only tighten unless the previous close broke through them.
+        if basic_upper < self.upper || self.prev_close > self.upper {
+            self.upper = basic_upper;
+        }
+        if basic_lower > self.lower || self.prev_close < self.lower {
+            self.lower = basic_lower;
+        }
+        self.prev_close = candle.close;
+
+        let previous = self.trend;
+        let trend = match previous {
+            Some(Trend::Up) if candle.close < self.lower => Trend::Down,
+            Some(Trend::Down) if candle.close > self.upper => Trend::Up,
+            Some(trend) => trend,
+            None if candle.close >= mid => Trend::Up,
+            None => Trend::Down,
+        };
+        self.trend = Some(trend);
+
+        let (value, direction) = match trend {
+            Trend::Up => (self.lower, "up"),
+            Trend::Down => (self.upper, "down"),
+        };
+        let event = match (previous, trend) {
+            (Some(Trend::Down), Trend::Up) => Some("bullish_flip"),
+            (Some(Trend::Up), Trend::Down) => Some("bearish_flip"),
+            _ => None,
+        };
+
+        let mut output = json!({ "value": value, "direction": direction });
+        if let Some(event) = event {
+            output["event"] = json!(event);
+        }
+        Some(output)
+    }
+}
@@ -0,0 +1,52 @@
+use super::Indicator;
+use crate::config::IndicatorConfig;
+use crate::KlineData;
+use serde_json::{json, Value};
+use std::collections::VecDeque;
+
+/// Compares each candle's volume with the average of the preceding `period`
+/// candles and raises a `volume_spike` event when it is at least `threshold`
+/// times larger.
+pub struct VolumeSpike {
+    period: usize,
+    threshold: f64,
+    window: VecDeque<f64>,
+}
+
+impl VolumeSpike {
+    pub fn new(period: usize, threshold: f64) -> Self {
+        Self {
+            period,
+            threshold,
+            window: VecDeque::with_capacity(period + 1),
+        }
+    }
+
+    /// As configured by a `volume_spike` entry, or with the defaults
+    /// without one.
+    pub fn from_config(config: Option<&IndicatorConfig>) -> Self {
+        match config {
+            Some(config) => Self::new(config.period("period", 20), config.param("threshold", 3.0)),
+            None => Self::new(20, 3.0),
+        }
+    }
+}
+
+impl Indicator for VolumeSpike {
+    fn update(&mut self, candle: &KlineData) -> Option<Value> {
+        let average = (self.window.len() == self.period)
+            .then(|| self.window.iter().sum::<f64>() / self.period as f64);
+        self.window.push_back(candle.volume);
+        if self.window.len() > self.period {
+            self.window.pop_front();
+        }
+
+        let average = average.filter(|average| *average > 0.0)?;
+        let ratio = candle.volume / average;
+        let mut output = json!({ "ratio": ratio, "average": average });
+        if ratio >= self.threshold {
+            output["event"] = json!("volume_spike");
+        }
+        Some(output)
+    }
+}
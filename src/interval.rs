@@ -0,0 +1,19 @@
+use chrono::Duration;
+
+/// Length of a Binance kline interval such as `1m`, `4h` or `1M`.
+///
+/// Months are approximated as 30 days.
+pub fn interval_duration(interval: &str) -> Option<Duration> {
+    let unit = interval.chars().last()?;
+    let count: i64 = interval[..interval.len() - unit.len_utf8()].parse().ok()?;
+    let seconds = match unit {
+        's' => 1,
+        'm' => 60,
+        'h' => 3_600,
+        'd' => 86_400,
+        'w' => 7 * 86_400,
+        'M' => 30 * 86_400,
+        _ => return None,
+    };
+    Some(Duration::seconds(count * seconds))
+}
@@ -0,0 +1,169 @@
+//! The trade journal: every paper and live fill, recorded in the store with
+//! the indicator values of its symbol at the time, for review and export.
+
+use crate::config::Side;
+use crate::paper::Fill;
+use crate::state::SharedState;
+use crate::store::Store;
+use chrono::{DateTime, Utc};
+use serde_json::{json, Map, Value};
+use std::sync::Arc;
+use tracing::error;
+
+/// Which account a journaled fill traded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Account {
+    Paper,
+    Live,
+}
+
+impl Account {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Account::Paper => "paper",
+            Account::Live => "live",
+        }
+    }
+
+    pub fn parse(text: &str) -> Option<Self> {
+        match text {
+            "paper" => Some(Account::Paper),
+            "live" => Some(Account::Live),
+            _ => None,
+        }
+    }
+}
+
+/// One fill as journaled.
+#[derive(Debug, Clone)]
+pub struct JournalEntry {
+    pub account: Account,
+    pub order_id: String,
+    pub symbol: String,
+    pub side: Side,
+    pub quantity: f64,
+    pub price: f64,
+    /// In the quote currency; zero for live fills, whose commission Binance
+    /// may charge in another asset.
+    pub fee: f64,
+    /// For a sell, the average price the quantity sold was bought at.
+    pub entry_price: Option<f64>,
+    /// For a sell, the PnL it realized, before fees.
+    pub pnl: Option<f64>,
+    pub reason: String,
+    /// The symbol's indicator values when it filled, by interval.
+    pub indicators: Value,
+    pub filled_at: DateTime<Utc>,
+}
+
+impl JournalEntry {
+    pub fn to_json(&self) -> Value {
+        json!({
+            "account": self.account.as_str(),
+            "order_id": self.order_id,
+            "symbol": self.symbol,
+            "side": self.side,
+            "quantity": self.quantity,
+            "price": self.price,
+            "fee": self.fee,
+            "entry_price": self.entry_price,
+            "pnl": self.pnl,
+            "reason": self.reason,
+            "indicators": self.indicators,
+            "filled_at": self.filled_at.to_rfc3339(),
+        })
+    }
+
+    /// Column names of [`JournalEntry::csv_row`].
+    pub const CSV_HEADER: &'static str =
+        "Date,Account,Order,Symbol,Side,Quantity,Price,Fee,Entry Price,PnL,Reason,Indicators";
+
+    /// The entry as one CSV line, without the line break.
+    pub fn csv_row(&self) -> String {
+        let optional = |value: Option<f64>| value.map_or(String::new(), |value| value.to_string());
+        [
+            self.filled_at.to_rfc3339(),
+            self.account.as_str().to_string(),
+            self.order_id.clone(),
+            self.symbol.to_uppercase(),
+            self.side.to_string(),
+            self.quantity.to_string(),
+            self.price.to_string(),
+            self.fee.to_string(),
+            optional(self.entry_price),
+            optional(self.pnl),
+            self.reason.clone(),
+            self.indicators.to_string(),
+        ]
+        .iter()
+        .map(|field| csv_field(field))
+        .collect::<Vec<_>>()
+        .join(",")
+    }
+}
+
+/// Quotes `field` if it holds a comma, quote or line break.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Records fills into the store as they happen, in the background.
+#[derive(Clone)]
+pub struct Journal {
+    store: Arc<Store>,
+    state: SharedState,
+}
+
+impl Journal {
+    pub fn new(store: Arc<Store>, state: SharedState) -> Self {
+        Self { store, state }
+    }
+
+    /// Journals a paper fill.
+    pub fn paper(&self, fill: &Fill) {
+        self.record(JournalEntry {
+            account: Account::Paper,
+            order_id: fill.order_id.to_string(),
+            symbol: fill.symbol.clone(),
+            side: fill.side,
+            quantity: fill.quantity,
+            price: fill.price,
+            fee: fill.fee,
+            entry_price: fill.entry_price,
+            pnl: fill
+                .entry_price
+                .map(|entry| (fill.price - entry) * fill.quantity),
+            reason: fill.reason.clone(),
+            indicators: Value::Null,
+            filled_at: fill.filled_at,
+        });
+    }
+
+    /// Journals `entry` with the latest indicator values of its symbol.
+    pub fn record(&self, mut entry: JournalEntry) {
+        entry.indicators = self.indicators(&entry.symbol);
+        let store = self.store.clone();
+        tokio::task::spawn_blocking(move || {
+            if let Err(e) = store.record_trade(&entry) {
+                error!("Failed to journal {} fill: {}", entry.symbol, e);
+            }
+        });
+    }
+
+    /// The latest indicator values of every series of `symbol`, by
+    /// interval.
+    fn indicators(&self, symbol: &str) -> Value {
+        let by_interval: Map<String, Value> = self
+            .state
+            .indicators
+            .iter()
+            .filter(|entry| &*entry.key().0 == symbol)
+            .map(|entry| (entry.key().1.to_string(), json!(entry.value())))
+            .collect();
+        Value::Object(by_interval)
+    }
+}
@@ -0,0 +1,287 @@
+//! Streams Binance klines and derives indicators, alerts and live feeds from
+//! them. The `crypto_kline_tracker` binary is a thin CLI over this library.
+//!
+//! A [`source`] such as a Binance stream feeds candles into a [`queue`], and
+//! [`process_kline_stream`] consumes that queue into [`Outputs`].
+
+pub mod actor;
+pub mod alerts;
+pub mod arbitrage;
+pub mod backtest;
+pub mod bench;
+pub mod chart;
+pub mod clock;
+pub mod config;
+pub mod control;
+pub mod dca;
+pub mod depeg;
+pub mod digest;
+pub mod events;
+pub mod expr;
+pub mod grid;
+pub mod halts;
+pub mod health;
+pub mod history;
+pub mod holdings;
+pub mod index;
+pub mod indicators;
+pub mod interval;
+pub mod journal;
+pub mod listings;
+pub mod live;
+pub mod metrics;
+pub mod mock;
+pub mod multiplex;
+pub mod notify;
+pub mod open_interest;
+pub mod ops;
+pub mod paper;
+pub mod portfolio;
+pub mod precision;
+pub mod processor;
+pub mod push;
+pub mod queue;
+pub mod ratio;
+pub mod replay;
+pub mod risk;
+pub mod rng;
+#[cfg(feature = "scripting")]
+pub mod script;
+#[cfg(feature = "server")]
+pub mod server;
+pub mod session;
+pub mod shard;
+pub mod sinks;
+pub mod snapshot;
+pub mod source;
+pub mod state;
+pub mod stats;
+pub mod store;
+pub mod strategy;
+pub mod streams;
+#[cfg(feature = "tui")]
+pub mod tui;
+pub mod walkforward;
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, TimeZone, Utc};
+use health::Health;
+use metrics::Metrics;
+use queue::KlineSender;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use source::WebSocketSource;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio_util::sync::CancellationToken;
+use tracing::{instrument, warn};
+
+pub use processor::{process_kline_stream, OutputFormat, Outputs};
+
+/// A (symbol, interval) series. The names are shared, so keys are cheap to
+/// clone on every update.
+pub type SeriesKey = (Arc<str>, Arc<str>);
+
+/// One kline update: the candle for `interval_start`, final once `closed`.
+#[derive(Debug, Clone)]
+pub struct KlineData {
+    pub symbol: Arc<str>,
+    pub interval: Arc<str>,
+    pub interval_start: DateTime<Utc>,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub volume: f64,
+    pub closed: bool,
+    /// Set for updates parsed from a live message; `None` for stored,
+    /// backfilled and synthetic candles.
+    pub timing: Option<Timing>,
+}
+
+/// When a live update left the exchange and passed through parsing, for
+/// latency metrics.
+#[derive(Debug, Clone, Copy)]
+pub struct Timing {
+    /// How long after the exchange's event time the message was read, by
+    /// the local clock, which may be skewed; `None` if the message carries
+    /// no event time.
+    pub exchange: Option<Duration>,
+    /// The exchange's event time, which replays of a capture keep.
+    pub event_time: Option<DateTime<Utc>>,
+    /// When parsing began, as soon as the message was read.
+    pub received: Instant,
+    pub parsed: Instant,
+}
+
+impl Timing {
+    fn new(event_time: Option<i64>, received: Instant) -> Self {
+        let exchange = event_time.map(|event_time| {
+            let behind = Utc::now().timestamp_millis().saturating_sub(event_time);
+            Duration::from_millis(behind.max(0) as u64)
+        });
+        Self {
+            exchange,
+            event_time: event_time
+                .and_then(|event_time| Utc.timestamp_millis_opt(event_time).single()),
+            received,
+            parsed: Instant::now(),
+        }
+    }
+}
+
+impl KlineData {
+    /// Parses a Binance kline message, or `None` for any other message.
+    /// Fields are read straight from `message` rather than through an
+    /// intermediate JSON tree, and the names are shared, not copied.
+    pub fn parse(symbol: &Arc<str>, interval: &Arc<str>, message: &str) -> Result<Option<Self>> {
+        let received = Instant::now();
+        let message = serde_json::from_str::<RawMessage>(message)?;
+        let Some(kline) = message.k else {
+            return Ok(None);
+        };
+        let mut kline = Self::from_raw((symbol.clone(), interval.clone()), kline)?;
+        kline.timing = Some(Timing::new(message.event_time, received));
+        Ok(Some(kline))
+    }
+
+    /// Parses a message from a combined stream, which names the stream it
+    /// came from, e.g. `btcusdt@kline_1m`. `series` gives the series of the
+    /// named stream; messages of unknown streams and anything other than a
+    /// kline are `None`.
+    pub fn parse_combined(
+        message: &str,
+        series: impl FnOnce(&str) -> Option<SeriesKey>,
+    ) -> Result<Option<Self>> {
+        let received = Instant::now();
+        let combined = serde_json::from_str::<RawCombined>(message)?;
+        let (Some(stream), Some(data)) = (combined.stream, combined.data) else {
+            return Ok(None);
+        };
+        let Some(kline) = data.k else {
+            return Ok(None);
+        };
+        let Some(key) = series(stream) else {
+            return Ok(None);
+        };
+        let mut kline = Self::from_raw(key, kline)?;
+        kline.timing = Some(Timing::new(data.event_time, received));
+        Ok(Some(kline))
+    }
+
+    fn from_raw((symbol, interval): SeriesKey, kline: RawKline) -> Result<Self> {
+        Ok(Self {
+            symbol,
+            interval,
+            interval_start: Utc
+                .timestamp_millis_opt(kline.t)
+                .single()
+                .ok_or_else(|| anyhow!("Invalid timestamp"))?,
+            open: parse_number(kline.o, "open price")?,
+            high: parse_number(kline.h, "high price")?,
+            low: parse_number(kline.l, "low price")?,
+            close: parse_number(kline.c, "close price")?,
+            volume: parse_number(kline.v, "volume")?,
+            closed: kline.x,
+            timing: None,
+        })
+    }
+
+    /// The series this update belongs to.
+    pub fn key(&self) -> SeriesKey {
+        (self.symbol.clone(), self.interval.clone())
+    }
+
+    pub fn price_change(&self) -> f64 {
+        self.close - self.open
+    }
+
+    /// The change as a percentage of the open; 0 for a candle opening at 0,
+    /// such as the first of a newly listed pair, which has no base to
+    /// measure against.
+    pub fn price_change_percent(&self) -> f64 {
+        if self.open == 0.0 {
+            return 0.0;
+        }
+        (self.price_change() / self.open) * 100.0
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "symbol": self.symbol,
+            "interval": self.interval,
+            "interval_start": self.interval_start.to_rfc3339(),
+            "open": self.open,
+            "high": self.high,
+            "low": self.low,
+            "close": self.close,
+            "volume": self.volume,
+            "closed": self.closed,
+        })
+    }
+}
+
+/// The parts of a Binance kline message the tracker reads.
+#[derive(Deserialize)]
+struct RawMessage<'a> {
+    #[serde(rename = "E")]
+    event_time: Option<i64>,
+    #[serde(borrow)]
+    k: Option<RawKline<'a>>,
+}
+
+/// A message on a combined stream, wrapping the stream's own message.
+#[derive(Deserialize)]
+struct RawCombined<'a> {
+    #[serde(borrow)]
+    stream: Option<&'a str>,
+    #[serde(borrow)]
+    data: Option<RawMessage<'a>>,
+}
+
+#[derive(Deserialize)]
+struct RawKline<'a> {
+    t: i64,
+    o: &'a str,
+    h: &'a str,
+    l: &'a str,
+    c: &'a str,
+    v: &'a str,
+    #[serde(default)]
+    x: bool,
+}
+
+/// Parses a price or quantity, which Binance sends as a decimal string.
+/// Rust also reads `NaN` and `inf`, which no real update carries and which
+/// would poison every indicator and aggregate fed with them.
+fn parse_number(text: &str, what: &str) -> Result<f64> {
+    text.parse()
+        .ok()
+        .filter(|number: &f64| number.is_finite())
+        .ok_or_else(|| anyhow!("Failed to parse {} '{}'", what, text))
+}
+
+/// Base of Binance's WebSocket stream URLs.
+pub const STREAM_URL: &str = "wss://stream.binance.com:9443";
+
+/// Streams `symbol` on `interval` from Binance, or whatever serves its
+/// streams at `stream_url`, into `tx` until the connection closes, or until
+/// `stopping` is cancelled, which closes it.
+#[instrument(name = "connection", skip_all, fields(symbol = %symbol, interval = %interval))]
+pub async fn run_websocket(
+    stream_url: &str,
+    symbol: String,
+    interval: String,
+    tx: KlineSender,
+    health: Health,
+    metrics: Metrics,
+    stopping: CancellationToken,
+) -> Result<()> {
+    let stream = WebSocketSource::new(&symbol, &interval, health, metrics, stopping.clone())
+        .with_stream_url(stream_url);
+    source::feed(Box::new(stream), |_| tx.clone()).await?;
+    if !stopping.is_cancelled() {
+        warn!("WebSocket connection closed for {} {}", symbol, interval);
+    }
+    Ok(())
+}
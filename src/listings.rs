@@ -0,0 +1,194 @@
+//! New trading pairs, found by polling Binance's exchange info and diffing
+//! it against the pairs seen before, so a listing is caught as soon as the
+//! pair is announced, usually before trading opens.
+
+use crate::alerts::Alert;
+use crate::clock::SharedClock;
+use crate::config::ListingsConfig;
+use crate::events::Event;
+use crate::notify::Notification;
+use crate::streams::Streams;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc};
+use tracing::{debug, info, warn};
+
+const EXCHANGE_INFO_PATH: &str = "/api/v3/exchangeInfo";
+
+/// The rule name listing alerts are reported under.
+pub const LISTING_RULE: &str = "listing";
+
+/// A trading pair as listed in the exchange info.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SymbolInfo {
+    /// E.g. `BTCUSDT`.
+    pub symbol: String,
+    /// `TRADING`, or e.g. `PRE_TRADING` for a pair announced but not yet open.
+    pub status: String,
+    pub base_asset: String,
+    pub quote_asset: String,
+    #[serde(default)]
+    pub filters: Vec<SymbolFilter>,
+}
+
+impl SymbolInfo {
+    /// The decimals of the pair's tick size, if its price filter has one.
+    pub fn price_decimals(&self) -> Option<usize> {
+        self.tick_size().and_then(crate::precision::tick_decimals)
+    }
+
+    /// The step prices must be a multiple of, from the price filter.
+    pub fn tick_size(&self) -> Option<&str> {
+        self.filter("PRICE_FILTER")?.tick_size.as_deref()
+    }
+
+    /// The step quantities must be a multiple of, from the lot size filter.
+    pub fn step_size(&self) -> Option<&str> {
+        self.filter("LOT_SIZE")?.step_size.as_deref()
+    }
+
+    fn filter(&self, filter_type: &str) -> Option<&SymbolFilter> {
+        self.filters
+            .iter()
+            .find(|filter| filter.filter_type == filter_type)
+    }
+}
+
+/// One of the trading rules of a pair. Only the price filter's tick size
+/// and the lot size's step are read.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SymbolFilter {
+    /// E.g. `PRICE_FILTER` or `LOT_SIZE`.
+    pub filter_type: String,
+    /// The price step, e.g. `0.00001000`.
+    pub tick_size: Option<String>,
+    /// The quantity step, e.g. `0.00100000`.
+    pub step_size: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ExchangeInfo {
+    symbols: Vec<SymbolInfo>,
+}
+
+/// The pairs of `current` not among `known`, quoted in one of
+/// `quote_assets`, or in anything if that is empty.
+pub fn new_listings<'a>(
+    known: &HashSet<String>,
+    current: &'a [SymbolInfo],
+    quote_assets: &[String],
+) -> Vec<&'a SymbolInfo> {
+    current
+        .iter()
+        .filter(|info| !known.contains(&info.symbol))
+        .filter(|info| quote_assets.is_empty() || quote_assets.contains(&info.quote_asset))
+        .collect()
+}
+
+/// The alert a new listing is reported as, on `interval`, the interval it
+/// would be streamed on.
+pub fn listing_alert(info: &SymbolInfo, interval: &str, now: DateTime<Utc>) -> Alert {
+    Alert {
+        rule: LISTING_RULE.to_string(),
+        symbol: info.symbol.to_lowercase(),
+        interval: interval.to_string(),
+        price: 0.0,
+        change_percent: 0.0,
+        message: format!(
+            "Binance listed {}/{} ({})",
+            info.base_asset, info.quote_asset, info.status
+        ),
+        triggered_at: now,
+    }
+}
+
+/// Sends an alert raised outside the alert engine to live subscribers and
+/// notifiers, as the processor does the engine's.
+pub(crate) fn publish(
+    alert: Alert,
+    events_tx: &broadcast::Sender<Event>,
+    notify_tx: &mpsc::Sender<Notification>,
+) {
+    let _ = events_tx.send(Event::Alert(alert.clone()));
+    if notify_tx.try_send(Notification::Alert(alert)).is_err() {
+        warn!("Notification queue is full; dropping alert notification");
+    }
+}
+
+/// Fetches every pair in the exchange info under `url`.
+pub async fn fetch(client: &reqwest::Client, url: &str) -> Result<Vec<SymbolInfo>> {
+    let url = format!("{}{}", url.trim_end_matches('/'), EXCHANGE_INFO_PATH);
+    let info: ExchangeInfo = client
+        .get(url)
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    Ok(info.symbols)
+}
+
+/// Polls the exchange info every `interval_secs` for as long as the tracker
+/// runs. The first poll only learns the pairs already listed; each pair
+/// appearing after it is reported as an alert on the shortest of
+/// `intervals` to `events_tx` and `notify_tx` and, with `subscribe`,
+/// streamed on all of them.
+pub async fn run(
+    config: ListingsConfig,
+    intervals: Vec<String>,
+    streams: Streams,
+    events_tx: broadcast::Sender<Event>,
+    notify_tx: mpsc::Sender<Notification>,
+    clock: SharedClock,
+) {
+    let client = match reqwest::Client::builder()
+        .timeout(Duration::from_secs(30))
+        .build()
+    {
+        Ok(client) => client,
+        Err(e) => {
+            warn!("Listing detection is disabled: {}", e);
+            return;
+        }
+    };
+    let shortest = intervals
+        .iter()
+        .min_by_key(|interval| crate::interval::interval_duration(interval))
+        .cloned()
+        .unwrap_or_default();
+    let mut known: Option<HashSet<String>> = None;
+    let mut timer = tokio::time::interval(Duration::from_secs(config.interval_secs));
+    loop {
+        timer.tick().await;
+        let current = match fetch(&client, &config.url).await {
+            Ok(current) => current,
+            Err(e) => {
+                warn!("Failed to poll the exchange info: {}", e);
+                continue;
+            }
+        };
+        let Some(known) = &mut known else {
+            debug!("Exchange info lists {} pairs", current.len());
+            known = Some(current.into_iter().map(|info| info.symbol).collect());
+            continue;
+        };
+        for info in new_listings(known, &current, &config.quote_assets) {
+            let alert = listing_alert(info, &shortest, clock.now());
+            warn!("NEW LISTING {}: {}", alert.symbol, alert.message);
+            let symbol = alert.symbol.clone();
+            publish(alert, &events_tx, &notify_tx);
+            if config.subscribe {
+                for interval in &intervals {
+                    streams.add(&symbol, interval);
+                }
+                info!("Subscribed to new listing {}", symbol);
+            }
+        }
+        known.extend(current.into_iter().map(|info| info.symbol));
+    }
+}
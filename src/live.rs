@@ -0,0 +1,804 @@
+//! Real orders on Binance's spot market: placed and cancelled over its signed
+//! REST API, and followed through the user-data stream.
+//!
+//! Signing needs the `live` feature, so a build without it can never place
+//! an order.
+
+use crate::config::{LiveConfig, PaperOrderConfig, Side, StrategyConfig};
+use crate::events::Event;
+use crate::journal::{Account, Journal, JournalEntry};
+use crate::listings::{self, SymbolInfo};
+use crate::risk::RiskLimits;
+use crate::state::{MarketState, SharedState};
+use crate::strategy::TradeSignal;
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::{DateTime, TimeZone, Utc};
+use futures_util::StreamExt;
+use reqwest::Method;
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio_tungstenite::connect_async;
+use tracing::{debug, info, warn};
+
+/// Binance closes a user-data stream whose listen key has not been renewed
+/// for an hour.
+const KEEPALIVE_EVERY: Duration = Duration::from_secs(30 * 60);
+
+/// How long to wait before reconnecting a dropped user-data stream.
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// How much an order trades.
+#[derive(Debug, Clone, Copy)]
+pub enum Amount {
+    /// Units of the base asset.
+    Quantity(f64),
+    /// Quote currency to spend or receive; market orders only.
+    Quote(f64),
+}
+
+/// An order as Binance last reported it.
+#[derive(Debug, Clone)]
+pub struct LiveOrder {
+    pub id: i64,
+    pub client_id: String,
+    pub symbol: String,
+    pub side: Side,
+    /// `MARKET` or `LIMIT`.
+    pub kind: String,
+    /// `None` for market orders.
+    pub price: Option<f64>,
+    /// Zero for market orders sized in quote currency.
+    pub quantity: f64,
+    pub filled: f64,
+    /// Quote value of the filled part.
+    pub filled_quote: f64,
+    /// Commission paid on the filled part, in `commission_asset`.
+    pub commission: f64,
+    /// Empty until something has filled.
+    pub commission_asset: String,
+    /// Binance's order status, e.g. `NEW`, `PARTIALLY_FILLED` or `FILLED`.
+    pub status: String,
+    pub reason: String,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl LiveOrder {
+    pub fn is_open(&self) -> bool {
+        matches!(self.status.as_str(), "NEW" | "PARTIALLY_FILLED")
+    }
+
+    /// Average price of the filled part, if any has filled.
+    pub fn average_price(&self) -> Option<f64> {
+        (self.filled > 0.0).then(|| self.filled_quote / self.filled)
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "id": self.id,
+            "client_id": self.client_id,
+            "symbol": self.symbol,
+            "side": self.side,
+            "kind": self.kind,
+            "price": self.price,
+            "quantity": self.quantity,
+            "filled": self.filled,
+            "filled_quote": self.filled_quote,
+            "commission": self.commission,
+            "commission_asset": self.commission_asset,
+            "average_price": self.average_price(),
+            "status": self.status,
+            "reason": self.reason,
+            "updated_at": self.updated_at.to_rfc3339(),
+        })
+    }
+
+    /// Reads an order from a REST response, with the commission of the
+    /// fills it lists.
+    fn from_response(value: &Value) -> Result<Self> {
+        let time = value["updateTime"]
+            .as_i64()
+            .or_else(|| value["transactTime"].as_i64());
+        let price = decimal(&value["price"]).filter(|price| *price > 0.0);
+        let fills = value["fills"]
+            .as_array()
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        Ok(Self {
+            id: value["orderId"]
+                .as_i64()
+                .ok_or_else(|| anyhow!("Order response without an orderId: {}", value))?,
+            client_id: value["clientOrderId"]
+                .as_str()
+                .unwrap_or_default()
+                .to_string(),
+            symbol: value["symbol"].as_str().unwrap_or_default().to_lowercase(),
+            side: side(&value["side"])?,
+            kind: value["type"].as_str().unwrap_or_default().to_string(),
+            price,
+            quantity: decimal(&value["origQty"]).unwrap_or_default(),
+            filled: decimal(&value["executedQty"]).unwrap_or_default(),
+            filled_quote: decimal(&value["cummulativeQuoteQty"]).unwrap_or_default(),
+            commission: fills
+                .iter()
+                .filter_map(|fill| decimal(&fill["commission"]))
+                .sum(),
+            commission_asset: fills
+                .first()
+                .and_then(|fill| fill["commissionAsset"].as_str())
+                .unwrap_or_default()
+                .to_string(),
+            status: value["status"].as_str().unwrap_or_default().to_string(),
+            reason: String::new(),
+            updated_at: time
+                .and_then(|ms| Utc.timestamp_millis_opt(ms).single())
+                .unwrap_or_else(Utc::now),
+        })
+    }
+
+    /// Reads an order from a user-data stream `executionReport`. Its
+    /// commission is only that of the trade reported, which
+    /// [`Book::record_report`] adds to what was paid before.
+    fn from_execution_report(value: &Value) -> Result<Self> {
+        let price = decimal(&value["p"]).filter(|price| *price > 0.0);
+        Ok(Self {
+            id: value["i"]
+                .as_i64()
+                .ok_or_else(|| anyhow!("Execution report without an order id"))?,
+            // A cancellation reports the cancelled order's id as `C`.
+            client_id: value["C"]
+                .as_str()
+                .filter(|id| !id.is_empty())
+                .or_else(|| value["c"].as_str())
+                .unwrap_or_default()
+                .to_string(),
+            symbol: value["s"].as_str().unwrap_or_default().to_lowercase(),
+            side: side(&value["S"])?,
+            kind: value["o"].as_str().unwrap_or_default().to_string(),
+            price,
+            quantity: decimal(&value["q"]).unwrap_or_default(),
+            filled: decimal(&value["z"]).unwrap_or_default(),
+            filled_quote: decimal(&value["Z"]).unwrap_or_default(),
+            commission: decimal(&value["n"]).unwrap_or_default(),
+            commission_asset: value["N"].as_str().unwrap_or_default().to_string(),
+            status: value["X"].as_str().unwrap_or_default().to_string(),
+            reason: String::new(),
+            updated_at: value["E"]
+                .as_i64()
+                .and_then(|ms| Utc.timestamp_millis_opt(ms).single())
+                .unwrap_or_else(Utc::now),
+        })
+    }
+}
+
+/// What `commission` paid on a fill of `order` at `price` is worth in the
+/// quote asset. Commission in neither of the pair's assets, such as BNB, is
+/// not counted.
+fn fee_value(order: &LiveOrder, commission: f64, price: f64) -> f64 {
+    let asset = order.commission_asset.to_lowercase();
+    if commission <= 0.0 || asset.is_empty() {
+        0.0
+    } else if order.symbol.ends_with(&asset) {
+        commission
+    } else if order.symbol.starts_with(&asset) {
+        commission * price
+    } else {
+        warn!(
+            "Not counting the commission of {} {} on order {}",
+            commission, order.commission_asset, order.id
+        );
+        0.0
+    }
+}
+
+/// Binance sends decimals as strings.
+fn decimal(value: &Value) -> Option<f64> {
+    value.as_str()?.parse().ok()
+}
+
+fn side(value: &Value) -> Result<Side> {
+    match value.as_str() {
+        Some("BUY") => Ok(Side::Buy),
+        Some("SELL") => Ok(Side::Sell),
+        other => bail!("Unknown order side {:?}", other),
+    }
+}
+
+/// Formats a decimal as Binance accepts it, without exponent or trailing
+/// zeros.
+pub fn format_decimal(value: f64) -> String {
+    let text = format!("{:.8}", value);
+    text.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+/// `value` as a multiple of `step`, a decimal as the exchange info gives
+/// it, rounded with `round`; unchanged without a valid step.
+pub fn to_step(value: f64, step: Option<&str>, round: fn(f64) -> f64) -> f64 {
+    match step.and_then(|step| step.parse::<f64>().ok()) {
+        // Allows for binary rounding, so that 0.3 is 3 steps of 0.1.
+        Some(step) if step > 0.0 => round(value / step + 1e-9) * step,
+        _ => value,
+    }
+}
+
+#[cfg(feature = "live")]
+fn sign(secret: &str, payload: &str) -> Result<String> {
+    use hmac::{Hmac, Mac};
+    use sha2::Sha256;
+
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())?;
+    mac.update(payload.as_bytes());
+    Ok(mac
+        .finalize()
+        .into_bytes()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect())
+}
+
+#[cfg(not(feature = "live"))]
+fn sign(_secret: &str, _payload: &str) -> Result<String> {
+    bail!("Live trading needs the `live` feature")
+}
+
+fn encode(params: &[(&str, String)]) -> String {
+    url::form_urlencoded::Serializer::new(String::new())
+        .extend_pairs(params.iter().map(|(key, value)| (*key, value.as_str())))
+        .finish()
+}
+
+/// Binance's REST API, authenticated with an API key.
+struct Client {
+    http: reqwest::Client,
+    rest_url: String,
+    api_key: String,
+    api_secret: String,
+    recv_window_ms: u64,
+}
+
+impl Client {
+    /// Sends a request signed with the API secret.
+    async fn signed(&self, method: Method, path: &str, params: &[(&str, String)]) -> Result<Value> {
+        let mut params = params.to_vec();
+        params.push(("recvWindow", self.recv_window_ms.to_string()));
+        params.push(("timestamp", Utc::now().timestamp_millis().to_string()));
+        let query = encode(&params);
+        let signature = sign(&self.api_secret, &query)?;
+        let url = format!(
+            "{}{}?{}&signature={}",
+            self.rest_url, path, query, signature
+        );
+        self.send(method, url).await
+    }
+
+    /// Sends a request carrying only the API key, as the user-data stream
+    /// endpoints take.
+    async fn keyed(&self, method: Method, path: &str, params: &[(&str, String)]) -> Result<Value> {
+        let url = format!("{}{}?{}", self.rest_url, path, encode(params));
+        self.send(method, url).await
+    }
+
+    async fn send(&self, method: Method, url: String) -> Result<Value> {
+        let response = self
+            .http
+            .request(method, url)
+            .header("X-MBX-APIKEY", &self.api_key)
+            .send()
+            .await?;
+        let status = response.status();
+        let body: Value = response.json().await.unwrap_or(Value::Null);
+        if !status.is_success() {
+            bail!(
+                "Binance rejected the request with {} ({}): {}",
+                status,
+                body["code"],
+                body["msg"].as_str().unwrap_or("no message")
+            );
+        }
+        Ok(body)
+    }
+}
+
+/// Orders placed by the tracker and what they have bought and sold.
+struct Book {
+    orders: BTreeMap<i64, LiveOrder>,
+    /// Net base quantity filled per symbol, which strategy sells close.
+    held: HashMap<String, f64>,
+    /// Average price paid for what is held of each symbol.
+    entry_prices: HashMap<String, f64>,
+    /// Quote value of the buys of each symbol sent but not yet confirmed.
+    pending: HashMap<String, f64>,
+    risk: RiskLimits,
+    journal: Journal,
+}
+
+impl Book {
+    /// Quote value of the unfilled part of the open buys of `symbol` and of
+    /// those being placed. Open market buys are valued at `price`.
+    fn buying(&self, symbol: &str, price: f64) -> f64 {
+        let open: f64 = self
+            .orders
+            .values()
+            .filter(|order| order.symbol == symbol && order.side == Side::Buy && order.is_open())
+            .map(|order| (order.quantity - order.filled) * order.price.unwrap_or(price))
+            .sum();
+        open + self.pending.get(symbol).copied().unwrap_or_default()
+    }
+
+    /// Stores an execution report of `order`, adding the commission of the
+    /// trade it reports to what the order had paid before.
+    fn record_report(&mut self, mut order: LiveOrder) -> LiveOrder {
+        if let Some(previous) = self.orders.get(&order.id) {
+            if order.filled > previous.filled {
+                order.commission += previous.commission;
+            } else {
+                order.commission = previous.commission;
+            }
+        }
+        self.record(order)
+    }
+
+    /// Stores the latest state of `order`, counting and journaling anything
+    /// newly filled with its commission, and returns it with the reason it
+    /// was placed for.
+    fn record(&mut self, mut order: LiveOrder) -> LiveOrder {
+        let previous = self.orders.get(&order.id);
+        let filled_before = previous.map_or(0.0, |previous| previous.filled);
+        let commission_before = previous.map_or(0.0, |previous| previous.commission);
+        if let Some(previous) = previous {
+            if order.reason.is_empty() {
+                order.reason = previous.reason.clone();
+            }
+            // Updates can arrive out of order between REST and the stream.
+            if previous.updated_at > order.updated_at || previous.filled > order.filled {
+                return previous.clone();
+            }
+            // Responses without fills, such as a cancellation's, keep what
+            // was paid.
+            if order.commission < previous.commission {
+                order.commission = previous.commission;
+            }
+            if order.commission_asset.is_empty() {
+                order.commission_asset = previous.commission_asset.clone();
+            }
+        }
+        let newly_filled = order.filled - filled_before;
+        if newly_filled > 0.0 {
+            let quote_before = previous.map_or(0.0, |previous| previous.filled_quote);
+            let fill_price = (order.filled_quote - quote_before) / newly_filled;
+            let fee = fee_value(&order, order.commission - commission_before, fill_price);
+            self.risk.record_pnl(-fee);
+            let held = self.held.entry(order.symbol.clone()).or_default();
+            let entry = self.entry_prices.entry(order.symbol.clone()).or_default();
+            let (entry_price, pnl) = match order.side {
+                Side::Buy => {
+                    *entry = (*entry * *held + fill_price * newly_filled) / (*held + newly_filled);
+                    *held += newly_filled;
+                    (None, None)
+                }
+                Side::Sell => {
+                    // Only what the tracker bought has a known cost.
+                    let closed = newly_filled.min(*held);
+                    let pnl = (fill_price - *entry) * closed;
+                    self.risk.record_pnl(pnl);
+                    *held -= closed;
+                    if closed > 0.0 {
+                        (Some(*entry), Some(pnl))
+                    } else {
+                        (None, None)
+                    }
+                }
+            };
+            self.journal.record(JournalEntry {
+                account: Account::Live,
+                order_id: order.id.to_string(),
+                symbol: order.symbol.clone(),
+                side: order.side,
+                quantity: newly_filled,
+                price: fill_price,
+                fee,
+                entry_price,
+                pnl,
+                reason: order.reason.clone(),
+                indicators: Value::Null,
+                filled_at: order.updated_at,
+            });
+        }
+        self.orders.insert(order.id, order.clone());
+        order
+    }
+}
+
+/// A Binance account the tracker trades for real, shared between the trading
+/// actor, the user-data stream and the control socket.
+#[derive(Clone)]
+pub struct Live {
+    client: Arc<Client>,
+    book: Arc<Mutex<Book>>,
+    /// Each pair's trading rules, by its uppercase symbol, fetched once
+    /// from the exchange info.
+    rules: Arc<Mutex<HashMap<String, SymbolInfo>>>,
+    next_client_id: Arc<AtomicU64>,
+    stream_url: String,
+}
+
+impl Live {
+    /// Takes the API key and secret from `config`, or else from the
+    /// `BINANCE_API_KEY` and `BINANCE_API_SECRET` environment variables.
+    /// Fills go into `journal`.
+    pub fn new(config: &LiveConfig, risk: RiskLimits, journal: Journal) -> Result<Self> {
+        let credential = |value: &str, variable: &str| -> Result<String> {
+            if !value.is_empty() {
+                return Ok(value.to_string());
+            }
+            std::env::var(variable)
+                .ok()
+                .filter(|value| !value.is_empty())
+                .with_context(|| format!("Live trading needs {} or its config key", variable))
+        };
+        let client = Client {
+            http: reqwest::Client::builder()
+                .timeout(Duration::from_secs(config.timeout_secs))
+                .build()?,
+            rest_url: config.rest_url.trim_end_matches('/').to_string(),
+            api_key: credential(&config.api_key, "BINANCE_API_KEY")?,
+            api_secret: credential(&config.api_secret, "BINANCE_API_SECRET")?,
+            recv_window_ms: config.recv_window_ms,
+        };
+        Ok(Self {
+            client: Arc::new(client),
+            book: Arc::new(Mutex::new(Book {
+                orders: BTreeMap::new(),
+                held: HashMap::new(),
+                entry_prices: HashMap::new(),
+                pending: HashMap::new(),
+                risk,
+                journal,
+            })),
+            rules: Arc::default(),
+            next_client_id: Arc::new(AtomicU64::new(1)),
+            stream_url: config.stream_url.trim_end_matches('/').to_string(),
+        })
+    }
+
+    fn book(&self) -> MutexGuard<'_, Book> {
+        self.book.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// The trading rules of `symbol`, fetched with every other pair's the
+    /// first time, and again for a pair listed since.
+    async fn rules(&self, symbol: &str) -> Result<SymbolInfo> {
+        let symbol = symbol.to_uppercase();
+        let cached = |rules: &Mutex<HashMap<String, SymbolInfo>>| {
+            rules
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .get(&symbol)
+                .cloned()
+        };
+        if let Some(info) = cached(&self.rules) {
+            return Ok(info);
+        }
+        let pairs = listings::fetch(&self.client.http, &self.client.rest_url)
+            .await
+            .with_context(|| format!("Failed to fetch the trading rules of {}", symbol))?;
+        self.rules
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .extend(pairs.into_iter().map(|info| (info.symbol.clone(), info)));
+        cached(&self.rules).with_context(|| format!("{} is not listed on Binance", symbol))
+    }
+
+    /// Places an order for `amount` of `symbol`: a limit order at `limit`
+    /// if given, or else a market order. `price` is the latest price, which
+    /// market orders are checked against the risk limits at. Quantities are
+    /// rounded down to the pair's lot size step and limits to its tick
+    /// size. The order counts towards the order rate, and a buy towards
+    /// the position limit, from when it is checked; the rate gets its slot
+    /// back if Binance cannot be reached or rejects it.
+    pub async fn place(
+        &self,
+        symbol: &str,
+        side: Side,
+        amount: Amount,
+        limit: Option<f64>,
+        price: Option<f64>,
+        reason: &str,
+    ) -> Result<LiveOrder> {
+        let info = self.rules(symbol).await?;
+        let amount = match amount {
+            Amount::Quantity(quantity) if quantity > 0.0 => {
+                let rounded = to_step(quantity, info.step_size(), f64::floor);
+                if rounded <= 0.0 {
+                    bail!(
+                        "{} {} is less than its lot size step of {}",
+                        quantity,
+                        symbol,
+                        info.step_size().unwrap_or_default()
+                    );
+                }
+                Amount::Quantity(rounded)
+            }
+            amount => amount,
+        };
+        let limit = limit.map(|limit| to_step(limit, info.tick_size(), f64::round));
+        let client_id = format!(
+            "ckt-{}-{}",
+            Utc::now().timestamp_millis(),
+            self.next_client_id.fetch_add(1, Ordering::Relaxed)
+        );
+        let mut params = vec![
+            ("symbol", symbol.to_uppercase()),
+            ("side", side.to_string().to_uppercase()),
+            ("newClientOrderId", client_id),
+            ("newOrderRespType", "FULL".to_string()),
+        ];
+        match (amount, limit) {
+            (Amount::Quantity(quantity), _) if quantity <= 0.0 => {
+                bail!("Cannot {} {} {}", side, quantity, symbol)
+            }
+            (Amount::Quote(_), Some(_)) => bail!("A limit order needs a base quantity"),
+            (Amount::Quote(quote), None) => {
+                params.push(("type", "MARKET".to_string()));
+                params.push(("quoteOrderQty", format_decimal(quote)));
+            }
+            (Amount::Quantity(quantity), None) => {
+                params.push(("type", "MARKET".to_string()));
+                params.push(("quantity", format_decimal(quantity)));
+            }
+            (Amount::Quantity(quantity), Some(price)) => {
+                params.push(("type", "LIMIT".to_string()));
+                params.push(("timeInForce", "GTC".to_string()));
+                params.push(("quantity", format_decimal(quantity)));
+                params.push(("price", format_decimal(price)));
+            }
+        }
+        let reserved = self.reserve(symbol, side, amount, limit.or(price))?;
+        let response = self
+            .client
+            .signed(Method::POST, "/api/v3/order", &params)
+            .await;
+        let mut book = self.book();
+        if let Some(pending) = book.pending.get_mut(symbol) {
+            *pending -= reserved;
+        }
+        let response = response.inspect_err(|_| book.risk.release_order())?;
+        let mut order = LiveOrder::from_response(&response)?;
+        order.reason = reason.to_string();
+        Ok(book.record(order))
+    }
+
+    /// Checks an order against the risk limits at `price`, which buys sized
+    /// in base units need, and counts it towards the order rate. A buy's
+    /// value is held as pending until Binance answers, so orders placed
+    /// meanwhile see it. Returns the value held.
+    fn reserve(&self, symbol: &str, side: Side, amount: Amount, price: Option<f64>) -> Result<f64> {
+        let mut book = self.book();
+        let held = book.held.get(symbol).copied().unwrap_or_default();
+        let (value, held_value) = match (amount, price) {
+            (Amount::Quantity(quantity), Some(price)) => {
+                (quantity * price, held * price + book.buying(symbol, price))
+            }
+            (Amount::Quote(quote), Some(price)) => {
+                (quote, held * price + book.buying(symbol, price))
+            }
+            (Amount::Quote(quote), None) => {
+                let entry = book.entry_prices.get(symbol).copied().unwrap_or_default();
+                (quote, held * entry + book.buying(symbol, entry))
+            }
+            (Amount::Quantity(_), None) if side == Side::Sell => (0.0, 0.0),
+            (Amount::Quantity(_), None) => {
+                bail!("No price for {} yet to check the risk limits at", symbol)
+            }
+        };
+        book.risk.approve(symbol, side, value, held_value)?;
+        if side == Side::Sell {
+            return Ok(0.0);
+        }
+        *book.pending.entry(symbol.to_string()).or_default() += value;
+        Ok(value)
+    }
+
+    /// Cancels the open order `id` of `symbol`.
+    pub async fn cancel(&self, symbol: &str, id: i64) -> Result<LiveOrder> {
+        let params = [
+            ("symbol", symbol.to_uppercase()),
+            ("orderId", id.to_string()),
+        ];
+        let response = self
+            .client
+            .signed(Method::DELETE, "/api/v3/order", &params)
+            .await?;
+        Ok(self.book().record(LiveOrder::from_response(&response)?))
+    }
+
+    /// Places the order `order` describes for `symbol`, whose latest price
+    /// is `price`, as paper trading would.
+    pub async fn place_config(
+        &self,
+        order: &PaperOrderConfig,
+        symbol: &str,
+        price: f64,
+        reason: &str,
+    ) -> Result<LiveOrder> {
+        let limit = order.limit_offset_pct.map(|offset| match order.side {
+            Side::Buy => price * (1.0 - offset / 100.0),
+            Side::Sell => price * (1.0 + offset / 100.0),
+        });
+        let amount = match (order.quantity, order.quote_amount, limit) {
+            (Some(quantity), _, _) => Amount::Quantity(quantity),
+            (None, Some(quote), Some(limit)) => Amount::Quantity(quote / limit),
+            (None, Some(quote), None) => Amount::Quote(quote),
+            (None, None, _) => {
+                let held = self.held(symbol);
+                if held <= 0.0 {
+                    bail!("Cannot {} {}: nothing was bought live", order.side, symbol);
+                }
+                Amount::Quantity(held)
+            }
+        };
+        self.place(symbol, order.side, amount, limit, Some(price), reason)
+            .await
+    }
+
+    /// Places the order `strategy` ties to its signal `signal`.
+    pub async fn on_signal(
+        &self,
+        strategy: &StrategyConfig,
+        signal: &TradeSignal,
+    ) -> Result<LiveOrder> {
+        let reason = format!("{}: {}", signal.strategy, signal.reason);
+        self.place_config(
+            &strategy.order(signal.side),
+            &signal.symbol,
+            signal.price,
+            &reason,
+        )
+        .await
+    }
+
+    /// Net base quantity of `symbol` filled by the tracker's orders.
+    pub fn held(&self, symbol: &str) -> f64 {
+        self.book().held.get(symbol).copied().unwrap_or_default()
+    }
+
+    /// Every order placed this run, newest first.
+    pub fn orders(&self) -> Vec<LiveOrder> {
+        self.book().orders.values().rev().cloned().collect()
+    }
+
+    pub fn to_json(&self) -> Value {
+        let book = self.book();
+        json!({
+            "orders": book.orders.values().rev().map(LiveOrder::to_json).collect::<Vec<_>>(),
+            "held": book.held,
+            "risk": book.risk.to_json(),
+        })
+    }
+
+    /// Applies one user-data stream message, returning the order it updated.
+    pub fn on_stream_message(&self, text: &str) -> Result<Option<LiveOrder>> {
+        let value: Value = serde_json::from_str(text)?;
+        if value["e"] != "executionReport" {
+            return Ok(None);
+        }
+        Ok(Some(
+            self.book()
+                .record_report(LiveOrder::from_execution_report(&value)?),
+        ))
+    }
+}
+
+/// Places the orders of `strategies` named in `enabled` for each of their
+/// signals on the live event feed, until it closes. Prices are logged with
+/// the precision in `state`.
+pub async fn run(
+    live: Live,
+    strategies: Vec<StrategyConfig>,
+    enabled: Vec<String>,
+    mut events: broadcast::Receiver<Event>,
+    state: SharedState,
+) {
+    loop {
+        match events.recv().await {
+            Ok(Event::Signal(signal)) => {
+                if !enabled.contains(&signal.strategy) {
+                    continue;
+                }
+                let Some(strategy) = strategies.iter().find(|s| s.name() == signal.strategy) else {
+                    continue;
+                };
+                match live.on_signal(strategy, &signal).await {
+                    Ok(order) => log_order(&order, &state),
+                    Err(e) => warn!(
+                        "Failed to place live order for {} on {}: {}",
+                        signal.strategy, signal.symbol, e
+                    ),
+                }
+            }
+            Ok(_) => {}
+            Err(RecvError::Lagged(skipped)) => {
+                warn!("Live trading fell behind; skipped {} events", skipped);
+            }
+            Err(RecvError::Closed) => break,
+        }
+    }
+}
+
+/// Follows the account's user-data stream, updating the state of the
+/// tracker's orders as Binance reports on them. Reconnects whenever the
+/// stream drops.
+pub async fn run_user_data(live: Live, state: SharedState) {
+    loop {
+        if let Err(e) = follow_user_data(&live, &state).await {
+            warn!("User-data stream failed: {}", e);
+        }
+        tokio::time::sleep(RECONNECT_DELAY).await;
+    }
+}
+
+async fn follow_user_data(live: &Live, state: &MarketState) -> Result<()> {
+    let response = live
+        .client
+        .keyed(Method::POST, "/api/v3/userDataStream", &[])
+        .await?;
+    let listen_key = response["listenKey"]
+        .as_str()
+        .context("Binance returned no listen key")?
+        .to_string();
+    let (mut stream, _) = connect_async(format!("{}/{}", live.stream_url, listen_key)).await?;
+    info!("Connected to the user-data stream");
+
+    let mut keepalive = tokio::time::interval(KEEPALIVE_EVERY);
+    keepalive.tick().await;
+    loop {
+        tokio::select! {
+            message = stream.next() => {
+                let Some(message) = message else {
+                    bail!("The user-data stream closed");
+                };
+                let message = message?;
+                let Ok(text) = message.to_text() else {
+                    continue;
+                };
+                if text.is_empty() {
+                    continue;
+                }
+                match live.on_stream_message(text) {
+                    Ok(Some(order)) => log_order(&order, state),
+                    Ok(None) => debug!("Ignoring user-data message: {}", text),
+                    Err(e) => warn!("Skipping unparseable user-data message: {}", e),
+                }
+            }
+            _ = keepalive.tick() => {
+                let params = [("listenKey", listen_key.clone())];
+                live.client
+                    .keyed(Method::PUT, "/api/v3/userDataStream", &params)
+                    .await?;
+            }
+        }
+    }
+}
+
+/// Logs `order`, its price shown with the precision `state` has for the
+/// symbol.
+pub fn log_order(order: &LiveOrder, state: &MarketState) {
+    let price = order.average_price().or(order.price).map_or_else(
+        || "market".to_string(),
+        |price| format!("{:.*}", state.decimals(&order.symbol, price), price),
+    );
+    info!(
+        "LIVE order {} {}: {} {}/{} {} at {}; {}",
+        order.id,
+        order.status,
+        order.side,
+        order.filled,
+        order.quantity,
+        order.symbol,
+        price,
+        order.reason
+    );
+}
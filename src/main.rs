@@ -1,191 +1,1125 @@
 use anyhow::{anyhow, Result};
-use chrono::{DateTime, Local, TimeZone, Utc};
-use futures_util::StreamExt;
-use log::{debug, error, info, warn};
-use rayon::prelude::*;
-use serde_json::Value;
-use std::collections::HashMap;
-use tokio::sync::mpsc;
-use tokio_tungstenite::connect_async;
-
-#[derive(Debug, Clone)]
-struct KlineData {
-    symbol: String,
-    interval: String,
-    interval_start: DateTime<Utc>,
-    open: f64,
-    high: f64,
-    low: f64,
-    close: f64,
-    volume: f64,
-}
-
-impl KlineData {
-    fn new(symbol: String, interval: String, kline: &Value) -> Result<Self> {
-        Ok(Self {
-            symbol,
-            interval,
-            interval_start: parse_timestamp(kline)?,
-            open: parse_price(kline, "o")?,
-            high: parse_price(kline, "h")?,
-            low: parse_price(kline, "l")?,
-            close: parse_price(kline, "c")?,
-            volume: parse_volume(kline)?,
-        })
-    }
+use chrono::{DateTime, Local, Utc};
+use clap::{Parser, Subcommand, ValueEnum};
+use crypto_kline_tracker::actor::{PanicReporter, Supervisor};
+use crypto_kline_tracker::alerts::AlertEngine;
+use crypto_kline_tracker::backtest;
+use crypto_kline_tracker::bench::{self, CountingAlloc};
+use crypto_kline_tracker::config::{Config, RuntimeConfig, RuntimeFlavor};
+use crypto_kline_tracker::expr::Expr;
+use crypto_kline_tracker::health::Health;
+use crypto_kline_tracker::indicators::{IndicatorPipeline, IndicatorRegistry};
+use crypto_kline_tracker::journal::{Account, Journal, JournalEntry};
+use crypto_kline_tracker::live::{self, Live};
+use crypto_kline_tracker::metrics::Metrics;
+use crypto_kline_tracker::mock::{self, Chaos, MockServer};
+use crypto_kline_tracker::paper::{self, Paper};
+#[cfg(feature = "server")]
+use crypto_kline_tracker::portfolio::Portfolio;
+use crypto_kline_tracker::replay;
+use crypto_kline_tracker::risk::{KillSwitch, RiskLimits};
+#[cfg(feature = "server")]
+use crypto_kline_tracker::server;
+use crypto_kline_tracker::shard::{Router, Shards};
+use crypto_kline_tracker::sinks::Sinks;
+use crypto_kline_tracker::snapshot;
+use crypto_kline_tracker::source::{self, CaptureSource, Source, SyntheticSource};
+use crypto_kline_tracker::state::SharedState;
+use crypto_kline_tracker::store::{AlertQuery, CandleRange, Store, TradeQuery};
+use crypto_kline_tracker::strategy::{StrategyRegistry, StrategySet};
+use crypto_kline_tracker::streams::Streams;
+#[cfg(feature = "tui")]
+use crypto_kline_tracker::tui;
+use crypto_kline_tracker::{
+    actor, arbitrage, chart, clock, control, halts, interval, listings, open_interest, precision,
+    process_kline_stream, walkforward, KlineData, OutputFormat, Outputs,
+};
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::runtime::{self, Runtime};
+use tokio::signal::unix::{signal, SignalKind};
+use tokio::sync::{broadcast, Notify};
+use tracing::{debug, error, info, warn};
+use tracing_subscriber::fmt::writer::BoxMakeWriter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{EnvFilter, Layer};
 
-    fn price_change(&self) -> f64 {
-        self.close - self.open
-    }
+// Counts allocations for `--bench-ingest`.
+#[global_allocator]
+static ALLOCATOR: CountingAlloc = CountingAlloc;
 
-    fn price_change_percent(&self) -> f64 {
-        (self.price_change() / self.open) * 100.0
-    }
+/// Events the slowest live subscriber may fall behind by before skipping.
+const EVENTS_CAPACITY: usize = 1024;
+
+#[derive(Debug, Parser)]
+#[command(about = "Stream Binance kline data for multiple symbols and intervals")]
+struct Cli {
+    /// Path to a TOML config file; built-in defaults are used when omitted.
+    #[arg(short, long)]
+    config: Option<PathBuf>,
+    /// Show an interactive dashboard instead of log lines, which go to
+    /// `kline_tracker.log`.
+    #[arg(long, conflicts_with = "output")]
+    tui: bool,
+    /// What to write to stdout for each kline update. Logs go to stderr.
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+    output: OutputFormat,
+    /// Only output updates matching this expression, in the alert rule
+    /// language, e.g. `symbol == 'btcusdt' && change_pct.abs() > 1`.
+    #[arg(long, value_parser = parse_filter, conflicts_with = "tui")]
+    filter: Option<Expr>,
+    /// Replay a capture of Binance combined-stream messages, one per line,
+    /// through the pipeline as fast as possible and report the throughput
+    /// instead of streaming.
+    #[arg(long, value_name = "CAPTURE", conflicts_with_all = ["tui", "output", "filter"])]
+    bench_ingest: Option<PathBuf>,
+    /// Read kline updates from a capture of Binance combined-stream
+    /// messages, one per line, or from stdin given `-`, instead of
+    /// streaming from Binance.
+    #[arg(long, value_name = "CAPTURE", conflicts_with = "bench_ingest")]
+    input: Option<PathBuf>,
+    /// Stream random-walk candles for the configured symbols and intervals,
+    /// shaped by the `[synthetic]` config section, instead of streaming
+    /// from Binance.
+    #[arg(long, conflicts_with_all = ["bench_ingest", "input"])]
+    synthetic: bool,
+    /// How log lines are written. Their verbosity is set by `RUST_LOG`.
+    #[arg(long, value_enum, default_value_t = LogFormat::Text)]
+    log_format: LogFormat,
+    #[command(subcommand)]
+    command: Option<Command>,
 }
 
-fn parse_timestamp(kline: &Value) -> Result<DateTime<Utc>> {
-    let timestamp = kline["t"]
-        .as_i64()
-        .ok_or_else(|| anyhow!("Invalid timestamp"))?;
-    Utc.timestamp_millis_opt(timestamp)
-        .single()
-        .ok_or_else(|| anyhow!("Invalid timestamp"))
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum LogFormat {
+    /// Human-readable lines, with span fields inline.
+    Text,
+    /// One JSON object per line, with span fields as keys.
+    Json,
 }
 
-fn parse_price(kline: &Value, key: &str) -> Result<f64> {
-    kline[key]
-        .as_str()
-        .ok_or_else(|| anyhow!("Invalid {} price", key))?
-        .parse()
-        .map_err(|_| anyhow!("Failed to parse {} price", key))
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Review alerts recorded in the local store.
+    #[command(subcommand)]
+    Alerts(AlertsCommand),
+    /// Export the trade journal of paper and live fills.
+    #[command(subcommand)]
+    Trades(TradesCommand),
+    /// Draw a candlestick chart of a series from stored candles.
+    Chart {
+        symbol: String,
+        interval: String,
+        /// Number of candles; defaults to the terminal width.
+        #[arg(long)]
+        width: Option<usize>,
+        #[arg(long, default_value_t = 20)]
+        height: usize,
+    },
+    /// Print a running tracker's in-memory state as JSON, read over its
+    /// control socket.
+    Dump,
+    /// Run a capture of Binance messages through the pipeline on a clock
+    /// that follows the capture, and print every candle, alert and signal
+    /// as JSON, one per line. The same capture always prints the same.
+    Replay { capture: PathBuf },
+    /// Serve a capture as Binance's streams would, for a tracker whose
+    /// `stream_url` points here, optionally injecting faults.
+    MockServer {
+        capture: PathBuf,
+        #[arg(long, default_value = "127.0.0.1:9443")]
+        listen: std::net::SocketAddr,
+        /// Seed for picking faults; the same seed injects the same faults.
+        #[arg(long, default_value_t = 0)]
+        seed: u64,
+        /// Chance per message, from 0 to 1, of dropping the connection.
+        #[arg(long, default_value_t = 0.0)]
+        disconnect: f64,
+        /// Chance per message of holding it back, for up to `--max-delay-ms`.
+        #[arg(long, default_value_t = 0.0)]
+        delay: f64,
+        #[arg(long, default_value_t = 1000)]
+        max_delay_ms: u64,
+        /// Chance per message of cutting it short.
+        #[arg(long, default_value_t = 0.0)]
+        truncate: f64,
+        /// Chance per message of swapping it with the next one.
+        #[arg(long, default_value_t = 0.0)]
+        reorder: f64,
+        /// Chance per message of sending it twice.
+        #[arg(long, default_value_t = 0.0)]
+        duplicate: f64,
+    },
+    /// Replay stored candles through the indicators, alert rules and paper
+    /// trading orders, and report the trades and equity of the account.
+    Backtest {
+        interval: String,
+        /// A symbol to replay; repeat for several. Defaults to the
+        /// configured symbols.
+        #[arg(long = "symbol")]
+        symbols: Vec<String>,
+        /// Only candles starting at or after this time: RFC 3339, or an age
+        /// such as `30d`.
+        #[arg(long, value_parser = parse_time)]
+        from: Option<DateTime<Utc>>,
+        /// Only candles starting before this time.
+        #[arg(long, value_parser = parse_time)]
+        to: Option<DateTime<Utc>>,
+        /// Print the whole report, equity curve included, as JSON.
+        #[arg(long)]
+        json: bool,
+    },
+    /// Optimize a strategy's parameters on rolling windows of stored
+    /// candles, and report how the best of each window fared on the candles
+    /// after it.
+    WalkForward {
+        interval: String,
+        /// The strategy to optimize, by name; defaults to the only one
+        /// configured.
+        #[arg(long)]
+        strategy: Option<String>,
+        /// A parameter to search and the values to try, e.g.
+        /// `fast=5,10,20`; repeat for several. Every combination is tried.
+        #[arg(long = "param", value_parser = parse_param, required = true)]
+        params: Vec<walkforward::Param>,
+        /// Length of each training window, e.g. `30d`.
+        #[arg(long, value_parser = parse_age)]
+        train: chrono::Duration,
+        /// Length of each test window, which windows roll forward by.
+        #[arg(long, value_parser = parse_age)]
+        test: chrono::Duration,
+        #[arg(long = "symbol")]
+        symbols: Vec<String>,
+        #[arg(long, value_parser = parse_time)]
+        from: Option<DateTime<Utc>>,
+        #[arg(long, value_parser = parse_time)]
+        to: Option<DateTime<Utc>>,
+        /// Print the whole analysis as JSON.
+        #[arg(long)]
+        json: bool,
+    },
 }
 
-fn parse_volume(kline: &Value) -> Result<f64> {
-    kline["v"]
-        .as_str()
-        .ok_or_else(|| anyhow!("Invalid volume"))?
-        .parse()
-        .map_err(|_| anyhow!("Failed to parse volume"))
+#[derive(Debug, Subcommand)]
+enum TradesCommand {
+    /// Print the journaled paper and live fills as CSV, oldest first.
+    Export {
+        /// Only fills of `paper` or `live` trading.
+        #[arg(long, value_parser = parse_account)]
+        account: Option<Account>,
+        #[arg(long)]
+        symbol: Option<String>,
+        /// Only fills at or after this time: RFC 3339, or an age such as
+        /// `30d`.
+        #[arg(long, value_parser = parse_time)]
+        from: Option<DateTime<Utc>>,
+        /// Only fills before this time.
+        #[arg(long, value_parser = parse_time)]
+        to: Option<DateTime<Utc>>,
+        /// Write to this file instead of stdout.
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
 }
 
-async fn run_websocket(
-    symbol: String,
-    interval: String,
-    tx: mpsc::Sender<KlineData>,
-) -> Result<()> {
-    let ws_url = format!(
-        "wss://stream.binance.com:9443/ws/{}@kline_{}",
-        symbol, interval
+#[derive(Debug, Subcommand)]
+enum AlertsCommand {
+    /// List fired alerts and their deliveries, newest first.
+    List {
+        #[arg(long)]
+        symbol: Option<String>,
+        #[arg(long)]
+        rule: Option<String>,
+        /// Only alerts fired within this long ago, e.g. `30m`, `12h` or `7d`.
+        #[arg(long, value_parser = parse_age)]
+        since: Option<chrono::Duration>,
+        #[arg(long, default_value_t = 50)]
+        limit: usize,
+    },
+}
+
+/// Serves `capture` on `listen` until interrupted, then reports the faults
+/// injected.
+async fn run_mock_server(capture: &Path, listen: std::net::SocketAddr, chaos: Chaos) -> Result<()> {
+    let server = MockServer::bind(listen, mock::read_capture(capture)?).await?;
+    server.set_chaos(chaos);
+    info!(
+        "Serving {} at {}; set stream_url = \"{}\" to stream from it",
+        capture.display(),
+        server.url(),
+        server.url()
+    );
+    if chaos.is_enabled() {
+        info!("Injecting faults: {:?}", chaos);
+    }
+    tokio::signal::ctrl_c().await?;
+    let faults = server.faults();
+    info!(
+        "Served {} connection(s); injected {} disconnect(s), {} delay(s), {} truncated, \
+         {} reordered and {} duplicated message(s)",
+        server.connections(),
+        faults.disconnects.load(Ordering::Relaxed),
+        faults.delays.load(Ordering::Relaxed),
+        faults.truncated.load(Ordering::Relaxed),
+        faults.reordered.load(Ordering::Relaxed),
+        faults.duplicated.load(Ordering::Relaxed),
     );
+    Ok(())
+}
 
-    info!("Connecting to Binance WebSocket for {} {}...", symbol, interval);
-    let (ws_stream, _) = connect_async(&ws_url).await?;
-    info!("Connected to WebSocket for {} {}.", symbol, interval);
+fn parse_age(age: &str) -> Result<chrono::Duration, String> {
+    interval::interval_duration(age).ok_or_else(|| format!("invalid duration '{}'", age))
+}
 
-    let (_, mut read) = ws_stream.split();
+fn parse_time(time: &str) -> Result<DateTime<Utc>, String> {
+    DateTime::parse_from_rfc3339(time)
+        .map(|time| time.with_timezone(&Utc))
+        .or_else(|_| parse_age(time).map(|age| Utc::now() - age))
+        .map_err(|_| format!("invalid time '{}'", time))
+}
+
+fn parse_account(account: &str) -> Result<Account, String> {
+    Account::parse(account).ok_or_else(|| format!("expected paper or live, not '{}'", account))
+}
+
+fn parse_param(param: &str) -> Result<walkforward::Param, String> {
+    walkforward::Param::parse(param).map_err(|e| e.to_string())
+}
+
+fn parse_filter(filter: &str) -> Result<Expr, String> {
+    Expr::parse(filter).map_err(|e| e.to_string())
+}
 
-    while let Some(Ok(message)) = read.next().await {
-        if let Ok(text) = message.to_text() {
-            let json: Value = serde_json::from_str(text)?;
-            if let Some(_kline) = json["k"].as_object() {
-                let kline_data =
-                    KlineData::new(symbol.clone(), interval.clone(), &json["k"])?;
-                tx.send(kline_data).await?;
-                debug!("Sent kline data for {} {}", symbol, interval);
+fn list_alerts(store: &Store, query: &AlertQuery) -> Result<()> {
+    for record in store.alerts(query)? {
+        let alert = &record.alert;
+        println!(
+            "#{} {} [{}] {} {} @ {} ({:+.2}%): {}",
+            record.id,
+            alert
+                .triggered_at
+                .with_timezone(&Local)
+                .format("%Y-%m-%d %H:%M:%S"),
+            alert.rule,
+            alert.symbol.to_uppercase(),
+            alert.interval,
+            alert.price,
+            alert.change_percent,
+            alert.message
+        );
+        for delivery in &record.deliveries {
+            let at = delivery.at.with_timezone(&Local).format("%H:%M:%S");
+            match &delivery.error {
+                None => println!("    {} delivered at {}", delivery.notifier, at),
+                Some(error) => println!("    {} failed at {}: {}", delivery.notifier, at, error),
             }
         }
     }
-    warn!("WebSocket connection closed for {} {}", symbol, interval);
     Ok(())
 }
 
-fn process_kline_data(kline_data: &KlineData) {
-    let local_time = Local::now();
-    info!(
-        "Symbol: {} | Interval: {} | Local time: {} | Interval start: {} | \
-         Open: {:.2} | High: {:.2} | Low: {:.2} | Close: {:.2} | \
-         Volume: {:.2} | Change: {:.2} ({:.2}%)",
-        kline_data.symbol,
-        kline_data.interval,
-        local_time.format("%Y-%m-%d %H:%M:%S"),
-        kline_data.interval_start.format("%Y-%m-%d %H:%M"),
-        kline_data.open,
-        kline_data.high,
-        kline_data.low,
-        kline_data.close,
-        kline_data.volume,
-        kline_data.price_change(),
-        kline_data.price_change_percent(),
-    );
+/// Writes the journaled fills matching `query` as CSV to `output`, or to
+/// stdout.
+fn export_trades(store: &Store, query: &TradeQuery, output: Option<&Path>) -> Result<()> {
+    let trades = store.trades(query)?;
+    let mut csv = String::from(JournalEntry::CSV_HEADER);
+    csv.push('\n');
+    for trade in &trades {
+        csv += &trade.csv_row();
+        csv.push('\n');
+    }
+    match output {
+        Some(path) => {
+            std::fs::write(path, csv)?;
+            eprintln!("Exported {} fill(s) to {}", trades.len(), path.display());
+        }
+        None => print!("{}", csv),
+    }
+    Ok(())
+}
+
+fn print_chart(
+    store: &Store,
+    symbol: &str,
+    interval: &str,
+    width: usize,
+    height: usize,
+) -> Result<()> {
+    use crossterm::style::Stylize;
+
+    let candles = store.candles(symbol, interval, &CandleRange::latest(width))?;
+    if candles.is_empty() {
+        return Err(anyhow!("No stored candles for {} {}", symbol, interval));
+    }
+    println!("{} {}", symbol.to_uppercase(), interval);
+    let chart = chart::render(&candles, height);
+    // Offline, so without tick sizes.
+    let decimals = precision::fallback_decimals(candles[candles.len() - 1].close);
+    for (row, label) in chart.rows.iter().zip(&chart.labels) {
+        let mut line = String::new();
+        for mark in row {
+            line += &match mark {
+                Some(mark) if mark.rising => mark.glyph.green().to_string(),
+                Some(mark) => mark.glyph.red().to_string(),
+                None => " ".to_string(),
+            };
+        }
+        if let Some(price) = label {
+            line += &format!(" {:.*}", decimals, price).dark_grey().to_string();
+        }
+        println!("{}", line);
+    }
+    Ok(())
 }
 
-fn spawn_websocket_tasks(
-    symbols: &[&str],
-    intervals: &[&str],
-    tx: mpsc::Sender<KlineData>,
-) -> Vec<tokio::task::JoinHandle<()>> {
-    symbols
+/// The stored `interval` candles of `symbols` within `range`, in the order
+/// a backtest replays them.
+fn replay_candles(
+    store: &Store,
+    symbols: &[String],
+    interval: &str,
+    range: &CandleRange,
+) -> Result<Vec<KlineData>> {
+    let mut candles = Vec::new();
+    for symbol in symbols {
+        candles.extend(store.candles(symbol, interval, range)?);
+    }
+    if candles.is_empty() {
+        return Err(anyhow!("No stored {} candles to replay", interval));
+    }
+    // Every symbol's candle for a time is seen before the next time's.
+    candles.sort_by(|a, b| (a.interval_start, &a.symbol).cmp(&(b.interval_start, &b.symbol)));
+    Ok(candles)
+}
+
+/// Replays the stored `interval` candles of `symbols` within `from..to` and
+/// prints the report.
+fn run_backtest(
+    store: &Store,
+    config: &Config,
+    symbols: &[String],
+    interval: &str,
+    range: CandleRange,
+    json: bool,
+) -> Result<()> {
+    let candles = replay_candles(store, symbols, interval, &range)?;
+    let pipeline = IndicatorPipeline::new(
+        IndicatorRegistry::with_builtins(),
+        config.indicators.clone(),
+    )?;
+    let alert_engine = AlertEngine::new(config.alerts.clone(), &[interval.to_string()])?;
+    let strategies =
+        StrategySet::new(StrategyRegistry::with_builtins(), config.strategies.clone())?;
+    let report = backtest::run(config, &candles, pipeline, alert_engine, strategies);
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report.to_json())?);
+        return Ok(());
+    }
+
+    let (first, last) = (&candles[0], &candles[candles.len() - 1]);
+    println!(
+        "Replayed {} {} candles from {} to {}",
+        report.candles,
+        interval,
+        first.interval_start.format("%Y-%m-%d %H:%M"),
+        last.interval_start.format("%Y-%m-%d %H:%M")
+    );
+    for trade in &report.trades {
+        let decimals = precision::fallback_decimals(trade.entry_price);
+        println!(
+            "{} -> {} {} {} @ {:.*} -> {:.*}: {:+.2}",
+            trade.opened.format("%Y-%m-%d %H:%M"),
+            trade.closed.format("%Y-%m-%d %H:%M"),
+            trade.symbol.to_uppercase(),
+            trade.quantity,
+            decimals,
+            trade.entry_price,
+            decimals,
+            trade.exit_price,
+            trade.pnl
+        );
+    }
+    for (symbol, quantity) in &report.open_positions {
+        println!("Still holding {} {}", quantity, symbol.to_uppercase());
+    }
+    // A handful of points is enough to see the curve's shape.
+    let step = report.equity.len().div_ceil(20).max(1);
+    let samples = report
+        .equity
         .iter()
-        .flat_map(|&symbol| {
-            let tx = tx.clone();
-            intervals.iter().map(move |&interval| {
-                let symbol = symbol.to_string();
-                let interval = interval.to_string();
-                let tx = tx.clone();
-                tokio::spawn(async move {
-                    if let Err(e) = run_websocket(symbol.clone(), interval.clone(), tx).await {
-                        error!("WebSocket error for {} {}: {}", symbol, interval, e);
-                    }
-                })
-            })
-        })
-        .collect()
+        .step_by(step)
+        .chain(report.equity.last());
+    let curve: Vec<String> = samples
+        .map(|(_, equity)| format!("{:.0}", equity))
+        .collect();
+    println!("Equity: {}", curve.join(" "));
+    println!(
+        "PnL {:+.2} ({:+.2}%) over {} trade(s), win rate {}, max drawdown {:.2}%",
+        report.pnl(),
+        report.pnl() / report.starting_balance.max(f64::EPSILON) * 100.0,
+        report.trades.len(),
+        report
+            .win_rate()
+            .map_or("n/a".to_string(), |rate| format!("{:.1}%", rate)),
+        report.max_drawdown_percent
+    );
+    Ok(())
 }
 
-async fn process_kline_stream(mut rx: mpsc::Receiver<KlineData>) {
-    let mut kline_cache: HashMap<(String, String), KlineData> = HashMap::new();
+/// Runs a walk-forward analysis of `strategy` over the stored `interval`
+/// candles of `symbols` within `range` and prints it.
+#[allow(clippy::too_many_arguments)]
+fn run_walk_forward(
+    store: &Store,
+    config: &Config,
+    symbols: &[String],
+    interval: &str,
+    range: CandleRange,
+    strategy: Option<String>,
+    grid: &[walkforward::Param],
+    windows: (chrono::Duration, chrono::Duration),
+    json: bool,
+) -> Result<()> {
+    let strategy = match (strategy, config.strategies.as_slice()) {
+        (Some(strategy), _) => strategy,
+        (None, [only]) => only.name(),
+        (None, _) => return Err(anyhow!("Name the strategy to optimize with --strategy")),
+    };
+    let candles = replay_candles(store, symbols, interval, &range)?;
+    let pipeline = IndicatorPipeline::new(
+        IndicatorRegistry::with_builtins(),
+        config.indicators.clone(),
+    )?;
+    let strategies =
+        StrategySet::new(StrategyRegistry::with_builtins(), config.strategies.clone())?;
+    let analysis = walkforward::run(
+        config,
+        &candles,
+        &strategy,
+        grid,
+        windows,
+        &pipeline,
+        &strategies,
+    )?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&analysis.to_json())?);
+        return Ok(());
+    }
+    if analysis.windows.is_empty() {
+        return Err(anyhow!(
+            "The stored {} candles do not span one training and test window",
+            interval
+        ));
+    }
 
-    while let Some(kline_data) = rx.recv().await {
-        kline_cache.insert(
-            (kline_data.symbol.clone(), kline_data.interval.clone()),
-            kline_data,
+    println!(
+        "Walked {} {} over {} window(s), trying {} combination(s) on each",
+        strategy,
+        interval,
+        analysis.windows.len(),
+        analysis.combinations
+    );
+    for window in &analysis.windows {
+        let params: Vec<String> = window
+            .params
+            .iter()
+            .map(|(name, value)| format!("{}={}", name, value))
+            .collect();
+        println!(
+            "{} -> {} -> {} {}: train {:+.2}, test {:+.2} over {} trade(s), max drawdown {:.2}%",
+            window.train_from.format("%Y-%m-%d %H:%M"),
+            window.test_from.format("%Y-%m-%d %H:%M"),
+            window.test_to.format("%Y-%m-%d %H:%M"),
+            params.join(" "),
+            window.train_pnl,
+            window.test.pnl(),
+            window.test.trades.len(),
+            window.test.max_drawdown_percent
         );
+    }
+    println!(
+        "Out of sample: PnL {:+.2} over {} trade(s), win rate {}, {} of {} window(s) profitable, \
+         max drawdown {:.2}%, efficiency {}",
+        analysis.pnl(),
+        analysis.trades(),
+        analysis
+            .win_rate()
+            .map_or("n/a".to_string(), |rate| format!("{:.1}%", rate)),
+        analysis.profitable_windows(),
+        analysis.windows.len(),
+        analysis.max_drawdown_percent(),
+        analysis
+            .efficiency()
+            .map_or("n/a".to_string(), |efficiency| format!("{:.2}", efficiency))
+    );
+    Ok(())
+}
 
-        kline_cache.par_iter().for_each(|(_, data)| {
-            process_kline_data(data);
-        });
+/// Logs to stderr, or to `kline_tracker.log` while the dashboard runs, at
+/// the levels set by `RUST_LOG`. With the `console` feature, tasks are also
+/// reported to `tokio-console`.
+fn init_tracing(format: LogFormat, tui: bool) -> Result<()> {
+    let writer = if tui {
+        BoxMakeWriter::new(Arc::new(std::fs::File::create("kline_tracker.log")?))
+    } else {
+        BoxMakeWriter::new(std::io::stderr)
+    };
+    let layer = tracing_subscriber::fmt::layer()
+        .with_writer(writer)
+        .with_ansi(!tui && std::io::stderr().is_terminal());
+    let layer = match format {
+        LogFormat::Text => layer.boxed(),
+        LogFormat::Json => layer.json().boxed(),
+    };
+    let registry =
+        tracing_subscriber::registry().with(layer.with_filter(EnvFilter::from_default_env()));
+    #[cfg(feature = "console")]
+    let registry = registry.with(console_subscriber::spawn());
+    registry.try_init()?;
+    Ok(())
+}
 
-        let avg_price_change: f64 = kline_cache
-            .values()
-            .map(|data| data.price_change_percent())
-            .sum::<f64>()
-            / kline_cache.len() as f64;
+/// A runtime of the flavor and size set by `config`.
+fn build_runtime(config: &RuntimeConfig) -> Result<Runtime> {
+    let mut builder = match config.flavor {
+        RuntimeFlavor::CurrentThread => runtime::Builder::new_current_thread(),
+        RuntimeFlavor::MultiThread => runtime::Builder::new_multi_thread(),
+    };
+    if let Some(threads) = config.worker_threads {
+        builder.worker_threads(threads);
+    }
+    if let Some(threads) = config.max_blocking_threads {
+        builder.max_blocking_threads(threads);
+    }
+    Ok(builder.enable_all().build()?)
+}
 
-        info!("Average price change across all symbols: {:.2}%", avg_price_change);
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+    #[cfg(not(feature = "tui"))]
+    if cli.tui {
+        return Err(anyhow!("The dashboard needs the `tui` feature"));
     }
+    init_tracing(cli.log_format, cli.tui)?;
+
+    // Loaded before the runtime exists, since it sets how the runtime is
+    // built.
+    let config = Config::load(cli.config.as_deref())?;
+    build_runtime(&config.runtime)?.block_on(run(cli, config))
 }
 
-#[tokio::main]
-async fn main() -> Result<()> {
-    env_logger::init();
+async fn run(cli: Cli, config: Config) -> Result<()> {
+    let store = Arc::new(Store::open(&config.store.path)?);
 
-    let symbols = &["btcusdt", "ethusdt", "bnbusdt", "adausdt", "dogeusdt"];
-    let intervals = &["1m", "5m", "15m"];
+    match cli.command {
+        Some(Command::Alerts(AlertsCommand::List {
+            symbol,
+            rule,
+            since,
+            limit,
+        })) => {
+            let query = AlertQuery {
+                symbol: symbol.map(|symbol| symbol.to_lowercase()),
+                rule,
+                since: since.map(|age| Utc::now() - age),
+                until: None,
+                offset: 0,
+                limit,
+            };
+            return list_alerts(&store, &query);
+        }
+        Some(Command::Trades(TradesCommand::Export {
+            account,
+            symbol,
+            from,
+            to,
+            output,
+        })) => {
+            let query = TradeQuery {
+                account,
+                symbol: symbol.map(|symbol| symbol.to_lowercase()),
+                since: from,
+                until: to,
+            };
+            return export_trades(&store, &query, output.as_deref());
+        }
+        Some(Command::Chart {
+            symbol,
+            interval,
+            width,
+            height,
+        }) => {
+            // Leave room for the price axis.
+            let width = width.unwrap_or_else(|| {
+                crossterm::terminal::size()
+                    .map_or(80, |(columns, _)| columns as usize)
+                    .saturating_sub(12)
+            });
+            return print_chart(
+                &store,
+                &symbol.to_lowercase(),
+                &interval,
+                width.max(1),
+                height,
+            );
+        }
+        Some(Command::Backtest {
+            interval,
+            symbols,
+            from,
+            to,
+            json,
+        }) => {
+            let symbols = if symbols.is_empty() {
+                config.symbols.clone()
+            } else {
+                symbols.iter().map(|symbol| symbol.to_lowercase()).collect()
+            };
+            let range = CandleRange {
+                from,
+                to,
+                offset: 0,
+                limit: i64::MAX as usize,
+            };
+            return run_backtest(&store, &config, &symbols, &interval, range, json);
+        }
+        Some(Command::WalkForward {
+            interval,
+            strategy,
+            params,
+            train,
+            test,
+            symbols,
+            from,
+            to,
+            json,
+        }) => {
+            let symbols = if symbols.is_empty() {
+                config.symbols.clone()
+            } else {
+                symbols.iter().map(|symbol| symbol.to_lowercase()).collect()
+            };
+            let range = CandleRange {
+                from,
+                to,
+                offset: 0,
+                limit: i64::MAX as usize,
+            };
+            return run_walk_forward(
+                &store,
+                &config,
+                &symbols,
+                &interval,
+                range,
+                strategy,
+                &params,
+                (train, test),
+                json,
+            );
+        }
+        Some(Command::Dump) => {
+            let snapshot = control::request(&config.control, "dump").await?;
+            println!("{}", serde_json::to_string_pretty(&snapshot["snapshot"])?);
+            return Ok(());
+        }
+        Some(Command::MockServer {
+            capture,
+            listen,
+            seed,
+            disconnect,
+            delay,
+            max_delay_ms,
+            truncate,
+            reorder,
+            duplicate,
+        }) => {
+            let chaos = Chaos {
+                seed,
+                disconnect,
+                delay,
+                max_delay: Duration::from_millis(max_delay_ms),
+                truncate,
+                reorder,
+                duplicate,
+            };
+            return run_mock_server(&capture, listen, chaos).await;
+        }
+        Some(Command::Replay { .. }) | None => {}
+    }
 
-    info!("Starting Binance WebSocket client");
-    debug!("Symbols: {:?}, Intervals: {:?}", symbols, intervals);
+    let pipeline = IndicatorPipeline::new(
+        IndicatorRegistry::with_builtins(),
+        config.indicators.clone(),
+    )?;
 
-    let (tx, rx) = mpsc::channel(100);
+    let alert_engine = AlertEngine::new(config.alerts.clone(), &config.intervals)?;
+    let strategies =
+        StrategySet::new(StrategyRegistry::with_builtins(), config.strategies.clone())?;
+    if let Some(capture) = &cli.bench_ingest {
+        let report = bench::ingest(capture, config, pipeline, alert_engine, strategies).await?;
+        println!("{}", report);
+        return Ok(());
+    }
+    if let Some(Command::Replay { capture }) = &cli.command {
+        let recording =
+            replay::replay_capture(capture, config, pipeline, alert_engine, strategies).await?;
+        print!("{}", recording.json_lines());
+        return Ok(());
+    }
 
-    let tasks = spawn_websocket_tasks(symbols, intervals, tx);
-    let processor = tokio::spawn(process_kline_stream(rx));
+    info!("Starting Binance WebSocket client");
+    debug!(
+        "Symbols: {:?}, Intervals: {:?}, Ratios: {:?}",
+        config.symbols, config.intervals, config.ratios
+    );
 
-    for task in tasks {
-        task.await?;
+    let (notify_tx, notifications) = actor::mailbox(100);
+    let (ops_tx, incidents) = actor::mailbox(100);
+    let (events_tx, _) = broadcast::channel(EVENTS_CAPACITY);
+    let metrics = Metrics::default();
+    metrics.watch_mpsc("notifications", &notify_tx);
+    metrics.watch_mpsc("incidents", &ops_tx);
+    metrics.watch_broadcast("events", EVENTS_CAPACITY, &events_tx);
+    let state = SharedState::default();
+    snapshot::restore_configured(&state, &config);
+    let snapshots = config.snapshot.clone();
+    if snapshots.enabled {
+        tokio::spawn(snapshot::run(state.clone(), snapshots.clone()));
+    }
+    let health = Health::new(Duration::from_secs(config.ops.stream_down_secs.max(1)));
+    let panics = PanicReporter::new(metrics.clone(), ops_tx.clone(), config.ops.panic_incidents);
+    let supervisor = Supervisor::new(panics.clone());
+    let sinks = Sinks::new(
+        supervisor.clone(),
+        notifications,
+        incidents,
+        ops_tx.clone(),
+        events_tx.clone(),
+        store.clone(),
+        health.clone(),
+        metrics.clone(),
+    );
+    sinks.start(&config)?;
+    let clock = clock::system();
+    let kill_switch = KillSwitch::new(config.risk.kill_switch);
+    if kill_switch.is_engaged() {
+        warn!("The kill switch is engaged; no orders will be placed");
+    }
+    let journal = Journal::new(store.clone(), state.clone());
+    let paper = Paper::new(
+        &config.paper,
+        RiskLimits::new(&config.risk, kill_switch.clone()).with_clock(clock.clone()),
+    );
+    if config.paper.enabled {
+        let (paper, paper_config) = (paper.clone(), config.paper.clone());
+        let (strategy_configs, journal) = (config.strategies.clone(), journal.clone());
+        let (events_tx, state) = (events_tx.clone(), state.clone());
+        let summary_every = Duration::from_secs(config.summary_interval_secs.max(1));
+        supervisor.spawn("paper", move || {
+            paper::run(
+                paper.clone(),
+                paper_config.clone(),
+                strategy_configs.clone(),
+                journal.clone(),
+                state.clone(),
+                events_tx.subscribe(),
+                summary_every,
+            )
+        });
+    }
+    // Config validation rejects enabling live trading in a build without it.
+    let live = config
+        .live
+        .enabled
+        .then(|| {
+            Live::new(
+                &config.live,
+                RiskLimits::new(&config.risk, kill_switch.clone()).with_clock(clock.clone()),
+                journal.clone(),
+            )
+        })
+        .transpose()?;
+    if let Some(live) = &live {
+        warn!("Live trading is enabled; orders are real");
+        let (live_orders, strategy_configs) = (live.clone(), config.strategies.clone());
+        let enabled = config.live.strategies.clone();
+        let (events_tx, live_state) = (events_tx.clone(), state.clone());
+        supervisor.spawn("live", move || {
+            live::run(
+                live_orders.clone(),
+                strategy_configs.clone(),
+                enabled.clone(),
+                events_tx.subscribe(),
+                live_state.clone(),
+            )
+        });
+        if config.live.user_data_stream {
+            let (live, state) = (live.clone(), state.clone());
+            supervisor.spawn("user-data", move || {
+                live::run_user_data(live.clone(), state.clone())
+            });
+        }
     }
+    let shards = Shards::new(config.workers, &config.ratios);
+    let (router, receivers) = Router::new(&config.queue, shards.clone(), metrics.clone());
+    // Config validation rejects enabling either API in a build without them.
+    #[cfg(feature = "server")]
+    {
+        let auth = server::Auth::new(&config.auth);
+        if config.server.enabled {
+            let (server, state, store) = (config.server.clone(), state.clone(), store.clone());
+            let (events_tx, health, metrics) = (events_tx.clone(), health.clone(), metrics.clone());
+            let (auth, portfolio) = (auth.clone(), Portfolio::new(&config));
+            tokio::spawn(async move {
+                let served = server::serve(
+                    &server, state, store, events_tx, health, metrics, auth, portfolio,
+                );
+                if let Err(e) = served.await {
+                    error!("HTTP API failed: {}", e);
+                }
+            });
+        }
+        if config.grpc.enabled {
+            let (grpc, state, store) = (config.grpc.clone(), state.clone(), store.clone());
+            let events_tx = events_tx.clone();
+            tokio::spawn(async move {
+                if let Err(e) = server::serve_grpc(&grpc, state, store, events_tx, auth).await {
+                    error!("gRPC API failed: {}", e);
+                }
+            });
+        }
+    }
+
+    let input_router = router.clone();
+    let streams = Streams::new(
+        router,
+        config.restart.clone(),
+        config.multiplex.clone(),
+        ops_tx.clone(),
+        health.clone(),
+        metrics.clone(),
+        panics,
+    )
+    .with_stream_url(&config.stream_url);
+    let streams = config.venues.iter().fold(streams, |streams, venue| {
+        streams.with_venue(&venue.name, &venue.stream_url)
+    });
+    let channel_metrics = metrics.clone();
+    let summary_every = Duration::from_secs(config.summary_interval_secs.max(1));
+    tokio::spawn(async move {
+        let mut timer = tokio::time::interval(summary_every);
+        timer.tick().await;
+        loop {
+            timer.tick().await;
+            channel_metrics.log_channels();
+        }
+    });
+    let input: Option<Box<dyn Source>> = match cli.input {
+        Some(capture) => Some(Box::new(CaptureSource::new(capture))),
+        None if cli.synthetic => Some(Box::new(SyntheticSource::new(
+            &config.symbols,
+            &config.intervals,
+            config.synthetic.clone(),
+        )?)),
+        None => None,
+    };
+    let reading_input = input.is_some();
+    let mut input = input.map(|input| {
+        // The processor finishes once the input ends and this router's
+        // senders are dropped.
+        tokio::spawn(async move {
+            if let Err(e) = source::feed(input, |symbol| input_router.sender(symbol)).await {
+                error!("Input failed: {}", e);
+            }
+        })
+    });
+    if config.open_interest.enabled && !reading_input {
+        let symbols = if config.open_interest.symbols.is_empty() {
+            config.symbols.clone()
+        } else {
+            config.open_interest.symbols.clone()
+        };
+        tokio::spawn(open_interest::run(
+            state.clone(),
+            config.open_interest.clone(),
+            symbols,
+        ));
+    }
+    if config.listings.enabled && !reading_input {
+        tokio::spawn(listings::run(
+            config.listings.clone(),
+            config.intervals.clone(),
+            streams.clone(),
+            events_tx.clone(),
+            notify_tx.clone(),
+            clock.clone(),
+        ));
+    }
+    if config.precision.enabled && !reading_input {
+        tokio::spawn(precision::run(config.precision.clone(), state.clone()));
+    }
+    if config.halts.enabled && !reading_input {
+        tokio::spawn(halts::run(
+            config.halts.clone(),
+            state.clone(),
+            streams.clone(),
+            health.clone(),
+            events_tx.clone(),
+            notify_tx.clone(),
+            clock.clone(),
+        ));
+    }
+    if !reading_input {
+        for symbol in &config.symbols {
+            for interval in &config.intervals {
+                streams.add(symbol, interval);
+            }
+        }
+        // Other venues are streamed on the shortest interval only.
+        let shortest = config
+            .intervals
+            .iter()
+            .min_by_key(|interval| interval::interval_duration(interval));
+        let mut venue_symbols: Vec<&String> = Vec::new();
+        if config.arbitrage.enabled {
+            venue_symbols.extend(&config.arbitrage.symbols);
+        }
+        if config.index.enabled {
+            venue_symbols.extend(&config.index.symbols);
+        }
+        if let Some(interval) = shortest {
+            for venue in &config.venues {
+                for symbol in &venue_symbols {
+                    streams.add(&arbitrage::venue_symbol(&venue.name, symbol), interval);
+                }
+            }
+        }
+    }
+    let shutdown = Arc::new(Notify::new());
+    let control_enabled = config.control.enabled;
+    if control_enabled {
+        let control_config = config.control.clone();
+        let control = control::Control {
+            streams: streams.clone(),
+            state: state.clone(),
+            health: health.clone(),
+            metrics: metrics.clone(),
+            supervisor,
+            sinks: sinks.clone(),
+            paper: paper.clone(),
+            live: live.clone(),
+            kill_switch: kill_switch.clone(),
+            config_path: cli.config.clone(),
+            shutdown: shutdown.clone(),
+        };
+        tokio::spawn(async move {
+            if let Err(e) = control::serve(&control_config, control).await {
+                error!("Control socket failed: {}", e);
+            }
+        });
+    }
+    #[cfg(feature = "tui")]
+    let (tui_state, intervals) = (state.clone(), config.intervals.clone());
+    let grace = Duration::from_secs(config.shutdown_grace_secs);
+    let snapshot_state = state.clone();
+    let paper_enabled = config.paper.enabled;
+    let processor = tokio::spawn(process_kline_stream(
+        receivers,
+        shards,
+        config,
+        pipeline,
+        alert_engine,
+        strategies,
+        Outputs {
+            state,
+            store,
+            notify_tx,
+            ops_tx,
+            events_tx,
+            format: cli.output,
+            filter: cli.filter,
+            health,
+            metrics,
+            clock,
+        },
+    ));
+
+    let mut terminate = signal(SignalKind::terminate())?;
+    let input_ended = async {
+        if let Some(input) = input.as_mut() {
+            let _ = input.await;
+        }
+    };
+    // With a control socket, streams can be re-added after the last one
+    // closes, so only an explicit shutdown or a signal ends the tracker.
+    let stopped = async {
+        tokio::select! {
+            _ = streams.closed(), if !control_enabled && !reading_input => None,
+            _ = input_ended, if !control_enabled && reading_input => None,
+            _ = shutdown.notified() => None,
+            _ = tokio::signal::ctrl_c() => {
+                info!("Interrupted");
+                None
+            }
+            _ = terminate.recv() => {
+                info!("Terminated");
+                None
+            }
+            reason = streams.failed() => Some(reason),
+        }
+    };
+    #[cfg(feature = "tui")]
+    let failure = if cli.tui {
+        drop(stopped);
+        // Quitting the dashboard ends the tracker.
+        tokio::task::spawn_blocking(move || tui::run(tui_state, intervals)).await??;
+        None
+    } else {
+        stopped.await
+    };
+    #[cfg(not(feature = "tui"))]
+    let failure = stopped.await;
 
+    tokio::spawn(async {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            warn!("Interrupted again; exiting without draining");
+            std::process::exit(130);
+        }
+    });
+    info!("Shutting down; draining queued updates (Ctrl-C again to skip)");
+    if let Some(input) = &input {
+        input.abort();
+    }
+    // Closing every stream drops the last kline sender, which lets the
+    // processor drain its queue and finish. Only then is nothing more
+    // handed to the sinks.
+    streams.shutdown(grace).await;
     processor.await?;
+    if paper_enabled {
+        for line in paper.lock().summary_lines(&snapshot_state) {
+            info!("{}", line);
+        }
+    }
+    if snapshots.enabled {
+        let path = snapshots.path.clone();
+        match tokio::task::spawn_blocking(move || snapshot::save(&snapshot_state, &path)).await? {
+            Ok(saved) => info!("Saved {} series to {}", saved, snapshots.path.display()),
+            Err(e) => error!(
+                "Failed to save snapshot {}: {}",
+                snapshots.path.display(),
+                e
+            ),
+        }
+    }
+    sinks.drain(grace).await;
 
+    if let Some(reason) = failure {
+        return Err(anyhow!("{}; stopping", reason));
+    }
     info!("Binance WebSocket client shutting down");
     Ok(())
-}
\ No newline at end of file
+}
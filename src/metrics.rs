@@ -0,0 +1,583 @@
+use crate::{KlineData, SeriesKey, Timing};
+use chrono::{DateTime, Utc};
+use hdrhistogram::Histogram;
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use tokio::sync::{broadcast, mpsc};
+use tracing::{info, warn};
+
+/// How often message and byte rates are recomputed.
+const RATE_WINDOW: Duration = Duration::from_secs(10);
+
+/// Counters for one (symbol, interval) stream.
+#[derive(Debug)]
+struct StreamMetrics {
+    messages: u64,
+    bytes: u64,
+    parse_errors: u64,
+    /// Updates discarded or merged by the queue's backpressure policy.
+    dropped: u64,
+    coalesced: u64,
+    connections: u64,
+    last_message: Option<DateTime<Utc>>,
+    window_start: Instant,
+    window_messages: u64,
+    window_bytes: u64,
+    /// Messages and bytes per second over the last complete window.
+    rates: (f64, f64),
+}
+
+impl Default for StreamMetrics {
+    fn default() -> Self {
+        Self {
+            messages: 0,
+            bytes: 0,
+            parse_errors: 0,
+            dropped: 0,
+            coalesced: 0,
+            connections: 0,
+            last_message: None,
+            window_start: Instant::now(),
+            window_messages: 0,
+            window_bytes: 0,
+            rates: (0.0, 0.0),
+        }
+    }
+}
+
+impl StreamMetrics {
+    fn roll_window(&mut self) {
+        let elapsed = self.window_start.elapsed();
+        if elapsed < RATE_WINDOW {
+            return;
+        }
+        let secs = elapsed.as_secs_f64();
+        self.rates = (
+            self.window_messages as f64 / secs,
+            self.window_bytes as f64 / secs,
+        );
+        self.window_start = Instant::now();
+        self.window_messages = 0;
+        self.window_bytes = 0;
+    }
+
+    fn reconnects(&self) -> u64 {
+        self.connections.saturating_sub(1)
+    }
+}
+
+/// Depth and send-wait figures for one internal channel.
+struct ChannelStats {
+    capacity: usize,
+    /// Reads how many messages are queued right now.
+    depth: Box<dyn Fn() -> usize + Send + Sync>,
+    sends: u64,
+    /// Sends that found the channel full and had to wait for room.
+    waits: u64,
+    wait_total: Duration,
+    /// The longest wait since the channel was last logged.
+    wait_max: Duration,
+}
+
+/// Latencies recorded for one stage of the pipeline.
+struct Latency {
+    /// In microseconds.
+    histogram: Histogram<u64>,
+    total: Duration,
+}
+
+impl Latency {
+    fn new() -> Self {
+        Self {
+            // Up to an hour, to two significant figures.
+            histogram: Histogram::new_with_bounds(1, 3_600_000_000, 2)
+                .expect("valid histogram bounds"),
+            total: Duration::ZERO,
+        }
+    }
+
+    fn record(&mut self, latency: Duration) {
+        self.histogram
+            .saturating_record(latency.as_micros().max(1) as u64);
+        self.total += latency;
+    }
+
+    /// The latency `quantile` of recordings fall under, in seconds.
+    fn quantile(&self, quantile: f64) -> f64 {
+        self.histogram.value_at_quantile(quantile) as f64 / 1e6
+    }
+}
+
+/// The quantiles latencies are reported at.
+const QUANTILES: [f64; 4] = [0.5, 0.9, 0.99, 0.999];
+
+/// Records the sends on one channel registered with [`Metrics::channel`].
+#[derive(Clone)]
+pub struct ChannelMetrics(Arc<Mutex<ChannelStats>>);
+
+impl ChannelMetrics {
+    /// Records a send that took `wait` to find room in the channel.
+    pub fn record_send(&self, wait: Duration) {
+        let mut stats = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        stats.sends += 1;
+        if !wait.is_zero() {
+            stats.waits += 1;
+            stats.wait_total += wait;
+            stats.wait_max = stats.wait_max.max(wait);
+        }
+    }
+}
+
+/// Operational metrics per stream, updated by the stream tasks, and for each
+/// internal channel between the stream tasks, the processor and the sinks.
+/// Read by the metrics endpoint and the control socket.
+#[derive(Clone, Default)]
+pub struct Metrics {
+    streams: Arc<Mutex<HashMap<SeriesKey, StreamMetrics>>>,
+    channels: Arc<Mutex<BTreeMap<String, ChannelMetrics>>>,
+    /// By stage: `exchange`, `parse`, `queue`, `process`, `end_to_end`, and
+    /// `sink:` followed by each sink's name.
+    latency: Arc<Mutex<BTreeMap<String, Latency>>>,
+    /// Panics caught per component.
+    panics: Arc<Mutex<BTreeMap<String, u64>>>,
+}
+
+impl Metrics {
+    fn lock(&self) -> MutexGuard<'_, HashMap<SeriesKey, StreamMetrics>> {
+        self.streams.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn lock_channels(&self) -> MutexGuard<'_, BTreeMap<String, ChannelMetrics>> {
+        self.channels.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn lock_latency(&self) -> MutexGuard<'_, BTreeMap<String, Latency>> {
+        self.latency.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn lock_panics(&self) -> MutexGuard<'_, BTreeMap<String, u64>> {
+        self.panics.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Records a panic caught in `component`.
+    pub fn record_panic(&self, component: &str) {
+        *self.lock_panics().entry(component.to_string()).or_default() += 1;
+    }
+
+    /// Panics caught per component, sorted by component.
+    pub fn panics_json(&self) -> Value {
+        self.lock_panics()
+            .iter()
+            .map(|(component, panics)| json!({ "component": component, "panics": panics }))
+            .collect()
+    }
+
+    /// Records one latency of `stage`.
+    pub fn record_latency(&self, stage: &str, latency: Duration) {
+        let mut stages = self.lock_latency();
+        match stages.get_mut(stage) {
+            Some(stage) => stage.record(latency),
+            None => {
+                let mut recorded = Latency::new();
+                recorded.record(latency);
+                stages.insert(stage.to_string(), recorded);
+            }
+        }
+    }
+
+    /// Records how long a live update took to reach the tracker and to be
+    /// parsed.
+    pub fn record_parsed(&self, kline: &KlineData) {
+        let Some(timing) = kline.timing else {
+            return;
+        };
+        if let Some(exchange) = timing.exchange {
+            self.record_latency("exchange", exchange);
+        }
+        self.record_latency("parse", timing.parsed - timing.received);
+    }
+
+    /// Records how long a live update waited in its queue until `dequeued`,
+    /// how long it has taken since to process, and how long since the
+    /// exchange's event time it was done, at `now`.
+    pub fn record_processed(&self, timing: &Timing, dequeued: Instant, now: DateTime<Utc>) {
+        self.record_latency("queue", dequeued.saturating_duration_since(timing.parsed));
+        self.record_latency("process", dequeued.elapsed());
+        // Without an exchange latency the event time is not the exchange's
+        // clock now, as in replayed captures.
+        if let (Some(_), Some(event_time)) = (timing.exchange, timing.event_time) {
+            let behind = (now - event_time).to_std().unwrap_or_default();
+            self.record_latency("end_to_end", behind);
+        }
+    }
+
+    /// Registers the channel `name` holding up to `capacity` messages, with
+    /// `depth` reading how many are queued. Replaces any channel of that name.
+    pub fn channel(
+        &self,
+        name: &str,
+        capacity: usize,
+        depth: impl Fn() -> usize + Send + Sync + 'static,
+    ) -> ChannelMetrics {
+        let channel = ChannelMetrics(Arc::new(Mutex::new(ChannelStats {
+            capacity,
+            depth: Box::new(depth),
+            sends: 0,
+            waits: 0,
+            wait_total: Duration::ZERO,
+            wait_max: Duration::ZERO,
+        })));
+        self.lock_channels()
+            .insert(name.to_string(), channel.clone());
+        channel
+    }
+
+    /// Reports the depth of a Tokio channel as `name`, without keeping it
+    /// open. Its sends are not timed.
+    pub fn watch_mpsc<T: Send + 'static>(&self, name: &str, tx: &mpsc::Sender<T>) {
+        let tx = tx.downgrade();
+        self.channel(
+            name,
+            tx.upgrade().map_or(0, |tx| tx.max_capacity()),
+            move || {
+                tx.upgrade()
+                    .map_or(0, |tx| tx.max_capacity() - tx.capacity())
+            },
+        );
+    }
+
+    /// Reports as `name` how far the slowest subscriber of a broadcast
+    /// channel lags behind, without keeping it open.
+    pub fn watch_broadcast<T: Send + 'static>(
+        &self,
+        name: &str,
+        capacity: usize,
+        tx: &broadcast::Sender<T>,
+    ) {
+        let tx = tx.downgrade();
+        self.channel(name, capacity, move || {
+            tx.upgrade().map_or(0, |tx| tx.len())
+        });
+    }
+
+    fn update(&self, key: &SeriesKey, update: impl FnOnce(&mut StreamMetrics)) {
+        let mut streams = self.lock();
+        let stream = streams.entry(key.clone()).or_default();
+        update(stream);
+        stream.roll_window();
+    }
+
+    pub fn record_connected(&self, key: &SeriesKey) {
+        self.update(key, |stream| stream.connections += 1);
+    }
+
+    /// Records a message of `bytes` received from the exchange.
+    pub fn record_message(&self, key: &SeriesKey, bytes: usize) {
+        self.update(key, |stream| {
+            stream.messages += 1;
+            stream.bytes += bytes as u64;
+            stream.window_messages += 1;
+            stream.window_bytes += bytes as u64;
+            stream.last_message = Some(Utc::now());
+        });
+    }
+
+    pub fn record_parse_error(&self, key: &SeriesKey) {
+        self.update(key, |stream| stream.parse_errors += 1);
+    }
+
+    pub fn record_dropped(&self, key: &SeriesKey) {
+        self.update(key, |stream| stream.dropped += 1);
+    }
+
+    pub fn record_coalesced(&self, key: &SeriesKey) {
+        self.update(key, |stream| stream.coalesced += 1);
+    }
+
+    pub fn remove(&self, symbol: &str, interval: &str) {
+        self.lock().remove(&(symbol.into(), interval.into()));
+    }
+
+    /// Every stream's metrics, sorted by symbol and interval.
+    pub fn to_json(&self) -> Value {
+        let mut streams = self.lock();
+        for stream in streams.values_mut() {
+            stream.roll_window();
+        }
+        let mut streams: Vec<_> = streams.iter().collect();
+        streams.sort_by(|a, b| a.0.cmp(b.0));
+        streams
+            .into_iter()
+            .map(|((symbol, interval), stream)| {
+                json!({
+                    "symbol": symbol,
+                    "interval": interval,
+                    "messages": stream.messages,
+                    "bytes": stream.bytes,
+                    "messages_per_sec": stream.rates.0,
+                    "bytes_per_sec": stream.rates.1,
+                    "parse_errors": stream.parse_errors,
+                    "dropped": stream.dropped,
+                    "coalesced": stream.coalesced,
+                    "reconnects": stream.reconnects(),
+                    "last_message": stream.last_message.map(|at| at.to_rfc3339()),
+                })
+            })
+            .collect()
+    }
+
+    /// Every channel's depth, capacity and send waits, sorted by name.
+    pub fn channels_json(&self) -> Value {
+        self.lock_channels()
+            .iter()
+            .map(|(name, channel)| {
+                let stats = channel.0.lock().unwrap_or_else(|e| e.into_inner());
+                json!({
+                    "channel": name,
+                    "depth": (stats.depth)(),
+                    "capacity": stats.capacity,
+                    "sends": stats.sends,
+                    "waits": stats.waits,
+                    "wait_secs": stats.wait_total.as_secs_f64(),
+                })
+            })
+            .collect()
+    }
+
+    /// Every stage's latency quantiles in milliseconds, sorted by stage.
+    pub fn latency_json(&self) -> Value {
+        self.lock_latency()
+            .iter()
+            .map(|(stage, latency)| {
+                json!({
+                    "stage": stage,
+                    "count": latency.histogram.len(),
+                    "p50_ms": latency.quantile(0.5) * 1000.0,
+                    "p90_ms": latency.quantile(0.9) * 1000.0,
+                    "p99_ms": latency.quantile(0.99) * 1000.0,
+                    "max_ms": latency.histogram.max() as f64 / 1000.0,
+                })
+            })
+            .collect()
+    }
+
+    /// Logs each channel's depth and the waits since the last call, warning
+    /// about channels at least three quarters full.
+    pub fn log_channels(&self) {
+        for (name, channel) in self.lock_channels().iter() {
+            let mut stats = channel.0.lock().unwrap_or_else(|e| e.into_inner());
+            let depth = (stats.depth)();
+            let line = format!(
+                "Channel {}: {}/{} queued, {} of {} sends waited (max {:.1}ms)",
+                name,
+                depth,
+                stats.capacity,
+                stats.waits,
+                stats.sends,
+                stats.wait_max.as_secs_f64() * 1000.0,
+            );
+            if depth * 4 >= stats.capacity * 3 && stats.capacity > 0 {
+                warn!("{}", line);
+            } else {
+                info!("{}", line);
+            }
+            stats.wait_max = Duration::ZERO;
+        }
+    }
+
+    /// Every stream's metrics in the Prometheus text exposition format.
+    pub fn to_prometheus(&self) -> String {
+        let mut out = String::new();
+        self.write_stream_metrics(&mut out);
+        // Separately, as reading a channel's depth locks the channel, which
+        // may itself be waiting on the stream metrics.
+        self.write_channel_metrics(&mut out);
+        self.write_latency_metrics(&mut out);
+        self.write_panic_metrics(&mut out);
+        out
+    }
+
+    fn write_stream_metrics(&self, out: &mut String) {
+        let mut streams = self.lock();
+        for stream in streams.values_mut() {
+            stream.roll_window();
+        }
+        let mut streams: Vec<_> = streams.iter().collect();
+        streams.sort_by(|a, b| a.0.cmp(b.0));
+
+        type Metric = (
+            &'static str,
+            &'static str,
+            &'static str,
+            fn(&StreamMetrics) -> f64,
+        );
+        let metrics: [Metric; 9] = [
+            (
+                "kline_stream_messages_total",
+                "counter",
+                "Messages received.",
+                |s| s.messages as f64,
+            ),
+            (
+                "kline_stream_bytes_total",
+                "counter",
+                "Bytes received.",
+                |s| s.bytes as f64,
+            ),
+            (
+                "kline_stream_messages_per_second",
+                "gauge",
+                "Recent message rate.",
+                |s| s.rates.0,
+            ),
+            (
+                "kline_stream_bytes_per_second",
+                "gauge",
+                "Recent byte rate.",
+                |s| s.rates.1,
+            ),
+            (
+                "kline_stream_parse_errors_total",
+                "counter",
+                "Messages that failed to parse.",
+                |s| s.parse_errors as f64,
+            ),
+            (
+                "kline_stream_dropped_total",
+                "counter",
+                "Updates dropped because the processor fell behind.",
+                |s| s.dropped as f64,
+            ),
+            (
+                "kline_stream_coalesced_total",
+                "counter",
+                "Updates merged into a queued update for the same candle.",
+                |s| s.coalesced as f64,
+            ),
+            (
+                "kline_stream_reconnects_total",
+                "counter",
+                "Reconnections after the first.",
+                |s| s.reconnects() as f64,
+            ),
+            (
+                "kline_stream_last_message_timestamp_seconds",
+                "gauge",
+                "When the last message arrived, as a Unix time.",
+                |s| {
+                    s.last_message
+                        .map_or(0.0, |at| at.timestamp_millis() as f64 / 1000.0)
+                },
+            ),
+        ];
+
+        for (name, kind, help, value) in metrics {
+            let _ = writeln!(out, "# HELP {} {}", name, help);
+            let _ = writeln!(out, "# TYPE {} {}", name, kind);
+            for ((symbol, interval), stream) in &streams {
+                let _ = writeln!(
+                    out,
+                    "{}{{symbol=\"{}\",interval=\"{}\"}} {}",
+                    name,
+                    symbol,
+                    interval,
+                    value(stream)
+                );
+            }
+        }
+    }
+
+    fn write_channel_metrics(&self, out: &mut String) {
+        type ChannelMetric = (
+            &'static str,
+            &'static str,
+            &'static str,
+            fn(&ChannelStats) -> f64,
+        );
+        let channel_metrics: [ChannelMetric; 5] = [
+            (
+                "kline_channel_depth",
+                "gauge",
+                "Messages queued in the channel.",
+                |c| (c.depth)() as f64,
+            ),
+            (
+                "kline_channel_capacity",
+                "gauge",
+                "Messages the channel can hold.",
+                |c| c.capacity as f64,
+            ),
+            (
+                "kline_channel_sends_total",
+                "counter",
+                "Messages sent through the channel.",
+                |c| c.sends as f64,
+            ),
+            (
+                "kline_channel_send_waits_total",
+                "counter",
+                "Sends that waited for room.",
+                |c| c.waits as f64,
+            ),
+            (
+                "kline_channel_send_wait_seconds_total",
+                "counter",
+                "Time senders spent waiting for room.",
+                |c| c.wait_total.as_secs_f64(),
+            ),
+        ];
+        let channels = self.lock_channels();
+        for (name, kind, help, value) in channel_metrics {
+            let _ = writeln!(out, "# HELP {} {}", name, help);
+            let _ = writeln!(out, "# TYPE {} {}", name, kind);
+            for (channel, metrics) in channels.iter() {
+                let stats = metrics.0.lock().unwrap_or_else(|e| e.into_inner());
+                let _ = writeln!(out, "{}{{channel=\"{}\"}} {}", name, channel, value(&stats));
+            }
+        }
+    }
+
+    fn write_panic_metrics(&self, out: &mut String) {
+        let name = "kline_task_panics_total";
+        let _ = writeln!(out, "# HELP {} Panics caught in supervised tasks.", name);
+        let _ = writeln!(out, "# TYPE {} counter", name);
+        for (component, panics) in self.lock_panics().iter() {
+            let _ = writeln!(out, "{}{{component=\"{}\"}} {}", name, component, panics);
+        }
+    }
+
+    fn write_latency_metrics(&self, out: &mut String) {
+        let name = "kline_latency_seconds";
+        let _ = writeln!(out, "# HELP {} Pipeline latency by stage.", name);
+        let _ = writeln!(out, "# TYPE {} summary", name);
+        for (stage, latency) in self.lock_latency().iter() {
+            for quantile in QUANTILES {
+                let _ = writeln!(
+                    out,
+                    "{}{{stage=\"{}\",quantile=\"{}\"}} {}",
+                    name,
+                    stage,
+                    quantile,
+                    latency.quantile(quantile)
+                );
+            }
+            let _ = writeln!(
+                out,
+                "{}_sum{{stage=\"{}\"}} {}",
+                name,
+                stage,
+                latency.total.as_secs_f64()
+            );
+            let _ = writeln!(
+                out,
+                "{}_count{{stage=\"{}\"}} {}",
+                name,
+                stage,
+                latency.histogram.len()
+            );
+        }
+    }
+}
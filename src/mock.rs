@@ -0,0 +1,398 @@
+//! A local stand-in for Binance's WebSocket streams, serving canned kline
+//! messages, so the streams and the whole pipeline can be run and tested
+//! deterministically without reaching Binance. Point `stream_url` at
+//! [`MockServer::url`]. With [`Chaos`], it also misbehaves the ways a real
+//! connection does, to exercise reconnection, gap detection and dedup.
+
+use crate::rng::Rng;
+use crate::KlineData;
+use anyhow::{anyhow, Context, Result};
+use futures_util::{SinkExt, StreamExt};
+use serde_json::{json, Value};
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::watch;
+use tokio_tungstenite::tungstenite::handshake::server::{Request, Response};
+use tokio_tungstenite::tungstenite::Message;
+use tokio_util::sync::CancellationToken;
+use tracing::debug;
+
+/// Faults to inject into what the mock server sends, each message drawn
+/// for each fault at random. Draws come from a generator seeded with `seed`
+/// and the connection's number, so a run that goes wrong can be reproduced.
+/// Probabilities are from 0 to 1; the default injects nothing.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Chaos {
+    pub seed: u64,
+    /// That the connection drops, without a close frame, instead of sending
+    /// the message.
+    pub disconnect: f64,
+    /// That the message is held back, for up to `max_delay`.
+    pub delay: f64,
+    pub max_delay: Duration,
+    /// That the message is cut short, leaving invalid JSON.
+    pub truncate: f64,
+    /// That the message is swapped with the one after it.
+    pub reorder: f64,
+    /// That the message is sent twice.
+    pub duplicate: f64,
+}
+
+impl Chaos {
+    pub fn is_enabled(&self) -> bool {
+        [
+            self.disconnect,
+            self.delay,
+            self.truncate,
+            self.reorder,
+            self.duplicate,
+        ]
+        .iter()
+        .any(|chance| *chance > 0.0)
+    }
+}
+
+/// How many of each fault the mock server has injected.
+#[derive(Debug, Default)]
+pub struct Faults {
+    pub disconnects: AtomicUsize,
+    pub delays: AtomicUsize,
+    pub truncated: AtomicUsize,
+    pub reordered: AtomicUsize,
+    pub duplicated: AtomicUsize,
+}
+
+/// One canned message: the data of a stream's message, and the stream.
+struct Frame {
+    stream: String,
+    data: String,
+}
+
+struct Shared {
+    frames: Vec<Frame>,
+    connections: AtomicUsize,
+    /// Path of every connection made, in order.
+    paths: Mutex<Vec<String>>,
+    /// Every text message clients sent, in order.
+    requests: Mutex<Vec<String>>,
+    /// Bumped to drop every open connection.
+    disconnects: watch::Sender<u64>,
+    chaos: Mutex<Chaos>,
+    faults: Faults,
+}
+
+impl Shared {
+    fn lock_paths(&self) -> MutexGuard<'_, Vec<String>> {
+        self.paths.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn lock_requests(&self) -> MutexGuard<'_, Vec<String>> {
+        self.requests.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn chaos(&self) -> Chaos {
+        *self.chaos.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// The canned messages of `stream`, as its own connection sends them.
+    fn stream_frames(&self, stream: &str) -> Vec<String> {
+        self.frames
+            .iter()
+            .filter(|frame| frame.stream == stream)
+            .map(|frame| frame.data.clone())
+            .collect()
+    }
+}
+
+/// Serves canned combined-stream messages on a local port until dropped.
+///
+/// A single-stream connection, `/ws/<stream>`, is sent the messages of its
+/// stream, unwrapped as Binance sends them. A combined-stream connection,
+/// `/stream`, is answered like Binance answers subscription requests, and
+/// sent the messages of the streams each request subscribes, wrapped. Both
+/// are sent in the order given, as fast as they are read, and then held open
+/// until the client leaves or [`MockServer::disconnect`] is called; every
+/// new connection is sent its messages again from the start.
+pub struct MockServer {
+    addr: SocketAddr,
+    shared: Arc<Shared>,
+    stopping: CancellationToken,
+}
+
+impl MockServer {
+    /// Serves `messages`, each a Binance combined-stream message naming its
+    /// stream, e.g. `{"stream":"btcusdt@kline_1m","data":{...}}`.
+    pub async fn start(messages: impl IntoIterator<Item = String>) -> Result<Self> {
+        Self::bind("127.0.0.1:0".parse()?, messages).await
+    }
+
+    /// Serves `messages`, as [`MockServer::start`] does, on `addr`.
+    pub async fn bind(
+        addr: SocketAddr,
+        messages: impl IntoIterator<Item = String>,
+    ) -> Result<Self> {
+        let frames = messages
+            .into_iter()
+            .map(|message| {
+                let message: Value = serde_json::from_str(&message)?;
+                let stream = message["stream"]
+                    .as_str()
+                    .ok_or_else(|| anyhow!("Canned message names no stream"))?;
+                Ok(Frame {
+                    stream: stream.to_string(),
+                    data: message["data"].to_string(),
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let listener = TcpListener::bind(addr)
+            .await
+            .with_context(|| format!("Failed to listen on {}", addr))?;
+        let addr = listener.local_addr()?;
+        let shared = Arc::new(Shared {
+            frames,
+            connections: AtomicUsize::new(0),
+            paths: Mutex::new(Vec::new()),
+            requests: Mutex::new(Vec::new()),
+            disconnects: watch::Sender::new(0),
+            chaos: Mutex::new(Chaos::default()),
+            faults: Faults::default(),
+        });
+        let stopping = CancellationToken::new();
+        tokio::spawn(accept(listener, shared.clone(), stopping.clone()));
+        Ok(Self {
+            addr,
+            shared,
+            stopping,
+        })
+    }
+
+    /// Serves the messages of a capture, one per line, as read by
+    /// `--input`.
+    pub async fn from_capture(path: &Path) -> Result<Self> {
+        Self::start(read_capture(path)?).await
+    }
+
+    /// The base URL to stream from, for `stream_url`.
+    pub fn url(&self) -> String {
+        format!("ws://{}", self.addr)
+    }
+
+    /// Connections made so far, including closed ones.
+    pub fn connections(&self) -> usize {
+        self.shared.connections.load(Ordering::Relaxed)
+    }
+
+    /// The path of every connection made so far, e.g. `/stream`.
+    pub fn paths(&self) -> Vec<String> {
+        self.shared.lock_paths().clone()
+    }
+
+    /// Every text message clients have sent, such as subscription requests.
+    pub fn requests(&self) -> Vec<String> {
+        self.shared.lock_requests().clone()
+    }
+
+    /// Drops every open connection with a close frame, as Binance does when
+    /// it closes a connection, so clients reconnect.
+    pub fn disconnect(&self) {
+        self.shared.disconnects.send_modify(|count| *count += 1);
+    }
+
+    /// Injects `chaos` into every message sent from now on, on open
+    /// connections and new ones.
+    pub fn set_chaos(&self, chaos: Chaos) {
+        *self.shared.chaos.lock().unwrap_or_else(|e| e.into_inner()) = chaos;
+    }
+
+    /// The faults injected so far.
+    pub fn faults(&self) -> &Faults {
+        &self.shared.faults
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.stopping.cancel();
+    }
+}
+
+/// The messages of a capture, one per line.
+pub fn read_capture(path: &Path) -> Result<Vec<String>> {
+    let capture = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read capture {}", path.display()))?;
+    Ok(capture
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(str::to_string)
+        .collect())
+}
+
+/// The combined-stream message Binance would send for `kline`, for canned
+/// messages built in code rather than captured.
+pub fn kline_message(kline: &KlineData) -> String {
+    let start = kline.interval_start.timestamp_millis();
+    let length = crate::interval::interval_duration(&kline.interval)
+        .map_or(0, |length| length.num_milliseconds());
+    json!({
+        "stream": crate::multiplex::stream_name(&kline.symbol, &kline.interval),
+        "data": {
+            "e": "kline",
+            "E": start + length.max(1),
+            "s": kline.symbol.to_uppercase(),
+            "k": {
+                "t": start,
+                "T": start + length - 1,
+                "s": kline.symbol.to_uppercase(),
+                "i": &*kline.interval,
+                "o": kline.open.to_string(),
+                "h": kline.high.to_string(),
+                "l": kline.low.to_string(),
+                "c": kline.close.to_string(),
+                "v": kline.volume.to_string(),
+                "x": kline.closed,
+            },
+        },
+    })
+    .to_string()
+}
+
+async fn accept(listener: TcpListener, shared: Arc<Shared>, stopping: CancellationToken) {
+    loop {
+        let stream = tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok((stream, _)) => stream,
+                Err(e) => {
+                    debug!("Mock server failed to accept a connection: {}", e);
+                    continue;
+                }
+            },
+            _ = stopping.cancelled() => return,
+        };
+        let (shared, stopping) = (shared.clone(), stopping.clone());
+        tokio::spawn(async move {
+            tokio::select! {
+                result = serve(stream, shared) => {
+                    if let Err(e) = result {
+                        debug!("Mock server connection failed: {}", e);
+                    }
+                }
+                _ = stopping.cancelled() => {}
+            }
+        });
+    }
+}
+
+// The handshake callback's error type is tungstenite's, however large.
+#[allow(clippy::result_large_err)]
+async fn serve(stream: TcpStream, shared: Arc<Shared>) -> Result<()> {
+    let mut path = String::new();
+    let ws =
+        tokio_tungstenite::accept_hdr_async(stream, |request: &Request, response: Response| {
+            path = request.uri().path().to_string();
+            Ok(response)
+        })
+        .await?;
+    let connection = shared.connections.fetch_add(1, Ordering::Relaxed);
+    shared.lock_paths().push(path.clone());
+    let mut rng =
+        Rng::new(shared.chaos().seed ^ (connection as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15));
+    let mut disconnects = shared.disconnects.subscribe();
+    disconnects.mark_unchanged();
+    let (mut write, mut read) = ws.split();
+
+    if let Some(stream) = path.strip_prefix("/ws/") {
+        if !send(&mut write, shared.stream_frames(stream), &shared, &mut rng).await? {
+            return Ok(());
+        }
+    }
+    loop {
+        tokio::select! {
+            message = read.next() => {
+                let text = match message {
+                    Some(Ok(Message::Text(text))) => text,
+                    Some(Ok(Message::Close(_))) | None => return Ok(()),
+                    Some(Ok(_)) => continue,
+                    Some(Err(e)) => return Err(e.into()),
+                };
+                shared.lock_requests().push(text.to_string());
+                let request: Value = serde_json::from_str(&text).unwrap_or_default();
+                write
+                    .send(Message::text(json!({ "result": null, "id": request["id"] }).to_string()))
+                    .await?;
+                if request["method"] != "SUBSCRIBE" || path != "/stream" {
+                    continue;
+                }
+                let streams: Vec<&str> = request["params"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(Value::as_str)
+                    .collect();
+                let messages = shared
+                    .frames
+                    .iter()
+                    .filter(|frame| streams.contains(&frame.stream.as_str()))
+                    .map(|frame| format!(r#"{{"stream":"{}","data":{}}}"#, frame.stream, frame.data))
+                    .collect();
+                if !send(&mut write, messages, &shared, &mut rng).await? {
+                    return Ok(());
+                }
+            }
+            _ = disconnects.changed() => {
+                write.send(Message::Close(None)).await?;
+                return Ok(());
+            }
+        }
+    }
+}
+
+type WebSocketSink =
+    futures_util::stream::SplitSink<tokio_tungstenite::WebSocketStream<TcpStream>, Message>;
+
+/// Sends `messages` in order, injecting the current chaos. Returns whether
+/// the connection is still up.
+async fn send(
+    write: &mut WebSocketSink,
+    mut messages: Vec<String>,
+    shared: &Shared,
+    rng: &mut Rng,
+) -> Result<bool> {
+    let chaos = shared.chaos();
+    let faults = &shared.faults;
+    for i in 1..messages.len() {
+        if rng.chance(chaos.reorder) {
+            messages.swap(i - 1, i);
+            faults.reordered.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+    for mut message in messages {
+        if rng.chance(chaos.disconnect) {
+            faults.disconnects.fetch_add(1, Ordering::Relaxed);
+            return Ok(false);
+        }
+        if rng.chance(chaos.delay) {
+            faults.delays.fetch_add(1, Ordering::Relaxed);
+            tokio::time::sleep(chaos.max_delay.mul_f64(rng.uniform())).await;
+        }
+        let mut copies = 1;
+        if rng.chance(chaos.truncate) {
+            let mut end = (message.len() as f64 * rng.uniform()) as usize;
+            while !message.is_char_boundary(end) {
+                end -= 1;
+            }
+            message.truncate(end);
+            faults.truncated.fetch_add(1, Ordering::Relaxed);
+        } else if rng.chance(chaos.duplicate) {
+            copies = 2;
+            faults.duplicated.fetch_add(1, Ordering::Relaxed);
+        }
+        for _ in 0..copies {
+            write.send(Message::text(message.clone())).await?;
+        }
+    }
+    Ok(true)
+}
@@ -0,0 +1,164 @@
+//! Shared connections carrying many streams at once over Binance's combined
+//! stream endpoint, subscribed and unsubscribed as the streams assigned to
+//! them change.
+
+use crate::health::{Health, StreamState};
+use crate::metrics::Metrics;
+use crate::queue::KlineSender;
+use crate::{KlineData, SeriesKey};
+use anyhow::Result;
+use futures_util::{SinkExt, StreamExt};
+use serde_json::{json, Value};
+use std::collections::{BTreeSet, HashMap};
+use std::time::Duration;
+use tokio::sync::watch;
+use tokio::time::MissedTickBehavior;
+use tokio_tungstenite::connect_async;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, debug_span, info, instrument, warn, Instrument};
+
+/// Where the updates of one stream go.
+#[derive(Clone)]
+pub struct Route {
+    pub key: SeriesKey,
+    pub tx: KlineSender,
+}
+
+/// The streams a connection should carry, by Binance stream name.
+pub type Routes = HashMap<String, Route>;
+
+/// The Binance name of the kline stream of `symbol` on `interval`.
+pub fn stream_name(symbol: &str, interval: &str) -> String {
+    format!("{}@kline_{}", symbol, interval)
+}
+
+/// Carries the streams in `routes` over one connection to the combined
+/// stream endpoint under `stream_url` until it closes,
+/// following changes to `routes` with at most `messages_per_sec`
+/// subscription requests a second. Closes the connection once `stopping`
+/// is cancelled.
+#[instrument(name = "connection", skip_all, fields(connection = %name))]
+pub async fn run_connection(
+    stream_url: &str,
+    name: String,
+    mut routes: watch::Receiver<Routes>,
+    messages_per_sec: u32,
+    health: Health,
+    metrics: Metrics,
+    stopping: CancellationToken,
+) -> Result<()> {
+    info!("Connecting {} to Binance...", name);
+    let (ws_stream, _) = connect_async(format!("{}/stream", stream_url)).await?;
+    info!("Connected {}.", name);
+    let (mut write, mut read) = ws_stream.split();
+
+    // Streams subscribed on this connection, as far as Binance knows.
+    let mut subscribed: BTreeSet<String> = BTreeSet::new();
+    let mut throttle = tokio::time::interval(Duration::from_secs(1) / messages_per_sec.max(1));
+    throttle.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    let mut request_id = 0u64;
+    let mut pending = true;
+
+    loop {
+        tokio::select! {
+            changed = routes.changed() => {
+                if changed.is_err() {
+                    return Ok(());
+                }
+                pending = true;
+            }
+            _ = stopping.cancelled() => {
+                write.send(Message::Close(None)).await?;
+                info!("Closed {}", name);
+                return Ok(());
+            }
+            _ = throttle.tick(), if pending => {
+                let (method, params) = {
+                    let routes = routes.borrow();
+                    let removed: Vec<String> = subscribed
+                        .iter()
+                        .filter(|stream| !routes.contains_key(*stream))
+                        .cloned()
+                        .collect();
+                    if removed.is_empty() {
+                        let added: Vec<String> = routes
+                            .keys()
+                            .filter(|stream| !subscribed.contains(*stream))
+                            .cloned()
+                            .collect();
+                        for stream in &added {
+                            let route = &routes[stream];
+                            health.set_stream_state(&route.key.0, &route.key.1, StreamState::Connected);
+                            metrics.record_connected(&route.key);
+                        }
+                        ("SUBSCRIBE", added)
+                    } else {
+                        ("UNSUBSCRIBE", removed)
+                    }
+                };
+                if params.is_empty() {
+                    pending = false;
+                    continue;
+                }
+                request_id += 1;
+                debug!("{} {} stream(s)", method, params.len());
+                let request = json!({ "method": method, "params": params, "id": request_id });
+                write.send(Message::text(request.to_string())).await?;
+                for stream in params {
+                    if method == "SUBSCRIBE" {
+                        subscribed.insert(stream);
+                    } else {
+                        subscribed.remove(&stream);
+                    }
+                }
+            }
+            message = read.next() => {
+                let text = match message {
+                    Some(Ok(Message::Text(text))) => text,
+                    Some(Ok(Message::Close(_))) | None => break,
+                    Some(Ok(_)) => continue,
+                    Some(Err(e)) => return Err(e.into()),
+                };
+                // Looked up while parsing, so the message can be attributed
+                // to its stream.
+                let mut route = None;
+                let parsed = debug_span!("parse").in_scope(|| {
+                    KlineData::parse_combined(&text, |stream| {
+                        route = routes.borrow().get(stream).cloned();
+                        route.as_ref().map(|route| route.key.clone())
+                    })
+                });
+                match (parsed, route) {
+                    (Ok(Some(kline_data)), Some(route)) => {
+                        metrics.record_message(&route.key, text.len());
+                        metrics.record_parsed(&kline_data);
+                        route.tx.send(kline_data).instrument(debug_span!("queue")).await?;
+                        health.record_message(&route.key);
+                    }
+                    (Ok(_), _) => log_response(&name, &text),
+                    (Err(e), Some(route)) => {
+                        metrics.record_parse_error(&route.key);
+                        warn!("Skipping unparseable message for {} {}: {}", route.key.0, route.key.1, e);
+                    }
+                    (Err(e), None) => warn!("Skipping unparseable message on {}: {}", name, e),
+                }
+            }
+        }
+    }
+    warn!("WebSocket connection closed for {}", name);
+    Ok(())
+}
+
+/// Logs Binance's answer to a subscription request if it reports an error.
+fn log_response(name: &str, text: &str) {
+    let Ok(response) = serde_json::from_str::<Value>(text) else {
+        return;
+    };
+    if let Some(error) = response.get("error") {
+        warn!(
+            "Binance rejected a subscription request on {}: {}",
+            name, error
+        );
+    }
+}
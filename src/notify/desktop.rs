@@ -0,0 +1,66 @@
+use super::{Notification, Notifier};
+use crate::config::DesktopConfig;
+use anyhow::Result;
+use futures_util::future::BoxFuture;
+use notify_rust::Timeout;
+
+/// Shows alerts as native OS notifications on the machine running the
+/// tracker.
+pub struct DesktopNotifier {
+    timeout_ms: u32,
+    summaries: bool,
+    signals: bool,
+}
+
+impl DesktopNotifier {
+    pub fn new(config: &DesktopConfig) -> Self {
+        Self {
+            timeout_ms: config.timeout_ms,
+            summaries: config.summaries,
+            signals: config.signals,
+        }
+    }
+}
+
+impl Notifier for DesktopNotifier {
+    fn name(&self) -> &str {
+        "desktop"
+    }
+
+    fn wants_summaries(&self) -> bool {
+        self.summaries
+    }
+
+    fn wants_signals(&self) -> bool {
+        self.signals
+    }
+
+    fn notify<'a>(&'a self, notification: &'a Notification) -> BoxFuture<'a, Result<()>> {
+        let (summary, body) = match notification {
+            Notification::Alert(alert) => (
+                format!("{} {}", alert.symbol.to_uppercase(), alert.rule),
+                format!(
+                    "{}\nPrice: {} ({:+.2}%)",
+                    alert.message, alert.price, alert.change_percent
+                ),
+            ),
+            Notification::Summary { title, lines } => (title.clone(), lines.join("\n")),
+            Notification::Signal(signal) => (signal.title(), signal.lines().join("\n")),
+        };
+        let timeout = Timeout::Milliseconds(self.timeout_ms);
+        Box::pin(async move {
+            // Showing a notification talks to the OS synchronously.
+            tokio::task::spawn_blocking(move || {
+                notify_rust::Notification::new()
+                    .appname("Crypto Kline Tracker")
+                    .summary(&summary)
+                    .body(&body)
+                    .timeout(timeout)
+                    .show()
+                    .map(|_| ())
+            })
+            .await??;
+            Ok(())
+        })
+    }
+}
@@ -0,0 +1,110 @@
+use super::{truncate, with_retries, Notification, Notifier};
+use crate::config::{DiscordConfig, Side};
+use anyhow::Result;
+use futures_util::future::BoxFuture;
+use serde_json::{json, Value};
+use std::time::Duration;
+
+const GREEN: u32 = 0x2ecc71;
+const RED: u32 = 0xe74c3c;
+const BLUE: u32 = 0x3498db;
+/// Discord rejects embed descriptions longer than this.
+const MAX_DESCRIPTION: usize = 4096;
+
+/// Posts alerts and summaries to a Discord channel webhook as embeds.
+pub struct DiscordNotifier {
+    client: reqwest::Client,
+    url: String,
+    username: Option<String>,
+    retries: u32,
+    summaries: bool,
+    signals: bool,
+}
+
+impl DiscordNotifier {
+    pub fn new(config: &DiscordConfig) -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(config.timeout_secs))
+            .build()?;
+        Ok(Self {
+            client,
+            url: config.webhook_url.clone(),
+            username: config.username.clone(),
+            retries: config.retries,
+            summaries: config.summaries,
+            signals: config.signals,
+        })
+    }
+}
+
+fn embed(notification: &Notification) -> Value {
+    match notification {
+        Notification::Alert(alert) => json!({
+            "title": format!("Alert: {}", alert.rule),
+            "description": alert.message,
+            "color": if alert.change_percent >= 0.0 { GREEN } else { RED },
+            "fields": [
+                { "name": "Symbol", "value": alert.symbol.to_uppercase(), "inline": true },
+                { "name": "Interval", "value": alert.interval, "inline": true },
+                { "name": "Price", "value": alert.price.to_string(), "inline": true },
+                { "name": "Change", "value": format!("{:+.2}%", alert.change_percent), "inline": true },
+                { "name": "Rule", "value": alert.rule, "inline": true },
+            ],
+            "timestamp": alert.triggered_at.to_rfc3339(),
+        }),
+        Notification::Summary { title, lines } => {
+            let mut description = lines.join("\n");
+            truncate(&mut description, MAX_DESCRIPTION);
+            json!({
+                "title": title,
+                "description": description,
+                "color": BLUE,
+            })
+        }
+        Notification::Signal(signal) => json!({
+            "title": signal.title(),
+            "description": signal.lines().join("\n"),
+            "color": match signal.side {
+                Side::Buy => GREEN,
+                Side::Sell => RED,
+            },
+            "timestamp": signal.raised_at.to_rfc3339(),
+        }),
+    }
+}
+
+impl Notifier for DiscordNotifier {
+    fn name(&self) -> &str {
+        "discord"
+    }
+
+    fn wants_summaries(&self) -> bool {
+        self.summaries
+    }
+
+    fn wants_signals(&self) -> bool {
+        self.signals
+    }
+
+    fn notify<'a>(&'a self, notification: &'a Notification) -> BoxFuture<'a, Result<()>> {
+        let mut payload = json!({ "embeds": [embed(notification)] });
+        if let Some(username) = &self.username {
+            payload["username"] = json!(username);
+        }
+        Box::pin(async move {
+            let payload = &payload;
+            with_retries(self.retries, move || async move {
+                // The webhook URL holds its token, which errors would quote.
+                self.client
+                    .post(&self.url)
+                    .json(payload)
+                    .send()
+                    .await
+                    .and_then(reqwest::Response::error_for_status)
+                    .map_err(reqwest::Error::without_url)?;
+                Ok(())
+            })
+            .await
+        })
+    }
+}
@@ -0,0 +1,226 @@
+use super::{Notification, Notifier};
+use crate::alerts::Alert;
+use crate::config::{EmailConfig, EmailTls};
+use anyhow::{anyhow, Result};
+use futures_util::future::BoxFuture;
+use lettre::message::Mailbox;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::oneshot;
+use tokio_util::sync::{CancellationToken, DropGuard};
+
+/// Sends alerts by email over SMTP.
+///
+/// With a non-zero `batch_secs`, alerts are collected and sent as a single
+/// digest mail per batch window, so a volatile market doesn't flood the inbox.
+/// Each alert's delivery finishes only once its digest is sent, so its
+/// outcome is recorded like any other.
+pub struct EmailNotifier {
+    mailer: Arc<Mailer>,
+    pending: Option<Arc<Pending>>,
+    /// Stops the batch task, sending what it holds, once the notifier is
+    /// dropped.
+    _batching: Option<DropGuard>,
+    summaries: bool,
+    signals: bool,
+}
+
+/// Alerts waiting for the next digest, each with whoever waits for the
+/// outcome of sending it.
+type Pending = Mutex<Vec<(Alert, oneshot::Sender<Result<(), String>>)>>;
+
+struct Mailer {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+    to: Vec<Mailbox>,
+    subject: String,
+    body: String,
+}
+
+impl EmailNotifier {
+    pub fn new(config: &EmailConfig) -> Result<Self> {
+        let mut builder = match config.tls {
+            EmailTls::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(&config.host)?,
+            EmailTls::Starttls => {
+                AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.host)?
+            }
+            EmailTls::None => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&config.host),
+        };
+        if let Some(port) = config.port {
+            builder = builder.port(port);
+        }
+        if let (Some(username), Some(password)) = (&config.username, &config.password) {
+            builder = builder.credentials(Credentials::new(username.clone(), password.clone()));
+        }
+
+        let mailer = Arc::new(Mailer {
+            transport: builder
+                .timeout(Some(Duration::from_secs(config.timeout_secs)))
+                .build(),
+            from: config.from.parse()?,
+            to: config
+                .to
+                .iter()
+                .map(|address| address.parse())
+                .collect::<Result<_, _>>()?,
+            subject: config.subject.clone(),
+            body: config.body.clone(),
+        });
+
+        let (pending, batching) = if config.batch_secs > 0 {
+            let pending = Arc::new(Mutex::new(Vec::new()));
+            let stop = CancellationToken::new();
+            tokio::spawn(flush_batches(
+                mailer.clone(),
+                pending.clone(),
+                Duration::from_secs(config.batch_secs),
+                stop.clone(),
+            ));
+            (Some(pending), Some(stop.drop_guard()))
+        } else {
+            (None, None)
+        };
+
+        Ok(Self {
+            mailer,
+            pending,
+            _batching: batching,
+            summaries: config.summaries,
+            signals: config.signals,
+        })
+    }
+}
+
+/// Fills `{rule}`, `{symbol}`, `{interval}`, `{price}`, `{change_percent}`,
+/// `{message}` and `{triggered_at}` in a template.
+fn render(template: &str, alert: &Alert) -> String {
+    template
+        .replace("{rule}", &alert.rule)
+        .replace("{symbol}", &alert.symbol.to_uppercase())
+        .replace("{interval}", &alert.interval)
+        .replace("{price}", &alert.price.to_string())
+        .replace("{change_percent}", &format!("{:+.2}", alert.change_percent))
+        .replace("{message}", &alert.message)
+        .replace("{triggered_at}", &alert.triggered_at.to_rfc3339())
+}
+
+impl Mailer {
+    async fn send(&self, subject: String, body: String) -> Result<()> {
+        let mut message = Message::builder().from(self.from.clone()).subject(subject);
+        for to in &self.to {
+            message = message.to(to.clone());
+        }
+        self.transport.send(message.body(body)?).await?;
+        Ok(())
+    }
+
+    async fn send_digest(&self, alerts: Vec<Alert>) -> Result<()> {
+        let [alert] = alerts.as_slice() else {
+            let bodies: Vec<String> = alerts.iter().map(|a| render(&self.body, a)).collect();
+            let subject = format!("{} crypto alerts", alerts.len());
+            return self.send(subject, bodies.join("\n\n---\n\n")).await;
+        };
+        self.send(render(&self.subject, alert), render(&self.body, alert))
+            .await
+    }
+}
+
+/// Sends a digest of the pending alerts every `every`, and once more when
+/// `stop` is cancelled. Failures are reported through each alert's delivery.
+async fn flush_batches(
+    mailer: Arc<Mailer>,
+    pending: Arc<Pending>,
+    every: Duration,
+    stop: CancellationToken,
+) {
+    let mut timer = tokio::time::interval(every);
+    timer.tick().await;
+    loop {
+        tokio::select! {
+            _ = timer.tick() => {
+                let _ = send_pending(&mailer, &pending).await;
+            }
+            _ = stop.cancelled() => {
+                let _ = send_pending(&mailer, &pending).await;
+                return;
+            }
+        }
+    }
+}
+
+/// Sends the pending alerts as one digest, passing the outcome on to each
+/// alert's delivery as well as returning it.
+async fn send_pending(mailer: &Mailer, pending: &Pending) -> Result<()> {
+    let batch = std::mem::take(&mut *pending.lock().unwrap_or_else(|e| e.into_inner()));
+    if batch.is_empty() {
+        return Ok(());
+    }
+    let (alerts, waiting): (Vec<_>, Vec<_>) = batch.into_iter().unzip();
+    let result = mailer.send_digest(alerts).await;
+    let outcome = result.as_ref().map(|_| ()).map_err(|e| e.to_string());
+    for waiter in waiting {
+        let _ = waiter.send(outcome.clone());
+    }
+    result
+}
+
+impl Notifier for EmailNotifier {
+    fn name(&self) -> &str {
+        "email"
+    }
+
+    fn wants_summaries(&self) -> bool {
+        self.summaries
+    }
+
+    fn wants_signals(&self) -> bool {
+        self.signals
+    }
+
+    fn notify<'a>(&'a self, notification: &'a Notification) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            match notification {
+                Notification::Alert(alert) => match &self.pending {
+                    Some(pending) => {
+                        let (sent, outcome) = oneshot::channel();
+                        pending
+                            .lock()
+                            .unwrap_or_else(|e| e.into_inner())
+                            .push((alert.clone(), sent));
+                        match outcome.await {
+                            Ok(outcome) => outcome.map_err(|e| anyhow!(e)),
+                            Err(_) => Err(anyhow!("The alert digest was dropped unsent")),
+                        }
+                    }
+                    None => {
+                        self.mailer
+                            .send(
+                                render(&self.mailer.subject, alert),
+                                render(&self.mailer.body, alert),
+                            )
+                            .await
+                    }
+                },
+                Notification::Summary { title, lines } => {
+                    self.mailer.send(title.clone(), lines.join("\n")).await
+                }
+                Notification::Signal(signal) => {
+                    self.mailer
+                        .send(signal.title(), signal.lines().join("\n"))
+                        .await
+                }
+            }
+        })
+    }
+
+    fn flush(&self) -> BoxFuture<'_, Result<()>> {
+        Box::pin(async move {
+            match &self.pending {
+                Some(pending) => send_pending(&self.mailer, pending).await,
+                None => Ok(()),
+            }
+        })
+    }
+}
@@ -0,0 +1,274 @@
+#[cfg(feature = "desktop")]
+mod desktop;
+mod discord;
+#[cfg(feature = "email")]
+mod email;
+mod slack;
+mod sound;
+mod telegram;
+mod webhook;
+
+use crate::actor::{InFlight, Mailbox, PanicReporter};
+use crate::alerts::Alert;
+use crate::config::NotifierConfig;
+use crate::health::Health;
+use crate::metrics::Metrics;
+use crate::ops::{OpsEvent, Severity};
+use crate::store::Store;
+use crate::strategy::TradeSignal;
+use anyhow::Result;
+use futures_util::future::{join_all, BoxFuture};
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+use tracing::{debug, error};
+
+/// Something worth telling the user about outside the log.
+#[derive(Debug, Clone)]
+pub enum Notification {
+    Alert(Alert),
+    /// A periodic digest, one line per entry.
+    Summary {
+        title: String,
+        lines: Vec<String>,
+    },
+    /// A trade signal from a strategy.
+    Signal(TradeSignal),
+}
+
+/// Delivers notifications to an external channel.
+pub trait Notifier: Send + Sync {
+    fn name(&self) -> &str;
+
+    /// Whether periodic summaries should be sent here as well as alerts.
+    fn wants_summaries(&self) -> bool;
+
+    /// Whether strategy signals should be sent here as well as alerts.
+    fn wants_signals(&self) -> bool;
+
+    fn notify<'a>(&'a self, notification: &'a Notification) -> BoxFuture<'a, Result<()>>;
+
+    /// Sends anything held back for batching right away.
+    fn flush(&self) -> BoxFuture<'_, Result<()>> {
+        Box::pin(async { Ok(()) })
+    }
+}
+
+pub fn build_notifiers(configs: &[NotifierConfig]) -> Result<Vec<Arc<dyn Notifier>>> {
+    configs
+        .iter()
+        .map(|config| -> Result<Arc<dyn Notifier>> {
+            Ok(match config {
+                NotifierConfig::Webhook(webhook) => {
+                    Arc::new(webhook::WebhookNotifier::new(webhook)?)
+                }
+                NotifierConfig::Discord(discord) => {
+                    Arc::new(discord::DiscordNotifier::new(discord)?)
+                }
+                #[cfg(feature = "desktop")]
+                NotifierConfig::Desktop(desktop) => {
+                    Arc::new(desktop::DesktopNotifier::new(desktop))
+                }
+                #[cfg(not(feature = "desktop"))]
+                NotifierConfig::Desktop(_) => {
+                    anyhow::bail!("Desktop notifications need the `desktop` feature")
+                }
+                #[cfg(feature = "email")]
+                NotifierConfig::Email(email) => Arc::new(email::EmailNotifier::new(email)?),
+                #[cfg(not(feature = "email"))]
+                NotifierConfig::Email(_) => {
+                    anyhow::bail!("Email notifications need the `email` feature")
+                }
+                NotifierConfig::Slack(slack) => Arc::new(slack::SlackNotifier::new(slack)?),
+                NotifierConfig::Sound(sound) => Arc::new(sound::SoundNotifier::new(sound)),
+                NotifierConfig::Telegram(telegram) => {
+                    Arc::new(telegram::TelegramNotifier::new(telegram)?)
+                }
+            })
+        })
+        .collect()
+}
+
+/// Runs `attempt` up to `retries + 1` times, doubling the delay between
+/// attempts starting at one second.
+pub async fn with_retries<F, Fut>(retries: u32, mut attempt: F) -> Result<()>
+where
+    F: FnMut() -> Fut,
+    Fut: std::future::Future<Output = Result<()>>,
+{
+    let mut delay = Duration::from_secs(1);
+    let mut tries = 0;
+    loop {
+        match attempt().await {
+            Ok(()) => return Ok(()),
+            Err(e) if tries < retries => {
+                debug!(
+                    "Delivery attempt {} failed: {}",
+                    tries + 1,
+                    redact(&e.to_string())
+                );
+                tries += 1;
+                tokio::time::sleep(delay).await;
+                delay *= 2;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Escapes text for Telegram's and similar HTML-subset message formats.
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// `error` with every URL in it cut down to its scheme and host. Notifier
+/// URLs may hold tokens, and delivery errors are logged, stored and served
+/// by the APIs.
+pub fn redact(error: &str) -> String {
+    let mut redacted = String::with_capacity(error.len());
+    let mut rest = error;
+    while let Some(start) = rest.find("://") {
+        let (before, after) = rest.split_at(start + 3);
+        redacted.push_str(before);
+        let end = after
+            .find(|c: char| c.is_whitespace() || matches!(c, ')' | '"' | '\'' | '>' | ','))
+            .unwrap_or(after.len());
+        let url = &after[..end];
+        let host_end = url.find(['/', '?', '#']).unwrap_or(url.len());
+        let host = &url[..host_end];
+        // Credentials before the host are dropped as well.
+        redacted.push_str(host.rsplit_once('@').map_or(host, |(_, host)| host));
+        if host_end < url.len() {
+            redacted.push_str("/…");
+        }
+        rest = &after[end..];
+    }
+    redacted.push_str(rest);
+    redacted
+}
+
+/// Cuts `text` down to at most `max` bytes, ending it with an ellipsis if
+/// anything was cut.
+fn truncate(text: &mut String, max: usize) {
+    if text.len() <= max {
+        return;
+    }
+    let mut end = max.saturating_sub('…'.len_utf8());
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    text.truncate(end);
+    text.push('…');
+}
+
+/// Forwards every notification received on `rx` to all interested notifiers
+/// concurrently, so a slow endpoint never holds up kline processing.
+///
+/// A notifier whose delivery fails (after its retries) is reported as an
+/// operational incident on `ops_tx`, resolved by its next success. Alerts and
+/// the outcome of each delivery are recorded in `store`, and the latest
+/// outcome per notifier in `health`. How long each delivery takes is
+/// recorded in `metrics` as the latency of the notifier's sink. Each
+/// notification counts in `in_flight` until every delivery has finished,
+/// and a delivery that panics is reported to `panics`.
+#[allow(clippy::too_many_arguments)]
+pub async fn run_notifiers(
+    rx: Mailbox<Notification>,
+    notifiers: Vec<Arc<dyn Notifier>>,
+    ops_tx: mpsc::Sender<OpsEvent>,
+    store: Arc<Store>,
+    health: Health,
+    metrics: Metrics,
+    in_flight: InFlight,
+    panics: PanicReporter,
+) {
+    let failing: Arc<Mutex<HashSet<String>>> = Arc::default();
+    while let Some(notification) = rx.recv().await {
+        let delivering = in_flight.start();
+        let notifiers = notifiers.clone();
+        let failing = failing.clone();
+        let ops_tx = ops_tx.clone();
+        let store = store.clone();
+        let health = health.clone();
+        let metrics = metrics.clone();
+        let delivery = async move {
+            let alert_id = match &notification {
+                Notification::Alert(alert) => {
+                    let (store, alert) = (store.clone(), alert.clone());
+                    let recorded = tokio::task::spawn_blocking(move || store.record_alert(&alert));
+                    match recorded
+                        .await
+                        .map_err(anyhow::Error::from)
+                        .and_then(|id| id)
+                    {
+                        Ok(id) => Some(id),
+                        Err(e) => {
+                            error!("Failed to record alert: {}", e);
+                            None
+                        }
+                    }
+                }
+                Notification::Summary { .. } | Notification::Signal(_) => None,
+            };
+            let deliveries = notifiers
+                .iter()
+                .filter(|notifier| match &notification {
+                    Notification::Alert(_) => true,
+                    Notification::Summary { .. } => notifier.wants_summaries(),
+                    Notification::Signal(_) => notifier.wants_signals(),
+                })
+                .map(|notifier| async {
+                    let started = Instant::now();
+                    let result = notifier.notify(&notification).await;
+                    metrics.record_latency(&format!("sink:{}", notifier.name()), started.elapsed());
+                    // Only ever passed on redacted.
+                    let error = result.as_ref().err().map(|e| redact(&e.to_string()));
+                    health.record_delivery(notifier.name(), error.clone());
+                    let key = format!("notifier:{}", notifier.name());
+                    let event = {
+                        let mut failing = failing.lock().unwrap_or_else(|e| e.into_inner());
+                        match &error {
+                            Some(e) => failing.insert(key.clone()).then(|| {
+                                OpsEvent::trigger(
+                                    key,
+                                    format!("Notifier {} is failing: {}", notifier.name(), e),
+                                    Severity::Error,
+                                )
+                            }),
+                            None => failing.remove(&key).then(|| OpsEvent::resolve(key)),
+                        }
+                    };
+                    if let Some(alert_id) = alert_id {
+                        let (store, name) = (store.clone(), notifier.name().to_string());
+                        let error = error.clone();
+                        let recorded = tokio::task::spawn_blocking(move || {
+                            store.record_delivery(alert_id, &name, error.as_deref())
+                        })
+                        .await;
+                        if let Ok(Err(e)) = recorded {
+                            error!("Failed to record alert delivery: {}", e);
+                        }
+                    }
+                    if let Some(e) = error {
+                        error!(
+                            "Failed to deliver notification via {}: {}",
+                            notifier.name(),
+                            e
+                        );
+                    }
+                    if let Some(event) = event {
+                        let _ = ops_tx.send(event).await;
+                    }
+                });
+            join_all(deliveries).await;
+        };
+        let panics = panics.clone();
+        tokio::spawn(async move {
+            let _delivering = delivering;
+            panics.catch("notifiers", delivery).await;
+        });
+    }
+}
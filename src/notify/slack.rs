@@ -0,0 +1,186 @@
+use super::{truncate, with_retries, Notification, Notifier};
+use crate::config::SlackConfig;
+use anyhow::{anyhow, bail, Result};
+use futures_util::future::BoxFuture;
+use serde_json::{json, Value};
+use std::time::Duration;
+
+const POST_MESSAGE_URL: &str = "https://slack.com/api/chat.postMessage";
+/// Slack rejects section text longer than this.
+const MAX_SECTION: usize = 3000;
+/// Sections a message may hold besides its header, within Slack's limit of
+/// 50 blocks.
+const MAX_SECTIONS: usize = 49;
+
+enum Target {
+    Webhook(String),
+    Bot { token: String, channel: String },
+}
+
+/// Posts alerts and summaries to Slack as Block Kit messages, either through
+/// an incoming webhook or as a bot via `chat.postMessage`.
+pub struct SlackNotifier {
+    client: reqwest::Client,
+    target: Target,
+    retries: u32,
+    summaries: bool,
+    signals: bool,
+}
+
+impl SlackNotifier {
+    pub fn new(config: &SlackConfig) -> Result<Self> {
+        let target = match (&config.webhook_url, &config.bot_token, &config.channel) {
+            (Some(url), None, _) => Target::Webhook(url.clone()),
+            (None, Some(token), Some(channel)) => Target::Bot {
+                token: token.clone(),
+                channel: channel.clone(),
+            },
+            _ => bail!("Slack notifier needs either webhook_url, or bot_token and channel"),
+        };
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(config.timeout_secs))
+            .build()?;
+        Ok(Self {
+            client,
+            target,
+            retries: config.retries,
+            summaries: config.summaries,
+            signals: config.signals,
+        })
+    }
+
+    async fn post(&self, payload: &Value) -> Result<()> {
+        match &self.target {
+            Target::Webhook(url) => {
+                // The webhook URL is a secret, which errors would quote.
+                self.client
+                    .post(url)
+                    .json(payload)
+                    .send()
+                    .await
+                    .and_then(reqwest::Response::error_for_status)
+                    .map_err(reqwest::Error::without_url)?;
+            }
+            Target::Bot { token, .. } => {
+                // The Web API reports failures in the body with a 200 status.
+                let response: Value = self
+                    .client
+                    .post(POST_MESSAGE_URL)
+                    .bearer_auth(token)
+                    .json(payload)
+                    .send()
+                    .await
+                    .and_then(reqwest::Response::error_for_status)
+                    .map_err(reqwest::Error::without_url)?
+                    .json()
+                    .await
+                    .map_err(reqwest::Error::without_url)?;
+                if response["ok"].as_bool() != Some(true) {
+                    return Err(anyhow!("Slack API error: {}", response["error"]));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn blocks(notification: &Notification) -> (String, Value) {
+    match notification {
+        Notification::Alert(alert) => {
+            let fallback = format!("Alert {}: {}", alert.rule, alert.message);
+            let blocks = json!([
+                {
+                    "type": "header",
+                    "text": { "type": "plain_text", "text": format!("Alert: {}", alert.rule) },
+                },
+                {
+                    "type": "section",
+                    "text": { "type": "mrkdwn", "text": alert.message },
+                    "fields": [
+                        { "type": "mrkdwn", "text": format!("*Symbol*\n{}", alert.symbol.to_uppercase()) },
+                        { "type": "mrkdwn", "text": format!("*Interval*\n{}", alert.interval) },
+                        { "type": "mrkdwn", "text": format!("*Price*\n{}", alert.price) },
+                        { "type": "mrkdwn", "text": format!("*Change*\n{:+.2}%", alert.change_percent) },
+                    ],
+                },
+                {
+                    "type": "context",
+                    "elements": [
+                        { "type": "mrkdwn", "text": alert.triggered_at.format("%Y-%m-%d %H:%M:%S UTC").to_string() },
+                    ],
+                },
+            ]);
+            (fallback, blocks)
+        }
+        Notification::Summary { title, lines } => {
+            // Each section is a code block of its own, fences included.
+            let sections = sections(lines, MAX_SECTION - 8)
+                .into_iter()
+                .map(|text| format!("```\n{}\n```", text));
+            (title.clone(), with_header(title, sections))
+        }
+        Notification::Signal(signal) => {
+            let title = signal.title();
+            let blocks = with_header(&title, sections(&signal.lines(), MAX_SECTION));
+            (title, blocks)
+        }
+    }
+}
+
+/// `lines` joined into as few texts of at most `max` bytes as fit, each
+/// line cut down to `max` if it is longer on its own.
+fn sections(lines: &[String], max: usize) -> Vec<String> {
+    let mut sections: Vec<String> = Vec::new();
+    for line in lines {
+        let mut line = line.clone();
+        truncate(&mut line, max);
+        match sections.last_mut() {
+            Some(section) if section.len() + 1 + line.len() <= max => {
+                section.push('\n');
+                section.push_str(&line);
+            }
+            _ => sections.push(line),
+        }
+    }
+    sections
+}
+
+/// A header block titled `title` followed by a section per text, as many
+/// as a message may hold.
+fn with_header(title: &str, texts: impl IntoIterator<Item = String>) -> Value {
+    let mut blocks =
+        vec![json!({ "type": "header", "text": { "type": "plain_text", "text": title } })];
+    blocks.extend(
+        texts
+            .into_iter()
+            .take(MAX_SECTIONS)
+            .map(|text| json!({ "type": "section", "text": { "type": "mrkdwn", "text": text } })),
+    );
+    Value::Array(blocks)
+}
+
+impl Notifier for SlackNotifier {
+    fn name(&self) -> &str {
+        "slack"
+    }
+
+    fn wants_summaries(&self) -> bool {
+        self.summaries
+    }
+
+    fn wants_signals(&self) -> bool {
+        self.signals
+    }
+
+    fn notify<'a>(&'a self, notification: &'a Notification) -> BoxFuture<'a, Result<()>> {
+        let (text, blocks) = blocks(notification);
+        let mut payload = json!({ "text": text, "blocks": blocks });
+        if let Target::Bot { channel, .. } = &self.target {
+            payload["channel"] = json!(channel);
+        }
+        Box::pin(async move {
+            let payload = &payload;
+            with_retries(self.retries, move || self.post(payload)).await
+        })
+    }
+}
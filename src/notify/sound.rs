@@ -0,0 +1,68 @@
+use super::{Notification, Notifier};
+use crate::config::SoundConfig;
+use anyhow::{bail, Result};
+use futures_util::future::BoxFuture;
+use std::io::Write;
+use std::path::PathBuf;
+use tokio::process::Command;
+
+/// Makes an audible alert on the machine running the tracker: the terminal
+/// bell, or a sound file played through an external player.
+pub struct SoundNotifier {
+    file: Option<PathBuf>,
+    player: String,
+    summaries: bool,
+    signals: bool,
+}
+
+impl SoundNotifier {
+    pub fn new(config: &SoundConfig) -> Self {
+        Self {
+            file: config.file.clone(),
+            player: config
+                .player
+                .clone()
+                .unwrap_or_else(|| default_player().to_string()),
+            summaries: config.summaries,
+            signals: config.signals,
+        }
+    }
+}
+
+fn default_player() -> &'static str {
+    if cfg!(target_os = "macos") {
+        "afplay"
+    } else {
+        "paplay"
+    }
+}
+
+impl Notifier for SoundNotifier {
+    fn name(&self) -> &str {
+        "sound"
+    }
+
+    fn wants_summaries(&self) -> bool {
+        self.summaries
+    }
+
+    fn wants_signals(&self) -> bool {
+        self.signals
+    }
+
+    fn notify<'a>(&'a self, _notification: &'a Notification) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let Some(file) = &self.file else {
+                let mut stderr = std::io::stderr();
+                stderr.write_all(b"\x07")?;
+                stderr.flush()?;
+                return Ok(());
+            };
+            let status = Command::new(&self.player).arg(file).status().await?;
+            if !status.success() {
+                bail!("{} exited with {}", self.player, status);
+            }
+            Ok(())
+        })
+    }
+}
@@ -0,0 +1,104 @@
+use super::{escape_html, with_retries, Notification, Notifier};
+use crate::config::TelegramConfig;
+use anyhow::Result;
+use futures_util::future::BoxFuture;
+use serde_json::json;
+use std::time::Duration;
+
+/// Sends alerts and summaries as messages from a Telegram bot to a chat.
+pub struct TelegramNotifier {
+    client: reqwest::Client,
+    url: String,
+    chat_id: String,
+    retries: u32,
+    summaries: bool,
+    signals: bool,
+}
+
+impl TelegramNotifier {
+    pub fn new(config: &TelegramConfig) -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(config.timeout_secs))
+            .build()?;
+        Ok(Self {
+            client,
+            url: format!(
+                "{}/bot{}/sendMessage",
+                config.api_url.trim_end_matches('/'),
+                config.bot_token
+            ),
+            chat_id: config.chat_id.clone(),
+            retries: config.retries,
+            summaries: config.summaries,
+            signals: config.signals,
+        })
+    }
+}
+
+fn format_message(notification: &Notification) -> String {
+    match notification {
+        Notification::Alert(alert) => format!(
+            "🚨 <b>{}</b>\n{} <code>{}</code> {}\n{}\nPrice: <b>{}</b>",
+            escape_html(&alert.rule),
+            escape_html(&alert.symbol.to_uppercase()),
+            escape_html(&alert.interval),
+            alert.triggered_at.format("%Y-%m-%d %H:%M:%S UTC"),
+            escape_html(&alert.message),
+            alert.price,
+        ),
+        Notification::Summary { title, lines } => {
+            let body: Vec<String> = lines.iter().map(|line| escape_html(line)).collect();
+            format!("📊 <b>{}</b>\n{}", escape_html(title), body.join("\n"))
+        }
+        Notification::Signal(signal) => {
+            let body: Vec<String> = signal
+                .lines()
+                .iter()
+                .map(|line| escape_html(line))
+                .collect();
+            format!(
+                "📈 <b>{}</b>\n{}",
+                escape_html(&signal.title()),
+                body.join("\n")
+            )
+        }
+    }
+}
+
+impl Notifier for TelegramNotifier {
+    fn name(&self) -> &str {
+        "telegram"
+    }
+
+    fn wants_summaries(&self) -> bool {
+        self.summaries
+    }
+
+    fn wants_signals(&self) -> bool {
+        self.signals
+    }
+
+    fn notify<'a>(&'a self, notification: &'a Notification) -> BoxFuture<'a, Result<()>> {
+        let payload = json!({
+            "chat_id": self.chat_id,
+            "text": format_message(notification),
+            "parse_mode": "HTML",
+            "disable_web_page_preview": true,
+        });
+        Box::pin(async move {
+            let payload = &payload;
+            with_retries(self.retries, move || async move {
+                // The URL holds the bot token, which errors would quote.
+                self.client
+                    .post(&self.url)
+                    .json(payload)
+                    .send()
+                    .await
+                    .and_then(reqwest::Response::error_for_status)
+                    .map_err(reqwest::Error::without_url)?;
+                Ok(())
+            })
+            .await
+        })
+    }
+}
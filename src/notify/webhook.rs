@@ -0,0 +1,81 @@
+use super::{with_retries, Notification, Notifier};
+use crate::config::WebhookConfig;
+use anyhow::Result;
+use futures_util::future::BoxFuture;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use serde_json::json;
+use std::time::Duration;
+
+/// POSTs each alert as JSON to a configured URL.
+pub struct WebhookNotifier {
+    client: reqwest::Client,
+    name: String,
+    url: String,
+    retries: u32,
+    summaries: bool,
+    signals: bool,
+}
+
+impl WebhookNotifier {
+    pub fn new(config: &WebhookConfig) -> Result<Self> {
+        let mut headers = HeaderMap::new();
+        for (name, value) in &config.headers {
+            headers.insert(
+                HeaderName::try_from(name.as_str())?,
+                HeaderValue::try_from(value)?,
+            );
+        }
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(config.timeout_secs))
+            .default_headers(headers)
+            .build()?;
+        Ok(Self {
+            client,
+            name: config.name.clone(),
+            url: config.url.clone(),
+            retries: config.retries,
+            summaries: config.summaries,
+            signals: config.signals,
+        })
+    }
+}
+
+impl Notifier for WebhookNotifier {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn wants_summaries(&self) -> bool {
+        self.summaries
+    }
+
+    fn wants_signals(&self) -> bool {
+        self.signals
+    }
+
+    fn notify<'a>(&'a self, notification: &'a Notification) -> BoxFuture<'a, Result<()>> {
+        let payload = match notification {
+            Notification::Alert(alert) => alert.to_json(),
+            Notification::Summary { title, lines } => json!({
+                "summary": title,
+                "lines": lines,
+            }),
+            Notification::Signal(signal) => signal.to_json(),
+        };
+        Box::pin(async move {
+            let payload = &payload;
+            with_retries(self.retries, move || async move {
+                // Errors would otherwise quote the URL and any token in it.
+                self.client
+                    .post(&self.url)
+                    .json(payload)
+                    .send()
+                    .await
+                    .and_then(reqwest::Response::error_for_status)
+                    .map_err(reqwest::Error::without_url)?;
+                Ok(())
+            })
+            .await
+        })
+    }
+}
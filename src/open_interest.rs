@@ -0,0 +1,152 @@
+//! Open interest of perpetual futures, polled from Binance's futures REST
+//! API and kept per symbol beside the spot candles: open interest rising or
+//! falling against the price tells whether new positions or closing ones
+//! drive a move.
+
+use crate::config::OpenInterestConfig;
+use crate::state::SharedState;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use reqwest::StatusCode;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::time::Duration;
+use tracing::{debug, warn};
+
+const OPEN_INTEREST_PATH: &str = "/fapi/v1/openInterest";
+
+/// A symbol's latest open interest, in contracts of its base asset.
+#[derive(Debug, Clone, Copy)]
+pub struct OpenInterest {
+    pub value: f64,
+    /// As of the poll before, if any.
+    pub previous: Option<f64>,
+    /// As of the session's first poll.
+    pub first: f64,
+    /// The exchange's time of the latest value.
+    pub updated_at: DateTime<Utc>,
+}
+
+impl OpenInterest {
+    pub fn new(value: f64, updated_at: DateTime<Utc>) -> Self {
+        Self {
+            value,
+            previous: None,
+            first: value,
+            updated_at,
+        }
+    }
+
+    pub fn update(&mut self, value: f64, updated_at: DateTime<Utc>) {
+        self.previous = Some(self.value);
+        self.value = value;
+        self.updated_at = updated_at;
+    }
+
+    /// Percent change since the poll before.
+    pub fn change_percent(&self) -> Option<f64> {
+        let previous = self.previous.filter(|previous| *previous > 0.0)?;
+        Some((self.value - previous) / previous * 100.0)
+    }
+
+    /// Percent change since the session's first poll.
+    pub fn session_change_percent(&self) -> Option<f64> {
+        (self.first > 0.0).then(|| (self.value - self.first) / self.first * 100.0)
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "value": self.value,
+            "change_percent": self.change_percent(),
+            "session_change_percent": self.session_change_percent(),
+            "updated_at": self.updated_at.to_rfc3339(),
+        })
+    }
+}
+
+#[derive(Deserialize)]
+struct RawOpenInterest {
+    #[serde(rename = "openInterest")]
+    open_interest: String,
+    time: i64,
+}
+
+/// Polls each of `symbols` every `interval_secs`, for as long as the tracker
+/// runs, merging what it gets into `state`. A symbol with no perpetual
+/// future is dropped after its first poll.
+pub async fn run(state: SharedState, config: OpenInterestConfig, mut symbols: Vec<String>) {
+    let client = match reqwest::Client::builder()
+        .timeout(Duration::from_secs(10))
+        .build()
+    {
+        Ok(client) => client,
+        Err(e) => {
+            warn!("Open interest polling is disabled: {}", e);
+            return;
+        }
+    };
+    let url = format!("{}{}", config.url.trim_end_matches('/'), OPEN_INTEREST_PATH);
+    let mut timer = tokio::time::interval(Duration::from_secs(config.interval_secs));
+    while !symbols.is_empty() {
+        timer.tick().await;
+        let mut unlisted = Vec::new();
+        for symbol in &symbols {
+            match fetch(&client, &url, symbol).await {
+                Ok((value, updated_at)) => {
+                    debug!("Open interest of {}: {}", symbol, value);
+                    state
+                        .open_interest
+                        .entry(symbol.as_str().into())
+                        .and_modify(|open_interest| open_interest.update(value, updated_at))
+                        .or_insert_with(|| OpenInterest::new(value, updated_at));
+                }
+                Err(Poll::Unlisted) => {
+                    warn!(
+                        "{} has no perpetual future; not polling its open interest",
+                        symbol
+                    );
+                    unlisted.push(symbol.clone());
+                }
+                Err(Poll::Failed(e)) => {
+                    warn!("Failed to poll the open interest of {}: {}", symbol, e)
+                }
+            }
+        }
+        symbols.retain(|symbol| !unlisted.contains(symbol));
+    }
+}
+
+enum Poll {
+    /// Binance rejected the symbol.
+    Unlisted,
+    Failed(anyhow::Error),
+}
+
+async fn fetch(
+    client: &reqwest::Client,
+    url: &str,
+    symbol: &str,
+) -> Result<(f64, DateTime<Utc>), Poll> {
+    let response = client
+        .get(url)
+        .query(&[("symbol", symbol.to_uppercase())])
+        .send()
+        .await
+        .map_err(|e| Poll::Failed(e.into()))?;
+    if response.status() == StatusCode::BAD_REQUEST {
+        return Err(Poll::Unlisted);
+    }
+    let raw: RawOpenInterest = response
+        .error_for_status()
+        .map_err(|e| Poll::Failed(e.into()))?
+        .json()
+        .await
+        .map_err(|e| Poll::Failed(e.into()))?;
+    let parse = || -> Result<(f64, DateTime<Utc>)> {
+        let value: f64 = raw.open_interest.parse()?;
+        let updated_at = DateTime::from_timestamp_millis(raw.time)
+            .ok_or_else(|| anyhow!("Invalid time {}", raw.time))?;
+        Ok((value, updated_at))
+    };
+    parse().map_err(Poll::Failed)
+}
@@ -0,0 +1,110 @@
+//! Operational incidents about the tracker itself (streams going quiet,
+//! notifiers failing, gaps in the data), kept separate from market alerts
+//! and routed to incident tools such as PagerDuty or Opsgenie.
+
+mod opsgenie;
+mod pagerduty;
+
+use crate::actor::{InFlight, Mailbox, PanicReporter};
+use crate::config::PagerConfig;
+use anyhow::Result;
+use futures_util::future::{join_all, BoxFuture};
+use futures_util::FutureExt;
+use std::sync::Arc;
+use tracing::{error, info, warn};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Critical,
+    Error,
+    Warning,
+}
+
+impl Severity {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Severity::Critical => "critical",
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        }
+    }
+}
+
+/// Opens or resolves an incident identified by `key`.
+#[derive(Debug, Clone)]
+pub enum OpsEvent {
+    Trigger {
+        key: String,
+        summary: String,
+        severity: Severity,
+    },
+    Resolve {
+        key: String,
+    },
+}
+
+impl OpsEvent {
+    pub fn trigger(key: impl Into<String>, summary: impl Into<String>, severity: Severity) -> Self {
+        OpsEvent::Trigger {
+            key: key.into(),
+            summary: summary.into(),
+            severity,
+        }
+    }
+
+    pub fn resolve(key: impl Into<String>) -> Self {
+        OpsEvent::Resolve { key: key.into() }
+    }
+}
+
+/// Sends operational incidents to an on-call system.
+pub trait Pager: Send + Sync {
+    fn name(&self) -> &str;
+    fn send<'a>(&'a self, event: &'a OpsEvent) -> BoxFuture<'a, Result<()>>;
+}
+
+pub fn build_pagers(configs: &[PagerConfig]) -> Result<Vec<Arc<dyn Pager>>> {
+    configs
+        .iter()
+        .map(|config| -> Result<Arc<dyn Pager>> {
+            Ok(match config {
+                PagerConfig::Pagerduty(pagerduty) => {
+                    Arc::new(pagerduty::PagerDutyPager::new(pagerduty)?)
+                }
+                PagerConfig::Opsgenie(opsgenie) => {
+                    Arc::new(opsgenie::OpsgeniePager::new(opsgenie)?)
+                }
+            })
+        })
+        .collect()
+}
+
+/// Logs every operational event and forwards it to all pagers, counting
+/// each event in `in_flight` until every pager has had it. A delivery that
+/// panics is reported to `panics`.
+pub async fn run_pagers(
+    rx: Mailbox<OpsEvent>,
+    pagers: Vec<Arc<dyn Pager>>,
+    in_flight: InFlight,
+    panics: PanicReporter,
+) {
+    while let Some(event) = rx.recv().await {
+        let paging = in_flight.start();
+        match &event {
+            OpsEvent::Trigger { key, summary, .. } => {
+                warn!("Operational incident [{}]: {}", key, summary)
+            }
+            OpsEvent::Resolve { key } => info!("Operational incident resolved [{}]", key),
+        }
+        let (pagers, panics) = (pagers.clone(), panics.clone());
+        tokio::spawn(async move {
+            let _paging = paging;
+            let delivery = join_all(pagers.iter().map(|pager| async {
+                if let Err(e) = pager.send(&event).await {
+                    error!("Failed to send incident via {}: {}", pager.name(), e);
+                }
+            }));
+            panics.catch("pagers", delivery.map(drop)).await;
+        });
+    }
+}
@@ -0,0 +1,68 @@
+use super::{OpsEvent, Pager, Severity};
+use crate::config::OpsgenieConfig;
+use anyhow::Result;
+use futures_util::future::BoxFuture;
+use serde_json::json;
+use std::time::Duration;
+
+/// Opens and closes Opsgenie alerts, using the incident key as the alias.
+pub struct OpsgeniePager {
+    client: reqwest::Client,
+    base_url: String,
+    api_key: String,
+}
+
+impl OpsgeniePager {
+    pub fn new(config: &OpsgenieConfig) -> Result<Self> {
+        let base_url = if config.eu {
+            "https://api.eu.opsgenie.com/v2/alerts"
+        } else {
+            "https://api.opsgenie.com/v2/alerts"
+        };
+        Ok(Self {
+            client: reqwest::Client::builder()
+                .timeout(Duration::from_secs(10))
+                .build()?,
+            base_url: base_url.to_string(),
+            api_key: config.api_key.clone(),
+        })
+    }
+}
+
+impl Pager for OpsgeniePager {
+    fn name(&self) -> &str {
+        "opsgenie"
+    }
+
+    fn send<'a>(&'a self, event: &'a OpsEvent) -> BoxFuture<'a, Result<()>> {
+        let request = match event {
+            OpsEvent::Trigger {
+                key,
+                summary,
+                severity,
+            } => {
+                let priority = match severity {
+                    Severity::Critical => "P1",
+                    Severity::Error => "P2",
+                    Severity::Warning => "P3",
+                };
+                self.client.post(&self.base_url).json(&json!({
+                    "message": summary,
+                    "alias": key,
+                    "priority": priority,
+                    "source": "crypto_kline_tracker",
+                }))
+            }
+            OpsEvent::Resolve { key } => self
+                .client
+                .post(format!("{}/{}/close", self.base_url, key))
+                .query(&[("identifierType", "alias")])
+                .json(&json!({ "source": "crypto_kline_tracker" })),
+        };
+        let request = request.header("Authorization", format!("GenieKey {}", self.api_key));
+        Box::pin(async move {
+            request.send().await?.error_for_status()?;
+            Ok(())
+        })
+    }
+}
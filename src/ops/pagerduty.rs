@@ -0,0 +1,67 @@
+use super::{OpsEvent, Pager};
+use crate::config::PagerDutyConfig;
+use anyhow::Result;
+use futures_util::future::BoxFuture;
+use serde_json::json;
+use std::time::Duration;
+
+const EVENTS_URL: &str = "https://events.pagerduty.com/v2/enqueue";
+
+/// Triggers and resolves PagerDuty incidents through the Events API v2.
+pub struct PagerDutyPager {
+    client: reqwest::Client,
+    routing_key: String,
+    source: String,
+}
+
+impl PagerDutyPager {
+    pub fn new(config: &PagerDutyConfig) -> Result<Self> {
+        Ok(Self {
+            client: reqwest::Client::builder()
+                .timeout(Duration::from_secs(10))
+                .build()?,
+            routing_key: config.routing_key.clone(),
+            source: config.source.clone(),
+        })
+    }
+}
+
+impl Pager for PagerDutyPager {
+    fn name(&self) -> &str {
+        "pagerduty"
+    }
+
+    fn send<'a>(&'a self, event: &'a OpsEvent) -> BoxFuture<'a, Result<()>> {
+        let body = match event {
+            OpsEvent::Trigger {
+                key,
+                summary,
+                severity,
+            } => json!({
+                "routing_key": self.routing_key,
+                "event_action": "trigger",
+                "dedup_key": key,
+                "payload": {
+                    "summary": summary,
+                    "source": self.source,
+                    "severity": severity.as_str(),
+                    "component": "crypto_kline_tracker",
+                },
+            }),
+            OpsEvent::Resolve { key } => json!({
+                "routing_key": self.routing_key,
+                "event_action": "resolve",
+                "dedup_key": key,
+            }),
+        };
+        Box::pin(async move {
+            self.client
+                .post(EVENTS_URL)
+                .json(&body)
+                .send()
+                .await?
+                .error_for_status()?;
+            Ok(())
+        })
+    }
+}
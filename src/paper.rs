@@ -0,0 +1,911 @@
+//! Paper trading: orders placed when alert rules fire or strategies signal
+//! are filled against live prices in a simulated account, which tracks
+//! positions, fees and PnL per symbol.
+
+use crate::alerts::Alert;
+use crate::config::{
+    ExitConfig, FillModelConfig, PaperConfig, PaperOrderConfig, Side, StrategyConfig,
+};
+use crate::dca::Dca;
+use crate::events::Event;
+use crate::grid::Grid;
+use crate::journal::Journal;
+use crate::precision;
+use crate::risk::RiskLimits;
+use crate::state::{MarketState, SharedState};
+use crate::strategy::TradeSignal;
+use crate::KlineData;
+use anyhow::{bail, Result};
+use chrono::{DateTime, Utc};
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::RecvError;
+use tracing::{info, warn};
+
+/// Fills kept for reporting; older ones are dropped.
+const RECENT_FILLS: usize = 100;
+
+/// Quantities below this are treated as zero, so rounding never leaves a
+/// dust position behind.
+const EPSILON: f64 = 1e-12;
+
+/// An order waiting for the price to reach its limit.
+#[derive(Debug, Clone)]
+pub struct Order {
+    pub id: u64,
+    pub symbol: String,
+    pub side: Side,
+    pub quantity: f64,
+    pub limit: f64,
+    pub reason: String,
+    pub placed_at: DateTime<Utc>,
+    /// Attached to what a buy fills.
+    pub exits: ExitConfig,
+}
+
+/// A market order waiting out the fill model's latency.
+#[derive(Debug, Clone)]
+pub struct Delayed {
+    pub id: u64,
+    pub symbol: String,
+    pub side: Side,
+    pub quantity: f64,
+    pub reason: String,
+    /// Filled by the first price of `symbol` at or after this time.
+    pub due: DateTime<Utc>,
+    /// Attached to what a buy fills.
+    pub exits: ExitConfig,
+}
+
+/// Exits protecting a bought quantity: whichever of its stop-loss,
+/// take-profit and trailing stop is reached first sells the quantity.
+#[derive(Debug, Clone)]
+pub struct Exit {
+    /// The buy order whose fill it protects.
+    pub order_id: u64,
+    pub symbol: String,
+    pub quantity: f64,
+    pub stop_loss: Option<f64>,
+    pub take_profit: Option<f64>,
+    /// Percent below `peak` the trailing stop follows.
+    pub trailing_pct: Option<f64>,
+    /// Highest price since the fill.
+    pub peak: f64,
+    pub placed_at: DateTime<Utc>,
+}
+
+impl Exit {
+    pub fn trailing_stop(&self) -> Option<f64> {
+        self.trailing_pct.map(|pct| self.peak * (1.0 - pct / 100.0))
+    }
+
+    /// The higher of the stop-loss and the trailing stop, and which it is.
+    fn stop(&self) -> Option<(f64, &'static str)> {
+        let stop_loss = self.stop_loss.map(|price| (price, "stop-loss"));
+        let trailing = self.trailing_stop().map(|price| (price, "trailing stop"));
+        match (stop_loss, trailing) {
+            (Some(stop_loss), Some(trailing)) if trailing.0 > stop_loss.0 => Some(trailing),
+            (stop_loss, trailing) => stop_loss.or(trailing),
+        }
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "order_id": self.order_id,
+            "symbol": self.symbol,
+            "quantity": self.quantity,
+            "stop_loss": self.stop_loss,
+            "take_profit": self.take_profit,
+            "trailing_stop": self.trailing_stop(),
+            "peak": self.peak,
+            "placed_at": self.placed_at.to_rfc3339(),
+        })
+    }
+}
+
+/// What placing an order came to.
+#[derive(Debug, Clone)]
+pub enum Placed {
+    /// A market order, filled at once.
+    Filled(Fill),
+    /// A limit order, waiting for its price.
+    Resting(Order),
+    /// A market order, filled once the fill model's latency has passed.
+    Delayed(Delayed),
+}
+
+/// An executed trade.
+#[derive(Debug, Clone)]
+pub struct Fill {
+    pub order_id: u64,
+    pub symbol: String,
+    pub side: Side,
+    pub quantity: f64,
+    pub price: f64,
+    /// In the quote currency.
+    pub fee: f64,
+    /// For a sell, the average price the quantity sold was bought at.
+    pub entry_price: Option<f64>,
+    pub reason: String,
+    pub filled_at: DateTime<Utc>,
+}
+
+/// A long holding of one symbol, marked to its latest price.
+#[derive(Debug, Clone, Default)]
+pub struct Position {
+    pub quantity: f64,
+    pub average_price: f64,
+    pub mark_price: f64,
+    /// From closed quantity, before fees.
+    pub realized_pnl: f64,
+    pub fees: f64,
+}
+
+impl Position {
+    pub fn unrealized_pnl(&self) -> f64 {
+        (self.mark_price - self.average_price) * self.quantity
+    }
+
+    pub fn value(&self) -> f64 {
+        self.mark_price * self.quantity
+    }
+}
+
+/// A simulated account: quote cash, positions and resting limit orders.
+pub struct PaperAccount {
+    starting_balance: f64,
+    taker_fee_pct: f64,
+    maker_fee_pct: f64,
+    fill_model: FillModelConfig,
+    cash: f64,
+    positions: BTreeMap<String, Position>,
+    open_orders: Vec<Order>,
+    delayed: Vec<Delayed>,
+    exits: Vec<Exit>,
+    grids: Vec<Grid>,
+    dca: Vec<Dca>,
+    fills: VecDeque<Fill>,
+    next_id: u64,
+    risk: RiskLimits,
+    /// Volume of each symbol's latest candle, for the fill model's impact.
+    volumes: HashMap<String, f64>,
+    /// Time of the latest price, which delayed orders and exits go by;
+    /// replayed prices set it, so it need not be the time of day.
+    now: DateTime<Utc>,
+}
+
+impl PaperAccount {
+    pub fn new(config: &PaperConfig) -> Self {
+        Self {
+            starting_balance: config.starting_balance,
+            taker_fee_pct: config.taker_fee_pct,
+            maker_fee_pct: config.maker_fee_pct,
+            fill_model: config.fills.clone(),
+            cash: config.starting_balance,
+            positions: BTreeMap::new(),
+            open_orders: Vec::new(),
+            delayed: Vec::new(),
+            exits: Vec::new(),
+            grids: config.grids.iter().map(Grid::new).collect(),
+            dca: config.dca.iter().map(Dca::new).collect(),
+            fills: VecDeque::new(),
+            next_id: 1,
+            risk: RiskLimits::unlimited(),
+            volumes: HashMap::new(),
+            now: DateTime::<Utc>::MIN_UTC,
+        }
+    }
+
+    /// Checks every order placed from now on against `risk`.
+    pub fn with_risk(mut self, risk: RiskLimits) -> Self {
+        self.risk = risk;
+        self
+    }
+
+    /// Checks an order against the risk limits. Resting and delayed buys
+    /// count as held, so that several cannot together pass the position
+    /// limit.
+    fn approve(&mut self, symbol: &str, side: Side, quantity: f64, price: f64) -> Result<()> {
+        let held = self.position(symbol).map_or(0.0, |p| p.quantity);
+        let resting: f64 = self
+            .open_orders
+            .iter()
+            .filter(|order| order.symbol == symbol && order.side == Side::Buy)
+            .map(|order| order.quantity * order.limit)
+            .sum();
+        let delayed: f64 = self
+            .delayed
+            .iter()
+            .filter(|order| order.symbol == symbol && order.side == Side::Buy)
+            .map(|order| order.quantity)
+            .sum();
+        let held_value = (held + delayed) * price + resting;
+        self.risk
+            .approve(symbol, side, quantity * price, held_value)
+    }
+
+    /// Fills a market order for `quantity` of `symbol` at once at `price`,
+    /// its latest price, less the fill model's costs.
+    pub fn market(
+        &mut self,
+        symbol: &str,
+        side: Side,
+        quantity: f64,
+        price: f64,
+        reason: String,
+        exits: ExitConfig,
+    ) -> Result<Fill> {
+        self.approve(symbol, side, quantity, price)?;
+        let id = self.next_id();
+        let price = self.execution_price(symbol, side, quantity, price);
+        let fill = self.fill(
+            id,
+            symbol,
+            side,
+            quantity,
+            price,
+            self.taker_fee_pct,
+            reason,
+        )?;
+        self.attach(&fill, exits);
+        Ok(fill)
+    }
+
+    /// Attaches `exits` to what `fill` bought.
+    fn attach(&mut self, fill: &Fill, exits: ExitConfig) {
+        if fill.side != Side::Buy || exits.is_empty() {
+            return;
+        }
+        self.exits.push(Exit {
+            order_id: fill.order_id,
+            symbol: fill.symbol.clone(),
+            quantity: fill.quantity,
+            stop_loss: exits
+                .stop_loss_pct
+                .map(|pct| fill.price * (1.0 - pct / 100.0)),
+            take_profit: exits
+                .take_profit_pct
+                .map(|pct| fill.price * (1.0 + pct / 100.0)),
+            trailing_pct: exits.trailing_stop_pct,
+            peak: fill.price,
+            placed_at: self.now,
+        });
+    }
+
+    /// Places a market order filled by the first price of `symbol` once
+    /// the fill model's latency has passed.
+    pub fn delayed(
+        &mut self,
+        symbol: &str,
+        side: Side,
+        quantity: f64,
+        price: f64,
+        reason: String,
+        exits: ExitConfig,
+    ) -> Result<Delayed> {
+        self.approve(symbol, side, quantity, price)?;
+        let order = Delayed {
+            id: self.next_id(),
+            symbol: symbol.to_string(),
+            side,
+            quantity,
+            reason,
+            due: self.now + chrono::Duration::milliseconds(self.fill_model.latency_ms as i64),
+            exits,
+        };
+        self.delayed.push(order.clone());
+        Ok(order)
+    }
+
+    /// What a market order for `quantity` of `symbol` fills at when its
+    /// latest price is `price`: worse by half the spread, the slippage and
+    /// the impact of the share of the latest candle's volume it takes.
+    fn execution_price(&self, symbol: &str, side: Side, quantity: f64, price: f64) -> f64 {
+        let model = &self.fill_model;
+        let volume = self.volumes.get(symbol).copied().unwrap_or_default();
+        let impact = if volume > 0.0 {
+            model.volume_impact_bps * quantity / volume * 100.0
+        } else {
+            0.0
+        };
+        let cost = (model.spread_bps / 2.0 + model.slippage_bps + impact) / 10_000.0;
+        match side {
+            Side::Buy => price * (1.0 + cost),
+            Side::Sell => price * (1.0 - cost),
+        }
+    }
+
+    /// Places a limit order, filled at `limit` by the first price at or
+    /// beyond it.
+    pub fn limit(
+        &mut self,
+        symbol: &str,
+        side: Side,
+        quantity: f64,
+        limit: f64,
+        reason: String,
+        exits: ExitConfig,
+    ) -> Result<Order> {
+        self.approve(symbol, side, quantity, limit)?;
+        let order = Order {
+            id: self.next_id(),
+            symbol: symbol.to_string(),
+            side,
+            quantity,
+            limit,
+            reason,
+            placed_at: self.now,
+            exits,
+        };
+        self.open_orders.push(order.clone());
+        Ok(order)
+    }
+
+    /// Marks `kline`'s symbol to its close, seen at `at`, triggers the
+    /// exits it reaches, fills the delayed orders now due and the limit
+    /// orders it reaches, lets the grids of its symbol answer their fills,
+    /// and makes the DCA buys due. Orders that can no longer be filled, for
+    /// lack of cash or holdings, are cancelled.
+    pub fn on_kline(&mut self, kline: &KlineData, at: DateTime<Utc>) -> Vec<Fill> {
+        let (symbol, price) = (&*kline.symbol, kline.close);
+        self.now = self.now.max(at);
+        self.volumes.insert(symbol.to_string(), kline.volume);
+        if let Some(position) = self.positions.get_mut(symbol) {
+            position.mark_price = price;
+        }
+        let mut fills = self.trigger_exits(kline);
+        let (due, waiting) = std::mem::take(&mut self.delayed)
+            .into_iter()
+            .partition(|order| order.symbol == symbol && order.due <= self.now);
+        self.delayed = waiting;
+        for order in due {
+            let Delayed {
+                id,
+                side,
+                quantity,
+                reason,
+                exits,
+                ..
+            } = order;
+            let fill_price = self.execution_price(symbol, side, quantity, price);
+            let filled = self.fill(
+                id,
+                symbol,
+                side,
+                quantity,
+                fill_price,
+                self.taker_fee_pct,
+                reason,
+            );
+            match filled {
+                Ok(fill) => {
+                    self.attach(&fill, exits);
+                    fills.push(fill);
+                }
+                Err(e) => warn!("Cancelled paper order {}: {}", id, e),
+            }
+        }
+        let (reached, waiting) =
+            std::mem::take(&mut self.open_orders)
+                .into_iter()
+                .partition(|order| {
+                    order.symbol == symbol
+                        && match order.side {
+                            Side::Buy => price <= order.limit,
+                            Side::Sell => price >= order.limit,
+                        }
+                });
+        self.open_orders = waiting;
+        for order in reached {
+            let filled = self.fill(
+                order.id,
+                &order.symbol,
+                order.side,
+                order.quantity,
+                order.limit,
+                self.maker_fee_pct,
+                order.reason,
+            );
+            match filled {
+                Ok(fill) => {
+                    self.attach(&fill, order.exits);
+                    fills.push(fill);
+                }
+                Err(e) => warn!("Cancelled paper order {}: {}", order.id, e),
+            }
+        }
+        let mut grids = std::mem::take(&mut self.grids);
+        for grid in &mut grids {
+            grid.on_kline(self, kline, &fills);
+        }
+        self.grids = grids;
+        let mut plans = std::mem::take(&mut self.dca);
+        for plan in &mut plans {
+            fills.extend(plan.on_kline(self, kline, self.now));
+        }
+        self.dca = plans;
+        fills
+    }
+
+    /// Sells the quantity of every exit of `kline`'s symbol that the candle
+    /// reaches. Within a candle the worst is assumed: its low came before
+    /// its high, so a stop is hit before a take-profit on the same candle,
+    /// and a trailing stop only rises with the high for later candles. A
+    /// stop fills at its price less the fill model's costs, or at the open
+    /// if the candle opened below it; a take-profit fills at its price.
+    /// Candles that began before an exit was placed only count their
+    /// close, since their high and low may predate it.
+    fn trigger_exits(&mut self, kline: &KlineData) -> Vec<Fill> {
+        let symbol = &*kline.symbol;
+        let mut fills = Vec::new();
+        for mut exit in std::mem::take(&mut self.exits) {
+            if exit.symbol != symbol {
+                self.exits.push(exit);
+                continue;
+            }
+            let (open, high, low) = if kline.interval_start >= exit.placed_at {
+                (kline.open, kline.high, kline.low)
+            } else {
+                (kline.close, kline.close, kline.close)
+            };
+            let (trigger, price, fee_pct, kind) = match (exit.stop(), exit.take_profit) {
+                (Some((stop, kind)), _) if low <= stop => {
+                    (stop, open.min(stop), self.taker_fee_pct, kind)
+                }
+                (_, Some(target)) if high >= target => {
+                    (target, target, self.maker_fee_pct, "take-profit")
+                }
+                _ => {
+                    exit.peak = exit.peak.max(high);
+                    self.exits.push(exit);
+                    continue;
+                }
+            };
+            // Whatever was sold since no longer needs protecting.
+            let held = self.position(symbol).map_or(0.0, |p| p.quantity);
+            let quantity = exit.quantity.min(held);
+            if quantity < EPSILON {
+                continue;
+            }
+            let price = if kind == "take-profit" {
+                price
+            } else {
+                self.execution_price(symbol, Side::Sell, quantity, price)
+            };
+            let id = self.next_id();
+            let reason = format!(
+                "{} of order {} at {:.*}",
+                kind,
+                exit.order_id,
+                precision::fallback_decimals(trigger),
+                trigger
+            );
+            match self.fill(id, symbol, Side::Sell, quantity, price, fee_pct, reason) {
+                Ok(fill) => fills.push(fill),
+                Err(e) => warn!("Cancelled {} of paper order {}: {}", kind, exit.order_id, e),
+            }
+        }
+        fills
+    }
+
+    /// Places every order in `orders` tied to `alert`'s rule, for the
+    /// alert's symbol at its price.
+    pub fn on_alert(&mut self, orders: &[PaperOrderConfig], alert: &Alert) -> Vec<Result<Placed>> {
+        orders
+            .iter()
+            .filter(|order| order.rule == alert.rule)
+            .map(|order| {
+                let reason = format!("{}: {}", alert.rule, alert.message);
+                self.place(order, &alert.symbol, alert.price, reason)
+            })
+            .collect()
+    }
+
+    /// Places the order `strategy` ties to its signal `signal`.
+    pub fn on_signal(&mut self, strategy: &StrategyConfig, signal: &TradeSignal) -> Result<Placed> {
+        let reason = format!("{}: {}", signal.strategy, signal.reason);
+        self.place(
+            &strategy.order(signal.side),
+            &signal.symbol,
+            signal.price,
+            reason,
+        )
+    }
+
+    /// Places the order `order` describes for `symbol`, whose latest price
+    /// is `price`.
+    pub fn place(
+        &mut self,
+        order: &PaperOrderConfig,
+        symbol: &str,
+        price: f64,
+        reason: String,
+    ) -> Result<Placed> {
+        let quantity = match (order.quantity, order.quote_amount) {
+            (Some(quantity), _) => quantity,
+            (None, Some(amount)) => amount / price,
+            (None, None) => self.position(symbol).map_or(0.0, |p| p.quantity),
+        };
+        if quantity <= 0.0 {
+            bail!("Cannot {} {}: nothing is held", order.side, symbol);
+        }
+        Ok(match order.limit_offset_pct {
+            Some(offset) => {
+                let limit = match order.side {
+                    Side::Buy => price * (1.0 - offset / 100.0),
+                    Side::Sell => price * (1.0 + offset / 100.0),
+                };
+                Placed::Resting(self.limit(
+                    symbol,
+                    order.side,
+                    quantity,
+                    limit,
+                    reason,
+                    order.exits,
+                )?)
+            }
+            None if self.fill_model.latency_ms > 0 => Placed::Delayed(self.delayed(
+                symbol,
+                order.side,
+                quantity,
+                price,
+                reason,
+                order.exits,
+            )?),
+            None => Placed::Filled(self.market(
+                symbol,
+                order.side,
+                quantity,
+                price,
+                reason,
+                order.exits,
+            )?),
+        })
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn fill(
+        &mut self,
+        order_id: u64,
+        symbol: &str,
+        side: Side,
+        quantity: f64,
+        price: f64,
+        fee_pct: f64,
+        reason: String,
+    ) -> Result<Fill> {
+        if quantity <= 0.0 || price <= 0.0 {
+            bail!("Cannot trade {} {} at {}", quantity, symbol, price);
+        }
+        let value = quantity * price;
+        let fee = value * fee_pct / 100.0;
+        let held = self.position(symbol).map_or(0.0, |p| p.quantity);
+        match side {
+            Side::Buy if value + fee > self.cash + EPSILON => bail!(
+                "Buying {} {} needs {:.2}, but only {:.2} is available",
+                quantity,
+                symbol,
+                value + fee,
+                self.cash
+            ),
+            Side::Sell if quantity > held + EPSILON => {
+                bail!("Cannot sell {} {}; only {} is held", quantity, symbol, held)
+            }
+            _ => {}
+        }
+
+        let position = self.positions.entry(symbol.to_string()).or_default();
+        let entry_price = (side == Side::Sell).then_some(position.average_price);
+        match side {
+            Side::Buy => {
+                position.average_price = (position.average_price * position.quantity + value)
+                    / (position.quantity + quantity);
+                position.quantity += quantity;
+                self.cash -= value + fee;
+            }
+            Side::Sell => {
+                let pnl = (price - position.average_price) * quantity;
+                self.risk.record_pnl(pnl);
+                position.realized_pnl += pnl;
+                position.quantity = (position.quantity - quantity).max(0.0);
+                if position.quantity < EPSILON {
+                    position.quantity = 0.0;
+                    // Nothing is left for exits to protect.
+                    self.exits.retain(|exit| exit.symbol != symbol);
+                }
+                self.cash += value - fee;
+            }
+        }
+        position.mark_price = price;
+        position.fees += fee;
+        self.risk.record_pnl(-fee);
+
+        let fill = Fill {
+            order_id,
+            symbol: symbol.to_string(),
+            side,
+            quantity,
+            price,
+            fee,
+            entry_price,
+            reason,
+            filled_at: self.now,
+        };
+        if self.fills.len() == RECENT_FILLS {
+            self.fills.pop_front();
+        }
+        self.fills.push_back(fill.clone());
+        Ok(fill)
+    }
+
+    fn next_id(&mut self) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+        id
+    }
+
+    pub fn open_orders(&self) -> &[Order] {
+        &self.open_orders
+    }
+
+    pub fn grids(&self) -> &[Grid] {
+        &self.grids
+    }
+
+    pub fn dca(&self) -> &[Dca] {
+        &self.dca
+    }
+
+    pub fn position(&self, symbol: &str) -> Option<&Position> {
+        self.positions.get(symbol)
+    }
+
+    pub fn cash(&self) -> f64 {
+        self.cash
+    }
+
+    /// Cash plus every position at its latest price.
+    pub fn equity(&self) -> f64 {
+        self.cash + self.positions.values().map(Position::value).sum::<f64>()
+    }
+
+    /// Equity gained or lost since the start, after fees.
+    pub fn pnl(&self) -> f64 {
+        self.equity() - self.starting_balance
+    }
+
+    pub fn to_json(&self) -> Value {
+        let positions: Vec<Value> = self
+            .positions
+            .iter()
+            .map(|(symbol, position)| {
+                json!({
+                    "symbol": symbol,
+                    "quantity": position.quantity,
+                    "average_price": position.average_price,
+                    "mark_price": position.mark_price,
+                    "value": position.value(),
+                    "unrealized_pnl": position.unrealized_pnl(),
+                    "realized_pnl": position.realized_pnl,
+                    "fees": position.fees,
+                })
+            })
+            .collect();
+        let open_orders: Vec<Value> = self
+            .open_orders
+            .iter()
+            .map(|order| {
+                json!({
+                    "id": order.id,
+                    "symbol": order.symbol,
+                    "side": order.side,
+                    "quantity": order.quantity,
+                    "limit": order.limit,
+                    "reason": order.reason,
+                    "placed_at": order.placed_at.to_rfc3339(),
+                })
+            })
+            .collect();
+        let delayed_orders: Vec<Value> = self
+            .delayed
+            .iter()
+            .map(|order| {
+                json!({
+                    "id": order.id,
+                    "symbol": order.symbol,
+                    "side": order.side,
+                    "quantity": order.quantity,
+                    "reason": order.reason,
+                    "due": order.due.to_rfc3339(),
+                })
+            })
+            .collect();
+        let exits: Vec<Value> = self.exits.iter().map(Exit::to_json).collect();
+        let grids: Vec<Value> = self.grids.iter().map(Grid::to_json).collect();
+        let dca: Vec<Value> = self.dca.iter().map(Dca::to_json).collect();
+        let fills: Vec<Value> = self.fills.iter().map(Fill::to_json).collect();
+        json!({
+            "starting_balance": self.starting_balance,
+            "cash": self.cash,
+            "equity": self.equity(),
+            "pnl": self.pnl(),
+            "positions": positions,
+            "open_orders": open_orders,
+            "delayed_orders": delayed_orders,
+            "exits": exits,
+            "grids": grids,
+            "dca": dca,
+            "fills": fills,
+            "risk": self.risk.to_json(),
+        })
+    }
+
+    /// One line for the account, then one per position held, grid and DCA
+    /// plan, prices shown with the precision `state` has for their symbol.
+    pub fn summary_lines(&self, state: &MarketState) -> Vec<String> {
+        let pnl_percent = if self.starting_balance > 0.0 {
+            self.pnl() / self.starting_balance * 100.0
+        } else {
+            0.0
+        };
+        let mut lines = vec![format!(
+            "Paper account: equity {:.2}, PnL {:+.2} ({:+.2}%), cash {:.2}, {} open order(s)",
+            self.equity(),
+            self.pnl(),
+            pnl_percent,
+            self.cash,
+            self.open_orders.len() + self.delayed.len()
+        )];
+        for (symbol, position) in &self.positions {
+            if position.quantity == 0.0 {
+                continue;
+            }
+            let decimals = state.decimals(symbol, position.mark_price);
+            lines.push(format!(
+                "  {}: {} @ {:.*}, marked {:.*}, unrealized {:+.2}, realized {:+.2}",
+                symbol,
+                position.quantity,
+                decimals,
+                position.average_price,
+                decimals,
+                position.mark_price,
+                position.unrealized_pnl(),
+                position.realized_pnl
+            ));
+        }
+        lines.extend(self.grids.iter().map(Grid::summary_line));
+        lines.extend(self.dca.iter().map(|dca| dca.summary_line(state)));
+        lines
+    }
+}
+
+impl Fill {
+    pub fn to_json(&self) -> Value {
+        json!({
+            "order_id": self.order_id,
+            "symbol": self.symbol,
+            "side": self.side,
+            "quantity": self.quantity,
+            "price": self.price,
+            "fee": self.fee,
+            "entry_price": self.entry_price,
+            "reason": self.reason,
+            "filled_at": self.filled_at.to_rfc3339(),
+        })
+    }
+}
+
+/// The paper account, shared between the trading actor and readers such as
+/// the control socket. It outlives the actor, so a restart keeps it.
+#[derive(Clone)]
+pub struct Paper(Arc<Mutex<PaperAccount>>);
+
+impl Paper {
+    pub fn new(config: &PaperConfig, risk: RiskLimits) -> Self {
+        Self(Arc::new(Mutex::new(
+            PaperAccount::new(config).with_risk(risk),
+        )))
+    }
+
+    pub fn lock(&self) -> MutexGuard<'_, PaperAccount> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Trades `paper` on the live event feed until it closes: places the orders
+/// `config` ties to each fired alert rule and the orders of `strategies` for
+/// each of their signals, and marks positions and fills limit orders on
+/// every kline update. Every fill goes into `journal`. Logs the account
+/// every `summary_every`, with the price precision in `state`.
+pub async fn run(
+    paper: Paper,
+    config: PaperConfig,
+    strategies: Vec<StrategyConfig>,
+    journal: Journal,
+    state: SharedState,
+    mut events: broadcast::Receiver<Event>,
+    summary_every: Duration,
+) {
+    let mut timer = tokio::time::interval(summary_every);
+    timer.tick().await;
+    loop {
+        tokio::select! {
+            event = events.recv() => match event {
+                Ok(Event::Kline(kline)) => {
+                    // Replayed captures keep the exchange's time, so delayed
+                    // orders wait as long as they would have live.
+                    let at = kline.timing.and_then(|timing| timing.event_time);
+                    let fills = paper.lock().on_kline(&kline, at.unwrap_or_else(Utc::now));
+                    for fill in &fills {
+                        filled(fill, &journal, &state);
+                    }
+                }
+                Ok(Event::Signal(signal)) => {
+                    let strategy = strategies.iter().find(|s| s.name() == signal.strategy);
+                    if let Some(strategy) = strategy {
+                        log_placed(paper.lock().on_signal(strategy, &signal), &journal, &state);
+                    }
+                }
+                Ok(Event::Alert(alert)) => {
+                    let placed = paper.lock().on_alert(&config.orders, &alert);
+                    for placed in placed {
+                        log_placed(placed, &journal, &state);
+                    }
+                }
+                Err(RecvError::Lagged(skipped)) => {
+                    warn!("Paper trading fell behind; skipped {} events", skipped);
+                }
+                Err(RecvError::Closed) => break,
+            },
+            _ = timer.tick() => {
+                for line in paper.lock().summary_lines(&state) {
+                    info!("{}", line);
+                }
+            }
+        }
+    }
+}
+
+/// Logs what an order came to, journaling it if it filled.
+fn log_placed(placed: Result<Placed>, journal: &Journal, state: &MarketState) {
+    match placed {
+        Ok(Placed::Filled(fill)) => filled(&fill, journal, state),
+        Ok(Placed::Resting(order)) => info!(
+            "PAPER order {}: {} {} {} at {:.*} or better",
+            order.id,
+            order.side,
+            order.quantity,
+            order.symbol,
+            state.decimals(&order.symbol, order.limit),
+            order.limit
+        ),
+        Ok(Placed::Delayed(order)) => info!(
+            "PAPER order {}: {} {} {} at market, due {}",
+            order.id,
+            order.side,
+            order.quantity,
+            order.symbol,
+            order.due.format("%H:%M:%S%.3f")
+        ),
+        Err(e) => warn!("Rejected paper order: {}", e),
+    }
+}
+
+/// Logs and journals `fill`.
+fn filled(fill: &Fill, journal: &Journal, state: &MarketState) {
+    info!(
+        "PAPER fill {}: {} {} {} at {:.*} (fee {:.4}); {}",
+        fill.order_id,
+        fill.side,
+        fill.quantity,
+        fill.symbol,
+        state.decimals(&fill.symbol, fill.price),
+        fill.price,
+        fill.fee,
+        fill.reason
+    );
+    journal.paper(fill);
+}
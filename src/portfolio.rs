@@ -0,0 +1,317 @@
+//! Portfolio-level analytics of the declared holdings: exposure by asset,
+//! how concentrated it is, and its risk with the holdings' correlations
+//! taken into account.
+
+use crate::config::{Config, HoldingConfig};
+use crate::holdings::{self, Mark};
+use crate::state::MarketState;
+use chrono::{DateTime, Utc};
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
+
+/// Quote currencies stripped from a symbol to find the asset it holds,
+/// longer ones first so `fdusd` is not mistaken for `usd`.
+const QUOTE_ASSETS: &[&str] = &[
+    "fdusd", "usdt", "usdc", "tusd", "busd", "dai", "try", "eur", "btc", "eth", "bnb",
+];
+
+/// One-sided 95% quantile of the normal distribution.
+const Z_95: f64 = 1.645;
+
+/// The asset a symbol's holding is exposed to, e.g. `btc` for `btcusdt`.
+pub fn base_asset(symbol: &str) -> &str {
+    QUOTE_ASSETS
+        .iter()
+        .find_map(|quote| symbol.strip_suffix(quote).filter(|base| !base.is_empty()))
+        .unwrap_or(symbol)
+}
+
+/// The declared holdings and how their risk is measured.
+#[derive(Debug, Clone)]
+pub struct Portfolio {
+    holdings: Vec<HoldingConfig>,
+    /// `None` when no interval is streamed, so risk cannot be measured.
+    interval: Option<String>,
+    lookback: usize,
+}
+
+impl Portfolio {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            holdings: config.holdings.clone(),
+            interval: config
+                .portfolio
+                .interval
+                .clone()
+                .or_else(|| config.intervals.first().cloned()),
+            lookback: config.portfolio.lookback,
+        }
+    }
+
+    /// Marks the holdings to market and measures the portfolio they make up.
+    pub fn analyze(&self, state: &MarketState) -> Analysis {
+        let marks = holdings::mark(&self.holdings, state);
+        let (value, _) = holdings::totals(&marks);
+        let mut by_asset: BTreeMap<String, f64> = BTreeMap::new();
+        for mark in &marks {
+            if let Some(held) = mark.value() {
+                *by_asset
+                    .entry(base_asset(&mark.symbol).to_string())
+                    .or_default() += held;
+            }
+        }
+        let weight = |held: f64| if value > 0.0 { held / value } else { 0.0 };
+        let mut exposures: Vec<Exposure> = by_asset
+            .into_iter()
+            .map(|(asset, held)| Exposure {
+                asset,
+                value: held,
+                weight: weight(held),
+            })
+            .collect();
+        exposures.sort_by(|a, b| b.value.total_cmp(&a.value));
+        let risk = self
+            .interval
+            .as_deref()
+            .and_then(|interval| self.risk(&marks, interval, state));
+        Analysis {
+            value,
+            largest_weight: exposures.first().map_or(0.0, |exposure| exposure.weight),
+            herfindahl: exposures
+                .iter()
+                .map(|exposure| exposure.weight.powi(2))
+                .sum(),
+            exposures,
+            risk,
+            marks,
+        }
+    }
+
+    /// Volatility of the priced holdings with a return history, from their
+    /// returns over the same candles; `None` until there are two of them.
+    fn risk(&self, marks: &[Mark], interval: &str, state: &MarketState) -> Option<Risk> {
+        let mut series = Vec::new();
+        {
+            let history = state.history();
+            for mark in marks {
+                let Some(value) = mark.value() else {
+                    continue;
+                };
+                let key = (Arc::from(mark.symbol.as_str()), Arc::from(interval));
+                let Some(history) = history.get(&key) else {
+                    continue;
+                };
+                let closes: Vec<_> = history
+                    .iter()
+                    .filter(|kline| kline.closed && kline.close > 0.0)
+                    .map(|kline| (kline.interval_start, kline.close))
+                    .collect();
+                let returns: BTreeMap<DateTime<Utc>, f64> = closes
+                    .windows(2)
+                    .map(|pair| (pair[1].0, pair[1].1 / pair[0].1 - 1.0))
+                    .collect();
+                if !returns.is_empty() {
+                    series.push((mark.symbol.clone(), value, returns));
+                }
+            }
+        }
+        // Only candles every holding has a return for are compared.
+        let mut common: BTreeSet<DateTime<Utc>> = series.first()?.2.keys().copied().collect();
+        for (_, _, returns) in &series[1..] {
+            common.retain(|start| returns.contains_key(start));
+        }
+        let starts: Vec<_> = common.into_iter().rev().take(self.lookback).collect();
+        if starts.len() < 2 {
+            return None;
+        }
+        let samples: Vec<Vec<f64>> = series
+            .iter()
+            .map(|(_, _, returns)| starts.iter().map(|start| returns[start]).collect())
+            .collect();
+        let covered: f64 = series.iter().map(|(_, value, _)| value).sum();
+        let weights: Vec<f64> = series.iter().map(|(_, value, _)| value / covered).collect();
+        let volatilities: Vec<f64> = samples.iter().map(|a| covariance(a, a).sqrt()).collect();
+        let mut variance = 0.0;
+        let mut correlations = Vec::new();
+        for i in 0..series.len() {
+            for j in 0..series.len() {
+                variance += weights[i] * weights[j] * covariance(&samples[i], &samples[j]);
+                if j > i {
+                    let scale = volatilities[i] * volatilities[j];
+                    correlations.push(Correlation {
+                        symbols: (series[i].0.clone(), series[j].0.clone()),
+                        value: (scale > 0.0).then(|| covariance(&samples[i], &samples[j]) / scale),
+                    });
+                }
+            }
+        }
+        let volatility = variance.max(0.0).sqrt();
+        Some(Risk {
+            interval: interval.to_string(),
+            samples: starts.len(),
+            symbols: series.iter().map(|(symbol, _, _)| symbol.clone()).collect(),
+            volatility,
+            undiversified_volatility: weights.iter().zip(&volatilities).map(|(w, v)| w * v).sum(),
+            value_at_risk: Z_95 * volatility * covered,
+            correlations,
+        })
+    }
+}
+
+/// Sample covariance of two equally long series.
+fn covariance(a: &[f64], b: &[f64]) -> f64 {
+    let n = a.len() as f64;
+    let (mean_a, mean_b) = (a.iter().sum::<f64>() / n, b.iter().sum::<f64>() / n);
+    a.iter()
+        .zip(b)
+        .map(|(x, y)| (x - mean_a) * (y - mean_b))
+        .sum::<f64>()
+        / (n - 1.0)
+}
+
+/// How much of the portfolio is in one asset.
+#[derive(Debug, Clone)]
+pub struct Exposure {
+    pub asset: String,
+    pub value: f64,
+    /// Fraction of the portfolio's value, from 0 to 1.
+    pub weight: f64,
+}
+
+/// How correlated two holdings' returns are, from -1 to 1; `None` if either
+/// never moved.
+#[derive(Debug, Clone)]
+pub struct Correlation {
+    pub symbols: (String, String),
+    pub value: Option<f64>,
+}
+
+/// The portfolio's risk over one candle of `interval`, measured on the
+/// holdings in `symbols`.
+#[derive(Debug, Clone)]
+pub struct Risk {
+    pub interval: String,
+    /// How many candles' returns it was measured over.
+    pub samples: usize,
+    pub symbols: Vec<String>,
+    /// Standard deviation of the portfolio's return, as a fraction.
+    pub volatility: f64,
+    /// What the volatility would be if the holdings moved in lockstep.
+    pub undiversified_volatility: f64,
+    /// Loss in quote currency that one candle should exceed only one time in
+    /// twenty, assuming normally distributed returns.
+    pub value_at_risk: f64,
+    pub correlations: Vec<Correlation>,
+}
+
+impl Risk {
+    /// How much the holdings' imperfect correlation lowers the volatility;
+    /// 1 means not at all.
+    pub fn diversification_ratio(&self) -> Option<f64> {
+        (self.volatility > 0.0).then(|| self.undiversified_volatility / self.volatility)
+    }
+}
+
+/// The holdings marked to market and measured as one portfolio.
+#[derive(Debug, Clone)]
+pub struct Analysis {
+    pub marks: Vec<Mark>,
+    /// Value of every priced holding.
+    pub value: f64,
+    /// Largest first.
+    pub exposures: Vec<Exposure>,
+    pub largest_weight: f64,
+    /// Sum of the squared weights: 1 when everything is in one asset, and
+    /// `1 / n` when spread evenly over `n`.
+    pub herfindahl: f64,
+    pub risk: Option<Risk>,
+}
+
+impl Analysis {
+    /// How many equally weighted assets would be as concentrated.
+    pub fn effective_assets(&self) -> Option<f64> {
+        (self.herfindahl > 0.0).then(|| 1.0 / self.herfindahl)
+    }
+
+    /// Exposure, concentration and risk lines for the summaries; none until
+    /// a holding is priced.
+    pub fn summary_lines(&self) -> Vec<String> {
+        if self.exposures.is_empty() {
+            return Vec::new();
+        }
+        let exposure: Vec<String> = self
+            .exposures
+            .iter()
+            .map(|exposure| {
+                format!(
+                    "{} {:.1}%",
+                    exposure.asset.to_uppercase(),
+                    exposure.weight * 100.0
+                )
+            })
+            .collect();
+        let mut lines = vec![
+            format!("Exposure: {}", exposure.join(" | ")),
+            format!(
+                "Concentration: largest {:.1}% | HHI {:.3} ({:.1} effective assets)",
+                self.largest_weight * 100.0,
+                self.herfindahl,
+                self.effective_assets().unwrap_or_default()
+            ),
+        ];
+        if let Some(risk) = &self.risk {
+            let mut line = format!(
+                "Risk per {} candle (last {}): volatility {:.2}% ({:.2}% undiversified) | 95% VaR {:.2}",
+                risk.interval,
+                risk.samples,
+                risk.volatility * 100.0,
+                risk.undiversified_volatility * 100.0,
+                risk.value_at_risk
+            );
+            let correlations: Vec<String> = risk
+                .correlations
+                .iter()
+                .filter_map(|correlation| {
+                    let (a, b) = &correlation.symbols;
+                    Some(format!("{}/{} {:+.2}", a, b, correlation.value?))
+                })
+                .collect();
+            if !correlations.is_empty() {
+                line.push_str(&format!(" | Correlations: {}", correlations.join(", ")));
+            }
+            lines.push(line);
+        }
+        lines
+    }
+
+    pub fn to_json(&self) -> Value {
+        let (_, unrealized_pnl) = holdings::totals(&self.marks);
+        json!({
+            "holdings": self.marks.iter().map(Mark::to_json).collect::<Vec<_>>(),
+            "value": self.value,
+            "unrealized_pnl": unrealized_pnl,
+            "exposures": self.exposures.iter().map(|exposure| json!({
+                "asset": exposure.asset,
+                "value": exposure.value,
+                "weight": exposure.weight,
+            })).collect::<Vec<_>>(),
+            "largest_weight": self.largest_weight,
+            "herfindahl": self.herfindahl,
+            "effective_assets": self.effective_assets(),
+            "risk": self.risk.as_ref().map(|risk| json!({
+                "interval": risk.interval,
+                "samples": risk.samples,
+                "symbols": risk.symbols,
+                "volatility": risk.volatility,
+                "undiversified_volatility": risk.undiversified_volatility,
+                "diversification_ratio": risk.diversification_ratio(),
+                "value_at_risk": risk.value_at_risk,
+                "correlations": risk.correlations.iter().map(|correlation| json!({
+                    "symbols": [correlation.symbols.0, correlation.symbols.1],
+                    "value": correlation.value,
+                })).collect::<Vec<_>>(),
+            })),
+        })
+    }
+}
@@ -0,0 +1,74 @@
+//! How many decimals each symbol's prices are shown with in logs, the
+//! dashboard and charts. Binance's exchange info gives every pair's tick
+//! size, fetched once at startup; until then, and for series without one
+//! such as ratios and spreads, a price gets enough decimals to show three
+//! significant digits, and never fewer than two. The same fetch records
+//! each pair's quote asset, which the digest totals volume by.
+
+use crate::config::PrecisionConfig;
+use crate::listings;
+use crate::state::SharedState;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, warn};
+
+/// The most decimals a price is shown with, Binance's own precision.
+pub const MAX_DECIMALS: usize = 8;
+
+/// The decimals of a tick size as given in the exchange info, e.g. 5 for
+/// `0.00001000` and 0 for `1.00000000`. `None` if it is not a positive
+/// number.
+pub fn tick_decimals(tick_size: &str) -> Option<usize> {
+    if !tick_size.parse::<f64>().is_ok_and(|tick| tick > 0.0) {
+        return None;
+    }
+    let decimals = tick_size
+        .split_once('.')
+        .map_or(0, |(_, fraction)| fraction.trim_end_matches('0').len());
+    Some(decimals.min(MAX_DECIMALS))
+}
+
+/// The decimals `price` is shown with when its symbol's tick size is
+/// unknown: two from 1 up, else enough for three significant digits, e.g.
+/// `0.0812` or `0.00001234`.
+pub fn fallback_decimals(price: f64) -> usize {
+    let price = price.abs();
+    if price >= 1.0 || price == 0.0 || !price.is_finite() {
+        return 2;
+    }
+    (-price.log10().floor() as usize + 2).min(MAX_DECIMALS)
+}
+
+/// Fetches the exchange info under `config.url` once and records every
+/// pair's tick size and quote asset in `state`. On failure, prices keep the
+/// fallback precision.
+pub async fn run(config: PrecisionConfig, state: SharedState) {
+    let client = match reqwest::Client::builder()
+        .timeout(Duration::from_secs(30))
+        .build()
+    {
+        Ok(client) => client,
+        Err(e) => {
+            warn!("Price precision is unavailable: {}", e);
+            return;
+        }
+    };
+    match listings::fetch(&client, &config.url).await {
+        Ok(pairs) => {
+            for info in &pairs {
+                let symbol: Arc<str> = info.symbol.to_lowercase().into();
+                if let Some(decimals) = info.price_decimals() {
+                    state.price_decimals.insert(symbol.clone(), decimals);
+                }
+                state
+                    .quote_assets
+                    .insert(symbol, info.quote_asset.as_str().into());
+            }
+            debug!("Loaded the price precision of {} pairs", pairs.len());
+        }
+        Err(e) => warn!(
+            "Failed to fetch the exchange info; prices are shown with a precision of their own: {}",
+            e
+        ),
+    }
+}
@@ -0,0 +1,792 @@
+//! Turns the raw kline streams into everything the tracker derives from
+//! them: history, indicators, alerts, sessions and live events.
+
+use crate::alerts::{Alert, AlertEngine, SeriesIndicators};
+use crate::clock::SharedClock;
+use crate::config::{ArbitrageConfig, Config, IndexConfig, RatioConfig, VenueConfig};
+use crate::digest::Digest;
+use crate::events::Event;
+use crate::expr::{Expr, KlineContext};
+use crate::health::Health;
+use crate::history::{CandleHistory, HistoryCache};
+use crate::indicators::IndicatorPipeline;
+use crate::metrics::Metrics;
+use crate::notify::Notification;
+use crate::ops::{OpsEvent, Severity};
+use crate::portfolio::Portfolio;
+use crate::queue::KlineReceiver;
+use crate::session::SessionStats;
+use crate::shard::Shards;
+use crate::state::{MarketState, SharedState};
+use crate::store::Store;
+use crate::strategy::{StrategySet, TradeSignal};
+use crate::{arbitrage, depeg, holdings, index, interval, ratio, stats, KlineData, SeriesKey};
+use chrono::{DateTime, Local, TimeZone, Utc};
+use dashmap::DashMap;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{broadcast, mpsc};
+use tokio::task::JoinSet;
+use tracing::{debug_span, error, info, info_span, warn, Instrument};
+
+/// Most updates a worker takes off its queue at once. Bursts, such as every
+/// stream closing a candle together, are processed as batches of up to this
+/// many.
+const BATCH_SIZE: usize = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum OutputFormat {
+    /// Nothing beyond the log.
+    Text,
+    /// One JSON object per line.
+    Json,
+}
+
+fn process_kline_data(kline_data: &KlineData, decimals: usize, now: DateTime<Utc>) {
+    info!(
+        "{}",
+        kline_line(kline_data, decimals, now.with_timezone(&Local))
+    );
+}
+
+/// The line logged for each candle update shown, its prices with
+/// `decimals`, `local_time` being when it was processed.
+pub fn kline_line<Tz: TimeZone>(
+    kline_data: &KlineData,
+    decimals: usize,
+    local_time: DateTime<Tz>,
+) -> String
+where
+    Tz::Offset: fmt::Display,
+{
+    format!(
+        "Symbol: {} | Interval: {} | Local time: {} | Interval start: {} | \
+         Open: {:.*} | High: {:.*} | Low: {:.*} | Close: {:.*} | \
+         Volume: {:.2} | Change: {:.*} ({:.2}%)",
+        kline_data.symbol,
+        kline_data.interval,
+        local_time.format("%Y-%m-%d %H:%M:%S"),
+        kline_data.interval_start.format("%Y-%m-%d %H:%M"),
+        decimals,
+        kline_data.open,
+        decimals,
+        kline_data.high,
+        decimals,
+        kline_data.low,
+        decimals,
+        kline_data.close,
+        kline_data.volume,
+        decimals,
+        kline_data.price_change(),
+        kline_data.price_change_percent(),
+    )
+}
+
+/// Recomputes every configured ratio that has `kline` as one of its legs.
+fn update_ratios(
+    ratios: &[RatioConfig],
+    kline: &KlineData,
+    kline_cache: &DashMap<SeriesKey, KlineData>,
+    history: &HistoryCache,
+) -> Vec<KlineData> {
+    ratios
+        .iter()
+        .filter(|ratio| *ratio.base == *kline.symbol || *ratio.quote == *kline.symbol)
+        .filter_map(|ratio| {
+            let leg = |symbol: &str| {
+                if symbol == &*kline.symbol {
+                    Some(kline.clone())
+                } else {
+                    kline_cache
+                        .get(&(symbol.into(), kline.interval.clone()))
+                        .map(|leg| leg.clone())
+                }
+            };
+            let previous = history
+                .get(&(ratio.name().into(), kline.interval.clone()))
+                .and_then(CandleHistory::latest);
+            ratio::compute_ratio(ratio, &leg(&ratio.base)?, &leg(&ratio.quote)?, previous)
+        })
+        .collect()
+}
+
+/// Recomputes the spreads between `kline`'s venue and every other venue
+/// quoting the same symbol.
+fn update_spreads(
+    config: &ArbitrageConfig,
+    venues: &[VenueConfig],
+    kline: &KlineData,
+    kline_cache: &DashMap<SeriesKey, KlineData>,
+    history: &HistoryCache,
+) -> Vec<KlineData> {
+    if !config.enabled {
+        return Vec::new();
+    }
+    let (venue, symbol) = arbitrage::split_venue(&kline.symbol);
+    let venue = venue.unwrap_or(arbitrage::PRIMARY_VENUE);
+    if !config.symbols.iter().any(|tracked| tracked == symbol) {
+        return Vec::new();
+    }
+    arbitrage::venue_pairs(config, venues)
+        .into_iter()
+        .filter(|(first, second, _)| *first == venue || *second == venue)
+        .filter_map(|(first, second, _)| {
+            let leg = |leg_venue: &str| {
+                if leg_venue == venue {
+                    Some(kline.clone())
+                } else {
+                    kline_cache
+                        .get(&(
+                            arbitrage::venue_symbol(leg_venue, symbol).into(),
+                            kline.interval.clone(),
+                        ))
+                        .map(|leg| leg.clone())
+                }
+            };
+            let name = arbitrage::spread_name(first, second, symbol);
+            let previous = history
+                .get(&(name.as_str().into(), kline.interval.clone()))
+                .and_then(CandleHistory::latest);
+            arbitrage::compute_spread(&name, &leg(first)?, &leg(second)?, previous)
+        })
+        .collect()
+}
+
+/// Recomputes the index of `kline`'s symbol, if it has one, from its
+/// latest candle on every venue.
+fn update_index(
+    config: &IndexConfig,
+    venues: &[VenueConfig],
+    kline: &KlineData,
+    kline_cache: &DashMap<SeriesKey, KlineData>,
+    history: &HistoryCache,
+) -> Option<KlineData> {
+    if !config.enabled {
+        return None;
+    }
+    let (venue, symbol) = arbitrage::split_venue(&kline.symbol);
+    let venue = venue.unwrap_or(arbitrage::PRIMARY_VENUE);
+    let mut names =
+        std::iter::once(arbitrage::PRIMARY_VENUE).chain(venues.iter().map(|v| v.name.as_str()));
+    // Indices and spreads are not venues.
+    if !names.any(|name| name == venue) || !config.symbols.iter().any(|s| s == symbol) {
+        return None;
+    }
+    let others = std::iter::once(arbitrage::PRIMARY_VENUE)
+        .chain(venues.iter().map(|venue| venue.name.as_str()))
+        .filter(|other| *other != venue)
+        .filter_map(|other| {
+            kline_cache
+                .get(&(
+                    arbitrage::venue_symbol(other, symbol).into(),
+                    kline.interval.clone(),
+                ))
+                .map(|leg| leg.clone())
+        });
+    let legs: Vec<KlineData> = std::iter::once(kline.clone()).chain(others).collect();
+    let name = index::index_symbol(symbol);
+    let previous = history
+        .get(&(name.as_str().into(), kline.interval.clone()))
+        .and_then(CandleHistory::latest);
+    index::compute_index(&name, &legs, config.method, config.min_venues, previous)
+}
+
+fn log_indicators(kline_data: &KlineData, values: &BTreeMap<String, Value>) {
+    if values.is_empty() {
+        return;
+    }
+    info!("{}", indicators_line(kline_data, values));
+}
+
+/// The line logged with a closed candle's indicator values.
+pub fn indicators_line(kline_data: &KlineData, values: &BTreeMap<String, Value>) -> String {
+    let formatted: Vec<String> = values
+        .iter()
+        .map(|(name, value)| format!("{}={}", name, format_indicator_value(value)))
+        .collect();
+    format!(
+        "Indicators for {} {} at {}: {}",
+        kline_data.symbol,
+        kline_data.interval,
+        kline_data.interval_start.format("%Y-%m-%d %H:%M"),
+        formatted.join(" | ")
+    )
+}
+
+fn format_indicator_value(value: &Value) -> String {
+    match value {
+        Value::Number(n) => format!("{:.4}", n.as_f64().unwrap_or_default()),
+        Value::Object(fields) => {
+            let parts: Vec<String> = fields
+                .iter()
+                .map(|(k, v)| format!("{}: {}", k, format_indicator_value(v)))
+                .collect();
+            format!("{{{}}}", parts.join(", "))
+        }
+        other => other.to_string(),
+    }
+}
+
+fn log_signal(signal: &TradeSignal) {
+    warn!("{}", signal_line(signal));
+}
+
+/// The line logged for a trade signal.
+pub fn signal_line(signal: &TradeSignal) -> String {
+    format!(
+        "SIGNAL [{}] {} {} {} at {} (strength {:.2}): {}",
+        signal.strategy,
+        signal.side,
+        signal.symbol,
+        signal.interval,
+        signal.price,
+        signal.strength,
+        signal.reason,
+    )
+}
+
+fn log_alert(alert: &Alert, decimals: usize) {
+    warn!("{}", alert_line(alert, decimals, &Local));
+}
+
+/// The line logged for an alert, its price with `decimals` and its time
+/// given in `timezone`.
+pub fn alert_line<Tz: TimeZone>(alert: &Alert, decimals: usize, timezone: &Tz) -> String
+where
+    Tz::Offset: fmt::Display,
+{
+    format!(
+        "ALERT [{}] {} {} at {}: {} (price {:.*})",
+        alert.rule,
+        alert.symbol,
+        alert.interval,
+        alert
+            .triggered_at
+            .with_timezone(timezone)
+            .format("%Y-%m-%d %H:%M:%S"),
+        alert.message,
+        decimals,
+        alert.price,
+    )
+}
+
+fn session_summary_lines(state: &MarketState) -> Vec<String> {
+    state
+        .sorted_sessions()
+        .into_iter()
+        .map(|(symbol, session)| {
+            let decimals = state.decimals(&symbol, session.last_price);
+            let mut line = format!(
+                "{} since {} | Last: {:.*} ({:+.2}%) | High: {:.*} | Low: {:.*} | \
+                 Max drawdown: {:.2}%",
+                symbol,
+                session
+                    .started
+                    .with_timezone(&Local)
+                    .format("%Y-%m-%d %H:%M:%S"),
+                decimals,
+                session.last_price,
+                session.change_percent(),
+                decimals,
+                session.high,
+                decimals,
+                session.low,
+                session.max_drawdown_percent,
+            );
+            if let Some(open_interest) = state.open_interest.get(&symbol) {
+                line.push_str(&format!(" | Open interest: {:.2}", open_interest.value));
+                if let Some(change) = open_interest.change_percent() {
+                    line.push_str(&format!(" ({:+.2}%)", change));
+                }
+            }
+            if let Some(status) = state.trading_status.get(&symbol) {
+                line.push_str(&format!(
+                    " | {} since {}",
+                    status.status,
+                    status
+                        .since
+                        .with_timezone(&Local)
+                        .format("%Y-%m-%d %H:%M:%S")
+                ));
+            }
+            line
+        })
+        .collect()
+}
+
+/// Logs the average price change per interval across every symbol's
+/// latest candle.
+fn log_market_stats(latest: &[KlineData]) {
+    for (interval, market) in stats::market_stats_by_interval(latest) {
+        match market.weighted_change {
+            Some(weighted) => info!(
+                "Average price change for {} across {} symbols: {:.2}% (volume-weighted: {:.2}%)",
+                interval, market.count, market.mean_change, weighted
+            ),
+            None => info!(
+                "Average price change for {} across {} symbols: {:.2}%",
+                interval, market.count, market.mean_change
+            ),
+        }
+    }
+}
+
+/// Number of candles skipped between the stream's previous candle and
+/// `kline`, if any.
+fn missing_candles(kline: &KlineData, history: &HistoryCache) -> Option<i64> {
+    let latest = history.get(&kline.key())?.latest()?;
+    let step = interval::interval_duration(&kline.interval)?;
+    let missing = (kline.interval_start - latest.interval_start).num_seconds()
+        / step.num_seconds().max(1)
+        - 1;
+    (missing > 0).then_some(missing)
+}
+
+/// Where the kline processor publishes what it derives from the streams.
+#[derive(Clone)]
+pub struct Outputs {
+    pub state: SharedState,
+    pub store: Arc<Store>,
+    pub notify_tx: mpsc::Sender<Notification>,
+    pub ops_tx: mpsc::Sender<OpsEvent>,
+    /// Every kline update, including synthetic ratios, every alert and
+    /// every strategy signal.
+    pub events_tx: broadcast::Sender<Event>,
+    pub format: OutputFormat,
+    /// Which updates to output; `None` outputs every one.
+    pub filter: Option<Expr>,
+    /// Which streams are subscribed, for the stream-down watchdog.
+    pub health: Health,
+    /// Where the latency of processing live updates is recorded.
+    pub metrics: Metrics,
+    /// What alerts and signals are stamped with.
+    pub clock: SharedClock,
+}
+
+/// Processes each of `receivers` on its own worker, the `shards` they were
+/// routed by, and logs periodic summaries until every worker has finished.
+pub async fn process_kline_stream(
+    receivers: Vec<KlineReceiver>,
+    shards: Shards,
+    config: Config,
+    pipeline: IndicatorPipeline,
+    alert_engine: AlertEngine,
+    strategies: StrategySet,
+    outputs: Outputs,
+) {
+    let config = Arc::new(config);
+    // Shared so that dedup keys and cooldowns hold across workers.
+    let alert_engine = Arc::new(Mutex::new(alert_engine));
+    let mut workers = JoinSet::new();
+    for (index, rx) in receivers.into_iter().enumerate() {
+        let worker = Worker {
+            index,
+            shards: shards.clone(),
+            config: config.clone(),
+            pipeline: pipeline.fork(),
+            alert_engine: alert_engine.clone(),
+            strategies: strategies.fork(),
+            outputs: outputs.clone(),
+        };
+        workers.spawn(worker.run(rx).instrument(info_span!("worker", index)));
+    }
+
+    let portfolio = Portfolio::new(&config);
+    let mut summary_timer =
+        tokio::time::interval(Duration::from_secs(config.summary_interval_secs.max(1)));
+    summary_timer.tick().await;
+    let mut digest = Digest::new(&config, outputs.clock.now());
+    let mut digest_timer =
+        tokio::time::interval(Duration::from_secs(config.digest.interval_secs.max(1)));
+    digest_timer.tick().await;
+    loop {
+        tokio::select! {
+            finished = workers.join_next() => match finished {
+                Some(Ok(())) => {}
+                Some(Err(e)) => error!("Kline worker failed: {}", e),
+                None => break,
+            },
+            _ = summary_timer.tick() => log_summaries(&outputs, &config, &portfolio),
+            _ = digest_timer.tick(), if config.digest.enabled => {
+                let lines =
+                    digest.summarize(&outputs.state, &outputs.health, outputs.clock.now());
+                for line in &lines {
+                    info!("Digest: {}", line);
+                }
+                send_summary(&outputs, "Digest", lines);
+            }
+        }
+    }
+}
+
+fn log_summaries(outputs: &Outputs, config: &Config, portfolio: &Portfolio) {
+    log_market_stats(&outputs.state.latest_klines());
+    let lines = session_summary_lines(&outputs.state);
+    for line in &lines {
+        info!("Session {}", line);
+    }
+    send_summary(outputs, "Session summary", lines);
+    if let Some(line) = depeg::summary_line(&config.depeg, &outputs.state) {
+        info!("Stablecoins: {}", line);
+        send_summary(outputs, "Stablecoins", vec![line]);
+    }
+    let analysis = portfolio.analyze(&outputs.state);
+    let lines = holdings::summary_lines(&analysis.marks, &outputs.state);
+    for line in &lines {
+        info!("Holdings: {}", line);
+    }
+    send_summary(outputs, "Holdings", lines);
+    let lines = analysis.summary_lines();
+    for line in &lines {
+        info!("Portfolio: {}", line);
+    }
+    send_summary(outputs, "Portfolio", lines);
+}
+
+fn send_summary(outputs: &Outputs, title: &str, lines: Vec<String>) {
+    if lines.is_empty() {
+        return;
+    }
+    let summary = Notification::Summary {
+        title: title.to_string(),
+        lines,
+    };
+    if outputs.notify_tx.try_send(summary).is_err() {
+        warn!(
+            "Notification queue is full; dropping {}",
+            title.to_lowercase()
+        );
+    }
+}
+
+/// Rebuilds the indicators and strategies of worker `index`'s series from
+/// the closed candles already held, such as those restored from a snapshot.
+/// Signals raised while catching up are not published.
+fn warm_up(
+    pipeline: &mut IndicatorPipeline,
+    strategies: &mut StrategySet,
+    state: &SharedState,
+    config: &Config,
+    shards: &Shards,
+    index: usize,
+    now: DateTime<Utc>,
+) {
+    let series: Vec<Vec<KlineData>> = {
+        let history = state.history();
+        history
+            .keys()
+            .filter(|(symbol, _)| {
+                // A ratio is computed by the worker of its legs.
+                let leg = config
+                    .ratios
+                    .iter()
+                    .find(|ratio| ratio.name() == **symbol)
+                    .map_or(&**symbol, |ratio| ratio.base.as_str());
+                shards.of(leg) == index
+            })
+            .filter_map(|key| history.get(key))
+            .map(|history| {
+                history
+                    .iter()
+                    .filter(|kline| kline.closed)
+                    .cloned()
+                    .collect()
+            })
+            .collect()
+    };
+    for candles in series {
+        let Some(last) = candles.last() else {
+            continue;
+        };
+        for kline in &candles[..candles.len() - 1] {
+            let (values, _) = pipeline.update(kline);
+            strategies.update(kline, Some(values), now);
+        }
+        let (values, _) = pipeline.update(last);
+        strategies.update(last, Some(values), now);
+        state.indicators.insert(last.key(), values.clone());
+    }
+}
+
+/// Processes the symbols of one shard.
+struct Worker {
+    index: usize,
+    shards: Shards,
+    config: Arc<Config>,
+    pipeline: IndicatorPipeline,
+    alert_engine: Arc<Mutex<AlertEngine>>,
+    strategies: StrategySet,
+    outputs: Outputs,
+}
+
+impl Worker {
+    async fn run(self, mut rx: KlineReceiver) {
+        let Worker {
+            index,
+            shards,
+            config,
+            mut pipeline,
+            alert_engine,
+            mut strategies,
+            outputs,
+        } = self;
+        let Outputs {
+            state,
+            store,
+            notify_tx,
+            ops_tx,
+            events_tx,
+            mut format,
+            filter,
+            health,
+            metrics,
+            clock,
+        } = outputs;
+        let shown = |kline: &KlineData, indicators: Option<&BTreeMap<String, Value>>| {
+            filter.as_ref().is_none_or(|filter| {
+                filter.matches(&KlineContext {
+                    kline,
+                    indicators,
+                    previous: None,
+                })
+            })
+        };
+
+        warm_up(
+            &mut pipeline,
+            &mut strategies,
+            &state,
+            &config,
+            &shards,
+            index,
+            clock.now(),
+        );
+
+        // Every subscribed stream starts out as "last seen" when the watchdog
+        // first notices it, so one that never delivers anything is reported
+        // as down too.
+        let stream_down_after = Duration::from_secs(config.ops.stream_down_secs.max(1));
+        let mut last_seen: HashMap<SeriesKey, Instant> = HashMap::new();
+        let mut streams_down: HashSet<SeriesKey> = HashSet::new();
+        let mut watchdog =
+            tokio::time::interval((stream_down_after / 4).max(Duration::from_secs(1)));
+
+        let mut batch = Vec::new();
+        loop {
+            tokio::select! {
+                received = rx.recv_many(&mut batch, BATCH_SIZE) => if received == 0 {
+                    break;
+                },
+                _ = watchdog.tick() => {
+                    // Follow streams subscribed or unsubscribed at runtime,
+                    // watching only the ones routed to this worker.
+                    let subscribed: HashSet<_> = health
+                        .stream_keys()
+                        .into_iter()
+                        .filter(|(symbol, _)| shards.of(symbol) == index)
+                        .collect();
+                    last_seen.retain(|stream, _| subscribed.contains(stream));
+                    streams_down.retain(|stream| {
+                        let kept = subscribed.contains(stream);
+                        if !kept {
+                            let key = format!("stream:{}:{}", stream.0, stream.1);
+                            let _ = ops_tx.try_send(OpsEvent::resolve(key));
+                        }
+                        kept
+                    });
+                    for stream in subscribed {
+                        last_seen.entry(stream).or_insert_with(Instant::now);
+                    }
+                    for (stream, seen) in &last_seen {
+                        if seen.elapsed() >= stream_down_after && streams_down.insert(stream.clone()) {
+                            let summary = format!(
+                                "Stream {} {} has sent no data for {}s",
+                                stream.0,
+                                stream.1,
+                                seen.elapsed().as_secs()
+                            );
+                            let key = format!("stream:{}:{}", stream.0, stream.1);
+                            let _ = ops_tx.try_send(OpsEvent::trigger(key, summary, Severity::Critical));
+                        }
+                    }
+                    continue;
+                }
+            };
+
+            // The whole batch is written out at once: closed candles in one
+            // store transaction and JSON output in one write.
+            let dequeued = Instant::now();
+            let mut to_store = Vec::new();
+            let mut json_lines = String::new();
+            for kline_data in batch.drain(..) {
+                let timing = kline_data.timing;
+                if let Some(event_time) = timing.and_then(|timing| timing.event_time) {
+                    clock.follow(event_time);
+                }
+                let now = clock.now();
+                // Nothing below awaits, so the span can be entered directly.
+                let _span = info_span!(
+                    "kline",
+                    symbol = %kline_data.symbol,
+                    interval = %kline_data.interval,
+                    closed = kline_data.closed,
+                )
+                .entered();
+                let stream = kline_data.key();
+                if streams_down.remove(&stream) {
+                    let key = format!("stream:{}:{}", stream.0, stream.1);
+                    let _ = ops_tx.try_send(OpsEvent::resolve(key));
+                }
+                last_seen.insert(stream, Instant::now());
+
+                let synthetic = {
+                    let _stage = debug_span!("ratios").entered();
+                    let history = state.history();
+                    if config.ops.detect_gaps {
+                        if let Some(gap) = missing_candles(&kline_data, &history) {
+                            let summary = format!(
+                                "Data gap in {} {}: {} candle(s) missing before {}",
+                                kline_data.symbol,
+                                kline_data.interval,
+                                gap,
+                                kline_data.interval_start
+                            );
+                            let key = format!(
+                                "gap:{}:{}:{}",
+                                kline_data.symbol,
+                                kline_data.interval,
+                                kline_data.interval_start.timestamp()
+                            );
+                            let _ =
+                                ops_tx.try_send(OpsEvent::trigger(key, summary, Severity::Warning));
+                        }
+                    }
+                    let mut synthetic =
+                        update_ratios(&config.ratios, &kline_data, &state.latest, &history);
+                    synthetic.extend(update_spreads(
+                        &config.arbitrage,
+                        &config.venues,
+                        &kline_data,
+                        &state.latest,
+                        &history,
+                    ));
+                    synthetic.extend(update_index(
+                        &config.index,
+                        &config.venues,
+                        &kline_data,
+                        &state.latest,
+                        &history,
+                    ));
+                    synthetic
+                };
+
+                for kline in std::iter::once(kline_data).chain(synthetic) {
+                    let key = kline.key();
+                    // Indicators are the expensive part, so they run before the
+                    // state lock is taken and other workers are not held up.
+                    let closed = kline.closed.then(|| {
+                        let _stage = debug_span!("indicators", symbol = %kline.symbol).entered();
+                        to_store.push(kline.clone());
+                        let (values, events) = pipeline.update(&kline);
+                        log_indicators(&kline, values);
+                        for event in &events {
+                            warn!(
+                                "Indicator event: {} {} {} {} ({})",
+                                event.symbol,
+                                event.interval,
+                                event.indicator,
+                                event.event,
+                                format_indicator_value(&event.value)
+                            );
+                        }
+                        let signals = strategies.update(&kline, Some(values), now);
+                        (values.clone(), events, signals)
+                    });
+
+                    let mut history = state.history_mut();
+                    let series_history = history.update(
+                        kline.clone(),
+                        config.history_size,
+                        config.cache.budget_bytes(),
+                    );
+                    let mut alerts = Vec::new();
+                    let mut signals = Vec::new();
+                    {
+                        let _stage = debug_span!("alerts", symbol = %kline.symbol).entered();
+                        let mut alert_engine =
+                            alert_engine.lock().unwrap_or_else(|e| e.into_inner());
+                        if let Some((values, events, raised)) = closed {
+                            state.indicators.insert(key.clone(), values);
+                            alerts.extend(alert_engine.evaluate_events(&kline, &events, now));
+                            signals = raised;
+                        }
+                        let indicator_values = SeriesIndicators {
+                            latest: pipeline.values(&kline.symbol, &kline.interval),
+                            previous: pipeline.previous_values(&kline.symbol, &kline.interval),
+                        };
+                        alerts.extend(alert_engine.evaluate(
+                            &kline,
+                            series_history,
+                            indicator_values,
+                            now,
+                        ));
+                    }
+                    drop(history);
+                    for alert in alerts {
+                        log_alert(&alert, state.decimals(&alert.symbol, alert.price));
+                        let _ = events_tx.send(Event::Alert(alert.clone()));
+                        if notify_tx.try_send(Notification::Alert(alert)).is_err() {
+                            warn!("Notification queue is full; dropping alert notification");
+                        }
+                    }
+                    for signal in signals {
+                        log_signal(&signal);
+                        let _ = events_tx.send(Event::Signal(signal.clone()));
+                        if notify_tx.try_send(Notification::Signal(signal)).is_err() {
+                            warn!("Notification queue is full; dropping signal notification");
+                        }
+                    }
+                    state
+                        .sessions
+                        .entry(kline.symbol.clone())
+                        .and_modify(|session| session.update(&kline))
+                        .or_insert_with(|| SessionStats::new(&kline, now));
+                    let _stage = debug_span!("outputs", symbol = %kline.symbol).entered();
+                    // Sending only fails when no client is listening.
+                    let _ = events_tx.send(Event::Kline(kline.clone()));
+                    let visible = shown(&kline, state.indicators.get(&key).as_deref());
+                    if visible && config.digest.log_updates {
+                        process_kline_data(&kline, state.decimals(&kline.symbol, kline.close), now);
+                    }
+                    if format == OutputFormat::Json && visible {
+                        json_lines += &kline.to_json().to_string();
+                        json_lines.push('\n');
+                    }
+                    state.latest.insert(key, kline);
+                }
+                if let Some(timing) = &timing {
+                    metrics.record_processed(timing, dequeued, now);
+                }
+            }
+            if !to_store.is_empty() {
+                let store = store.clone();
+                tokio::task::spawn_blocking(move || {
+                    if let Err(e) = store.record_candles(&to_store) {
+                        error!("Failed to store candles: {}", e);
+                    }
+                });
+            }
+            if !json_lines.is_empty() {
+                if let Err(e) = std::io::stdout().lock().write_all(json_lines.as_bytes()) {
+                    // Typically the reading end of a pipe has gone away.
+                    warn!("Stopping JSON output: {}", e);
+                    format = OutputFormat::Text;
+                }
+            }
+        }
+    }
+}
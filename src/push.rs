@@ -0,0 +1,185 @@
+//! Pushes candles to downstream HTTP endpoints, for consumers that cannot
+//! hold a socket open.
+
+use crate::actor::InFlight;
+use crate::config::PushConfig;
+use crate::events::Event;
+use crate::health::Health;
+use crate::metrics::Metrics;
+use crate::notify::{redact, with_retries};
+use crate::{KlineData, SeriesKey};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use futures_util::future::join_all;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::{broadcast, mpsc};
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info, warn};
+
+/// Batches waiting to be sent; past this, new batches are dropped rather
+/// than let a slow endpoint hold up the rest.
+const QUEUE_SIZE: usize = 64;
+
+/// Builds a client for every configured endpoint.
+pub fn build_pushers(configs: &[PushConfig]) -> Result<Vec<Pusher>> {
+    configs.iter().map(Pusher::new).collect()
+}
+
+/// Pushes to every endpoint until the event feed closes or `stopping` is
+/// cancelled, recording how long each push takes in `metrics`. On stopping, the
+/// batch being gathered is still pushed. Counts in `in_flight` until every
+/// queued batch has been pushed.
+pub async fn run_pushers(
+    pushers: Vec<Pusher>,
+    events: broadcast::Sender<Event>,
+    health: Health,
+    metrics: Metrics,
+    stopping: CancellationToken,
+    in_flight: InFlight,
+) {
+    let _pushing = in_flight.start();
+    join_all(pushers.into_iter().map(|pusher| {
+        let (tx, rx) = mpsc::channel(QUEUE_SIZE);
+        info!("Pushing candles to {}", pusher.config.url);
+        let collected = collect(
+            pusher.config.clone(),
+            events.subscribe(),
+            tx,
+            stopping.clone(),
+        );
+        let (health, metrics) = (health.clone(), metrics.clone());
+        async move {
+            tokio::join!(collected, pusher.run(rx, health, metrics));
+        }
+    }))
+    .await;
+}
+
+/// Gathers the candles an endpoint wants into batches, either one per
+/// candle or one per `batch_secs`.
+async fn collect(
+    config: PushConfig,
+    mut events: broadcast::Receiver<Event>,
+    tx: mpsc::Sender<Vec<KlineData>>,
+    stopping: CancellationToken,
+) {
+    let wanted = |kline: &KlineData| {
+        (config.include_open || kline.closed)
+            && (config.symbols.is_empty()
+                || config
+                    .symbols
+                    .iter()
+                    .any(|symbol| **symbol == *kline.symbol))
+            && (config.intervals.is_empty()
+                || config
+                    .intervals
+                    .iter()
+                    .any(|interval| **interval == *kline.interval))
+    };
+    let batch_every = (config.batch_secs > 0).then(|| Duration::from_secs(config.batch_secs));
+    let mut timer = tokio::time::interval(batch_every.unwrap_or(Duration::from_secs(1)));
+    // Later updates to a candle replace earlier ones within a batch.
+    let mut pending: BTreeMap<(DateTime<Utc>, SeriesKey), KlineData> = BTreeMap::new();
+    let mut stopped = false;
+
+    loop {
+        let batch = tokio::select! {
+            event = events.recv() => match event {
+                Ok(Event::Kline(kline)) if wanted(&kline) => {
+                    if batch_every.is_none() {
+                        vec![kline]
+                    } else {
+                        pending.insert((kline.interval_start, kline.key()), kline);
+                        continue;
+                    }
+                }
+                Ok(_) => continue,
+                Err(RecvError::Lagged(skipped)) => {
+                    warn!("Push to {} fell behind; skipped {} events", config.url, skipped);
+                    continue;
+                }
+                Err(RecvError::Closed) => break,
+            },
+            _ = timer.tick(), if batch_every.is_some() => {
+                if pending.is_empty() {
+                    continue;
+                }
+                std::mem::take(&mut pending).into_values().collect()
+            }
+            _ = stopping.cancelled() => {
+                stopped = true;
+                std::mem::take(&mut pending).into_values().collect()
+            }
+        };
+        if !batch.is_empty() && tx.try_send(batch).is_err() {
+            warn!("Push queue for {} is full; dropping a batch", config.url);
+        }
+        if stopped {
+            break;
+        }
+    }
+}
+
+/// Sends batches to one endpoint, retrying failures.
+#[derive(Clone)]
+pub struct Pusher {
+    client: reqwest::Client,
+    config: PushConfig,
+}
+
+impl Pusher {
+    fn new(config: &PushConfig) -> Result<Self> {
+        let mut headers = HeaderMap::new();
+        for (name, value) in &config.headers {
+            headers.insert(
+                HeaderName::try_from(name.as_str())?,
+                HeaderValue::try_from(value)?,
+            );
+        }
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(config.timeout_secs))
+            .default_headers(headers)
+            .build()?;
+        Ok(Self {
+            client,
+            config: config.clone(),
+        })
+    }
+
+    async fn run(self, mut rx: mpsc::Receiver<Vec<KlineData>>, health: Health, metrics: Metrics) {
+        // Named in health, which needs no token, so without the URL's path.
+        let sink = format!("push {}", redact(&self.config.url));
+        while let Some(batch) = rx.recv().await {
+            let payload = json!({
+                "candles": batch.iter().map(KlineData::to_json).collect::<Vec<Value>>(),
+            });
+            let started = Instant::now();
+            let result = with_retries(self.config.retries, || self.send(&payload)).await;
+            metrics.record_latency(&format!("sink:{}", sink), started.elapsed());
+            let error = result.err().map(|e| redact(&e.to_string()));
+            if let Some(e) = &error {
+                error!(
+                    "Failed to push {} candle(s) to {}: {}",
+                    batch.len(),
+                    sink,
+                    e
+                );
+            }
+            health.record_delivery(&sink, error);
+        }
+    }
+
+    async fn send(&self, payload: &Value) -> Result<()> {
+        self.client
+            .post(&self.config.url)
+            .json(payload)
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
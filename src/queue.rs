@@ -0,0 +1,263 @@
+//! The bounded queue carrying kline updates from the stream tasks to the
+//! processor, with a configurable policy for when the processor falls behind.
+//! Closed candles take a priority lane the policy never applies to.
+
+use crate::config::{Backpressure, QueueConfig};
+use crate::metrics::{ChannelMetrics, Metrics};
+use crate::KlineData;
+use anyhow::{bail, Result};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock};
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
+
+struct Shared {
+    queue: Mutex<Queue>,
+    /// Live senders; the queue closes once the last is dropped.
+    senders: AtomicUsize,
+    /// Signalled when an update is queued or the last sender goes away.
+    queued: Notify,
+    /// Signalled when room frees up or the receiver goes away.
+    room: Notify,
+    capacity: usize,
+    backpressure: Backpressure,
+    metrics: Metrics,
+    /// Set once the queue is registered, which needs the queue itself.
+    channel: OnceLock<ChannelMetrics>,
+}
+
+struct Queue {
+    /// Closed candles, taken ahead of `updates`. They are never dropped or
+    /// coalesced and never wait for room, so this lane is unbounded, though
+    /// it holds at most one candle per series per interval of lag.
+    closed: VecDeque<KlineData>,
+    /// Updates to candles still in progress, bounded by the capacity.
+    updates: VecDeque<KlineData>,
+    receiver_closed: bool,
+}
+
+/// Queues updates for the processor. Cloning adds another sender.
+pub struct KlineSender(Arc<Shared>);
+
+/// Takes updates off the queue: closed candles first, each lane in order.
+pub struct KlineReceiver(Arc<Shared>);
+
+/// A queue applying `config`'s backpressure policy, counting what it drops
+/// or coalesces in `metrics`, where its depth and send waits are reported as
+/// the channel `name`.
+pub fn channel(name: &str, config: &QueueConfig, metrics: Metrics) -> (KlineSender, KlineReceiver) {
+    let shared = Arc::new(Shared {
+        queue: Mutex::new(Queue {
+            closed: VecDeque::new(),
+            updates: VecDeque::with_capacity(config.capacity),
+            receiver_closed: false,
+        }),
+        senders: AtomicUsize::new(1),
+        queued: Notify::new(),
+        room: Notify::new(),
+        capacity: config.capacity.max(1),
+        backpressure: config.backpressure,
+        metrics: metrics.clone(),
+        channel: OnceLock::new(),
+    });
+    let queue = Arc::downgrade(&shared);
+    let channel = metrics.channel(name, shared.capacity, move || {
+        queue.upgrade().map_or(0, |shared| {
+            let queue = shared.lock();
+            queue.closed.len() + queue.updates.len()
+        })
+    });
+    let _ = shared.channel.set(channel);
+    (KlineSender(shared.clone()), KlineReceiver(shared))
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, Queue> {
+        self.queue.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Called with the queue unlocked, as reading the depth locks it.
+    fn record_send(&self, started: Instant, waited: bool) {
+        if let Some(channel) = self.channel.get() {
+            // Only a send that had to wait for room counts as waiting, not
+            // one merely slowed by contention on the lock.
+            channel.record_send(if waited {
+                started.elapsed()
+            } else {
+                Duration::ZERO
+            });
+        }
+    }
+}
+
+impl KlineSender {
+    /// Queues `kline`, waiting for room when the policy calls for it. Fails
+    /// once the receiver has gone away.
+    pub async fn send(&self, kline: KlineData) -> Result<()> {
+        if kline.closed {
+            return self.send_closed(kline);
+        }
+        let shared = &self.0;
+        let started = Instant::now();
+        let mut waited = false;
+        loop {
+            // Registered before checking, so room freed in between is not
+            // missed.
+            let room = shared.room.notified();
+            tokio::pin!(room);
+            room.as_mut().enable();
+            {
+                let mut queue = shared.lock();
+                if queue.receiver_closed {
+                    bail!("The kline processor has stopped");
+                }
+                if shared.backpressure == Backpressure::Coalesce {
+                    // A linear scan, but the queue is short and this only
+                    // runs when coalescing.
+                    let queued = queue.updates.iter_mut().find(|queued| {
+                        queued.interval_start == kline.interval_start
+                            && queued.symbol == kline.symbol
+                            && queued.interval == kline.interval
+                    });
+                    if let Some(queued) = queued {
+                        *queued = kline;
+                        shared.metrics.record_coalesced(&queued.key());
+                        drop(queue);
+                        shared.record_send(started, waited);
+                        return Ok(());
+                    }
+                }
+                if queue.updates.len() >= shared.capacity
+                    && shared.backpressure == Backpressure::DropOldest
+                {
+                    if let Some(dropped) = queue.updates.pop_front() {
+                        shared.metrics.record_dropped(&dropped.key());
+                    }
+                }
+                if queue.updates.len() < shared.capacity {
+                    queue.updates.push_back(kline);
+                    drop(queue);
+                    shared.queued.notify_one();
+                    shared.record_send(started, waited);
+                    return Ok(());
+                }
+            }
+            room.await;
+            waited = true;
+        }
+    }
+
+    /// Queues a closed candle in the priority lane, discarding any queued
+    /// updates to the same candle, which it supersedes and which would
+    /// otherwise be taken after it.
+    fn send_closed(&self, kline: KlineData) -> Result<()> {
+        let shared = &self.0;
+        let started = Instant::now();
+        let mut superseded = Vec::new();
+        {
+            let mut queue = shared.lock();
+            if queue.receiver_closed {
+                bail!("The kline processor has stopped");
+            }
+            queue.updates.retain(|queued| {
+                let same = queued.interval_start == kline.interval_start
+                    && queued.symbol == kline.symbol
+                    && queued.interval == kline.interval;
+                if same {
+                    superseded.push(queued.key());
+                }
+                !same
+            });
+            queue.closed.push_back(kline);
+        }
+        for key in &superseded {
+            shared.metrics.record_coalesced(key);
+        }
+        if !superseded.is_empty() {
+            shared.room.notify_waiters();
+        }
+        shared.queued.notify_one();
+        shared.record_send(started, false);
+        Ok(())
+    }
+
+    /// Whether the receiver has gone away, so nothing more can be sent.
+    pub fn is_closed(&self) -> bool {
+        self.0.lock().receiver_closed
+    }
+}
+
+impl Clone for KlineSender {
+    fn clone(&self) -> Self {
+        self.0.senders.fetch_add(1, Ordering::Relaxed);
+        Self(self.0.clone())
+    }
+}
+
+impl Drop for KlineSender {
+    fn drop(&mut self) {
+        if self.0.senders.fetch_sub(1, Ordering::AcqRel) == 1 {
+            self.0.queued.notify_one();
+        }
+    }
+}
+
+impl KlineReceiver {
+    /// The next update, closed candles first, or `None` once every sender
+    /// is gone and the queue is drained.
+    pub async fn recv(&mut self) -> Option<KlineData> {
+        let shared = &self.0;
+        loop {
+            // Only one receiver waits, so a stored permit is never lost.
+            {
+                let mut queue = shared.lock();
+                if let Some(kline) = queue.closed.pop_front() {
+                    return Some(kline);
+                }
+                if let Some(kline) = queue.updates.pop_front() {
+                    shared.room.notify_one();
+                    return Some(kline);
+                }
+                if shared.senders.load(Ordering::Acquire) == 0 {
+                    return None;
+                }
+            }
+            shared.queued.notified().await;
+        }
+    }
+
+    /// Waits for at least one update, then moves up to `limit` of those
+    /// queued into `buffer`, closed candles first. Returns how many were
+    /// moved, which is zero once every sender is gone and the queue is
+    /// drained.
+    pub async fn recv_many(&mut self, buffer: &mut Vec<KlineData>, limit: usize) -> usize {
+        let shared = &self.0;
+        loop {
+            {
+                let mut queue = shared.lock();
+                let closed = queue.closed.len().min(limit);
+                buffer.extend(queue.closed.drain(..closed));
+                let updates = queue.updates.len().min(limit - closed);
+                buffer.extend(queue.updates.drain(..updates));
+                if updates > 0 {
+                    shared.room.notify_waiters();
+                }
+                if closed + updates > 0 {
+                    return closed + updates;
+                }
+                if shared.senders.load(Ordering::Acquire) == 0 {
+                    return 0;
+                }
+            }
+            shared.queued.notified().await;
+        }
+    }
+}
+
+impl Drop for KlineReceiver {
+    fn drop(&mut self) {
+        self.0.lock().receiver_closed = true;
+        self.0.room.notify_waiters();
+    }
+}
@@ -0,0 +1,44 @@
+use crate::config::RatioConfig;
+use crate::KlineData;
+
+/// Builds the synthetic candle for `base / quote` from the latest candle of
+/// each leg. Returns `None` until both legs are on the same interval start.
+///
+/// Only the legs' open and close prices line up in time, so the ratio's high
+/// and low are the extremes observed across updates of the current candle
+/// (`previous` is the last synthetic candle emitted for this ratio/interval).
+pub fn compute_ratio(
+    ratio: &RatioConfig,
+    base: &KlineData,
+    quote: &KlineData,
+    previous: Option<&KlineData>,
+) -> Option<KlineData> {
+    if base.interval != quote.interval || base.interval_start != quote.interval_start {
+        return None;
+    }
+    if quote.open == 0.0 || quote.close == 0.0 {
+        return None;
+    }
+
+    let open = base.open / quote.open;
+    let close = base.close / quote.close;
+    let (mut high, mut low) = (open.max(close), open.min(close));
+    if let Some(prev) = previous.filter(|p| p.interval_start == base.interval_start) {
+        high = high.max(prev.high);
+        low = low.min(prev.low);
+    }
+
+    Some(KlineData {
+        symbol: ratio.name().into(),
+        interval: base.interval.clone(),
+        interval_start: base.interval_start,
+        open,
+        high,
+        low,
+        close,
+        // Synthetic series have no traded volume of their own.
+        volume: 0.0,
+        closed: base.closed && quote.closed,
+        timing: None,
+    })
+}
@@ -0,0 +1,175 @@
+//! Runs the whole pipeline over recorded messages on a virtual clock, so the
+//! same capture always yields the same candles, indicators, alerts and
+//! signals, stamped with the capture's own times, for tests and for
+//! debugging what the tracker did with a given stretch of data.
+
+use crate::actor;
+use crate::alerts::AlertEngine;
+use crate::clock::VirtualClock;
+use crate::config::{Backpressure, Config};
+use crate::events::Event;
+use crate::health::Health;
+use crate::indicators::IndicatorPipeline;
+use crate::metrics::Metrics;
+use crate::notify::Notification;
+use crate::ops::OpsEvent;
+use crate::processor::{process_kline_stream, OutputFormat, Outputs};
+use crate::shard::{Router, Shards};
+use crate::state::SharedState;
+use crate::store::Store;
+use crate::strategy::StrategySet;
+use crate::{KlineData, SeriesKey};
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast::{self, error::RecvError};
+
+/// Events held for the recording before the replay gives up on keeping up.
+const EVENT_CAPACITY: usize = 65_536;
+
+/// Everything a replay produced, in the order it was produced.
+#[derive(Debug)]
+pub struct Recording {
+    /// What live subscribers and sinks would have been sent.
+    pub events: Vec<Event>,
+    pub notifications: Vec<Notification>,
+    pub incidents: Vec<OpsEvent>,
+    /// Latest candles, histories, indicators and session stats at the end.
+    pub state: SharedState,
+}
+
+impl Recording {
+    /// The events as JSON, one per line.
+    pub fn json_lines(&self) -> String {
+        self.events
+            .iter()
+            .map(|event| event.to_json().to_string() + "\n")
+            .collect()
+    }
+}
+
+/// Replays `messages`, each a Binance combined-stream message as captured by
+/// `--input`, through the pipeline configured by `config`: the worker
+/// queues, ratios, indicators, alerts, strategies and state. Every update
+/// belongs to the series its stream names, tracked or not.
+///
+/// Unlike a live run, a single worker processes every update, queues block
+/// rather than drop, candles are stored in memory only, and alerts, signals
+/// and cooldowns go by a virtual clock that follows each message's event
+/// time instead of the system's. Messages without one are stamped with the
+/// time of the last message that had one.
+pub async fn replay(
+    messages: impl IntoIterator<Item = impl AsRef<str>>,
+    mut config: Config,
+    pipeline: IndicatorPipeline,
+    alert_engine: AlertEngine,
+    strategies: StrategySet,
+) -> Result<Recording> {
+    config.workers = 1;
+    config.queue.backpressure = Backpressure::Block;
+    let metrics = Metrics::default();
+    let shards = Shards::new(config.workers, &config.ratios);
+    let (router, receivers) = Router::new(&config.queue, shards.clone(), metrics.clone());
+    let (notify_tx, notifications) = actor::mailbox(100);
+    let (ops_tx, incidents) = actor::mailbox(100);
+    let (events_tx, mut events) = broadcast::channel(EVENT_CAPACITY);
+    let state = SharedState::default();
+    let outputs = Outputs {
+        state: state.clone(),
+        store: Arc::new(Store::open(Path::new(":memory:"))?),
+        notify_tx,
+        ops_tx,
+        events_tx,
+        format: OutputFormat::Text,
+        filter: None,
+        health: Health::new(Duration::from_secs(config.ops.stream_down_secs.max(1))),
+        metrics,
+        clock: Arc::new(VirtualClock::new(DateTime::<Utc>::MIN_UTC)),
+    };
+
+    // Everything is collected as it comes, so the processor never waits on
+    // a full mailbox.
+    let events = tokio::spawn(async move {
+        let mut recorded = Vec::new();
+        loop {
+            match events.recv().await {
+                Ok(event) => recorded.push(event),
+                Err(RecvError::Closed) => return Ok(recorded),
+                Err(RecvError::Lagged(skipped)) => {
+                    bail!("Replay fell behind and lost {} events", skipped)
+                }
+            }
+        }
+    });
+    let notifications = tokio::spawn(async move {
+        let mut recorded = Vec::new();
+        while let Some(notification) = notifications.recv().await {
+            recorded.push(notification);
+        }
+        recorded
+    });
+    let incidents = tokio::spawn(async move {
+        let mut recorded = Vec::new();
+        while let Some(incident) = incidents.recv().await {
+            recorded.push(incident);
+        }
+        recorded
+    });
+
+    let processor = tokio::spawn(process_kline_stream(
+        receivers,
+        shards,
+        config,
+        pipeline,
+        alert_engine,
+        strategies,
+        outputs,
+    ));
+    let mut series: HashMap<String, SeriesKey> = HashMap::new();
+    for message in messages {
+        let parsed = KlineData::parse_combined(message.as_ref(), |stream| {
+            if let Some(key) = series.get(stream) {
+                return Some(key.clone());
+            }
+            let (symbol, interval) = stream.split_once("@kline_")?;
+            let key: SeriesKey = (symbol.into(), interval.into());
+            series.insert(stream.to_string(), key.clone());
+            Some(key)
+        });
+        let Ok(Some(mut kline)) = parsed else {
+            continue;
+        };
+        // Latency is not part of what is replayed.
+        if let Some(timing) = &mut kline.timing {
+            timing.exchange = None;
+        }
+        router.sender(&kline.symbol).send(kline).await?;
+    }
+    // Closing the queues lets the workers finish.
+    drop(router);
+    processor.await?;
+
+    Ok(Recording {
+        events: events.await??,
+        notifications: notifications.await?,
+        incidents: incidents.await?,
+        state,
+    })
+}
+
+/// Replays the capture at `path`, messages one per line; see [`replay`].
+pub async fn replay_capture(
+    path: &Path,
+    config: Config,
+    pipeline: IndicatorPipeline,
+    alert_engine: AlertEngine,
+    strategies: StrategySet,
+) -> Result<Recording> {
+    let capture = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read capture {}", path.display()))?;
+    let messages = capture.lines().filter(|line| !line.trim().is_empty());
+    replay(messages, config, pipeline, alert_engine, strategies).await
+}
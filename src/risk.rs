@@ -0,0 +1,177 @@
+//! Risk limits every paper and live order is checked against before it is
+//! placed.
+
+use crate::clock::{self, SharedClock};
+use crate::config::{RiskConfig, Side};
+use anyhow::{bail, Result};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use serde_json::{json, Value};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Allowance for rounding when comparing a position with its limit.
+const EPSILON: f64 = 1e-9;
+
+/// Refuses every new order while engaged, across paper and live trading.
+#[derive(Debug, Clone, Default)]
+pub struct KillSwitch(Arc<AtomicBool>);
+
+impl KillSwitch {
+    pub fn new(engaged: bool) -> Self {
+        Self(Arc::new(AtomicBool::new(engaged)))
+    }
+
+    pub fn engage(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn release(&self) {
+        self.0.store(false, Ordering::Relaxed);
+    }
+
+    pub fn is_engaged(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// One account's limits, and what it has done so far that counts against
+/// them.
+#[derive(Debug, Clone)]
+pub struct RiskLimits {
+    config: RiskConfig,
+    kill_switch: KillSwitch,
+    clock: SharedClock,
+    /// When each order of the last minute was placed.
+    placed: VecDeque<DateTime<Utc>>,
+    day: NaiveDate,
+    daily_pnl: f64,
+}
+
+impl RiskLimits {
+    pub fn new(config: &RiskConfig, kill_switch: KillSwitch) -> Self {
+        let clock = clock::system();
+        Self {
+            config: config.clone(),
+            kill_switch,
+            placed: VecDeque::new(),
+            day: clock.now().date_naive(),
+            clock,
+            daily_pnl: 0.0,
+        }
+    }
+
+    /// Reads the time, for the order rate and the trading day, from `clock`
+    /// rather than the system's.
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.day = clock.now().date_naive();
+        self.clock = clock;
+        self
+    }
+
+    /// No limits, for simulations that run faster than the clock.
+    pub fn unlimited() -> Self {
+        Self::new(&RiskConfig::default(), KillSwitch::default())
+    }
+
+    /// Checks an order worth `value` of `symbol`, of which `held_value` is
+    /// already held, counting it towards the order rate if it may be placed.
+    /// Sells are only held back by the kill switch and the order rate.
+    pub fn approve(&mut self, symbol: &str, side: Side, value: f64, held_value: f64) -> Result<()> {
+        self.check(symbol, side, value, held_value)?;
+        self.record_order();
+        Ok(())
+    }
+
+    /// Checks an order as [`approve`](Self::approve) does, without counting
+    /// it towards the order rate, for orders that may yet fail to be placed.
+    pub fn check(&mut self, symbol: &str, side: Side, value: f64, held_value: f64) -> Result<()> {
+        if self.kill_switch.is_engaged() {
+            bail!("The kill switch is engaged");
+        }
+        let now = self.clock.now();
+        self.roll_day(now);
+        if let Some(max) = self.config.max_orders_per_minute {
+            while self
+                .placed
+                .front()
+                .is_some_and(|placed| now - *placed >= Duration::minutes(1))
+            {
+                self.placed.pop_front();
+            }
+            if self.placed.len() >= max as usize {
+                bail!(
+                    "{} orders were placed in the last minute, the most allowed",
+                    max
+                );
+            }
+        }
+        if side == Side::Buy {
+            if let Some(max) = self.config.max_daily_loss {
+                if -self.daily_pnl >= max {
+                    bail!(
+                        "Today's realized loss of {:.2} has reached the limit of {:.2}",
+                        -self.daily_pnl,
+                        max
+                    );
+                }
+            }
+            if let Some(limit) = self.position_limit(symbol) {
+                if held_value + value > limit + EPSILON {
+                    bail!(
+                        "Buying {:.2} more {} would make the position worth {:.2}, over the limit of {:.2}",
+                        value,
+                        symbol,
+                        held_value + value,
+                        limit
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Counts an order placed now towards the order rate.
+    pub fn record_order(&mut self) {
+        if self.config.max_orders_per_minute.is_some() {
+            self.placed.push_back(self.clock.now());
+        }
+    }
+
+    /// Stops counting the latest order towards the order rate, for one that
+    /// failed to be placed.
+    pub fn release_order(&mut self) {
+        if self.config.max_orders_per_minute.is_some() {
+            self.placed.pop_back();
+        }
+    }
+
+    /// Counts a realized gain or loss, including fees, towards today's.
+    pub fn record_pnl(&mut self, pnl: f64) {
+        self.roll_day(self.clock.now());
+        self.daily_pnl += pnl;
+    }
+
+    fn roll_day(&mut self, now: DateTime<Utc>) {
+        let today = now.date_naive();
+        if today != self.day {
+            self.day = today;
+            self.daily_pnl = 0.0;
+        }
+    }
+
+    fn position_limit(&self, symbol: &str) -> Option<f64> {
+        self.config
+            .position_limits
+            .get(symbol)
+            .copied()
+            .or(self.config.max_position_value)
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "kill_switch": self.kill_switch.is_engaged(),
+            "daily_pnl": self.daily_pnl,
+        })
+    }
+}
@@ -0,0 +1,35 @@
+//! A small seeded random number generator, for the simulations that must
+//! be reproducible from their seed: injected faults and synthetic prices.
+
+use std::f64::consts::TAU;
+
+/// SplitMix64: small, fast and plenty random for simulations, though not
+/// for anything that must stay secret.
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    /// Uniform in `[0, 1)`.
+    pub fn uniform(&mut self) -> f64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^= z >> 31;
+        (z >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Whether an event of probability `chance` happens.
+    pub fn chance(&mut self, chance: f64) -> bool {
+        chance > 0.0 && self.uniform() < chance
+    }
+
+    /// Standard normal, by the Box–Muller transform.
+    pub fn gaussian(&mut self) -> f64 {
+        let (u1, u2) = (1.0 - self.uniform(), self.uniform());
+        (-2.0 * u1.ln()).sqrt() * (TAU * u2).cos()
+    }
+}
@@ -0,0 +1,154 @@
+//! Rhai scripts for alert conditions and strategies, read from the files the
+//! config names and read again whenever they change, so their logic can be
+//! edited while the tracker runs.
+
+use crate::KlineData;
+use anyhow::{anyhow, Context, Result};
+use rhai::{Dynamic, Engine, Map, Scope, AST};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+use tracing::{info, warn};
+
+/// How often a script's file is checked for changes.
+const RELOAD_CHECK: Duration = Duration::from_secs(1);
+
+/// Operations one run of a script may take, so a runaway loop fails rather
+/// than stalling a worker.
+const MAX_OPERATIONS: u64 = 1_000_000;
+
+/// A compiled script and the file it came from.
+pub struct Script {
+    path: PathBuf,
+    engine: Engine,
+    ast: AST,
+    modified: Option<SystemTime>,
+    checked: Instant,
+    /// Whether the last run failed, so a failure is only reported once.
+    failing: bool,
+}
+
+impl Script {
+    /// Reads and compiles the script at `path`.
+    pub fn load(path: &Path) -> Result<Self> {
+        let mut engine = Engine::new();
+        engine.set_max_operations(MAX_OPERATIONS);
+        let ast = compile(&engine, path)?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            engine,
+            ast,
+            modified: modified(path),
+            checked: Instant::now(),
+            failing: false,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Runs the script with `scope`, reading it again first if its file has
+    /// changed. A failure is logged the first time in a row it happens and
+    /// returns `None`.
+    pub fn run(&mut self, scope: &mut Scope) -> Option<Dynamic> {
+        self.reload();
+        match self.engine.eval_ast_with_scope::<Dynamic>(scope, &self.ast) {
+            Ok(value) => {
+                self.failing = false;
+                Some(value)
+            }
+            Err(e) => {
+                if !std::mem::replace(&mut self.failing, true) {
+                    warn!("Script {} failed: {}", self.path.display(), e);
+                }
+                None
+            }
+        }
+    }
+
+    /// Runs the script as a condition, which holds if it returns `true`.
+    pub fn matches(&mut self, scope: &mut Scope) -> bool {
+        self.run(scope)
+            .and_then(|value| value.as_bool().ok())
+            .unwrap_or(false)
+    }
+
+    /// Compiles the file again if it changed since it was last read. A
+    /// version that does not compile is reported and the last one kept.
+    fn reload(&mut self) {
+        if self.checked.elapsed() < RELOAD_CHECK {
+            return;
+        }
+        self.checked = Instant::now();
+        let modified = modified(&self.path);
+        if modified == self.modified {
+            return;
+        }
+        self.modified = modified;
+        match compile(&self.engine, &self.path) {
+            Ok(ast) => {
+                self.ast = ast;
+                self.failing = false;
+                info!("Reloaded script {}", self.path.display());
+            }
+            Err(e) => warn!("Keeping the previous version of a script: {:#}", e),
+        }
+    }
+}
+
+fn compile(engine: &Engine, path: &Path) -> Result<AST> {
+    let source = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read script {}", path.display()))?;
+    engine
+        .compile(source)
+        .map_err(|e| anyhow!("Invalid script {}: {}", path.display(), e))
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|meta| meta.modified())
+        .ok()
+}
+
+/// The variables a script sees for `kline`, named as in alert expressions:
+/// its indicator values by name, then the candle's own fields.
+pub fn candle_scope(
+    kline: &KlineData,
+    indicators: Option<&BTreeMap<String, Value>>,
+) -> Scope<'static> {
+    let mut scope = Scope::new();
+    for (name, value) in candle_map(kline, indicators) {
+        scope.push_dynamic(name.as_str(), value);
+    }
+    scope
+}
+
+/// [`candle_scope`] as a map, for the previous candle.
+pub fn candle_map(kline: &KlineData, indicators: Option<&BTreeMap<String, Value>>) -> Map {
+    let mut map = Map::new();
+    for (name, value) in indicators.into_iter().flatten() {
+        if let Ok(value) = rhai::serde::to_dynamic(value) {
+            map.insert(name.as_str().into(), value);
+        }
+    }
+    let fields = [
+        ("open", kline.open),
+        ("high", kline.high),
+        ("low", kline.low),
+        ("close", kline.close),
+        ("price", kline.close),
+        ("volume", kline.volume),
+        ("quote_volume", kline.volume * kline.close),
+        ("change", kline.price_change()),
+        ("change_pct", kline.price_change_percent()),
+    ];
+    for (name, value) in fields {
+        map.insert(name.into(), Dynamic::from_float(value));
+    }
+    map.insert("symbol".into(), kline.symbol.to_string().into());
+    map.insert("interval".into(), kline.interval.to_string().into());
+    map.insert("closed".into(), kline.closed.into());
+    map
+}
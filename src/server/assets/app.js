@@ -0,0 +1,228 @@
+"use strict";
+
+const MAX_CANDLES = 120;
+const REFRESH_MS = 10000;
+
+const symbolSelect = document.getElementById("symbol");
+const intervalSelect = document.getElementById("interval");
+const statusLabel = document.getElementById("status");
+const canvas = document.getElementById("chart");
+
+// An API token given in the page's own URL is passed on to every request.
+const token = new URLSearchParams(location.search).get("token");
+
+let candles = [];
+let source = null;
+
+function withToken(path) {
+  if (!token) {
+    return path;
+  }
+  const separator = path.includes("?") ? "&" : "?";
+  return `${path}${separator}token=${encodeURIComponent(token)}`;
+}
+
+async function getJson(path) {
+  const response = await fetch(withToken(path));
+  if (!response.ok) {
+    throw new Error(`${path}: ${response.status}`);
+  }
+  return response.json();
+}
+
+function setOptions(select, values) {
+  const current = select.value;
+  const known = Array.from(select.options, (option) => option.value);
+  if (known.join() === values.join()) {
+    return;
+  }
+  select.replaceChildren(...values.map((value) => new Option(value, value)));
+  if (values.includes(current)) {
+    select.value = current;
+  }
+}
+
+function signed(value) {
+  return `${value >= 0 ? "+" : ""}${value.toFixed(2)}%`;
+}
+
+function cell(text, className) {
+  const td = document.createElement("td");
+  td.textContent = text;
+  if (className) {
+    td.className = className;
+  }
+  return td;
+}
+
+function renderSessions(sessions) {
+  const rows = sessions.map((session) => {
+    const tr = document.createElement("tr");
+    tr.append(
+      cell(session.symbol.toUpperCase()),
+      cell(session.last_price.toFixed(2)),
+      cell(signed(session.change_percent), session.change_percent >= 0 ? "up" : "down"),
+      cell(session.high.toFixed(2)),
+      cell(session.low.toFixed(2)),
+      cell(`${session.max_drawdown_percent.toFixed(2)}%`),
+    );
+    return tr;
+  });
+  document.querySelector("#sessions tbody").replaceChildren(...rows);
+}
+
+function renderAlerts(alerts) {
+  const rows = alerts.map((alert) => {
+    const tr = document.createElement("tr");
+    tr.append(
+      cell(new Date(alert.triggered_at).toLocaleString()),
+      cell(alert.rule),
+      cell(`${alert.symbol.toUpperCase()} ${alert.interval}`),
+      cell(alert.price.toFixed(2)),
+      cell(alert.message),
+    );
+    return tr;
+  });
+  document.querySelector("#alerts tbody").replaceChildren(...rows);
+}
+
+function renderPortfolio(portfolio) {
+  document.getElementById("portfolio").hidden = portfolio.holdings.length === 0;
+  const rows = portfolio.holdings.map((holding) => {
+    const tr = document.createElement("tr");
+    const pnl = holding.unrealized_pnl;
+    const weight = holding.value === null || portfolio.value === 0 ? null : holding.value / portfolio.value;
+    tr.append(
+      cell(holding.symbol.toUpperCase()),
+      cell(String(holding.quantity)),
+      cell(holding.price === null ? "–" : holding.price.toFixed(2)),
+      cell(holding.value === null ? "–" : holding.value.toFixed(2)),
+      cell(weight === null ? "–" : `${(weight * 100).toFixed(1)}%`),
+      cell(pnl === null ? "–" : `${pnl >= 0 ? "+" : ""}${pnl.toFixed(2)}`, pnl === null ? "" : pnl >= 0 ? "up" : "down"),
+    );
+    return tr;
+  });
+  document.querySelector("#holdings tbody").replaceChildren(...rows);
+  const metrics = [
+    `Value ${portfolio.value.toFixed(2)}`,
+    `Largest ${(portfolio.largest_weight * 100).toFixed(1)}%`,
+    `HHI ${portfolio.herfindahl.toFixed(3)}`,
+  ];
+  const risk = portfolio.risk;
+  if (risk) {
+    metrics.push(
+      `Volatility per ${risk.interval} ${(risk.volatility * 100).toFixed(2)}%`,
+      `95% VaR ${risk.value_at_risk.toFixed(2)}`,
+    );
+  }
+  document.getElementById("portfolio-metrics").textContent = metrics.join(" · ");
+}
+
+async function refresh() {
+  try {
+    const summary = await getJson("/summary");
+    const symbols = summary.sessions.map((session) => session.symbol);
+    const intervals = summary.markets.map((market) => market.interval);
+    const changed = symbolSelect.value === "" && symbols.length > 0;
+    setOptions(symbolSelect, symbols);
+    setOptions(intervalSelect, intervals);
+    renderSessions(summary.sessions);
+    renderPortfolio(await getJson("/portfolio"));
+    renderAlerts(await getJson("/alerts?limit=20"));
+    if (changed) {
+      selectSeries();
+    }
+  } catch (e) {
+    statusLabel.textContent = e.message;
+  }
+}
+
+function upsert(kline) {
+  const last = candles[candles.length - 1];
+  if (last && last.interval_start === kline.interval_start) {
+    candles[candles.length - 1] = kline;
+  } else if (!last || last.interval_start < kline.interval_start) {
+    candles.push(kline);
+    candles = candles.slice(-MAX_CANDLES);
+  }
+}
+
+async function selectSeries() {
+  const symbol = symbolSelect.value;
+  const interval = intervalSelect.value;
+  if (source) {
+    source.close();
+    source = null;
+  }
+  if (!symbol || !interval) {
+    return;
+  }
+  try {
+    candles = await getJson(`/klines/${symbol}/${interval}?limit=${MAX_CANDLES}`);
+  } catch {
+    candles = [];
+  }
+  draw();
+
+  source = new EventSource(withToken(`/events?symbols=${symbol}&intervals=${interval}`));
+  source.onopen = () => {
+    statusLabel.textContent = "live";
+  };
+  source.onerror = () => {
+    statusLabel.textContent = "reconnecting…";
+  };
+  source.addEventListener("kline", (event) => {
+    upsert(JSON.parse(event.data));
+    draw();
+  });
+}
+
+function draw() {
+  const ratio = window.devicePixelRatio || 1;
+  const width = canvas.clientWidth;
+  const height = canvas.clientHeight;
+  canvas.width = width * ratio;
+  canvas.height = height * ratio;
+  const ctx = canvas.getContext("2d");
+  ctx.scale(ratio, ratio);
+  ctx.clearRect(0, 0, width, height);
+  if (candles.length === 0) {
+    return;
+  }
+
+  const axis = 70;
+  const high = Math.max(...candles.map((candle) => candle.high));
+  const low = Math.min(...candles.map((candle) => candle.low));
+  const range = high - low || 1;
+  const y = (price) => 10 + ((high - price) / range) * (height - 20);
+  const step = (width - axis) / MAX_CANDLES;
+  const body = Math.max(1, step * 0.7);
+
+  ctx.fillStyle = "#8a929c";
+  ctx.font = "12px system-ui, sans-serif";
+  for (let i = 0; i <= 4; i++) {
+    const price = low + (range * i) / 4;
+    ctx.fillText(price.toFixed(2), width - axis + 6, y(price) + 4);
+  }
+
+  candles.forEach((candle, i) => {
+    const x = (MAX_CANDLES - candles.length + i) * step + step / 2;
+    const color = candle.close >= candle.open ? "#26a69a" : "#ef5350";
+    ctx.strokeStyle = color;
+    ctx.fillStyle = color;
+    ctx.beginPath();
+    ctx.moveTo(x, y(candle.high));
+    ctx.lineTo(x, y(candle.low));
+    ctx.stroke();
+    const top = y(Math.max(candle.open, candle.close));
+    const bottom = y(Math.min(candle.open, candle.close));
+    ctx.fillRect(x - body / 2, top, body, Math.max(1, bottom - top));
+  });
+}
+
+symbolSelect.addEventListener("change", selectSeries);
+intervalSelect.addEventListener("change", selectSeries);
+window.addEventListener("resize", draw);
+
+refresh();
+setInterval(refresh, REFRESH_MS);
@@ -0,0 +1,153 @@
+use super::{ApiError, AppState};
+use crate::config::AuthConfig;
+use anyhow::{bail, Result};
+use axum::extract::{Request, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use tracing::{debug, warn};
+
+/// The API tokens the server accepts. Without any configured, every request
+/// is let in as an anonymous client with no limits.
+#[derive(Clone)]
+pub struct Auth {
+    clients: Arc<Vec<(String, Client)>>,
+    anonymous: Client,
+}
+
+/// A client that presented a valid token, shared by all its connections so
+/// its subscription limit holds across them.
+#[derive(Clone)]
+pub struct Client(Arc<ClientState>);
+
+struct ClientState {
+    name: String,
+    max_subscriptions: Option<usize>,
+    active: AtomicUsize,
+}
+
+/// One of a client's live subscriptions, released when dropped.
+pub struct Lease(Client);
+
+impl Drop for Lease {
+    fn drop(&mut self) {
+        self.0 .0.active.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+impl Auth {
+    pub fn new(config: &AuthConfig) -> Self {
+        let clients = config
+            .tokens
+            .iter()
+            .map(|token| {
+                (
+                    token.token.clone(),
+                    Client::new(&token.name, token.max_subscriptions),
+                )
+            })
+            .collect();
+        Self {
+            clients: Arc::new(clients),
+            anonymous: Client::new("anonymous", None),
+        }
+    }
+
+    /// The client `token` belongs to, if any.
+    pub fn authenticate(&self, token: Option<&str>) -> Option<Client> {
+        if self.clients.is_empty() {
+            return Some(self.anonymous.clone());
+        }
+        let token = token?;
+        self.clients
+            .iter()
+            .find(|(known, _)| constant_time_eq(known.as_bytes(), token.as_bytes()))
+            .map(|(_, client)| client.clone())
+    }
+}
+
+impl Client {
+    fn new(name: &str, max_subscriptions: Option<usize>) -> Self {
+        Self(Arc::new(ClientState {
+            name: name.to_string(),
+            max_subscriptions,
+            active: AtomicUsize::new(0),
+        }))
+    }
+
+    /// Takes one of the client's subscriptions, failing once it holds as
+    /// many as it is allowed.
+    pub fn subscribe(&self) -> Result<Lease> {
+        let state = &self.0;
+        let taken = state
+            .active
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |active| {
+                match state.max_subscriptions {
+                    Some(max) if active >= max => None,
+                    _ => Some(active + 1),
+                }
+            });
+        if taken.is_err() {
+            bail!(
+                "Client '{}' already holds its limit of {} subscriptions",
+                state.name,
+                state.max_subscriptions.unwrap_or_default()
+            );
+        }
+        debug!("Client '{}' opened a subscription", state.name);
+        Ok(Lease(self.clone()))
+    }
+}
+
+/// The token in an `Authorization: Bearer` or `X-API-Key` header value.
+pub fn header_token<'a>(
+    authorization: Option<&'a str>,
+    api_key: Option<&'a str>,
+) -> Option<&'a str> {
+    authorization
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .or(api_key)
+        .map(str::trim)
+}
+
+/// Rejects requests without a valid token, and hands the client on to the
+/// handlers. Browsers cannot set headers on `EventSource` or WebSocket
+/// connections, so the token may also come as a `token` query parameter.
+pub(super) async fn require_token(
+    State(state): State<AppState>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let from_query = request.uri().query().and_then(|query| {
+        url::form_urlencoded::parse(query.as_bytes())
+            .find(|(key, _)| key == "token")
+            .map(|(_, token)| token.into_owned())
+    });
+    let token = token_from_headers(request.headers()).or(from_query);
+    let Some(client) = state.auth.authenticate(token.as_deref()) else {
+        warn!(
+            "Rejected request to {} without a valid token",
+            request.uri().path()
+        );
+        return ApiError(
+            StatusCode::UNAUTHORIZED,
+            "Missing or invalid API token".to_string(),
+        )
+        .into_response();
+    };
+    request.extensions_mut().insert(client);
+    next.run(request).await
+}
+
+fn token_from_headers(headers: &HeaderMap) -> Option<String> {
+    let header = |name| headers.get(name).and_then(|value| value.to_str().ok());
+    header_token(header("authorization"), header("x-api-key")).map(str::to_string)
+}
+
+/// Compares without stopping at the first difference, so response times do
+/// not reveal how much of a guessed token was right.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
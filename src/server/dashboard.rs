@@ -0,0 +1,23 @@
+//! A zero-setup web UI, compiled into the binary, built on the JSON and SSE
+//! endpoints.
+
+use axum::http::header;
+use axum::response::{Html, IntoResponse};
+
+pub(super) async fn index() -> Html<&'static str> {
+    Html(include_str!("assets/index.html"))
+}
+
+pub(super) async fn script() -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "text/javascript")],
+        include_str!("assets/app.js"),
+    )
+}
+
+pub(super) async fn style() -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "text/css")],
+        include_str!("assets/style.css"),
+    )
+}
@@ -0,0 +1,395 @@
+use super::auth::Client;
+use super::{parse_time, recent_candles, AppState, DEFAULT_LIMIT, MAX_LIMIT};
+use crate::alerts;
+use crate::events::Event;
+use crate::store::{self, AlertQuery, CandleRange};
+use crate::strategy::TradeSignal;
+use crate::KlineData;
+use async_graphql::http::GraphiQLSource;
+use async_graphql::{Context, Data, EmptyMutation, Json, Object, SimpleObject, Subscription};
+use async_graphql_axum::{GraphQLProtocol, GraphQLWebSocket};
+use axum::extract::ws::WebSocketUpgrade;
+use axum::response::{Html, IntoResponse, Response};
+use axum::Extension;
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use tokio_stream::wrappers::BroadcastStream;
+use tokio_stream::{Stream, StreamExt};
+use tracing::warn;
+
+pub(super) type Schema = async_graphql::Schema<QueryRoot, EmptyMutation, SubscriptionRoot>;
+
+pub(super) fn schema(state: AppState) -> Schema {
+    Schema::build(QueryRoot, EmptyMutation, SubscriptionRoot)
+        .data(state)
+        .finish()
+}
+
+/// An in-browser IDE for exploring the schema.
+pub(super) async fn graphiql() -> impl IntoResponse {
+    Html(
+        GraphiQLSource::build()
+            .endpoint("/graphql")
+            .subscription_endpoint("/graphql/ws")
+            .finish(),
+    )
+}
+
+/// Upgrades to a WebSocket serving subscriptions. The client is passed to
+/// the resolvers so each subscription counts towards its limit.
+pub(super) async fn subscriptions(
+    Extension(schema): Extension<Schema>,
+    Extension(client): Extension<Client>,
+    protocol: GraphQLProtocol,
+    upgrade: WebSocketUpgrade,
+) -> Response {
+    upgrade
+        .protocols(async_graphql::http::ALL_WEBSOCKET_PROTOCOLS)
+        .on_upgrade(move |socket| {
+            let mut data = Data::default();
+            data.insert(client);
+            GraphQLWebSocket::new(socket, schema, protocol)
+                .with_data(data)
+                .serve()
+        })
+}
+
+#[derive(SimpleObject)]
+struct Kline {
+    symbol: String,
+    interval: String,
+    /// Start of the candle, as RFC 3339.
+    interval_start: String,
+    open: f64,
+    high: f64,
+    low: f64,
+    close: f64,
+    volume: f64,
+    closed: bool,
+}
+
+impl From<&KlineData> for Kline {
+    fn from(kline: &KlineData) -> Self {
+        Self {
+            symbol: kline.symbol.to_string(),
+            interval: kline.interval.to_string(),
+            interval_start: kline.interval_start.to_rfc3339(),
+            open: kline.open,
+            high: kline.high,
+            low: kline.low,
+            close: kline.close,
+            volume: kline.volume,
+            closed: kline.closed,
+        }
+    }
+}
+
+#[derive(SimpleObject)]
+struct Alert {
+    rule: String,
+    symbol: String,
+    interval: String,
+    price: f64,
+    change_percent: f64,
+    message: String,
+    /// When the alert fired, as RFC 3339.
+    triggered_at: String,
+}
+
+impl From<&alerts::Alert> for Alert {
+    fn from(alert: &alerts::Alert) -> Self {
+        Self {
+            rule: alert.rule.clone(),
+            symbol: alert.symbol.clone(),
+            interval: alert.interval.clone(),
+            price: alert.price,
+            change_percent: alert.change_percent,
+            message: alert.message.clone(),
+            triggered_at: alert.triggered_at.to_rfc3339(),
+        }
+    }
+}
+
+#[derive(SimpleObject)]
+struct Signal {
+    strategy: String,
+    symbol: String,
+    interval: String,
+    /// `buy` or `sell`.
+    side: String,
+    /// From 0 to 1.
+    strength: f64,
+    reason: String,
+    price: f64,
+    /// The series' indicator values when the signal was raised.
+    indicators: Json<BTreeMap<String, Value>>,
+    /// When the signal was raised, as RFC 3339.
+    raised_at: String,
+}
+
+impl From<&TradeSignal> for Signal {
+    fn from(signal: &TradeSignal) -> Self {
+        Self {
+            strategy: signal.strategy.clone(),
+            symbol: signal.symbol.clone(),
+            interval: signal.interval.clone(),
+            side: signal.side.to_string(),
+            strength: signal.strength,
+            reason: signal.reason.clone(),
+            price: signal.price,
+            indicators: Json(signal.indicators.clone()),
+            raised_at: signal.raised_at.to_rfc3339(),
+        }
+    }
+}
+
+/// A recorded alert with the outcome of each delivery.
+#[derive(SimpleObject)]
+struct AlertRecord {
+    id: i64,
+    alert: Alert,
+    deliveries: Vec<Delivery>,
+}
+
+#[derive(SimpleObject)]
+struct Delivery {
+    notifier: String,
+    /// The delivery error, or null if it succeeded.
+    error: Option<String>,
+    at: String,
+}
+
+impl From<&store::AlertRecord> for AlertRecord {
+    fn from(record: &store::AlertRecord) -> Self {
+        Self {
+            id: record.id,
+            alert: Alert::from(&record.alert),
+            deliveries: record
+                .deliveries
+                .iter()
+                .map(|delivery| Delivery {
+                    notifier: delivery.notifier.clone(),
+                    error: delivery.error.clone(),
+                    at: delivery.at.to_rfc3339(),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Running statistics for one symbol since the tracker started.
+#[derive(SimpleObject)]
+struct Session {
+    symbol: String,
+    started: String,
+    first_price: f64,
+    last_price: f64,
+    change_percent: f64,
+    high: f64,
+    low: f64,
+    max_drawdown_percent: f64,
+}
+
+pub(super) struct QueryRoot;
+
+// Each resolver argument is a GraphQL argument, so they cannot be grouped.
+#[allow(clippy::too_many_arguments)]
+#[Object]
+impl QueryRoot {
+    /// A page of a series' candles, oldest first: counting back from the
+    /// newest candle starting within `from..to`, skip `offset` and take
+    /// `limit`. Older closed candles come from the store. Times may be
+    /// RFC 3339, Unix milliseconds or an age such as `12h`.
+    async fn klines(
+        &self,
+        ctx: &Context<'_>,
+        symbol: String,
+        interval: String,
+        from: Option<String>,
+        to: Option<String>,
+        #[graphql(default)] offset: usize,
+        limit: Option<usize>,
+    ) -> async_graphql::Result<Vec<Kline>> {
+        let state = ctx.data::<AppState>()?;
+        let range = CandleRange {
+            from: from.as_deref().map(time_arg).transpose()?,
+            to: to.as_deref().map(time_arg).transpose()?,
+            offset,
+            limit: limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT),
+        };
+        let candles = recent_candles(
+            &state.market,
+            &state.store,
+            &symbol.to_lowercase(),
+            &interval,
+            range,
+        )
+        .await?;
+        Ok(candles.iter().map(Kline::from).collect())
+    }
+
+    /// The latest update of every series, optionally narrowed to one symbol.
+    async fn latest(
+        &self,
+        ctx: &Context<'_>,
+        symbol: Option<String>,
+    ) -> async_graphql::Result<Vec<Kline>> {
+        let symbol = symbol.map(|symbol| symbol.to_lowercase());
+        let market = &ctx.data::<AppState>()?.market;
+        let mut latest: Vec<Kline> = market
+            .latest
+            .iter()
+            .filter(|entry| {
+                symbol
+                    .as_ref()
+                    .is_none_or(|symbol| **symbol == *entry.symbol)
+            })
+            .map(|entry| Kline::from(entry.value()))
+            .collect();
+        latest.sort_by(|a, b| (&a.symbol, &a.interval).cmp(&(&b.symbol, &b.interval)));
+        Ok(latest)
+    }
+
+    /// Session statistics per symbol.
+    async fn sessions(&self, ctx: &Context<'_>) -> async_graphql::Result<Vec<Session>> {
+        let market = &ctx.data::<AppState>()?.market;
+        let sessions: Vec<Session> = market
+            .sorted_sessions()
+            .into_iter()
+            .map(|(symbol, session)| Session {
+                symbol: symbol.to_string(),
+                started: session.started.to_rfc3339(),
+                first_price: session.first_price,
+                last_price: session.last_price,
+                change_percent: session.change_percent(),
+                high: session.high,
+                low: session.low,
+                max_drawdown_percent: session.max_drawdown_percent,
+            })
+            .collect();
+        Ok(sessions)
+    }
+
+    /// Recorded alerts and their deliveries, newest first. `since` is a
+    /// duration such as `12h`.
+    async fn alerts(
+        &self,
+        ctx: &Context<'_>,
+        symbol: Option<String>,
+        rule: Option<String>,
+        since: Option<String>,
+        until: Option<String>,
+        #[graphql(default)] offset: usize,
+        limit: Option<usize>,
+    ) -> async_graphql::Result<Vec<AlertRecord>> {
+        let state = ctx.data::<AppState>()?;
+        let query = AlertQuery {
+            symbol: symbol.map(|symbol| symbol.to_lowercase()),
+            rule,
+            since: since.as_deref().map(time_arg).transpose()?,
+            until: until.as_deref().map(time_arg).transpose()?,
+            offset,
+            limit: limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT),
+        };
+        let store = state.store.clone();
+        let records = tokio::task::spawn_blocking(move || store.alerts(&query)).await??;
+        Ok(records.iter().map(AlertRecord::from).collect())
+    }
+}
+
+pub(super) struct SubscriptionRoot;
+
+#[Subscription]
+impl SubscriptionRoot {
+    /// Live kline updates. Empty lists match every symbol or interval.
+    async fn klines(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(default)] symbols: Vec<String>,
+        #[graphql(default)] intervals: Vec<String>,
+    ) -> async_graphql::Result<impl Stream<Item = Kline>> {
+        let filter = Filter::new(symbols, intervals);
+        Ok(events(ctx)?
+            .filter(move |event| filter.matches(event))
+            .filter_map(|event| match event {
+                Event::Kline(kline) => Some(Kline::from(&kline)),
+                _ => None,
+            }))
+    }
+
+    /// Alerts as they fire. Empty lists match every symbol or interval.
+    async fn alerts(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(default)] symbols: Vec<String>,
+        #[graphql(default)] intervals: Vec<String>,
+    ) -> async_graphql::Result<impl Stream<Item = Alert>> {
+        let filter = Filter::new(symbols, intervals);
+        Ok(events(ctx)?
+            .filter(move |event| filter.matches(event))
+            .filter_map(|event| match event {
+                Event::Alert(alert) => Some(Alert::from(&alert)),
+                _ => None,
+            }))
+    }
+
+    /// Strategy signals as they are raised. Empty lists match every symbol
+    /// or interval.
+    async fn signals(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(default)] symbols: Vec<String>,
+        #[graphql(default)] intervals: Vec<String>,
+    ) -> async_graphql::Result<impl Stream<Item = Signal>> {
+        let filter = Filter::new(symbols, intervals);
+        Ok(events(ctx)?
+            .filter(move |event| filter.matches(event))
+            .filter_map(|event| match event {
+                Event::Signal(signal) => Some(Signal::from(&signal)),
+                _ => None,
+            }))
+    }
+}
+
+fn time_arg(text: &str) -> async_graphql::Result<DateTime<Utc>> {
+    parse_time(text).ok_or_else(|| format!("Invalid time '{}'", text).into())
+}
+
+/// Live events for one subscriber, skipping any it fell too far behind on.
+fn events(ctx: &Context<'_>) -> async_graphql::Result<impl Stream<Item = Event>> {
+    let lease = ctx.data::<Client>()?.subscribe()?;
+    let events = ctx.data::<AppState>()?.events.subscribe();
+    Ok(
+        BroadcastStream::new(events).filter_map(move |event| match event {
+            Ok(event) => {
+                // Held for as long as the subscription runs.
+                let _ = &lease;
+                Some(event)
+            }
+            Err(e) => {
+                warn!("GraphQL subscriber fell behind: {}", e);
+                None
+            }
+        }),
+    )
+}
+
+struct Filter {
+    symbols: Vec<String>,
+    intervals: Vec<String>,
+}
+
+impl Filter {
+    fn new(symbols: Vec<String>, intervals: Vec<String>) -> Self {
+        Self {
+            symbols: symbols.iter().map(|symbol| symbol.to_lowercase()).collect(),
+            intervals,
+        }
+    }
+
+    fn matches(&self, event: &Event) -> bool {
+        (self.symbols.is_empty() || self.symbols.iter().any(|s| s == event.symbol()))
+            && (self.intervals.is_empty() || self.intervals.iter().any(|i| i == event.interval()))
+    }
+}
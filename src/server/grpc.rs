@@ -0,0 +1,203 @@
+use super::auth::{header_token, Auth, Client};
+use super::Replay;
+use crate::config::GrpcConfig;
+use crate::events::Event;
+use crate::state::SharedState;
+use crate::store::Store;
+use anyhow::Result;
+use chrono::DateTime;
+use std::pin::Pin;
+use std::sync::Arc;
+use tokio::sync::broadcast;
+use tokio_stream::wrappers::BroadcastStream;
+use tokio_stream::{Stream, StreamExt};
+use tonic::{Request, Response, Status};
+use tracing::{info, warn};
+
+mod proto {
+    tonic::include_proto!("tracker");
+}
+
+use proto::tracker_server::{Tracker, TrackerServer};
+use proto::{EventKind, SubscribeRequest};
+
+/// Serves the gRPC streaming API until the listener fails.
+pub async fn serve_grpc(
+    config: &GrpcConfig,
+    market: SharedState,
+    store: Arc<Store>,
+    events: broadcast::Sender<Event>,
+    auth: Auth,
+) -> Result<()> {
+    info!("gRPC API listening on {}", config.listen);
+    let service = TrackerService {
+        market,
+        store,
+        events,
+    };
+    let service =
+        TrackerServer::with_interceptor(service, move |request| authenticate(&auth, request));
+    tonic::transport::Server::builder()
+        .add_service(service)
+        .serve(config.listen)
+        .await?;
+    Ok(())
+}
+
+struct TrackerService {
+    market: SharedState,
+    store: Arc<Store>,
+    events: broadcast::Sender<Event>,
+}
+
+#[tonic::async_trait]
+impl Tracker for TrackerService {
+    type SubscribeStream = Pin<Box<dyn Stream<Item = Result<proto::Event, Status>> + Send>>;
+
+    async fn subscribe(
+        &self,
+        request: Request<SubscribeRequest>,
+    ) -> Result<Response<Self::SubscribeStream>, Status> {
+        let lease = request
+            .extensions()
+            .get::<Client>()
+            .ok_or_else(|| Status::unauthenticated("Missing API token"))?
+            .subscribe()
+            .map_err(|e| Status::resource_exhausted(e.to_string()))?;
+        let request = request.into_inner();
+        let since = match request.replay_since_ms {
+            Some(ms) => Some(
+                DateTime::from_timestamp_millis(ms)
+                    .ok_or_else(|| Status::invalid_argument("Invalid replay_since_ms"))?,
+            ),
+            None => None,
+        };
+        let replay = Replay::new(request.replay.map(|count| count as usize), since);
+        let filter = Filter::new(request);
+        // Subscribed before replaying, so nothing falls between the two.
+        let live = BroadcastStream::new(self.events.subscribe());
+
+        let mut replayed = Vec::new();
+        if let Some(replay) = replay.filter(|_| filter.wants(EventKind::Kline)) {
+            let wanted = |symbol: &str, interval: &str| filter.wants_series(symbol, interval);
+            let candles = replay
+                .candles(&self.market, &self.store, wanted)
+                .await
+                .map_err(|e| Status::internal(e.to_string()))?;
+            replayed.extend(
+                candles
+                    .into_iter()
+                    .map(|kline| Ok(to_proto(&Event::Kline(kline)))),
+            );
+        }
+
+        let stream = live.filter_map(move |event| match event {
+            Ok(event) => {
+                // Held for as long as the client stays subscribed.
+                let _ = &lease;
+                filter.matches(&event).then(|| Ok(to_proto(&event)))
+            }
+            Err(e) => {
+                warn!("gRPC subscriber fell behind: {}", e);
+                None
+            }
+        });
+        Ok(Response::new(Box::pin(
+            tokio_stream::iter(replayed).chain(stream),
+        )))
+    }
+}
+
+/// Rejects calls without a valid token in their `authorization` or
+/// `x-api-key` metadata, and hands the client on to the service.
+fn authenticate(auth: &Auth, mut request: Request<()>) -> Result<Request<()>, Status> {
+    let metadata = request.metadata();
+    let value = |key| metadata.get(key).and_then(|value| value.to_str().ok());
+    let token = header_token(value("authorization"), value("x-api-key"));
+    let Some(client) = auth.authenticate(token) else {
+        warn!("Rejected gRPC call without a valid token");
+        return Err(Status::unauthenticated("Missing or invalid API token"));
+    };
+    request.extensions_mut().insert(client);
+    Ok(request)
+}
+
+/// A subscription's filters; an empty list matches everything.
+struct Filter {
+    symbols: Vec<String>,
+    intervals: Vec<String>,
+    kinds: Vec<EventKind>,
+}
+
+impl Filter {
+    fn new(request: SubscribeRequest) -> Self {
+        Self {
+            kinds: request.kinds().collect(),
+            symbols: request
+                .symbols
+                .into_iter()
+                .map(|symbol| symbol.to_lowercase())
+                .collect(),
+            intervals: request.intervals,
+        }
+    }
+
+    fn matches(&self, event: &Event) -> bool {
+        let kind = match event {
+            Event::Kline(_) => EventKind::Kline,
+            Event::Alert(_) => EventKind::Alert,
+            Event::Signal(_) => EventKind::Signal,
+        };
+        self.wants(kind) && self.wants_series(event.symbol(), event.interval())
+    }
+
+    fn wants(&self, kind: EventKind) -> bool {
+        self.kinds.is_empty() || self.kinds.contains(&kind)
+    }
+
+    fn wants_series(&self, symbol: &str, interval: &str) -> bool {
+        (self.symbols.is_empty() || self.symbols.iter().any(|s| s == symbol))
+            && (self.intervals.is_empty() || self.intervals.iter().any(|i| i == interval))
+    }
+}
+
+fn to_proto(event: &Event) -> proto::Event {
+    let event = match event {
+        Event::Kline(kline) => proto::event::Event::Kline(proto::Kline {
+            symbol: kline.symbol.to_string(),
+            interval: kline.interval.to_string(),
+            interval_start_ms: kline.interval_start.timestamp_millis(),
+            open: kline.open,
+            high: kline.high,
+            low: kline.low,
+            close: kline.close,
+            volume: kline.volume,
+            closed: kline.closed,
+        }),
+        Event::Alert(alert) => proto::event::Event::Alert(proto::Alert {
+            rule: alert.rule.clone(),
+            symbol: alert.symbol.clone(),
+            interval: alert.interval.clone(),
+            price: alert.price,
+            change_percent: alert.change_percent,
+            message: alert.message.clone(),
+            triggered_at_ms: alert.triggered_at.timestamp_millis(),
+        }),
+        Event::Signal(signal) => proto::event::Event::Signal(proto::Signal {
+            strategy: signal.strategy.clone(),
+            symbol: signal.symbol.clone(),
+            interval: signal.interval.clone(),
+            side: signal.side.to_string(),
+            strength: signal.strength,
+            reason: signal.reason.clone(),
+            price: signal.price,
+            indicators: signal
+                .indicators
+                .iter()
+                .map(|(name, value)| (name.clone(), value.to_string()))
+                .collect(),
+            raised_at_ms: signal.raised_at.timestamp_millis(),
+        }),
+    };
+    proto::Event { event: Some(event) }
+}
@@ -0,0 +1,459 @@
+mod auth;
+mod dashboard;
+mod graphql;
+mod grpc;
+mod sse;
+mod ws;
+
+pub use auth::Auth;
+pub use grpc::serve_grpc;
+
+use crate::config::ServerConfig;
+use crate::events::Event;
+use crate::health::{Health, Report};
+use crate::interval::interval_duration;
+use crate::metrics::Metrics;
+use crate::portfolio::Portfolio;
+use crate::state::SharedState;
+use crate::stats::market_stats_by_interval;
+use crate::store::{AlertQuery, CandleRange, Store};
+use crate::KlineData;
+use anyhow::{Context, Result};
+use async_graphql_axum::GraphQL;
+use axum::extract::{Path, Query, State};
+use axum::http::{header, HeaderValue, Method, StatusCode};
+use axum::middleware;
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::{Extension, Json, Router};
+use axum_server::tls_rustls::RustlsConfig;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
+use tokio::sync::broadcast;
+use tower_http::cors::{AllowOrigin, CorsLayer};
+use tracing::info;
+
+const DEFAULT_LIMIT: usize = 100;
+const MAX_LIMIT: usize = 1000;
+
+#[derive(Clone)]
+struct AppState {
+    market: SharedState,
+    store: Arc<Store>,
+    /// Live events, for streaming to WebSocket clients.
+    events: broadcast::Sender<Event>,
+    health: Health,
+    metrics: Metrics,
+    auth: Auth,
+    portfolio: Arc<Portfolio>,
+}
+
+/// An error response with a JSON `{"error": ...}` body.
+struct ApiError(StatusCode, String);
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.0, Json(json!({ "error": self.1 }))).into_response()
+    }
+}
+
+impl From<anyhow::Error> for ApiError {
+    fn from(e: anyhow::Error) -> Self {
+        Self(StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+    }
+}
+
+/// Serves the HTTP API until the listener fails.
+#[allow(clippy::too_many_arguments)]
+pub async fn serve(
+    config: &ServerConfig,
+    market: SharedState,
+    store: Arc<Store>,
+    events: broadcast::Sender<Event>,
+    health: Health,
+    metrics: Metrics,
+    auth: Auth,
+    portfolio: Portfolio,
+) -> Result<()> {
+    let state = AppState {
+        market,
+        store,
+        events,
+        health,
+        metrics,
+        auth,
+        portfolio: Arc::new(portfolio),
+    };
+    let schema = graphql::schema(state.clone());
+    let api = Router::new()
+        .route("/klines/{symbol}/{interval}", get(klines))
+        .route("/summary", get(summary))
+        .route("/snapshot", get(snapshot))
+        .route("/portfolio", get(portfolio_analysis))
+        .route("/alerts", get(alerts))
+        .route("/sinks", get(sinks))
+        .route("/metrics", get(prometheus))
+        .route("/ws", get(ws::handler))
+        .route("/events", get(sse::handler))
+        .route("/signals", get(sse::signals))
+        .route(
+            "/graphql",
+            get(graphql::graphiql).post_service(GraphQL::new(schema.clone())),
+        )
+        .route("/graphql/ws", get(graphql::subscriptions))
+        .layer(Extension(schema))
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            auth::require_token,
+        ));
+    // The dashboard's assets hold no data, and orchestrators probing health
+    // rarely carry credentials.
+    let app = Router::new()
+        .route("/", get(dashboard::index))
+        .route("/dashboard/app.js", get(dashboard::script))
+        .route("/dashboard/style.css", get(dashboard::style))
+        .route("/healthz", get(healthz))
+        .route("/readyz", get(readyz))
+        .merge(api)
+        .with_state(state);
+    let app = match cors(&config.cors_origins)? {
+        Some(cors) => app.layer(cors),
+        None => app,
+    };
+
+    if let Some(tls) = &config.tls {
+        // rustls needs a crypto provider chosen before any config is built.
+        let _ = rustls::crypto::ring::default_provider().install_default();
+        let rustls = RustlsConfig::from_pem_file(&tls.cert, &tls.key)
+            .await
+            .with_context(|| {
+                format!(
+                    "Failed to load TLS certificate {} and key {}",
+                    tls.cert.display(),
+                    tls.key.display()
+                )
+            })?;
+        info!("HTTPS API listening on {}", config.listen);
+        axum_server::bind_rustls(config.listen, rustls)
+            .serve(app.into_make_service())
+            .await?;
+        return Ok(());
+    }
+
+    let listener = tokio::net::TcpListener::bind(config.listen).await?;
+    info!("HTTP API listening on {}", config.listen);
+    axum::serve(listener, app).await?;
+    Ok(())
+}
+
+/// Lets browsers on the given origins call the API. Preflight requests are
+/// answered before authentication, as browsers send them without tokens.
+fn cors(origins: &[String]) -> Result<Option<CorsLayer>> {
+    if origins.is_empty() {
+        return Ok(None);
+    }
+    let allow_origin = if origins.iter().any(|origin| origin == "*") {
+        AllowOrigin::any()
+    } else {
+        let origins = origins
+            .iter()
+            .map(|origin| {
+                HeaderValue::from_str(origin)
+                    .with_context(|| format!("Invalid CORS origin '{}'", origin))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        AllowOrigin::list(origins)
+    };
+    Ok(Some(
+        CorsLayer::new()
+            .allow_origin(allow_origin)
+            .allow_methods([Method::GET, Method::POST])
+            .allow_headers([
+                header::AUTHORIZATION,
+                header::CONTENT_TYPE,
+                header::HeaderName::from_static("x-api-key"),
+            ]),
+    ))
+}
+
+#[derive(Deserialize)]
+struct KlinesQuery {
+    limit: Option<usize>,
+    /// How many of the newest matching candles to skip, for paging back.
+    #[serde(default)]
+    offset: usize,
+    /// Only candles starting at or after this time.
+    from: Option<String>,
+    /// Only candles starting before this time.
+    to: Option<String>,
+    /// Comma-separated fields to return, e.g. `interval_start,close`.
+    fields: Option<String>,
+}
+
+/// A page of a series' candles, oldest first: the latest `limit` by default.
+async fn klines(
+    State(state): State<AppState>,
+    Path((symbol, interval)): Path<(String, String)>,
+    Query(query): Query<KlinesQuery>,
+) -> Result<Json<Value>, ApiError> {
+    let symbol = symbol.to_lowercase();
+    let range = CandleRange {
+        from: query.from.as_deref().map(time_param).transpose()?,
+        to: query.to.as_deref().map(time_param).transpose()?,
+        offset: query.offset,
+        limit: query.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT),
+    };
+    let candles = recent_candles(&state.market, &state.store, &symbol, &interval, range).await?;
+    if candles.is_empty() {
+        return Err(ApiError(
+            StatusCode::NOT_FOUND,
+            format!("No candles for {} {}", symbol, interval),
+        ));
+    }
+    let candles: Vec<Value> = candles.iter().map(KlineData::to_json).collect();
+    let Some(fields) = query.fields else {
+        return Ok(Json(candles.into()));
+    };
+    let fields: Vec<&str> = fields.split(',').map(str::trim).collect();
+    if let Some(unknown) = fields
+        .iter()
+        .find(|field| candles[0].get(**field).is_none())
+    {
+        return Err(ApiError(
+            StatusCode::BAD_REQUEST,
+            format!("Unknown field '{}'", unknown),
+        ));
+    }
+    Ok(Json(
+        candles
+            .into_iter()
+            .map(|candle| {
+                fields
+                    .iter()
+                    .map(|field| (field.to_string(), candle[*field].clone()))
+                    .collect::<serde_json::Map<_, _>>()
+                    .into()
+            })
+            .collect::<Vec<Value>>()
+            .into(),
+    ))
+}
+
+/// A page of a series' candles, oldest first. Candles that have rolled out
+/// of the in-memory history are read from the store.
+async fn recent_candles(
+    market: &SharedState,
+    store: &Arc<Store>,
+    symbol: &str,
+    interval: &str,
+    range: CandleRange,
+) -> Result<Vec<KlineData>> {
+    // Each source's newest `offset + limit` candles in range are enough to
+    // find the page among both.
+    let needed = range.offset + range.limit;
+    let recent: Vec<KlineData> = market
+        .history()
+        .get(&(symbol.into(), interval.into()))
+        .map(|history| {
+            history
+                .iter()
+                .rev()
+                .filter(|kline| range.contains(kline.interval_start))
+                .take(needed)
+                .cloned()
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let mut candles: BTreeMap<_, KlineData> = BTreeMap::new();
+    if recent.len() < needed {
+        let store = store.clone();
+        let (symbol, interval) = (symbol.to_string(), interval.to_string());
+        let stored_range = CandleRange {
+            offset: 0,
+            limit: needed,
+            ..range
+        };
+        let stored =
+            tokio::task::spawn_blocking(move || store.candles(&symbol, &interval, &stored_range))
+                .await??;
+        candles.extend(
+            stored
+                .into_iter()
+                .map(|kline| (kline.interval_start, kline)),
+        );
+    }
+    candles.extend(
+        recent
+            .into_iter()
+            .map(|kline| (kline.interval_start, kline)),
+    );
+
+    let skip = candles.len().saturating_sub(needed);
+    Ok(candles.into_values().skip(skip).take(range.limit).collect())
+}
+
+/// Candles to send a streaming client from history before it goes live, so
+/// one reconnecting misses nothing: the last `count` of each series, or all
+/// since `since`, capped at [`MAX_LIMIT`] per series.
+#[derive(Debug, Clone, Copy)]
+struct Replay {
+    count: Option<usize>,
+    since: Option<DateTime<Utc>>,
+}
+
+impl Replay {
+    /// The replay asked for, if any.
+    fn new(count: Option<usize>, since: Option<DateTime<Utc>>) -> Option<Self> {
+        (count.is_some() || since.is_some()).then_some(Self { count, since })
+    }
+
+    /// The replayed candles of every series held in memory or stored that
+    /// `wanted` accepts, oldest first.
+    async fn candles(
+        &self,
+        market: &SharedState,
+        store: &Arc<Store>,
+        wanted: impl Fn(&str, &str) -> bool,
+    ) -> Result<Vec<KlineData>> {
+        let stored = {
+            let store = store.clone();
+            tokio::task::spawn_blocking(move || store.series()).await??
+        };
+        let mut series: BTreeSet<(String, String)> = market
+            .history()
+            .keys()
+            .map(|(symbol, interval)| (symbol.to_string(), interval.to_string()))
+            .collect();
+        series.extend(stored);
+        series.retain(|(symbol, interval)| wanted(symbol, interval));
+
+        let range = CandleRange {
+            from: self.since,
+            to: None,
+            offset: 0,
+            limit: self.count.unwrap_or(MAX_LIMIT).clamp(1, MAX_LIMIT),
+        };
+        let mut candles = Vec::new();
+        for (symbol, interval) in series {
+            candles.extend(recent_candles(market, store, &symbol, &interval, range).await?);
+        }
+        candles.sort_by_key(|kline| kline.interval_start);
+        Ok(candles)
+    }
+}
+
+/// Parses a time given as RFC 3339, Unix milliseconds, or an age counted
+/// back from now such as `12h`.
+fn parse_time(text: &str) -> Option<DateTime<Utc>> {
+    if let Ok(millis) = text.parse::<i64>() {
+        return DateTime::from_timestamp_millis(millis);
+    }
+    if let Ok(time) = DateTime::parse_from_rfc3339(text) {
+        return Some(time.with_timezone(&Utc));
+    }
+    interval_duration(text).map(|age| Utc::now() - age)
+}
+
+fn time_param(text: &str) -> Result<DateTime<Utc>, ApiError> {
+    parse_time(text)
+        .ok_or_else(|| ApiError(StatusCode::BAD_REQUEST, format!("Invalid time '{}'", text)))
+}
+
+/// Session statistics per symbol and market statistics per interval.
+async fn summary(State(state): State<AppState>) -> Json<Value> {
+    let sessions = state.market.sessions_json();
+    let markets: Vec<Value> = market_stats_by_interval(&state.market.latest_klines())
+        .into_iter()
+        .map(|(interval, stats)| {
+            json!({
+                "interval": interval,
+                "count": stats.count,
+                "mean_change": stats.mean_change,
+                "weighted_change": stats.weighted_change,
+            })
+        })
+        .collect();
+
+    Json(json!({ "sessions": sessions, "markets": markets }))
+}
+
+/// The holdings marked to market, with their exposure, concentration and
+/// risk.
+async fn portfolio_analysis(State(state): State<AppState>) -> Json<Value> {
+    Json(state.portfolio.analyze(&state.market).to_json())
+}
+
+/// Everything held in memory, for debugging and for clients that poll.
+async fn snapshot(State(state): State<AppState>) -> Json<Value> {
+    Json(state.market.snapshot())
+}
+
+#[derive(Deserialize)]
+struct AlertsQuery {
+    symbol: Option<String>,
+    rule: Option<String>,
+    /// How far back to look, e.g. `12h`, or a time.
+    since: Option<String>,
+    until: Option<String>,
+    #[serde(default)]
+    offset: usize,
+    limit: Option<usize>,
+}
+
+/// Recorded alerts and their deliveries, newest first.
+async fn alerts(
+    State(state): State<AppState>,
+    Query(query): Query<AlertsQuery>,
+) -> Result<Json<Value>, ApiError> {
+    let query = AlertQuery {
+        symbol: query.symbol.map(|symbol| symbol.to_lowercase()),
+        rule: query.rule,
+        since: query.since.as_deref().map(time_param).transpose()?,
+        until: query.until.as_deref().map(time_param).transpose()?,
+        offset: query.offset,
+        limit: query.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT),
+    };
+    let store = state.store.clone();
+    let records = tokio::task::spawn_blocking(move || store.alerts(&query))
+        .await
+        .map_err(anyhow::Error::from)??;
+    Ok(Json(
+        records.iter().map(|record| record.to_json()).collect(),
+    ))
+}
+
+/// Liveness: 503 once a stream has died or gone quiet.
+async fn healthz(State(state): State<AppState>) -> Response {
+    health_response(state.health.liveness())
+}
+
+/// Readiness: 503 until every stream is connected and delivering data.
+async fn readyz(State(state): State<AppState>) -> Response {
+    health_response(state.health.readiness())
+}
+
+/// The latest delivery of every sink, with its error, if any.
+async fn sinks(State(state): State<AppState>) -> Json<Vec<Value>> {
+    Json(state.health.sinks())
+}
+
+fn health_response(report: Report) -> Response {
+    let status = if report.ok {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (status, Json(report.body)).into_response()
+}
+
+/// Per-stream metrics for Prometheus to scrape.
+async fn prometheus(State(state): State<AppState>) -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        state.metrics.to_prometheus(),
+    )
+}
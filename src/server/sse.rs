@@ -0,0 +1,110 @@
+use super::auth::Client;
+use super::ws::{self, Streams, Subscription};
+use super::{ApiError, AppState};
+use crate::events::Event;
+use crate::KlineData;
+use axum::extract::{Query, State};
+use axum::response::sse::{self, KeepAlive, Sse};
+use axum::Extension;
+use serde::Deserialize;
+use serde_json::json;
+use std::convert::Infallible;
+use tokio_stream::wrappers::errors::BroadcastStreamRecvError;
+use tokio_stream::wrappers::BroadcastStream;
+use tokio_stream::{Stream, StreamExt};
+
+/// Streams kline updates for the streams given in the query string as
+/// Server-Sent Events, for clients that would rather not speak WebSocket.
+/// Without any symbols, every stream is sent. Replayed candles come first,
+/// followed by a `replayed` event.
+pub(super) async fn handler(
+    State(state): State<AppState>,
+    Extension(client): Extension<Client>,
+    Query(streams): Query<Streams>,
+) -> Result<Sse<impl Stream<Item = Result<sse::Event, Infallible>>>, ApiError> {
+    let lease = ws::subscribe(&client)?;
+    let replay = streams.replay()?;
+    let mut subscription = Subscription::default();
+    subscription.add(streams);
+    // Subscribed before replaying, so nothing falls between the two.
+    let live = BroadcastStream::new(state.events.subscribe());
+
+    let mut replayed = Vec::new();
+    if let Some(replay) = replay {
+        let wanted = |symbol: &str, interval: &str| {
+            subscription.is_empty() || subscription.contains(symbol, interval)
+        };
+        let candles = replay.candles(&state.market, &state.store, wanted).await?;
+        replayed.extend(candles.iter().map(kline_event));
+        replayed.push(
+            sse::Event::default()
+                .event("replayed")
+                .data(json!({ "count": candles.len() }).to_string()),
+        );
+    }
+
+    let events = live.filter_map(move |event| match event {
+        Ok(Event::Kline(kline)) if subscription.is_empty() || subscription.matches(&kline) => {
+            // Held for as long as the client stays connected.
+            let _ = &lease;
+            Some(kline_event(&kline))
+        }
+        Ok(_) => None,
+        Err(BroadcastStreamRecvError::Lagged(skipped)) => Some(
+            sse::Event::default()
+                .event("lagged")
+                .data(json!({ "skipped": skipped }).to_string()),
+        ),
+    });
+    let events = tokio_stream::iter(replayed).chain(events).map(Ok);
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
+
+fn kline_event(kline: &KlineData) -> sse::Event {
+    sse::Event::default()
+        .event("kline")
+        .data(kline.to_json().to_string())
+}
+
+/// Filters for the signal stream; an empty list matches everything.
+#[derive(Debug, Default, Deserialize)]
+pub(super) struct Signals {
+    #[serde(default, deserialize_with = "ws::comma_separated")]
+    symbols: Vec<String>,
+    #[serde(default, deserialize_with = "ws::comma_separated")]
+    strategies: Vec<String>,
+}
+
+/// Streams strategy signals as Server-Sent Events, apart from market data.
+pub(super) async fn signals(
+    State(state): State<AppState>,
+    Extension(client): Extension<Client>,
+    Query(filter): Query<Signals>,
+) -> Result<Sse<impl Stream<Item = Result<sse::Event, Infallible>>>, ApiError> {
+    let lease = ws::subscribe(&client)?;
+    let symbols: Vec<String> = filter.symbols.iter().map(|s| s.to_lowercase()).collect();
+    let events = BroadcastStream::new(state.events.subscribe()).filter_map(move |event| {
+        match event {
+            Ok(Event::Signal(signal))
+                if (symbols.is_empty() || symbols.contains(&signal.symbol))
+                    && (filter.strategies.is_empty()
+                        || filter.strategies.contains(&signal.strategy)) =>
+            {
+                // Held for as long as the client stays connected.
+                let _ = &lease;
+                Some(
+                    sse::Event::default()
+                        .event("signal")
+                        .data(signal.to_json().to_string()),
+                )
+            }
+            Ok(_) => None,
+            Err(BroadcastStreamRecvError::Lagged(skipped)) => Some(
+                sse::Event::default()
+                    .event("lagged")
+                    .data(json!({ "skipped": skipped }).to_string()),
+            ),
+        }
+    });
+    Ok(Sse::new(events.map(Ok)).keep_alive(KeepAlive::default()))
+}
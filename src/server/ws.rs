@@ -0,0 +1,240 @@
+use super::auth::{Client, Lease};
+use super::{time_param, ApiError, AppState, Replay};
+use crate::events::Event;
+use crate::KlineData;
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::response::Response;
+use axum::Extension;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashSet;
+use tokio::sync::broadcast::error::RecvError;
+use tracing::debug;
+
+/// Symbols and intervals to add to or remove from a subscription, given as
+/// comma-separated lists in the query string or as JSON arrays in messages.
+/// An empty list of intervals means every interval.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub(super) struct Streams {
+    #[serde(default, deserialize_with = "comma_separated")]
+    symbols: Vec<String>,
+    #[serde(default, deserialize_with = "comma_separated")]
+    intervals: Vec<String>,
+    /// How many of each series' latest candles to send before going live.
+    replay: Option<usize>,
+    /// Send each series' candles since this time before going live.
+    since: Option<String>,
+}
+
+impl Streams {
+    /// The history to replay when subscribing to these streams, if asked
+    /// for.
+    pub(super) fn replay(&self) -> Result<Option<Replay>, ApiError> {
+        let since = self.since.as_deref().map(time_param).transpose()?;
+        Ok(Replay::new(self.replay, since))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+enum ClientMessage {
+    Subscribe(Streams),
+    Unsubscribe(Streams),
+}
+
+/// The (symbol, interval) pairs a client receives; `None` matches any
+/// interval.
+#[derive(Debug, Clone, Default)]
+pub(super) struct Subscription(HashSet<(String, Option<String>)>);
+
+impl Subscription {
+    fn keys(streams: Streams) -> impl Iterator<Item = (String, Option<String>)> {
+        let intervals: Vec<Option<String>> = if streams.intervals.is_empty() {
+            vec![None]
+        } else {
+            streams.intervals.into_iter().map(Some).collect()
+        };
+        streams.symbols.into_iter().flat_map(move |symbol| {
+            let symbol = symbol.to_lowercase();
+            intervals
+                .clone()
+                .into_iter()
+                .map(move |interval| (symbol.clone(), interval))
+        })
+    }
+
+    pub(super) fn add(&mut self, streams: Streams) {
+        self.0.extend(Self::keys(streams));
+    }
+
+    /// Removes the given streams; without intervals, drops the symbols
+    /// entirely.
+    fn remove(&mut self, streams: Streams) {
+        if streams.intervals.is_empty() {
+            let symbols: HashSet<String> =
+                streams.symbols.iter().map(|s| s.to_lowercase()).collect();
+            self.0.retain(|(symbol, _)| !symbols.contains(symbol));
+            return;
+        }
+        for key in Self::keys(streams) {
+            self.0.remove(&key);
+        }
+    }
+
+    pub(super) fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub(super) fn matches(&self, kline: &KlineData) -> bool {
+        self.contains(&kline.symbol, &kline.interval)
+    }
+
+    pub(super) fn contains(&self, symbol: &str, interval: &str) -> bool {
+        self.0
+            .contains(&(symbol.to_string(), Some(interval.to_string())))
+            || self.0.contains(&(symbol.to_string(), None))
+    }
+}
+
+/// Upgrades to a WebSocket streaming kline updates for the subscribed
+/// streams. The initial subscription may be given in the query string.
+pub(super) async fn handler(
+    State(state): State<AppState>,
+    Extension(client): Extension<Client>,
+    Query(streams): Query<Streams>,
+    upgrade: WebSocketUpgrade,
+) -> Result<Response, ApiError> {
+    let lease = subscribe(&client)?;
+    let replay = streams.replay()?;
+    let mut subscription = Subscription::default();
+    subscription.add(streams);
+    let replay = replay.map(|replay| (replay, subscription.clone()));
+    Ok(upgrade.on_upgrade(move |socket| serve_client(socket, state, subscription, replay, lease)))
+}
+
+/// Takes one of the client's subscriptions for a streaming connection.
+pub(super) fn subscribe(client: &Client) -> Result<Lease, ApiError> {
+    client
+        .subscribe()
+        .map_err(|e| ApiError(StatusCode::TOO_MANY_REQUESTS, e.to_string()))
+}
+
+async fn serve_client(
+    mut socket: WebSocket,
+    state: AppState,
+    mut subscription: Subscription,
+    replay: Option<(Replay, Subscription)>,
+    _lease: Lease,
+) {
+    // Subscribed before replaying, so nothing falls between the two.
+    let mut events = state.events.subscribe();
+    if let Some((replay, streams)) = replay {
+        if send_replay(&mut socket, &state, replay, &streams)
+            .await
+            .is_err()
+        {
+            return;
+        }
+    }
+    loop {
+        let reply = tokio::select! {
+            message = socket.recv() => match message {
+                Some(Ok(Message::Text(text))) => {
+                    let result = match serde_json::from_str::<ClientMessage>(&text) {
+                        Ok(ClientMessage::Subscribe(streams)) => match streams.replay() {
+                            Ok(Some(replay)) => {
+                                // Only the streams just added are replayed.
+                                let mut added = Subscription::default();
+                                added.add(streams.clone());
+                                subscription.add(streams);
+                                send_replay(&mut socket, &state, replay, &added).await
+                            }
+                            Ok(None) => {
+                                subscription.add(streams);
+                                Ok(())
+                            }
+                            Err(ApiError(_, e)) => send_error(&mut socket, e).await,
+                        },
+                        Ok(ClientMessage::Unsubscribe(streams)) => {
+                            subscription.remove(streams);
+                            Ok(())
+                        }
+                        Err(e) => send_error(&mut socket, e.to_string()).await,
+                    };
+                    if result.is_err() {
+                        break;
+                    }
+                    continue;
+                }
+                Some(Ok(_)) => continue,
+                Some(Err(_)) | None => break,
+            },
+            event = events.recv() => match event {
+                Ok(Event::Kline(kline)) if subscription.matches(&kline) => {
+                    json!({ "type": "kline", "data": kline.to_json() })
+                }
+                Ok(_) => continue,
+                Err(RecvError::Lagged(skipped)) => json!({ "type": "lagged", "skipped": skipped }),
+                Err(RecvError::Closed) => break,
+            },
+        };
+        if socket.send(text_message(&reply)).await.is_err() {
+            break;
+        }
+    }
+    debug!("WebSocket client disconnected");
+}
+
+/// Sends the replayed candles, then a `replayed` message marking where the
+/// live updates begin.
+async fn send_replay(
+    socket: &mut WebSocket,
+    state: &AppState,
+    replay: Replay,
+    streams: &Subscription,
+) -> Result<(), axum::Error> {
+    let wanted = |symbol: &str, interval: &str| streams.contains(symbol, interval);
+    let candles = match replay.candles(&state.market, &state.store, wanted).await {
+        Ok(candles) => candles,
+        Err(e) => return send_error(socket, format!("Replay failed: {}", e)).await,
+    };
+    for kline in &candles {
+        let message = json!({ "type": "kline", "data": kline.to_json() });
+        socket.send(text_message(&message)).await?;
+    }
+    let done = json!({ "type": "replayed", "count": candles.len() });
+    socket.send(text_message(&done)).await
+}
+
+async fn send_error(socket: &mut WebSocket, message: String) -> Result<(), axum::Error> {
+    let error = json!({ "type": "error", "message": message });
+    socket.send(text_message(&error)).await
+}
+
+fn text_message(value: &Value) -> Message {
+    Message::Text(value.to_string().into())
+}
+
+/// Accepts either a comma-separated string or a list of strings.
+pub(super) fn comma_separated<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum List {
+        Joined(String),
+        Items(Vec<String>),
+    }
+    Ok(match List::deserialize(deserializer)? {
+        List::Joined(joined) => joined
+            .split(',')
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+            .map(str::to_string)
+            .collect(),
+        List::Items(items) => items,
+    })
+}
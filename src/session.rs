@@ -0,0 +1,93 @@
+use crate::interval::interval_duration;
+use crate::KlineData;
+use chrono::{DateTime, Utc};
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+/// Running statistics for one symbol since the tracker started, taken from
+/// one interval's candles so that highs and lows of different intervals are
+/// never compared.
+#[derive(Debug, Clone)]
+pub struct SessionStats {
+    pub started: DateTime<Utc>,
+    /// The shortest interval the symbol has been seen on, which the session
+    /// follows.
+    pub interval: Arc<str>,
+    pub first_price: f64,
+    pub last_price: f64,
+    pub high: f64,
+    pub low: f64,
+    /// Largest fall from a session high to a later low, as a percentage.
+    pub max_drawdown_percent: f64,
+}
+
+impl SessionStats {
+    /// Starts a session at `started` from its first update. Only its close
+    /// is known to be from within the session, so it seeds every price.
+    pub fn new(kline: &KlineData, started: DateTime<Utc>) -> Self {
+        Self {
+            started,
+            interval: kline.interval.clone(),
+            first_price: kline.close,
+            last_price: kline.close,
+            high: kline.close,
+            low: kline.close,
+            max_drawdown_percent: 0.0,
+        }
+    }
+
+    /// Folds in an update on the session's interval. Updates on a longer
+    /// interval are ignored, and one on a shorter interval starts the
+    /// session over on it.
+    pub fn update(&mut self, kline: &KlineData) {
+        if kline.interval != self.interval {
+            let shorter = match (
+                interval_duration(&kline.interval),
+                interval_duration(&self.interval),
+            ) {
+                (Some(new), Some(current)) => new < current,
+                (new, current) => new.is_some() && current.is_none(),
+            };
+            if shorter {
+                *self = Self::new(kline, self.started);
+            }
+            return;
+        }
+        // A candle opened before the session may have reached its high or
+        // low before it, too.
+        let (high, low) = if kline.interval_start < self.started {
+            (kline.close, kline.close)
+        } else {
+            (kline.high, kline.low)
+        };
+        // The order of high and low within a candle is unknown, so the
+        // drawdown is measured from the peak reached before this update.
+        if self.high > 0.0 {
+            let drawdown = (self.high - low) / self.high * 100.0;
+            self.max_drawdown_percent = self.max_drawdown_percent.max(drawdown);
+        }
+        self.high = self.high.max(high);
+        self.low = self.low.min(low);
+        self.last_price = kline.close;
+    }
+
+    pub fn change_percent(&self) -> f64 {
+        if self.first_price == 0.0 {
+            return 0.0;
+        }
+        (self.last_price - self.first_price) / self.first_price * 100.0
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "started": self.started.to_rfc3339(),
+            "interval": self.interval,
+            "first_price": self.first_price,
+            "last_price": self.last_price,
+            "change_percent": self.change_percent(),
+            "high": self.high,
+            "low": self.low,
+            "max_drawdown_percent": self.max_drawdown_percent,
+        })
+    }
+}
@@ -0,0 +1,88 @@
+//! Partitions symbols across the processor workers, so heavy indicator work
+//! for one symbol cannot hold up the others.
+
+use crate::arbitrage::split_venue;
+use crate::config::{QueueConfig, RatioConfig};
+use crate::metrics::Metrics;
+use crate::queue::{self, KlineReceiver, KlineSender};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+
+/// Assigns each symbol to one of `count` workers by hash. The legs of a
+/// ratio always share a worker, since the ratio is computed from both, and
+/// so do a symbol's prices on every venue and the spreads between them.
+#[derive(Debug, Clone)]
+pub struct Shards {
+    count: usize,
+    /// Links from a symbol towards the representative of its ratio group.
+    links: Arc<HashMap<String, String>>,
+}
+
+impl Shards {
+    pub fn new(count: usize, ratios: &[RatioConfig]) -> Self {
+        let mut links = HashMap::new();
+        for ratio in ratios {
+            let base = group(&links, &ratio.base).to_string();
+            let quote = group(&links, &ratio.quote).to_string();
+            if base != quote {
+                links.insert(quote, base);
+            }
+        }
+        Self {
+            count: count.max(1),
+            links: Arc::new(links),
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// The worker `symbol` is processed by.
+    pub fn of(&self, symbol: &str) -> usize {
+        if self.count == 1 {
+            return 0;
+        }
+        let symbol = split_venue(symbol).1;
+        let mut hasher = DefaultHasher::new();
+        group(&self.links, symbol).hash(&mut hasher);
+        (hasher.finish() % self.count as u64) as usize
+    }
+}
+
+fn group<'a>(links: &'a HashMap<String, String>, symbol: &'a str) -> &'a str {
+    let mut current = symbol;
+    while let Some(next) = links.get(current) {
+        current = next;
+    }
+    current
+}
+
+/// Sends each symbol's updates to its worker's queue.
+#[derive(Clone)]
+pub struct Router {
+    shards: Shards,
+    senders: Vec<KlineSender>,
+}
+
+impl Router {
+    /// A router over one queue per shard, returning the receiving end of
+    /// each in shard order. The queues are reported as the channels
+    /// `klines-0`, `klines-1` and so on.
+    pub fn new(
+        config: &QueueConfig,
+        shards: Shards,
+        metrics: Metrics,
+    ) -> (Self, Vec<KlineReceiver>) {
+        let (senders, receivers) = (0..shards.count())
+            .map(|shard| queue::channel(&format!("klines-{}", shard), config, metrics.clone()))
+            .unzip();
+        (Self { shards, senders }, receivers)
+    }
+
+    pub fn sender(&self, symbol: &str) -> KlineSender {
+        self.senders[self.shards.of(symbol)].clone()
+    }
+}
@@ -0,0 +1,162 @@
+//! The actors carrying alerts, incidents and candles out of the tracker:
+//! notifiers, pagers and push endpoints.
+
+use crate::actor::{InFlight, Mailbox, Supervisor};
+use crate::config::Config;
+use crate::events::Event;
+use crate::health::Health;
+use crate::metrics::Metrics;
+use crate::notify::{self, Notification, Notifier};
+use crate::ops::{self, OpsEvent};
+use crate::push;
+use crate::store::Store;
+use anyhow::Result;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc};
+use tokio_util::sync::CancellationToken;
+use tracing::{error, warn};
+
+/// Starts the sink actors, and starts them again when their settings
+/// change. Their mailboxes are kept across restarts.
+#[derive(Clone)]
+pub struct Sinks {
+    supervisor: Supervisor,
+    notifications: Mailbox<Notification>,
+    incidents: Mailbox<OpsEvent>,
+    ops_tx: mpsc::Sender<OpsEvent>,
+    events: broadcast::Sender<Event>,
+    store: Arc<Store>,
+    health: Health,
+    metrics: Metrics,
+    /// The notifiers currently delivering, for flushing.
+    notifiers: Arc<Mutex<Vec<Arc<dyn Notifier>>>>,
+    /// Deliveries and pushes not yet finished.
+    in_flight: InFlight,
+    /// Set on shutdown, which stops the pushers taking new candles.
+    stopping: CancellationToken,
+}
+
+impl Sinks {
+    /// `ops_tx` lets the notifier actor report failing notifiers as
+    /// incidents.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        supervisor: Supervisor,
+        notifications: Mailbox<Notification>,
+        incidents: Mailbox<OpsEvent>,
+        ops_tx: mpsc::Sender<OpsEvent>,
+        events: broadcast::Sender<Event>,
+        store: Arc<Store>,
+        health: Health,
+        metrics: Metrics,
+    ) -> Self {
+        Self {
+            supervisor,
+            notifications,
+            incidents,
+            ops_tx,
+            events,
+            store,
+            health,
+            metrics,
+            notifiers: Arc::default(),
+            in_flight: InFlight::default(),
+            stopping: CancellationToken::new(),
+        }
+    }
+
+    /// Starts every sink with its settings from `config`, replacing any
+    /// already running. Nothing is replaced unless every sink can be built.
+    pub fn start(&self, config: &Config) -> Result<()> {
+        let notifiers = notify::build_notifiers(&config.notifiers)?;
+        let pagers = ops::build_pagers(&config.ops.pagers)?;
+        let pushers = push::build_pushers(&config.push)?;
+
+        let replaced = std::mem::replace(&mut *self.lock_notifiers(), notifiers.clone());
+        // Replaced notifiers may hold back batched notifications.
+        tokio::spawn(async move {
+            for notifier in replaced {
+                if let Err(e) = notifier.flush().await {
+                    error!("Failed to flush {}: {}", notifier.name(), e);
+                }
+            }
+        });
+
+        let this = self.clone();
+        self.supervisor.spawn("notifiers", move || {
+            notify::run_notifiers(
+                this.notifications.clone(),
+                notifiers.clone(),
+                this.ops_tx.clone(),
+                this.store.clone(),
+                this.health.clone(),
+                this.metrics.clone(),
+                this.in_flight.clone(),
+                this.supervisor.panics().clone(),
+            )
+        });
+        let (incidents, in_flight) = (self.incidents.clone(), self.in_flight.clone());
+        let panics = self.supervisor.panics().without_incidents();
+        self.supervisor.spawn("pagers", move || {
+            ops::run_pagers(
+                incidents.clone(),
+                pagers.clone(),
+                in_flight.clone(),
+                panics.clone(),
+            )
+        });
+        let this = self.clone();
+        self.supervisor.spawn("push", move || {
+            push::run_pushers(
+                pushers.clone(),
+                this.events.clone(),
+                this.health.clone(),
+                this.metrics.clone(),
+                this.stopping.clone(),
+                this.in_flight.clone(),
+            )
+        });
+        Ok(())
+    }
+
+    fn lock_notifiers(&self) -> MutexGuard<'_, Vec<Arc<dyn Notifier>>> {
+        self.notifiers.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn notifiers(&self) -> Vec<Arc<dyn Notifier>> {
+        self.lock_notifiers().clone()
+    }
+
+    /// Delivers what the sinks still hold before the tracker exits: stops
+    /// the pushers gathering candles, waits for queued notifications and
+    /// incidents to be taken up, then flushes every notifier until all
+    /// deliveries and pushes have gone out. Gives up after `grace`.
+    pub async fn drain(&self, grace: Duration) {
+        self.stopping.cancel();
+        let drained = async {
+            while !(self.notifications.is_empty() && self.incidents.is_empty()) {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+            }
+            // A batched delivery finishes only once its batch is flushed,
+            // and may join one after the previous flush.
+            loop {
+                for notifier in self.notifiers() {
+                    if let Err(e) = notifier.flush().await {
+                        error!("Failed to flush {}: {}", notifier.name(), e);
+                    }
+                }
+                if self.in_flight.is_idle() {
+                    break;
+                }
+                tokio::time::sleep(Duration::from_millis(50)).await;
+            }
+        };
+        if tokio::time::timeout(grace, drained).await.is_err() {
+            warn!(
+                "Sinks still delivering after {}s; exiting anyway",
+                grace.as_secs()
+            );
+        }
+    }
+}
@@ -0,0 +1,213 @@
+//! Saves the in-memory market state to disk and restores it on startup, so a
+//! restart keeps each series' history and every symbol's session stats.
+//! Indicators are not saved: each worker rebuilds them from the restored
+//! history when it starts.
+
+use crate::config::{Config, SnapshotConfig};
+use crate::session::SessionStats;
+use crate::state::{MarketState, SharedState};
+use crate::KlineData;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, error, info};
+
+#[derive(Serialize, Deserialize)]
+struct Snapshot {
+    /// Unix milliseconds.
+    taken_at: i64,
+    series: Vec<Series>,
+    sessions: Vec<Session>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Series {
+    symbol: String,
+    interval: String,
+    /// Oldest first; the last is the series' latest update.
+    candles: Vec<Candle>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Candle {
+    /// Unix milliseconds.
+    start: i64,
+    open: f64,
+    high: f64,
+    low: f64,
+    close: f64,
+    volume: f64,
+    closed: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Session {
+    symbol: String,
+    /// Unix milliseconds.
+    started: i64,
+    interval: String,
+    first_price: f64,
+    last_price: f64,
+    high: f64,
+    low: f64,
+    max_drawdown_percent: f64,
+}
+
+/// Writes `state` to `path`, replacing any earlier snapshot only once the
+/// new one is complete. Returns how many series were saved.
+pub fn save(state: &MarketState, path: &Path) -> Result<usize> {
+    let series: Vec<Series> = {
+        let history = state.history();
+        history
+            .keys()
+            .filter_map(|key| {
+                let candles: Vec<Candle> = history
+                    .get(key)?
+                    .iter()
+                    .map(|kline| Candle {
+                        start: kline.interval_start.timestamp_millis(),
+                        open: kline.open,
+                        high: kline.high,
+                        low: kline.low,
+                        close: kline.close,
+                        volume: kline.volume,
+                        closed: kline.closed,
+                    })
+                    .collect();
+                Some(Series {
+                    symbol: key.0.to_string(),
+                    interval: key.1.to_string(),
+                    candles,
+                })
+            })
+            .collect()
+    };
+    let sessions = state
+        .sorted_sessions()
+        .into_iter()
+        .map(|(symbol, session)| Session {
+            symbol: symbol.to_string(),
+            started: session.started.timestamp_millis(),
+            interval: session.interval.to_string(),
+            first_price: session.first_price,
+            last_price: session.last_price,
+            high: session.high,
+            low: session.low,
+            max_drawdown_percent: session.max_drawdown_percent,
+        })
+        .collect();
+    let saved = series.len();
+    let snapshot = Snapshot {
+        taken_at: Utc::now().timestamp_millis(),
+        series,
+        sessions,
+    };
+
+    let partial = path.with_extension("partial");
+    let file = std::fs::File::create(&partial)?;
+    serde_json::to_writer(std::io::BufWriter::new(file), &snapshot)?;
+    std::fs::rename(&partial, path)?;
+    Ok(saved)
+}
+
+/// Loads the snapshot at `path` into `state`, keeping up to `config`'s
+/// history size per series. Returns how many series were restored and when
+/// the snapshot was taken, or `None` if there is no snapshot.
+pub fn restore(
+    state: &MarketState,
+    path: &Path,
+    config: &Config,
+) -> Result<Option<(usize, DateTime<Utc>)>> {
+    let file = match std::fs::File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    let snapshot: Snapshot = serde_json::from_reader(std::io::BufReader::new(file))?;
+    let taken_at = time(snapshot.taken_at)?;
+
+    let mut history = state.history_mut();
+    for series in &snapshot.series {
+        let symbol: Arc<str> = series.symbol.as_str().into();
+        let interval: Arc<str> = series.interval.as_str().into();
+        for candle in &series.candles {
+            let kline = KlineData {
+                symbol: Arc::clone(&symbol),
+                interval: Arc::clone(&interval),
+                interval_start: time(candle.start)?,
+                open: candle.open,
+                high: candle.high,
+                low: candle.low,
+                close: candle.close,
+                volume: candle.volume,
+                closed: candle.closed,
+                timing: None,
+            };
+            history.update(
+                kline.clone(),
+                config.history_size,
+                config.cache.budget_bytes(),
+            );
+            state.latest.insert(kline.key(), kline);
+        }
+    }
+    drop(history);
+    for session in snapshot.sessions {
+        state.sessions.insert(
+            session.symbol.into(),
+            SessionStats {
+                started: time(session.started)?,
+                interval: session.interval.into(),
+                first_price: session.first_price,
+                last_price: session.last_price,
+                high: session.high,
+                low: session.low,
+                max_drawdown_percent: session.max_drawdown_percent,
+            },
+        );
+    }
+    Ok(Some((snapshot.series.len(), taken_at)))
+}
+
+fn time(millis: i64) -> Result<DateTime<Utc>> {
+    DateTime::from_timestamp_millis(millis).ok_or_else(|| anyhow!("Invalid time {}", millis))
+}
+
+/// Saves `state` every `interval_secs`, for as long as the tracker runs.
+pub async fn run(state: SharedState, config: SnapshotConfig) {
+    let mut timer = tokio::time::interval(Duration::from_secs(config.interval_secs));
+    timer.tick().await;
+    loop {
+        timer.tick().await;
+        let (state, path) = (state.clone(), config.path.clone());
+        match tokio::task::spawn_blocking(move || save(&state, &path)).await {
+            Ok(Ok(saved)) => debug!("Saved {} series to {}", saved, config.path.display()),
+            Ok(Err(e)) => error!("Failed to save snapshot {}: {}", config.path.display(), e),
+            Err(e) => error!("Snapshot task failed: {}", e),
+        }
+    }
+}
+
+/// Restores the snapshot configured in `config`, if enabled, logging what
+/// was restored. A missing or unreadable snapshot starts the tracker afresh.
+pub fn restore_configured(state: &MarketState, config: &Config) {
+    if !config.snapshot.enabled {
+        return;
+    }
+    match restore(state, &config.snapshot.path, config) {
+        Ok(Some((series, taken_at))) => info!(
+            "Restored {} series from the snapshot taken at {}",
+            series,
+            taken_at.to_rfc3339()
+        ),
+        Ok(None) => debug!("No snapshot at {}", config.snapshot.path.display()),
+        Err(e) => error!(
+            "Ignoring snapshot {}: {}",
+            config.snapshot.path.display(),
+            e
+        ),
+    }
+}
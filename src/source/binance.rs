@@ -0,0 +1,213 @@
+use super::Source;
+use crate::arbitrage::split_venue;
+use crate::health::{Health, StreamState};
+use crate::metrics::Metrics;
+use crate::{KlineData, RawKline, SeriesKey, STREAM_URL};
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use futures_util::stream::{self, BoxStream};
+use futures_util::{future, StreamExt, TryStreamExt};
+use serde_json::Value;
+use std::time::Duration;
+use tokio_tungstenite::connect_async;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, debug_span, info, warn};
+
+const KLINES_URL: &str = "https://api.binance.com/api/v3/klines";
+
+/// The most candles Binance returns for one request.
+const MAX_BACKFILL: usize = 1000;
+
+/// One Binance kline stream, live.
+pub struct WebSocketSource {
+    stream_url: String,
+    key: SeriesKey,
+    health: Health,
+    metrics: Metrics,
+    stopping: CancellationToken,
+}
+
+impl WebSocketSource {
+    /// Reports the stream's state to `health` and its traffic to `metrics`.
+    /// Once `stopping` is cancelled the connection is closed and the stream
+    /// ends.
+    pub fn new(
+        symbol: &str,
+        interval: &str,
+        health: Health,
+        metrics: Metrics,
+        stopping: CancellationToken,
+    ) -> Self {
+        Self {
+            stream_url: STREAM_URL.to_string(),
+            // Shared by every update from this connection.
+            key: (symbol.into(), interval.into()),
+            health,
+            metrics,
+            stopping,
+        }
+    }
+
+    /// Connects to `stream_url` instead of Binance.
+    pub fn with_stream_url(mut self, stream_url: &str) -> Self {
+        self.stream_url = stream_url.trim_end_matches('/').to_string();
+        self
+    }
+
+    async fn connect(self) -> Result<BoxStream<'static, Result<KlineData>>> {
+        let Self {
+            stream_url,
+            key,
+            health,
+            metrics,
+            stopping,
+        } = self;
+        let (symbol, interval) = (&key.0, &key.1);
+        let ws_url = format!(
+            "{}/ws/{}@kline_{}",
+            stream_url,
+            split_venue(symbol).1,
+            interval
+        );
+        info!(
+            "Connecting to Binance WebSocket for {} {}...",
+            symbol, interval
+        );
+        health.set_stream_state(symbol, interval, StreamState::Connecting);
+        let (ws_stream, _) = connect_async(&ws_url).await?;
+        info!("Connected to WebSocket for {} {}.", symbol, interval);
+        health.set_stream_state(symbol, interval, StreamState::Connected);
+        metrics.record_connected(&key);
+
+        let messages = stream::unfold(Some((ws_stream, stopping)), |state| async move {
+            let (mut ws_stream, stopping) = state?;
+            tokio::select! {
+                message = ws_stream.next() => Some((message?, Some((ws_stream, stopping)))),
+                _ = stopping.cancelled() => {
+                    // A close frame tells Binance the client is leaving,
+                    // rather than the connection having dropped.
+                    if let Err(e) = ws_stream.close(None).await {
+                        debug!("Failed to close WebSocket cleanly: {}", e);
+                    }
+                    None
+                }
+            }
+        });
+        let klines = messages.filter_map(move |message| {
+            let message = match message {
+                Ok(message) => message,
+                Err(e) => return future::ready(Some(Err(e.into()))),
+            };
+            // Parsed where the connection left it, without copying it.
+            let Ok(text) = message.to_text() else {
+                return future::ready(None);
+            };
+            metrics.record_message(&key, text.len());
+            let parsed = debug_span!("parse").in_scope(|| KlineData::parse(&key.0, &key.1, text));
+            future::ready(match parsed {
+                Ok(Some(kline)) => {
+                    metrics.record_parsed(&kline);
+                    health.record_message(&key);
+                    Some(Ok(kline))
+                }
+                Ok(None) => None,
+                Err(e) => {
+                    metrics.record_parse_error(&key);
+                    warn!(
+                        "Skipping unparseable message for {} {}: {}",
+                        key.0, key.1, e
+                    );
+                    None
+                }
+            })
+        });
+        Ok(klines.boxed())
+    }
+}
+
+impl Source for WebSocketSource {
+    fn name(&self) -> String {
+        format!("Binance stream {} {}", self.key.0, self.key.1)
+    }
+
+    fn klines(self: Box<Self>) -> BoxStream<'static, Result<KlineData>> {
+        stream::once(self.connect()).try_flatten().boxed()
+    }
+}
+
+/// The most recent candles of one series, fetched once over Binance's REST
+/// API, oldest first. The last is usually still in progress.
+pub struct BackfillSource {
+    key: SeriesKey,
+    limit: usize,
+}
+
+impl BackfillSource {
+    /// Fetches up to `limit` candles, at most 1000.
+    pub fn new(symbol: &str, interval: &str, limit: usize) -> Self {
+        Self {
+            key: (symbol.into(), interval.into()),
+            limit: limit.clamp(1, MAX_BACKFILL),
+        }
+    }
+
+    async fn fetch(self) -> Result<Vec<KlineData>> {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(30))
+            .build()?;
+        let rows: Vec<Vec<Value>> = client
+            .get(KLINES_URL)
+            .query(&[
+                ("symbol", self.key.0.to_uppercase()),
+                ("interval", self.key.1.to_string()),
+                ("limit", self.limit.to_string()),
+            ])
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        let now = Utc::now().timestamp_millis();
+        rows.iter()
+            .map(|row| parse_row(self.key.clone(), row, now))
+            .collect()
+    }
+}
+
+impl Source for BackfillSource {
+    fn name(&self) -> String {
+        format!("Backfill of {} {}", self.key.0, self.key.1)
+    }
+
+    fn klines(self: Box<Self>) -> BoxStream<'static, Result<KlineData>> {
+        stream::once(self.fetch())
+            .map_ok(|klines| stream::iter(klines.into_iter().map(Ok)))
+            .try_flatten()
+            .boxed()
+    }
+}
+
+/// A candle as the REST API returns it: an array of open time, open, high,
+/// low, close, volume and close time, followed by fields not read here.
+fn parse_row(key: SeriesKey, row: &[Value], now: i64) -> Result<KlineData> {
+    let text = |index: usize| {
+        row.get(index)
+            .and_then(Value::as_str)
+            .ok_or_else(|| anyhow!("Malformed kline from the REST API"))
+    };
+    let time = |index: usize| {
+        row.get(index)
+            .and_then(Value::as_i64)
+            .ok_or_else(|| anyhow!("Malformed kline from the REST API"))
+    };
+    let raw = RawKline {
+        t: time(0)?,
+        o: text(1)?,
+        h: text(2)?,
+        l: text(3)?,
+        c: text(4)?,
+        v: text(5)?,
+        x: time(6)? < now,
+    };
+    KlineData::from_raw(key, raw)
+}
@@ -0,0 +1,96 @@
+use super::Source;
+use crate::{KlineData, SeriesKey};
+use anyhow::{Context, Result};
+use futures_util::stream::{self, BoxStream};
+use futures_util::{StreamExt, TryStreamExt};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tracing::warn;
+
+type Reader = BufReader<Box<dyn AsyncRead + Send + Unpin>>;
+
+/// Messages from Binance's combined stream endpoint, one per line, read from
+/// a file or, given `-`, from stdin. Each update belongs to the series named
+/// by its stream, whether or not the config tracks it.
+pub struct CaptureSource {
+    path: PathBuf,
+}
+
+impl CaptureSource {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    fn is_stdin(&self) -> bool {
+        self.path.as_os_str() == "-"
+    }
+
+    async fn open(self) -> Result<Reader> {
+        let reader: Box<dyn AsyncRead + Send + Unpin> = if self.is_stdin() {
+            Box::new(tokio::io::stdin())
+        } else {
+            let file = tokio::fs::File::open(&self.path)
+                .await
+                .with_context(|| format!("Failed to read capture {}", self.path.display()))?;
+            Box::new(file)
+        };
+        Ok(BufReader::new(reader))
+    }
+}
+
+impl Source for CaptureSource {
+    fn name(&self) -> String {
+        if self.is_stdin() {
+            "stdin".to_string()
+        } else {
+            format!("capture {}", self.path.display())
+        }
+    }
+
+    fn klines(self: Box<Self>) -> BoxStream<'static, Result<KlineData>> {
+        let name = self.name();
+        stream::once(self.open())
+            .map_ok(move |reader| {
+                // Each series' names, shared by all of its updates, and the
+                // buffer every line is read into.
+                let state = (reader, String::new(), HashMap::<String, SeriesKey>::new());
+                let name = name.clone();
+                stream::try_unfold(state, move |(mut reader, mut line, mut series)| {
+                    let name = name.clone();
+                    async move {
+                        loop {
+                            line.clear();
+                            if reader.read_line(&mut line).await? == 0 {
+                                return Ok(None);
+                            }
+                            let message = line.trim_end_matches(['\r', '\n']);
+                            let parsed = KlineData::parse_combined(message, |stream| {
+                                if let Some(key) = series.get(stream) {
+                                    return Some(key.clone());
+                                }
+                                let (symbol, interval) = stream.split_once("@kline_")?;
+                                let key: SeriesKey = (symbol.into(), interval.into());
+                                series.insert(stream.to_string(), key.clone());
+                                Some(key)
+                            });
+                            match parsed {
+                                Ok(Some(mut kline)) => {
+                                    // Captured event times say nothing
+                                    // about the exchange's latency now.
+                                    if let Some(timing) = &mut kline.timing {
+                                        timing.exchange = None;
+                                    }
+                                    return Ok(Some((kline, (reader, line, series))));
+                                }
+                                Ok(None) => {}
+                                Err(e) => warn!("Skipping unparseable line in {}: {}", name, e),
+                            }
+                        }
+                    }
+                })
+            })
+            .try_flatten()
+            .boxed()
+    }
+}
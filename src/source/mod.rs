@@ -0,0 +1,52 @@
+//! Where kline updates come from. Every input — a live Binance stream, a
+//! REST backfill, a replayed capture, stdin or a synthetic market — is a
+//! [`Source`], and
+//! [`feed`] carries any of them into the processing pipeline the same way.
+
+mod binance;
+mod capture;
+mod synthetic;
+
+pub use binance::{BackfillSource, WebSocketSource};
+pub use capture::CaptureSource;
+pub use synthetic::SyntheticSource;
+
+use crate::queue::KlineSender;
+use crate::KlineData;
+use anyhow::Result;
+use futures_util::stream::BoxStream;
+use futures_util::StreamExt;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tracing::{debug, debug_span, Instrument};
+
+/// Produces normalized kline updates.
+pub trait Source: Send {
+    /// Identifies the source in logs.
+    fn name(&self) -> String;
+
+    /// The source's updates, in order. The stream ends once the source is
+    /// exhausted or its connection closes, or with an error it cannot
+    /// recover from; updates it merely fails to parse are skipped.
+    fn klines(self: Box<Self>) -> BoxStream<'static, Result<KlineData>>;
+}
+
+/// Sends every update from `source` to the queue `queue` picks for its
+/// symbol, until the source ends. Fails if the source fails or the
+/// processor has stopped. Returns the number of updates fed.
+pub async fn feed(source: Box<dyn Source>, queue: impl Fn(&str) -> KlineSender) -> Result<u64> {
+    let name = source.name();
+    let mut klines = source.klines();
+    let mut senders: HashMap<Arc<str>, KlineSender> = HashMap::new();
+    let mut fed = 0;
+    while let Some(kline) = klines.next().await {
+        let kline = kline?;
+        let tx = senders
+            .entry(kline.symbol.clone())
+            .or_insert_with(|| queue(&kline.symbol));
+        tx.send(kline).instrument(debug_span!("queue")).await?;
+        fed += 1;
+    }
+    debug!("{} ended after {} update(s)", name, fed);
+    Ok(fed)
+}
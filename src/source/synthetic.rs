@@ -0,0 +1,209 @@
+use super::Source;
+use crate::config::SyntheticConfig;
+use crate::interval::interval_duration;
+use crate::rng::Rng;
+use crate::KlineData;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Duration, DurationRound, Utc};
+use futures_util::stream::{self, BoxStream};
+use futures_util::StreamExt;
+use std::f64::consts::PI;
+use std::sync::Arc;
+use tokio::time::MissedTickBehavior;
+use tracing::info;
+
+/// Random-walk candles for any symbols and intervals, for demos, load
+/// testing sinks and building dashboards offline. Each symbol's price
+/// follows one geometric Brownian motion, stepped several times per
+/// candle of the shortest interval; every interval's candles are built
+/// from the same steps, so a 1h candle agrees with its 1m candles.
+pub struct SyntheticSource {
+    market: Market,
+    config: SyntheticConfig,
+}
+
+impl SyntheticSource {
+    /// Starts the walk at the current candle of the shortest interval.
+    /// Fails if an interval cannot be parsed.
+    pub fn new(symbols: &[String], intervals: &[String], config: SyntheticConfig) -> Result<Self> {
+        let mut lengths = Vec::with_capacity(intervals.len());
+        for interval in intervals {
+            let length = interval_duration(interval)
+                .filter(|length| *length > Duration::zero())
+                .ok_or_else(|| anyhow!("Invalid interval '{}'", interval))?;
+            lengths.push((Arc::<str>::from(interval.as_str()), length));
+        }
+        let shortest = lengths
+            .iter()
+            .map(|(_, length)| *length)
+            .min()
+            .ok_or_else(|| anyhow!("No intervals to generate"))?;
+        let step = shortest / config.updates_per_candle as i32;
+        let step = step.max(Duration::milliseconds(1));
+        let seed = config.seed.unwrap_or_else(|| {
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_nanos() as u64
+        });
+        info!("Synthetic market seeded with {}", seed);
+
+        let walks = symbols
+            .iter()
+            .map(|symbol| Walk {
+                price: config.prices.get(symbol).copied().unwrap_or(100.0),
+                symbol: Arc::from(symbol.as_str()),
+                series: lengths
+                    .iter()
+                    .map(|(interval, length)| Series {
+                        interval: interval.clone(),
+                        length: *length,
+                        candle: None,
+                    })
+                    .collect(),
+            })
+            .collect();
+        let steps = config.updates_per_candle as f64;
+        let market = Market {
+            walks,
+            rng: Rng::new(seed),
+            now: align(Utc::now(), shortest),
+            step,
+            drift: config.drift_pct / 100.0 / steps,
+            volatility: config.volatility_pct / 100.0 / steps.sqrt(),
+            volume: config.volume / steps,
+        };
+        Ok(Self { market, config })
+    }
+}
+
+impl Source for SyntheticSource {
+    fn name(&self) -> String {
+        "synthetic market".to_string()
+    }
+
+    fn klines(self: Box<Self>) -> BoxStream<'static, Result<KlineData>> {
+        let Self { market, config } = *self;
+        let steps = config
+            .candles
+            .map(|candles| candles.saturating_mul(config.updates_per_candle as u64));
+        // At speed 0 the pipeline's backpressure sets the pace.
+        let timer = (config.speed > 0.0)
+            .then(|| market.step.to_std().ok())
+            .flatten()
+            .map(|step| {
+                let mut timer = tokio::time::interval(step.div_f64(config.speed));
+                timer.set_missed_tick_behavior(MissedTickBehavior::Delay);
+                timer
+            });
+        stream::unfold(
+            (market, timer, 0u64),
+            move |(mut market, mut timer, taken)| async move {
+                if steps.is_some_and(|steps| taken >= steps) {
+                    return None;
+                }
+                if let Some(timer) = &mut timer {
+                    timer.tick().await;
+                }
+                let klines = market.step();
+                Some((stream::iter(klines), (market, timer, taken + 1)))
+            },
+        )
+        .flatten()
+        .map(Ok)
+        .boxed()
+    }
+}
+
+/// Every symbol's walk, stepped together from `now`.
+struct Market {
+    walks: Vec<Walk>,
+    rng: Rng,
+    /// When the next step starts.
+    now: DateTime<Utc>,
+    step: Duration,
+    /// Mean and standard deviation of one step's log return.
+    drift: f64,
+    volatility: f64,
+    /// Mean volume traded in one step.
+    volume: f64,
+}
+
+struct Walk {
+    symbol: Arc<str>,
+    price: f64,
+    series: Vec<Series>,
+}
+
+struct Series {
+    interval: Arc<str>,
+    length: Duration,
+    /// The candle being built, until the step that closes it.
+    candle: Option<KlineData>,
+}
+
+impl Market {
+    /// Moves every price one step and returns each series' updated candle.
+    fn step(&mut self) -> Vec<KlineData> {
+        let (start, end) = (self.now, self.now + self.step);
+        self.now = end;
+        let mut klines = Vec::new();
+        for walk in &mut self.walks {
+            let open = walk.price;
+            let change = self.drift + self.volatility * self.rng.gaussian();
+            let close = open * change.exp();
+            // Wicks beyond the step's open and close, a fraction of its
+            // volatility each.
+            let high = open.max(close) * (self.volatility * self.rng.uniform()).exp();
+            let low = open.min(close) / (self.volatility * self.rng.uniform()).exp();
+            // Busier when the price moves more, but `volume` on average:
+            // a standard normal's mean absolute value is √(2/π).
+            let surprise = if self.volatility > 0.0 {
+                (change - self.drift).abs() / self.volatility
+            } else {
+                (2.0 / PI).sqrt()
+            };
+            let volume = self.volume * (0.5 + self.rng.uniform()) * (1.0 + surprise)
+                / (1.0 + (2.0 / PI).sqrt());
+            walk.price = close;
+
+            for series in &mut walk.series {
+                let interval_start = align(start, series.length);
+                let candle = match series.candle.take() {
+                    Some(mut candle) if candle.interval_start == interval_start => {
+                        candle.high = candle.high.max(high);
+                        candle.low = candle.low.min(low);
+                        candle.close = close;
+                        candle.volume += volume;
+                        candle
+                    }
+                    _ => KlineData {
+                        symbol: walk.symbol.clone(),
+                        interval: series.interval.clone(),
+                        interval_start,
+                        open,
+                        high,
+                        low,
+                        close,
+                        volume,
+                        closed: false,
+                        timing: None,
+                    },
+                };
+                let closed = end >= interval_start + series.length;
+                let candle = KlineData { closed, ..candle };
+                klines.push(candle.clone());
+                if !closed {
+                    series.candle = Some(candle);
+                }
+            }
+        }
+        klines
+    }
+}
+
+/// The start of the candle of length `length` containing `time`, counting
+/// from the Unix epoch.
+fn align(time: DateTime<Utc>, length: Duration) -> DateTime<Utc> {
+    time.duration_trunc(length).unwrap_or(time)
+}
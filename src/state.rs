@@ -0,0 +1,168 @@
+use crate::halts::TradingStatus;
+use crate::history::HistoryCache;
+use crate::open_interest::OpenInterest;
+use crate::session::SessionStats;
+use crate::{arbitrage, precision, KlineData, SeriesKey};
+use chrono::Utc;
+use dashmap::DashMap;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::ops::Deref;
+use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+/// Everything the tracker currently knows about the market, keyed by
+/// (symbol, interval) or symbol.
+///
+/// Each map is concurrent on its own, so readers such as the HTTP API and
+/// the dashboard never wait on a worker for longer than one entry update.
+/// Nothing is updated atomically across maps, so a reader may see a
+/// series' latest update before its history.
+#[derive(Debug, Default)]
+pub struct MarketState {
+    /// The latest update of every series, including synthetic ratios.
+    pub latest: DashMap<SeriesKey, KlineData>,
+    history: RwLock<HistoryCache>,
+    pub sessions: DashMap<Arc<str>, SessionStats>,
+    /// Indicator values of every series as of its latest closed candle.
+    pub indicators: DashMap<SeriesKey, BTreeMap<String, Value>>,
+    /// The latest open interest of each symbol's perpetual future, if
+    /// polled.
+    pub open_interest: DashMap<Arc<str>, OpenInterest>,
+    /// Symbols found not trading, if halts are watched.
+    pub trading_status: DashMap<Arc<str>, TradingStatus>,
+    /// The decimals of each Binance symbol's tick size, if fetched.
+    pub price_decimals: DashMap<Arc<str>, usize>,
+    /// The quote asset of each Binance symbol, if fetched.
+    pub quote_assets: DashMap<Arc<str>, Arc<str>>,
+}
+
+/// Quote assets recognised by their suffix when a symbol's exchange info is
+/// unknown, longer ones first so that `FDUSD` wins over `USD`.
+const QUOTE_SUFFIXES: [&str; 12] = [
+    "FDUSD", "USDT", "USDC", "BUSD", "TUSD", "USD", "EUR", "TRY", "BRL", "BTC", "ETH", "BNB",
+];
+
+impl MarketState {
+    /// The candle history, which evicts across series and so is locked as
+    /// a whole.
+    pub fn history(&self) -> RwLockReadGuard<'_, HistoryCache> {
+        self.history.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn history_mut(&self) -> RwLockWriteGuard<'_, HistoryCache> {
+        self.history.write().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// The decimals `price` of the series `symbol` is shown with: its tick
+    /// size's on any venue, or one fitting the price for spreads and
+    /// symbols whose tick size is unknown.
+    pub fn decimals(&self, symbol: &str, price: f64) -> usize {
+        if arbitrage::split_spread(symbol).is_some() {
+            return precision::fallback_decimals(price);
+        }
+        let (_, symbol) = arbitrage::split_venue(symbol);
+        self.price_decimals
+            .get(symbol)
+            .map_or_else(|| precision::fallback_decimals(price), |decimals| *decimals)
+    }
+
+    /// The asset the prices and quote volume of `symbol` are in, on any
+    /// venue: its exchange info's, else a known suffix's, else `None`.
+    pub fn quote_asset(&self, symbol: &str) -> Option<Arc<str>> {
+        let (_, symbol) = arbitrage::split_venue(symbol);
+        if let Some(asset) = self.quote_assets.get(symbol) {
+            return Some(asset.clone());
+        }
+        let upper = symbol.to_uppercase();
+        QUOTE_SUFFIXES
+            .iter()
+            .find(|suffix| upper.len() > suffix.len() && upper.ends_with(*suffix))
+            .map(|suffix| Arc::from(*suffix))
+    }
+
+    /// A copy of the latest update of every series, in no particular order.
+    pub fn latest_klines(&self) -> Vec<KlineData> {
+        self.latest
+            .iter()
+            .map(|entry| entry.value().clone())
+            .collect()
+    }
+
+    /// Every symbol's session statistics, sorted by symbol.
+    pub fn sorted_sessions(&self) -> Vec<(Arc<str>, SessionStats)> {
+        let mut sessions: Vec<_> = self
+            .sessions
+            .iter()
+            .map(|entry| (entry.key().clone(), entry.value().clone()))
+            .collect();
+        sessions.sort_by(|a, b| a.0.cmp(&b.0));
+        sessions
+    }
+
+    /// Every symbol's session statistics and open interest, sorted by
+    /// symbol.
+    pub fn sessions_json(&self) -> Vec<Value> {
+        self.sorted_sessions()
+            .into_iter()
+            .map(|(symbol, session)| {
+                let mut value = session.to_json();
+                if let Some(open_interest) = self.open_interest.get(&symbol) {
+                    value["open_interest"] = open_interest.to_json();
+                }
+                if let Some(status) = self.trading_status.get(&symbol) {
+                    value["trading_status"] = status.to_json();
+                }
+                value["symbol"] = json!(symbol);
+                value
+            })
+            .collect()
+    }
+
+    /// Everything held in memory as one JSON document, sorted by symbol and
+    /// interval.
+    pub fn snapshot(&self) -> Value {
+        let mut latest: Vec<_> = self
+            .latest
+            .iter()
+            .map(|entry| (entry.key().clone(), entry.value().to_json()))
+            .collect();
+        latest.sort_by(|a, b| a.0.cmp(&b.0));
+        let history = self.history();
+        let series: Vec<Value> = latest
+            .into_iter()
+            .map(|(key, kline)| {
+                json!({
+                    "symbol": key.0,
+                    "interval": key.1,
+                    "latest": kline,
+                    "candles_held": history.get(&key).map_or(0, |history| history.iter().count()),
+                    "indicators": self.indicators.get(&key).map(|values| values.clone()),
+                })
+            })
+            .collect();
+
+        json!({
+            "taken_at": Utc::now().to_rfc3339(),
+            "series": series,
+            "sessions": self.sessions_json(),
+            "history": {
+                "series": history.keys().count(),
+                "bytes": history.bytes(),
+                "evictions": history.evictions(),
+            },
+        })
+    }
+}
+
+/// Market state shared between the kline workers, which update it, and
+/// readers such as the HTTP API.
+#[derive(Debug, Clone, Default)]
+pub struct SharedState(Arc<MarketState>);
+
+impl Deref for SharedState {
+    type Target = MarketState;
+
+    fn deref(&self) -> &MarketState {
+        &self.0
+    }
+}
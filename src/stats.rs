@@ -0,0 +1,63 @@
+use crate::interval::interval_duration;
+use crate::KlineData;
+use std::collections::HashMap;
+
+/// Aggregate price change over a set of candles.
+#[derive(Debug, Clone, Copy)]
+pub struct MarketStats {
+    pub count: usize,
+    /// Unweighted mean of the candles' percent change.
+    pub mean_change: f64,
+    /// Percent change weighted by quote volume (volume × close), so that thin
+    /// pairs barely move the figure. `None` when no candle has traded volume,
+    /// as with synthetic ratios.
+    pub weighted_change: Option<f64>,
+}
+
+pub fn market_stats<'a>(klines: impl IntoIterator<Item = &'a KlineData>) -> MarketStats {
+    let mut count = 0;
+    let mut change_sum = 0.0;
+    let mut weighted_sum = 0.0;
+    let mut weight_total = 0.0;
+
+    for kline in klines {
+        let change = kline.price_change_percent();
+        count += 1;
+        change_sum += change;
+
+        let weight = kline.volume * kline.close;
+        if weight > 0.0 {
+            weighted_sum += change * weight;
+            weight_total += weight;
+        }
+    }
+
+    MarketStats {
+        count,
+        mean_change: if count > 0 {
+            change_sum / count as f64
+        } else {
+            0.0
+        },
+        weighted_change: (weight_total > 0.0).then(|| weighted_sum / weight_total),
+    }
+}
+
+/// Market stats computed separately for each interval, ordered from the
+/// shortest interval to the longest, so candles of different horizons are
+/// never averaged together.
+pub fn market_stats_by_interval<'a>(
+    klines: impl IntoIterator<Item = &'a KlineData>,
+) -> Vec<(String, MarketStats)> {
+    let mut groups: HashMap<&str, Vec<&KlineData>> = HashMap::new();
+    for kline in klines {
+        groups.entry(&*kline.interval).or_default().push(kline);
+    }
+
+    let mut stats: Vec<(String, MarketStats)> = groups
+        .into_iter()
+        .map(|(interval, klines)| (interval.to_string(), market_stats(klines)))
+        .collect();
+    stats.sort_by_key(|(interval, _)| (interval_duration(interval), interval.clone()));
+    stats
+}
@@ -0,0 +1,416 @@
+use crate::alerts::Alert;
+use crate::config::Side;
+use crate::journal::{Account, JournalEntry};
+use crate::KlineData;
+use anyhow::{Context, Result};
+use chrono::{DateTime, SecondsFormat, Utc};
+use rusqlite::{params, Connection};
+use serde_json::{json, Value};
+use std::path::Path;
+use std::sync::Mutex;
+
+/// Local SQLite database the tracker persists its records to.
+pub struct Store {
+    conn: Mutex<Connection>,
+}
+
+/// A fired alert as recorded in the store, with the outcome of each delivery.
+pub struct AlertRecord {
+    pub id: i64,
+    pub alert: Alert,
+    pub deliveries: Vec<Delivery>,
+}
+
+impl AlertRecord {
+    pub fn to_json(&self) -> Value {
+        let mut record = self.alert.to_json();
+        record["id"] = json!(self.id);
+        record["deliveries"] = self
+            .deliveries
+            .iter()
+            .map(|delivery| {
+                json!({
+                    "notifier": delivery.notifier,
+                    "delivered": delivery.error.is_none(),
+                    "error": delivery.error,
+                    "at": delivery.at.to_rfc3339(),
+                })
+            })
+            .collect();
+        record
+    }
+}
+
+/// The outcome of sending one alert through one notifier.
+pub struct Delivery {
+    pub notifier: String,
+    /// The delivery error, or `None` if it succeeded.
+    pub error: Option<String>,
+    pub at: DateTime<Utc>,
+}
+
+/// Which alerts to return from [`Store::alerts`], newest first.
+pub struct AlertQuery {
+    pub symbol: Option<String>,
+    pub rule: Option<String>,
+    pub since: Option<DateTime<Utc>>,
+    /// Only alerts triggered before this.
+    pub until: Option<DateTime<Utc>>,
+    /// How many of the newest matching alerts to skip.
+    pub offset: usize,
+    pub limit: usize,
+}
+
+/// Which journaled fills to return from [`Store::trades`], oldest first.
+pub struct TradeQuery {
+    pub account: Option<Account>,
+    pub symbol: Option<String>,
+    pub since: Option<DateTime<Utc>>,
+    /// Only fills before this.
+    pub until: Option<DateTime<Utc>>,
+}
+
+/// A page of a series' candles: counting back from the newest candle that
+/// starts within `from..to`, skip `offset` and take `limit`.
+#[derive(Debug, Clone, Copy)]
+pub struct CandleRange {
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub offset: usize,
+    pub limit: usize,
+}
+
+impl CandleRange {
+    /// The newest `limit` candles.
+    pub fn latest(limit: usize) -> Self {
+        Self {
+            from: None,
+            to: None,
+            offset: 0,
+            limit,
+        }
+    }
+
+    pub fn contains(&self, start: DateTime<Utc>) -> bool {
+        self.from.is_none_or(|from| start >= from) && self.to.is_none_or(|to| start < to)
+    }
+}
+
+impl Store {
+    /// Opens the database at `path`, creating it and its tables if needed.
+    pub fn open(path: &Path) -> Result<Self> {
+        let conn = Connection::open(path)
+            .with_context(|| format!("Failed to open store {}", path.display()))?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS alerts (
+                id INTEGER PRIMARY KEY,
+                rule TEXT NOT NULL,
+                symbol TEXT NOT NULL,
+                interval TEXT NOT NULL,
+                price REAL NOT NULL,
+                change_percent REAL NOT NULL,
+                message TEXT NOT NULL,
+                triggered_at TEXT NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS alerts_triggered_at ON alerts (triggered_at);
+            CREATE TABLE IF NOT EXISTS alert_deliveries (
+                alert_id INTEGER NOT NULL REFERENCES alerts (id),
+                notifier TEXT NOT NULL,
+                error TEXT,
+                delivered_at TEXT NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS candles (
+                symbol TEXT NOT NULL,
+                interval TEXT NOT NULL,
+                interval_start TEXT NOT NULL,
+                open REAL NOT NULL,
+                high REAL NOT NULL,
+                low REAL NOT NULL,
+                close REAL NOT NULL,
+                volume REAL NOT NULL,
+                PRIMARY KEY (symbol, interval, interval_start)
+            );
+            CREATE TABLE IF NOT EXISTS trades (
+                id INTEGER PRIMARY KEY,
+                account TEXT NOT NULL,
+                order_id TEXT NOT NULL,
+                symbol TEXT NOT NULL,
+                side TEXT NOT NULL,
+                quantity REAL NOT NULL,
+                price REAL NOT NULL,
+                fee REAL NOT NULL,
+                entry_price REAL,
+                pnl REAL,
+                reason TEXT NOT NULL,
+                indicators TEXT NOT NULL,
+                filled_at TEXT NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS trades_filled_at ON trades (filled_at);",
+        )?;
+        Ok(Self {
+            conn: Mutex::new(conn),
+        })
+    }
+
+    fn conn(&self) -> std::sync::MutexGuard<'_, Connection> {
+        self.conn.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Records a fired alert, returning its id for [`Store::record_delivery`].
+    pub fn record_alert(&self, alert: &Alert) -> Result<i64> {
+        let conn = self.conn();
+        conn.execute(
+            "INSERT INTO alerts (rule, symbol, interval, price, change_percent, message, triggered_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                alert.rule,
+                alert.symbol,
+                alert.interval,
+                alert.price,
+                alert.change_percent,
+                alert.message,
+                format_time(alert.triggered_at),
+            ],
+        )?;
+        Ok(conn.last_insert_rowid())
+    }
+
+    pub fn record_delivery(
+        &self,
+        alert_id: i64,
+        notifier: &str,
+        error: Option<&str>,
+    ) -> Result<()> {
+        self.conn().execute(
+            "INSERT INTO alert_deliveries (alert_id, notifier, error, delivered_at)
+             VALUES (?1, ?2, ?3, ?4)",
+            params![alert_id, notifier, error, format_time(Utc::now())],
+        )?;
+        Ok(())
+    }
+
+    /// Every (symbol, interval) with stored candles.
+    pub fn series(&self) -> Result<Vec<(String, String)>> {
+        let conn = self.conn();
+        let mut statement = conn.prepare("SELECT DISTINCT symbol, interval FROM candles")?;
+        let series = statement
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(series)
+    }
+
+    /// Records closed candles in one transaction, replacing any earlier
+    /// copies of them.
+    pub fn record_candles(&self, klines: &[KlineData]) -> Result<()> {
+        let mut conn = self.conn();
+        let transaction = conn.transaction()?;
+        {
+            let mut statement = transaction.prepare_cached(
+                "INSERT OR REPLACE INTO candles
+                    (symbol, interval, interval_start, open, high, low, close, volume)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            )?;
+            for kline in klines {
+                statement.execute(params![
+                    kline.symbol,
+                    kline.interval,
+                    format_time(kline.interval_start),
+                    kline.open,
+                    kline.high,
+                    kline.low,
+                    kline.close,
+                    kline.volume,
+                ])?;
+            }
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+
+    /// A page of a series' stored candles, oldest first.
+    pub fn candles(
+        &self,
+        symbol: &str,
+        interval: &str,
+        range: &CandleRange,
+    ) -> Result<Vec<KlineData>> {
+        let conn = self.conn();
+        let mut statement = conn.prepare(
+            "SELECT interval_start, open, high, low, close, volume
+             FROM candles
+             WHERE symbol = ?1 AND interval = ?2
+               AND (?3 IS NULL OR interval_start >= ?3)
+               AND (?4 IS NULL OR interval_start < ?4)
+             ORDER BY interval_start DESC
+             LIMIT ?5 OFFSET ?6",
+        )?;
+        let params = params![
+            symbol,
+            interval,
+            range.from.map(format_time),
+            range.to.map(format_time),
+            range.limit as i64,
+            range.offset as i64,
+        ];
+        let mut candles = statement
+            .query_map(params, |row| {
+                Ok(KlineData {
+                    symbol: symbol.into(),
+                    interval: interval.into(),
+                    interval_start: parse_time(&row.get::<_, String>(0)?),
+                    open: row.get(1)?,
+                    high: row.get(2)?,
+                    low: row.get(3)?,
+                    close: row.get(4)?,
+                    volume: row.get(5)?,
+                    closed: true,
+                    timing: None,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        candles.reverse();
+        Ok(candles)
+    }
+
+    /// Journals a paper or live fill.
+    pub fn record_trade(&self, entry: &JournalEntry) -> Result<()> {
+        self.conn().execute(
+            "INSERT INTO trades (account, order_id, symbol, side, quantity, price, fee,
+                entry_price, pnl, reason, indicators, filled_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+            params![
+                entry.account.as_str(),
+                entry.order_id,
+                entry.symbol,
+                entry.side.to_string(),
+                entry.quantity,
+                entry.price,
+                entry.fee,
+                entry.entry_price,
+                entry.pnl,
+                entry.reason,
+                entry.indicators.to_string(),
+                format_time(entry.filled_at),
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn trades(&self, query: &TradeQuery) -> Result<Vec<JournalEntry>> {
+        let conn = self.conn();
+        let mut statement = conn.prepare(
+            "SELECT account, order_id, symbol, side, quantity, price, fee, entry_price, pnl,
+                reason, indicators, filled_at
+             FROM trades
+             WHERE (?1 IS NULL OR account = ?1)
+               AND (?2 IS NULL OR symbol = ?2)
+               AND (?3 IS NULL OR filled_at >= ?3)
+               AND (?4 IS NULL OR filled_at < ?4)
+             ORDER BY filled_at, id",
+        )?;
+        let params = params![
+            query.account.map(Account::as_str),
+            query.symbol,
+            query.since.map(format_time),
+            query.until.map(format_time),
+        ];
+        let trades = statement
+            .query_map(params, |row| {
+                Ok(JournalEntry {
+                    account: Account::parse(&row.get::<_, String>(0)?).unwrap_or(Account::Paper),
+                    order_id: row.get(1)?,
+                    symbol: row.get(2)?,
+                    side: if row.get::<_, String>(3)? == "sell" {
+                        Side::Sell
+                    } else {
+                        Side::Buy
+                    },
+                    quantity: row.get(4)?,
+                    price: row.get(5)?,
+                    fee: row.get(6)?,
+                    entry_price: row.get(7)?,
+                    pnl: row.get(8)?,
+                    reason: row.get(9)?,
+                    indicators: serde_json::from_str(&row.get::<_, String>(10)?)
+                        .unwrap_or(Value::Null),
+                    filled_at: parse_time(&row.get::<_, String>(11)?),
+                })
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(trades)
+    }
+
+    pub fn alerts(&self, query: &AlertQuery) -> Result<Vec<AlertRecord>> {
+        let conn = self.conn();
+        let mut statement = conn.prepare(
+            "SELECT id, rule, symbol, interval, price, change_percent, message, triggered_at
+             FROM alerts
+             WHERE (?1 IS NULL OR symbol = ?1)
+               AND (?2 IS NULL OR rule = ?2)
+               AND (?3 IS NULL OR triggered_at >= ?3)
+               AND (?4 IS NULL OR triggered_at < ?4)
+             ORDER BY triggered_at DESC, id DESC
+             LIMIT ?5 OFFSET ?6",
+        )?;
+        let rows = statement.query_map(
+            params![
+                query.symbol,
+                query.rule,
+                query.since.map(format_time),
+                query.until.map(format_time),
+                query.limit as i64,
+                query.offset as i64,
+            ],
+            |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    Alert {
+                        rule: row.get(1)?,
+                        symbol: row.get(2)?,
+                        interval: row.get(3)?,
+                        price: row.get(4)?,
+                        change_percent: row.get(5)?,
+                        message: row.get(6)?,
+                        triggered_at: parse_time(&row.get::<_, String>(7)?),
+                    },
+                ))
+            },
+        )?;
+
+        let mut deliveries = conn.prepare(
+            "SELECT notifier, error, delivered_at FROM alert_deliveries
+             WHERE alert_id = ?1 ORDER BY delivered_at, rowid",
+        )?;
+        let mut records = Vec::new();
+        for row in rows {
+            let (id, alert) = row?;
+            let deliveries = deliveries
+                .query_map([id], |row| {
+                    Ok(Delivery {
+                        notifier: row.get(0)?,
+                        error: row.get(1)?,
+                        at: parse_time(&row.get::<_, String>(2)?),
+                    })
+                })?
+                .collect::<rusqlite::Result<_>>()?;
+            records.push(AlertRecord {
+                id,
+                alert,
+                deliveries,
+            });
+        }
+        Ok(records)
+    }
+}
+
+/// Timestamps are stored as fixed-width RFC 3339 in UTC, so they also sort
+/// correctly as text.
+fn format_time(time: DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Micros, true)
+}
+
+fn parse_time(text: &str) -> DateTime<Utc> {
+    DateTime::parse_from_rfc3339(text)
+        .map(|time| time.with_timezone(&Utc))
+        .unwrap_or_default()
+}
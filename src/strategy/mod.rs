@@ -0,0 +1,289 @@
+#[cfg(feature = "indicators")]
+mod rsi_reversion;
+#[cfg(feature = "scripting")]
+mod script;
+#[cfg(feature = "indicators")]
+mod sma_cross;
+
+#[cfg(feature = "scripting")]
+use {crate::script::Script, script::ScriptStrategy};
+#[cfg(feature = "indicators")]
+use {rsi_reversion::RsiReversion, sma_cross::SmaCross};
+
+use crate::config::{Side, StrategyConfig};
+use crate::{KlineData, SeriesKey};
+use anyhow::{bail, Result};
+use chrono::{DateTime, Utc};
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+/// A strategy's call to trade the symbol of the candle it was fed.
+#[derive(Debug, Clone)]
+pub struct Signal {
+    pub side: Side,
+    /// How strongly the strategy means it, from 0 to 1.
+    pub strength: f64,
+    pub reason: String,
+}
+
+/// A signal as published: which strategy raised it on which candle, and the
+/// series' indicator values at the time.
+#[derive(Debug, Clone)]
+pub struct TradeSignal {
+    pub strategy: String,
+    pub symbol: String,
+    pub interval: String,
+    pub side: Side,
+    pub strength: f64,
+    pub reason: String,
+    /// Close of the candle that raised it.
+    pub price: f64,
+    pub indicators: BTreeMap<String, Value>,
+    pub raised_at: DateTime<Utc>,
+}
+
+impl TradeSignal {
+    pub fn to_json(&self) -> Value {
+        json!({
+            "strategy": self.strategy,
+            "symbol": self.symbol,
+            "interval": self.interval,
+            "side": self.side,
+            "strength": self.strength,
+            "reason": self.reason,
+            "price": self.price,
+            "indicators": self.indicators,
+            "raised_at": self.raised_at.to_rfc3339(),
+        })
+    }
+
+    /// A one-line heading, for notifiers.
+    pub fn title(&self) -> String {
+        format!(
+            "Signal: {} {} {}",
+            self.strategy,
+            self.side,
+            self.symbol.to_uppercase()
+        )
+    }
+
+    /// The details, one per line, for notifiers.
+    pub fn lines(&self) -> Vec<String> {
+        let mut lines = vec![
+            self.reason.clone(),
+            format!(
+                "{} {} at {}",
+                self.symbol.to_uppercase(),
+                self.interval,
+                self.price
+            ),
+            format!("Strength: {:.2}", self.strength),
+        ];
+        lines.extend(
+            self.indicators
+                .iter()
+                .map(|(name, value)| format!("{}: {}", name, value)),
+        );
+        lines
+    }
+}
+
+/// Trading logic fed one closed candle at a time, the brain of paper trading
+/// and backtests.
+///
+/// Built-in strategies implement this trait, and so can custom ones: register
+/// a factory for a new `kind` with [`StrategyRegistry::register`] and it can
+/// be declared in the config like the built-ins.
+pub trait Strategy: Send {
+    /// Called with each closed candle of the series the strategy runs on, and
+    /// whether its last signal there was to buy. Returns a signal to trade,
+    /// if any.
+    fn on_candle(&mut self, candle: &KlineData, long: bool) -> Option<Signal>;
+}
+
+/// Creates a strategy instance from its config entry.
+pub type StrategyFactory = Box<dyn Fn(&StrategyConfig) -> Result<Box<dyn Strategy>> + Send + Sync>;
+
+/// Maps strategy kinds, as written in the config, to their factories.
+pub struct StrategyRegistry {
+    factories: HashMap<String, StrategyFactory>,
+}
+
+impl StrategyRegistry {
+    /// A registry with no strategies registered, for custom kinds only. See
+    /// [`with_builtins`](Self::with_builtins), which `default` also gives.
+    pub fn empty() -> Self {
+        Self {
+            factories: HashMap::new(),
+        }
+    }
+
+    /// A registry with every built-in strategy registered: those needing
+    /// the `indicators` feature, and `script` with the `scripting` one.
+    pub fn with_builtins() -> Self {
+        #[allow(unused_mut)]
+        let mut registry = Self::empty();
+        #[cfg(feature = "indicators")]
+        registry.register_builtins();
+        #[cfg(feature = "scripting")]
+        registry.register("script", |c| {
+            let Some(path) = &c.script else {
+                bail!("Strategy '{}' needs a script", c.name());
+            };
+            Ok(Box::new(ScriptStrategy::new(
+                Script::load(path)?,
+                c.period("lookback", 100),
+            )))
+        });
+        registry
+    }
+
+    #[cfg(feature = "indicators")]
+    fn register_builtins(&mut self) {
+        self.register("sma_cross", |c| {
+            let (fast, slow) = (c.period("fast", 10), c.period("slow", 30));
+            if fast >= slow {
+                bail!("Strategy '{}' needs fast shorter than slow", c.name());
+            }
+            Ok(Box::new(SmaCross::new(fast, slow)))
+        });
+        self.register("rsi_reversion", |c| {
+            Ok(Box::new(RsiReversion::new(
+                c.period("period", 14),
+                c.param("oversold", 30.0),
+                c.param("overbought", 70.0),
+            )))
+        });
+    }
+
+    /// Registers (or replaces) the factory for `kind`.
+    pub fn register<F>(&mut self, kind: &str, factory: F)
+    where
+        F: Fn(&StrategyConfig) -> Result<Box<dyn Strategy>> + Send + Sync + 'static,
+    {
+        self.factories
+            .insert(kind.to_lowercase(), Box::new(factory));
+    }
+
+    fn build(&self, config: &StrategyConfig) -> Result<Box<dyn Strategy>> {
+        match self.factories.get(&config.kind) {
+            Some(factory) => factory(config),
+            None => bail!("Unknown strategy kind '{}'", config.kind),
+        }
+    }
+}
+
+impl Default for StrategyRegistry {
+    fn default() -> Self {
+        Self::with_builtins()
+    }
+}
+
+/// One strategy running on one series.
+struct Instance {
+    /// Index of its spec.
+    spec: usize,
+    strategy: Box<dyn Strategy>,
+    /// Whether its last signal was to buy.
+    long: bool,
+}
+
+/// Runs the configured strategies against every matching series, each
+/// series getting its own instances the first time a candle for it is seen.
+pub struct StrategySet {
+    registry: Arc<StrategyRegistry>,
+    specs: Vec<StrategyConfig>,
+    series: HashMap<SeriesKey, Vec<Instance>>,
+}
+
+impl StrategySet {
+    /// Validates every configured strategy up front so typos fail at startup.
+    pub fn new(registry: StrategyRegistry, specs: Vec<StrategyConfig>) -> Result<Self> {
+        for spec in &specs {
+            registry.build(spec)?;
+        }
+        Ok(Self {
+            registry: Arc::new(registry),
+            specs,
+            series: HashMap::new(),
+        })
+    }
+
+    /// A set running the same strategies from scratch.
+    pub fn fork(&self) -> Self {
+        Self {
+            registry: self.registry.clone(),
+            specs: self.specs.clone(),
+            series: HashMap::new(),
+        }
+    }
+
+    /// A set running `specs` from scratch with the same registry, validated
+    /// as by [`StrategySet::new`].
+    pub fn with_specs(&self, specs: Vec<StrategyConfig>) -> Result<Self> {
+        for spec in &specs {
+            self.registry.build(spec)?;
+        }
+        Ok(Self {
+            registry: self.registry.clone(),
+            specs,
+            series: HashMap::new(),
+        })
+    }
+
+    /// Forgets every instance's last signal, as if none had bought.
+    pub fn flatten(&mut self) {
+        for instance in self.series.values_mut().flatten() {
+            instance.long = false;
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.specs.is_empty()
+    }
+
+    /// Feeds a closed candle to every strategy scoped to its series and
+    /// returns the signals raised at `now`, carrying `indicators`, the
+    /// series' latest indicator values.
+    pub fn update(
+        &mut self,
+        candle: &KlineData,
+        indicators: Option<&BTreeMap<String, Value>>,
+        now: DateTime<Utc>,
+    ) -> Vec<TradeSignal> {
+        let (registry, specs) = (&self.registry, &self.specs);
+        let instances = self.series.entry(candle.key()).or_insert_with(|| {
+            specs
+                .iter()
+                .enumerate()
+                .filter(|(_, spec)| spec.applies_to(&candle.symbol, &candle.interval))
+                .filter_map(|(index, spec)| {
+                    Some(Instance {
+                        spec: index,
+                        strategy: registry.build(spec).ok()?,
+                        long: false,
+                    })
+                })
+                .collect()
+        });
+        instances
+            .iter_mut()
+            .filter_map(|instance| {
+                let signal = instance.strategy.on_candle(candle, instance.long)?;
+                instance.long = signal.side == Side::Buy;
+                Some(TradeSignal {
+                    strategy: specs[instance.spec].name(),
+                    symbol: candle.symbol.to_string(),
+                    interval: candle.interval.to_string(),
+                    side: signal.side,
+                    strength: signal.strength,
+                    reason: signal.reason,
+                    price: candle.close,
+                    indicators: indicators.cloned().unwrap_or_default(),
+                    raised_at: now,
+                })
+            })
+            .collect()
+    }
+}
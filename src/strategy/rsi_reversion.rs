@@ -0,0 +1,43 @@
+use super::{Signal, Strategy};
+use crate::config::Side;
+use crate::indicators::Rsi;
+use crate::KlineData;
+
+/// Buys when the RSI of closes falls below `oversold`, betting on a bounce,
+/// and sells when it rises above `overbought`.
+pub struct RsiReversion {
+    rsi: Rsi,
+    oversold: f64,
+    overbought: f64,
+}
+
+impl RsiReversion {
+    pub fn new(period: usize, oversold: f64, overbought: f64) -> Self {
+        Self {
+            rsi: Rsi::new(period),
+            oversold,
+            overbought,
+        }
+    }
+}
+
+impl Strategy for RsiReversion {
+    fn on_candle(&mut self, candle: &KlineData, long: bool) -> Option<Signal> {
+        let rsi = self.rsi.next(candle.close)?;
+        let (side, strength) = if rsi < self.oversold && !long {
+            (Side::Buy, (self.oversold - rsi) / self.oversold.max(1.0))
+        } else if rsi > self.overbought && long {
+            (
+                Side::Sell,
+                (rsi - self.overbought) / (100.0 - self.overbought).max(1.0),
+            )
+        } else {
+            return None;
+        };
+        Some(Signal {
+            side,
+            strength: strength.clamp(0.0, 1.0),
+            reason: format!("RSI {:.2}", rsi),
+        })
+    }
+}
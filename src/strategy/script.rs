@@ -0,0 +1,75 @@
+use super::{Signal, Strategy};
+use crate::config::Side;
+use crate::script::{candle_scope, Script};
+use crate::KlineData;
+use rhai::{Array, Dynamic, Map};
+use std::collections::VecDeque;
+
+/// Runs a Rhai script on every candle, which decides whether to trade.
+///
+/// Besides the candle's fields, the script sees `long`, whether its last
+/// signal was to buy, `closes`, the latest closes oldest first, and `state`,
+/// a map it may keep anything in from one candle to the next. It returns
+/// `"buy"`, `"sell"`, a map with a `side` and optionally a `strength` and a
+/// `reason`, or nothing.
+pub struct ScriptStrategy {
+    script: Script,
+    closes: VecDeque<f64>,
+    lookback: usize,
+    state: Map,
+}
+
+impl ScriptStrategy {
+    pub fn new(script: Script, lookback: usize) -> Self {
+        Self {
+            script,
+            closes: VecDeque::with_capacity(lookback),
+            lookback,
+            state: Map::new(),
+        }
+    }
+}
+
+impl Strategy for ScriptStrategy {
+    fn on_candle(&mut self, candle: &KlineData, long: bool) -> Option<Signal> {
+        if self.closes.len() == self.lookback {
+            self.closes.pop_front();
+        }
+        self.closes.push_back(candle.close);
+
+        let mut scope = candle_scope(candle, None);
+        let closes: Array = self
+            .closes
+            .iter()
+            .map(|close| Dynamic::from_float(*close))
+            .collect();
+        scope.push("long", long);
+        scope.push("closes", closes);
+        scope.push("state", std::mem::take(&mut self.state));
+        let result = self.script.run(&mut scope);
+        self.state = scope.get_value::<Map>("state").unwrap_or_default();
+
+        let result = result?;
+        let (side, strength, reason) = match result.clone().try_cast::<Map>() {
+            Some(map) => (
+                map.get("side")?.clone().into_string().ok()?,
+                map.get("strength").and_then(|s| s.as_float().ok()),
+                map.get("reason").and_then(|r| r.clone().into_string().ok()),
+            ),
+            None => (result.into_string().ok()?, None, None),
+        };
+        let side = match side.to_lowercase().as_str() {
+            "buy" => Side::Buy,
+            "sell" => Side::Sell,
+            _ => return None,
+        };
+        if (side == Side::Buy) == long {
+            return None;
+        }
+        Some(Signal {
+            side,
+            strength: strength.unwrap_or(1.0).clamp(0.0, 1.0),
+            reason: reason.unwrap_or_else(|| format!("script {}", self.script.path().display())),
+        })
+    }
+}
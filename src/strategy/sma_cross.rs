@@ -0,0 +1,56 @@
+use super::{Signal, Strategy};
+use crate::config::Side;
+use crate::indicators::Sma;
+use crate::KlineData;
+
+/// Buys when the fast moving average of closes crosses above the slow one,
+/// and sells when it crosses back below.
+pub struct SmaCross {
+    fast: Sma,
+    slow: Sma,
+    /// Whether fast was above slow at the previous candle.
+    above: Option<bool>,
+}
+
+impl SmaCross {
+    pub fn new(fast: usize, slow: usize) -> Self {
+        Self {
+            fast: Sma::new(fast),
+            slow: Sma::new(slow),
+            above: None,
+        }
+    }
+}
+
+impl Strategy for SmaCross {
+    fn on_candle(&mut self, candle: &KlineData, long: bool) -> Option<Signal> {
+        let fast = self.fast.next(candle.close);
+        let slow = self.slow.next(candle.close)?;
+        let fast = fast?;
+        let above = fast > slow;
+        let crossed = self.above.replace(above).is_some_and(|was| was != above);
+        if !crossed {
+            return None;
+        }
+        let side = if above { Side::Buy } else { Side::Sell };
+        if (side == Side::Buy) == long {
+            return None;
+        }
+        // A wider gap on the crossing candle is a more decisive cross.
+        let gap = if slow == 0.0 {
+            0.0
+        } else {
+            (fast - slow).abs() / slow * 100.0
+        };
+        Some(Signal {
+            side,
+            strength: gap.min(1.0),
+            reason: format!(
+                "fast SMA {:.4} crossed {} slow SMA {:.4}",
+                fast,
+                if above { "above" } else { "below" },
+                slow
+            ),
+        })
+    }
+}
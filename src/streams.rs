@@ -0,0 +1,525 @@
+use crate::actor::PanicReporter;
+use crate::arbitrage::split_venue;
+use crate::config::{Escalation, MultiplexConfig, RestartConfig};
+use crate::health::{Health, StreamState};
+use crate::metrics::Metrics;
+use crate::multiplex::{self, Route, Routes};
+use crate::ops::{OpsEvent, Severity};
+use crate::shard::Router;
+use crate::{run_websocket, STREAM_URL};
+use anyhow::{anyhow, Result};
+use futures_util::FutureExt;
+use std::collections::{BTreeMap, HashMap};
+use std::future::Future;
+use std::panic::AssertUnwindSafe;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+use tokio::sync::{mpsc, watch};
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info, warn};
+
+/// The Binance streams currently subscribed, either one connection per
+/// (symbol, interval) or packed into shared connections as set by
+/// [`MultiplexConfig`]. Streams can be added and removed while the tracker
+/// runs. Every connection is restarted whenever it fails or is closed, as
+/// set by [`RestartConfig`].
+#[derive(Clone)]
+pub struct Streams {
+    inner: Arc<Mutex<Inner>>,
+    /// Number of connection tasks still running.
+    running: watch::Sender<usize>,
+    /// Why the tracker must stop, once a connection has failed too often
+    /// under [`Escalation::Exit`].
+    failed: watch::Sender<Option<String>>,
+    /// Set by [`Streams::shutdown`], which has every connection close.
+    stopping: CancellationToken,
+    restart: Arc<RestartConfig>,
+    multiplex: Arc<MultiplexConfig>,
+    /// Base of the WebSocket URLs connected to.
+    stream_url: Arc<str>,
+    /// Base URLs of the other venues, by name.
+    venues: Arc<HashMap<String, Arc<str>>>,
+    ops_tx: mpsc::Sender<OpsEvent>,
+    health: Health,
+    metrics: Metrics,
+    panics: PanicReporter,
+}
+
+/// A supervised connection task.
+struct Task {
+    handle: JoinHandle<()>,
+    /// Identifies the incident raised when the connection keeps failing.
+    incident: String,
+    /// Whether that incident is open.
+    escalated: Arc<AtomicBool>,
+}
+
+impl Task {
+    fn stop(self, ops_tx: &mpsc::Sender<OpsEvent>) {
+        self.handle.abort();
+        if self.escalated.load(Ordering::Relaxed) {
+            let _ = ops_tx.try_send(OpsEvent::resolve(self.incident));
+        }
+    }
+}
+
+/// A connection shared by several streams.
+struct Connection {
+    task: Task,
+    routes: watch::Sender<Routes>,
+}
+
+impl Connection {
+    fn len(&self) -> usize {
+        self.routes.borrow().len()
+    }
+}
+
+struct Inner {
+    /// One connection per stream, when not multiplexing.
+    tasks: HashMap<(String, String), Task>,
+    /// Shared connections by number, when multiplexing.
+    connections: BTreeMap<u64, Connection>,
+    next_connection: u64,
+    /// Taken by [`Streams::stop_all`], after which no stream can be added.
+    router: Option<Router>,
+}
+
+/// Counts a connection task as running until the task ends or is aborted.
+struct Running(watch::Sender<usize>);
+
+impl Drop for Running {
+    fn drop(&mut self) {
+        self.0.send_modify(|running| *running -= 1);
+    }
+}
+
+impl Streams {
+    /// `ops_tx` receives the incidents raised for failing connections, and
+    /// `panics` reports connections that panic, which count as failures.
+    pub fn new(
+        router: Router,
+        restart: RestartConfig,
+        multiplex: MultiplexConfig,
+        ops_tx: mpsc::Sender<OpsEvent>,
+        health: Health,
+        metrics: Metrics,
+        panics: PanicReporter,
+    ) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(Inner {
+                tasks: HashMap::new(),
+                connections: BTreeMap::new(),
+                next_connection: 1,
+                router: Some(router),
+            })),
+            running: watch::Sender::new(0),
+            failed: watch::Sender::new(None),
+            stopping: CancellationToken::new(),
+            restart: Arc::new(restart),
+            multiplex: Arc::new(multiplex),
+            stream_url: STREAM_URL.into(),
+            venues: Arc::default(),
+            ops_tx,
+            health,
+            metrics,
+            panics,
+        }
+    }
+
+    /// Connects to `stream_url` instead of Binance, e.g. a
+    /// [`MockServer`](crate::mock::MockServer).
+    pub fn with_stream_url(mut self, stream_url: &str) -> Self {
+        self.stream_url = stream_url.trim_end_matches('/').into();
+        self
+    }
+
+    /// Connects to `stream_url` for symbols on the venue `name`, named
+    /// `name:symbol`. A venue's streams always have connections of their
+    /// own.
+    pub fn with_venue(mut self, name: &str, stream_url: &str) -> Self {
+        Arc::make_mut(&mut self.venues)
+            .insert(name.to_string(), stream_url.trim_end_matches('/').into());
+        self
+    }
+
+    fn inner(&self) -> MutexGuard<'_, Inner> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Starts streaming `symbol` on `interval`, unless it already is or
+    /// is on a venue not configured.
+    pub fn add(&self, symbol: &str, interval: &str) -> bool {
+        let stream_url = match split_venue(symbol).0 {
+            None => self.stream_url.clone(),
+            Some(venue) => match self.venues.get(venue) {
+                Some(stream_url) => stream_url.clone(),
+                None => {
+                    warn!("Cannot stream {}: no venue named '{}'", symbol, venue);
+                    return false;
+                }
+            },
+        };
+        let key = (symbol.to_string(), interval.to_string());
+        let name = multiplex::stream_name(symbol, interval);
+        let mut inner = self.inner();
+        let Some(tx) = inner.router.as_ref().map(|router| router.sender(symbol)) else {
+            return false;
+        };
+        let subscribed = inner
+            .tasks
+            .get(&key)
+            .is_some_and(|task| !task.handle.is_finished())
+            || inner
+                .connections
+                .values()
+                .any(|connection| connection.routes.borrow().contains_key(&name));
+        if subscribed {
+            return false;
+        }
+
+        // Registered up front so the stream counts towards health checks
+        // before its connection first runs.
+        self.health
+            .set_stream_state(symbol, interval, StreamState::Connecting);
+        let route = Route {
+            key: (symbol.into(), interval.into()),
+            tx,
+        };
+        if self.multiplex.enabled && split_venue(symbol).0.is_none() {
+            self.pack(&mut inner, name, route);
+            return true;
+        }
+
+        let (health, metrics) = (self.health.clone(), self.metrics.clone());
+        let (symbol, interval) = key.clone();
+        let stopping = self.stopping.clone();
+        let stopped_tx = route.tx.clone();
+        let task = self.supervise(
+            format!("stream {} {}", symbol, interval),
+            format!("stream-failing:{}:{}", symbol, interval),
+            move || {
+                let (symbol, interval) = (symbol.clone(), interval.clone());
+                let (tx, health, metrics) = (route.tx.clone(), health.clone(), metrics.clone());
+                let (stopping, stream_url) = (stopping.clone(), stream_url.clone());
+                async move {
+                    let result = run_websocket(
+                        &stream_url,
+                        symbol.clone(),
+                        interval.clone(),
+                        tx,
+                        health.clone(),
+                        metrics,
+                        stopping,
+                    )
+                    .await;
+                    health.set_stream_state(&symbol, &interval, StreamState::Disconnected);
+                    result
+                }
+            },
+            move || stopped_tx.is_closed(),
+        );
+        inner.tasks.insert(key, task);
+        true
+    }
+
+    /// Adds a stream to the fullest shared connection with room, so streams
+    /// take as few connections as possible, or to a new connection.
+    fn pack(&self, inner: &mut Inner, name: String, route: Route) {
+        let per_connection = self.multiplex.streams_per_connection;
+        let fullest = inner
+            .connections
+            .iter()
+            .filter(|(_, connection)| connection.len() < per_connection)
+            .max_by_key(|(_, connection)| connection.len());
+        if let Some((_, connection)) = fullest {
+            connection.routes.send_modify(|routes| {
+                routes.insert(name, route);
+            });
+            return;
+        }
+        let number = inner.next_connection;
+        inner.next_connection += 1;
+        let connection = self.open_connection(number, Routes::from([(name, route)]));
+        inner.connections.insert(number, connection);
+    }
+
+    fn open_connection(&self, number: u64, routes: Routes) -> Connection {
+        let (routes, watched) = watch::channel(routes);
+        let (health, metrics) = (self.health.clone(), self.metrics.clone());
+        let messages_per_sec = self.multiplex.messages_per_sec;
+        let stopping = self.stopping.clone();
+        let stream_url = self.stream_url.clone();
+        let name = format!("connection {}", number);
+        let stopped_routes = watched.clone();
+        let task = self.supervise(
+            name.clone(),
+            format!("connection-failing:{}", number),
+            move || {
+                let (name, watched) = (name.clone(), watched.clone());
+                let (health, metrics) = (health.clone(), metrics.clone());
+                let (stopping, stream_url) = (stopping.clone(), stream_url.clone());
+                async move {
+                    let result = multiplex::run_connection(
+                        &stream_url,
+                        name,
+                        watched.clone(),
+                        messages_per_sec,
+                        health.clone(),
+                        metrics,
+                        stopping,
+                    )
+                    .await;
+                    for route in watched.borrow().values() {
+                        health.set_stream_state(
+                            &route.key.0,
+                            &route.key.1,
+                            StreamState::Disconnected,
+                        );
+                    }
+                    result
+                }
+            },
+            move || {
+                stopped_routes
+                    .borrow()
+                    .values()
+                    .any(|route| route.tx.is_closed())
+            },
+        );
+        info!("Opened shared connection {}", number);
+        Connection { task, routes }
+    }
+
+    /// Closes shared connections for as long as their streams fit in fewer,
+    /// emptiest first, moving their streams to the others.
+    fn rebalance(&self, inner: &mut Inner) {
+        let per_connection = self.multiplex.streams_per_connection;
+        loop {
+            let streams: usize = inner.connections.values().map(Connection::len).sum();
+            if inner.connections.len() <= streams.div_ceil(per_connection) {
+                return;
+            }
+            let Some(emptiest) = inner
+                .connections
+                .iter()
+                .min_by_key(|(_, connection)| connection.len())
+                .map(|(number, _)| *number)
+            else {
+                return;
+            };
+            let Some(connection) = inner.connections.remove(&emptiest) else {
+                return;
+            };
+            let routes = connection.routes.borrow().clone();
+            connection.task.stop(&self.ops_tx);
+            if routes.is_empty() {
+                info!("Closed shared connection {}", emptiest);
+            } else {
+                info!(
+                    "Closed shared connection {}, moving its {} stream(s)",
+                    emptiest,
+                    routes.len()
+                );
+            }
+            for (name, route) in routes {
+                self.pack(inner, name, route);
+            }
+        }
+    }
+
+    /// Stops streaming `symbol` on `interval`, if it was.
+    pub fn remove(&self, symbol: &str, interval: &str) -> bool {
+        let mut inner = self.inner();
+        if let Some(task) = inner
+            .tasks
+            .remove(&(symbol.to_string(), interval.to_string()))
+        {
+            task.stop(&self.ops_tx);
+        } else {
+            let name = multiplex::stream_name(symbol, interval);
+            let removed = inner.connections.values().any(|connection| {
+                connection
+                    .routes
+                    .send_if_modified(|routes| routes.remove(&name).is_some())
+            });
+            if !removed {
+                return false;
+            }
+            self.rebalance(&mut inner);
+        }
+        drop(inner);
+        self.health.remove_stream(symbol, interval);
+        self.metrics.remove(symbol, interval);
+        info!("Unsubscribed from {} {}", symbol, interval);
+        true
+    }
+
+    /// Every subscribed (symbol, interval), sorted.
+    pub fn list(&self) -> Vec<(String, String)> {
+        let inner = self.inner();
+        let mut streams: Vec<_> = inner.tasks.keys().cloned().collect();
+        for connection in inner.connections.values() {
+            streams.extend(
+                connection
+                    .routes
+                    .borrow()
+                    .values()
+                    .map(|route| (route.key.0.to_string(), route.key.1.to_string())),
+            );
+        }
+        streams.sort();
+        streams
+    }
+
+    /// Stops every stream for good, letting each connection close cleanly
+    /// for up to `grace` before it is aborted. Once their tasks have ended,
+    /// the kline channel closes.
+    pub async fn shutdown(&self, grace: Duration) {
+        self.inner().router = None;
+        self.stopping.cancel();
+        if tokio::time::timeout(grace, self.closed()).await.is_err() {
+            warn!(
+                "Connections still open after {}s; aborting them",
+                grace.as_secs()
+            );
+        }
+        self.stop_all();
+    }
+
+    /// Stops every stream for good at once. Once their tasks have ended, the
+    /// kline channel closes.
+    pub fn stop_all(&self) {
+        let mut inner = self.inner();
+        inner.router = None;
+        for (_, task) in inner.tasks.drain() {
+            task.handle.abort();
+        }
+        for (_, connection) in std::mem::take(&mut inner.connections) {
+            connection.task.handle.abort();
+        }
+    }
+
+    /// Resolves once no connection task is running.
+    pub async fn closed(&self) {
+        let _ = self
+            .running
+            .subscribe()
+            .wait_for(|running| *running == 0)
+            .await;
+    }
+
+    /// Resolves, with the reason, once a connection has failed too often and
+    /// escalation is set to stop the tracker.
+    pub async fn failed(&self) -> String {
+        let mut failed = self.failed.subscribe();
+        let reason = match failed.wait_for(Option::is_some).await {
+            Ok(reason) => reason.clone(),
+            // The sender lives in `self`, so this cannot happen.
+            Err(_) => None,
+        };
+        reason.unwrap_or_default()
+    }
+
+    /// Runs `connect` as the connection `name` until `stopped` says the
+    /// processor is gone, restarting it with backoff whenever it ends.
+    fn supervise<C, F>(
+        &self,
+        name: String,
+        incident: String,
+        mut connect: C,
+        stopped: impl Fn() -> bool + Send + 'static,
+    ) -> Task
+    where
+        C: FnMut() -> F + Send + 'static,
+        F: Future<Output = Result<()>> + Send,
+    {
+        self.running.send_modify(|running| *running += 1);
+        let running = Running(self.running.clone());
+        let escalated = Arc::new(AtomicBool::new(false));
+        let streams = self.clone();
+        let (key, flag) = (incident.clone(), escalated.clone());
+        let handle = tokio::spawn(async move {
+            let _running = running;
+            let Streams {
+                restart,
+                ops_tx,
+                failed,
+                stopping,
+                panics,
+                ..
+            } = &streams;
+            let stable_after = Duration::from_secs(restart.stable_secs);
+            let mut failures = 0u32;
+            loop {
+                let connection = AssertUnwindSafe(connect()).catch_unwind().map(|result| {
+                    result.unwrap_or_else(|panic| {
+                        panics.report(&name, panic);
+                        Err(anyhow!("the connection task panicked"))
+                    })
+                });
+                tokio::pin!(connection);
+                let result = tokio::select! {
+                    result = &mut connection => result,
+                    _ = tokio::time::sleep(stable_after) => {
+                        // Up long enough to count as recovered.
+                        failures = 0;
+                        if flag.swap(false, Ordering::Relaxed) {
+                            info!("{} is stable again", name);
+                            let _ = ops_tx.try_send(OpsEvent::resolve(&key));
+                        }
+                        connection.await
+                    }
+                };
+                if stopped() || stopping.is_cancelled() {
+                    return;
+                }
+                match result {
+                    Err(e) => error!("WebSocket error on {}: {}", name, e),
+                    Ok(()) => warn!("Binance closed {}", name),
+                }
+
+                failures += 1;
+                if failures >= restart.max_failures && !flag.load(Ordering::Relaxed) {
+                    let summary = format!("Repeated failures on {}: {} in a row", name, failures);
+                    match restart.escalation {
+                        Escalation::Alert => {
+                            flag.store(true, Ordering::Relaxed);
+                            let _ = ops_tx.try_send(OpsEvent::trigger(
+                                &key,
+                                &summary,
+                                Severity::Critical,
+                            ));
+                            error!("{}; raised an incident", summary);
+                        }
+                        Escalation::Exit => {
+                            failed.send_replace(Some(summary));
+                            return;
+                        }
+                    }
+                }
+
+                let backoff = Duration::from_secs(restart.backoff_secs)
+                    .saturating_mul(2u32.saturating_pow(failures - 1))
+                    .min(Duration::from_secs(restart.max_backoff_secs));
+                info!(
+                    "Restarting {} in {}s (failure {})",
+                    name,
+                    backoff.as_secs(),
+                    failures
+                );
+                tokio::select! {
+                    _ = tokio::time::sleep(backoff) => {}
+                    _ = stopping.cancelled() => return,
+                }
+            }
+        });
+        Task {
+            handle,
+            incident,
+            escalated,
+        }
+    }
+}
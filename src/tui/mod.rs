@@ -0,0 +1,325 @@
+//! Interactive terminal dashboard, an alternative to the log output.
+
+use crate::chart;
+use crate::state::{MarketState, SharedState};
+use crate::KlineData;
+use anyhow::Result;
+use chrono::Local;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, Cell, Paragraph, Row, Table, TableState};
+use ratatui::{DefaultTerminal, Frame};
+use std::time::Duration;
+
+const REFRESH: Duration = Duration::from_millis(250);
+const SPARK_WIDTH: usize = 30;
+/// Room for the price axis beside a chart.
+const LABEL_WIDTH: usize = 12;
+const SPARK_BLOCKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+enum View {
+    Table,
+    Detail(String),
+}
+
+struct App {
+    state: SharedState,
+    intervals: Vec<String>,
+    /// Index into `intervals` of the interval shown.
+    interval: usize,
+    table: TableState,
+    view: View,
+}
+
+/// Runs the dashboard until the user quits. Blocks the calling thread.
+pub fn run(state: SharedState, intervals: Vec<String>) -> Result<()> {
+    let mut terminal = ratatui::init();
+    let result = App {
+        state,
+        intervals,
+        interval: 0,
+        table: TableState::default().with_selected(0),
+        view: View::Table,
+    }
+    .run(&mut terminal);
+    ratatui::restore();
+    result
+}
+
+impl App {
+    fn run(&mut self, terminal: &mut DefaultTerminal) -> Result<()> {
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+            if !event::poll(REFRESH)? {
+                continue;
+            }
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            match (&self.view, key.code) {
+                (_, KeyCode::Char('q')) => return Ok(()),
+                (_, KeyCode::Tab | KeyCode::Char('i')) => {
+                    self.interval = (self.interval + 1) % self.intervals.len().max(1);
+                }
+                (View::Table, KeyCode::Down | KeyCode::Char('j')) => self.table.select_next(),
+                (View::Table, KeyCode::Up | KeyCode::Char('k')) => self.table.select_previous(),
+                (View::Table, KeyCode::Enter) => {
+                    let symbols = self.symbols(&self.state);
+                    if let Some(symbol) = self.table.selected().and_then(|i| symbols.get(i)) {
+                        self.view = View::Detail(symbol.clone());
+                    }
+                }
+                (View::Table, KeyCode::Esc) => return Ok(()),
+                (View::Detail(_), KeyCode::Esc | KeyCode::Backspace) => self.view = View::Table,
+                _ => {}
+            }
+        }
+    }
+
+    fn interval(&self) -> &str {
+        self.intervals.get(self.interval).map_or("", String::as_str)
+    }
+
+    /// Every symbol with data for the shown interval, sorted.
+    fn symbols(&self, market: &MarketState) -> Vec<String> {
+        let mut symbols: Vec<String> = market
+            .latest
+            .iter()
+            .filter(|entry| *entry.key().1 == *self.interval())
+            .map(|entry| entry.key().0.to_string())
+            .collect();
+        symbols.sort();
+        symbols
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [header, body, footer] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Min(0),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+
+        frame.render_widget(
+            Line::from(vec![
+                Span::styled(" Kline Tracker ", Style::new().add_modifier(Modifier::BOLD)),
+                Span::raw(format!("  interval {}  ", self.interval())),
+                Span::raw(Local::now().format("%H:%M:%S").to_string()),
+            ]),
+            header,
+        );
+
+        let state = self.state.clone();
+        let keys = match &self.view {
+            View::Table => {
+                self.draw_table(frame, body, &state);
+                " ↑/↓ select  Enter details  Tab interval  q quit"
+            }
+            View::Detail(symbol) => {
+                self.draw_detail(frame, body, &state, symbol);
+                " Esc back  Tab interval  q quit"
+            }
+        };
+        frame.render_widget(
+            Line::from(keys).style(Style::new().fg(Color::DarkGray)),
+            footer,
+        );
+    }
+
+    fn draw_table(&mut self, frame: &mut Frame, area: Rect, market: &MarketState) {
+        let rows: Vec<Row> = self
+            .symbols(market)
+            .iter()
+            .filter_map(|symbol| {
+                let key = (symbol.as_str().into(), self.interval().into());
+                let kline = market.latest.get(&key)?;
+                let closes: Vec<f64> = market
+                    .history()
+                    .get(&key)
+                    .map(|history| history.last_n(SPARK_WIDTH).map(|k| k.close).collect())
+                    .unwrap_or_default();
+                let decimals = market.decimals(symbol, kline.close);
+                Some(Row::new(vec![
+                    Cell::from(symbol.to_uppercase()),
+                    Cell::from(format!("{:.*}", decimals, kline.close)),
+                    Cell::from(format!("{:+.2}%", kline.price_change_percent()))
+                        .style(change_style(&kline)),
+                    Cell::from(format!("{:.2}", kline.volume)),
+                    Cell::from(sparkline(&closes)),
+                ]))
+            })
+            .collect();
+
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Length(20),
+                Constraint::Length(14),
+                Constraint::Length(10),
+                Constraint::Length(16),
+                Constraint::Length(SPARK_WIDTH as u16),
+            ],
+        )
+        .header(
+            Row::new(["Symbol", "Price", "Change", "Volume", "Trend"])
+                .style(Style::new().add_modifier(Modifier::BOLD)),
+        )
+        .block(Block::default().borders(Borders::ALL))
+        .row_highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(table, area, &mut self.table);
+    }
+
+    fn draw_detail(&self, frame: &mut Frame, area: Rect, market: &MarketState, symbol: &str) {
+        let [stats, chart] = Layout::vertical([
+            Constraint::Length(self.intervals.len() as u16 + 5),
+            Constraint::Min(0),
+        ])
+        .areas(area);
+
+        let mut lines = Vec::new();
+        if let Some(session) = market.sessions.get(symbol) {
+            let decimals = market.decimals(symbol, session.last_price);
+            lines.push(Line::from(format!(
+                "Session since {}: {:.*} ({:+.2}%)  high {:.*}  low {:.*}  max drawdown {:.2}%",
+                session.started.with_timezone(&Local).format("%H:%M:%S"),
+                decimals,
+                session.last_price,
+                session.change_percent(),
+                decimals,
+                session.high,
+                decimals,
+                session.low,
+                session.max_drawdown_percent,
+            )));
+            lines.push(Line::default());
+        }
+        for interval in &self.intervals {
+            if let Some(kline) = market
+                .latest
+                .get(&(symbol.into(), interval.as_str().into()))
+            {
+                let decimals = market.decimals(symbol, kline.close);
+                lines.push(Line::from(vec![
+                    Span::raw(format!(
+                        "{:>4}  O {:.*}  H {:.*}  L {:.*}  C {:.*}  V {:.2}  ",
+                        interval,
+                        decimals,
+                        kline.open,
+                        decimals,
+                        kline.high,
+                        decimals,
+                        kline.low,
+                        decimals,
+                        kline.close,
+                        kline.volume
+                    )),
+                    Span::styled(
+                        format!("{:+.2}%", kline.price_change_percent()),
+                        change_style(&kline),
+                    ),
+                ]));
+            }
+        }
+        frame.render_widget(
+            Paragraph::new(lines).block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(format!(" {} ", symbol.to_uppercase())),
+            ),
+            stats,
+        );
+
+        let width = chart.width.saturating_sub(2 + LABEL_WIDTH as u16) as usize;
+        let candles: Vec<KlineData> = market
+            .history()
+            .get(&(symbol.into(), self.interval().into()))
+            .map(|history| history.last_n(width).cloned().collect())
+            .unwrap_or_default();
+        let decimals = candles
+            .last()
+            .map_or(2, |candle| market.decimals(symbol, candle.close));
+        frame.render_widget(
+            Paragraph::new(chart_lines(
+                &candles,
+                chart.height.saturating_sub(2) as usize,
+                decimals,
+            ))
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(format!(" {} ", self.interval())),
+            ),
+            chart,
+        );
+    }
+}
+
+/// A candlestick chart as styled lines, with a price axis on the right
+/// labelled with `decimals`.
+fn chart_lines(candles: &[KlineData], height: usize, decimals: usize) -> Vec<Line<'static>> {
+    if candles.is_empty() {
+        return Vec::new();
+    }
+    let chart = chart::render(candles, height);
+    chart
+        .rows
+        .into_iter()
+        .zip(chart.labels)
+        .map(|(row, label)| {
+            let mut spans: Vec<Span> = row
+                .into_iter()
+                .map(|mark| match mark {
+                    Some(mark) => Span::styled(
+                        mark.glyph.to_string(),
+                        Style::new().fg(if mark.rising {
+                            Color::Green
+                        } else {
+                            Color::Red
+                        }),
+                    ),
+                    None => Span::raw(" "),
+                })
+                .collect();
+            if let Some(price) = label {
+                spans.push(Span::styled(
+                    format!(" {:.*}", decimals, price),
+                    Style::new().fg(Color::DarkGray),
+                ));
+            }
+            Line::from(spans)
+        })
+        .collect()
+}
+
+fn change_style(kline: &KlineData) -> Style {
+    if kline.price_change() >= 0.0 {
+        Style::new().fg(Color::Green)
+    } else {
+        Style::new().fg(Color::Red)
+    }
+}
+
+/// Maps values onto `0..=max` between their own low and high, so small moves
+/// of a large price stay visible.
+fn scale(values: &[f64], max: f64) -> Vec<u64> {
+    let low = values.iter().copied().fold(f64::INFINITY, f64::min);
+    let high = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    let range = (high - low).max(f64::EPSILON);
+    values
+        .iter()
+        .map(|value| ((value - low) / range * max).round() as u64)
+        .collect()
+}
+
+/// A one-line sparkline of block characters.
+fn sparkline(values: &[f64]) -> String {
+    scale(values, (SPARK_BLOCKS.len() - 1) as f64)
+        .into_iter()
+        .map(|level| SPARK_BLOCKS[(level as usize).min(SPARK_BLOCKS.len() - 1)])
+        .collect()
+}
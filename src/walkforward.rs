@@ -0,0 +1,277 @@
+//! Walk-forward analysis: optimizes a strategy's parameters on rolling
+//! windows of stored candles, then tests the best of them on the candles
+//! that follow each window, which the optimization never saw.
+
+use crate::alerts::AlertEngine;
+use crate::backtest::{self, Report};
+use crate::config::{Config, StrategyConfig};
+use crate::indicators::IndicatorPipeline;
+use crate::interval::interval_duration;
+use crate::strategy::StrategySet;
+use crate::KlineData;
+use anyhow::{anyhow, bail, Result};
+use chrono::{DateTime, Duration, Utc};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+
+/// A strategy parameter to search and the values to try.
+#[derive(Debug, Clone)]
+pub struct Param {
+    pub name: String,
+    pub values: Vec<f64>,
+}
+
+impl Param {
+    /// Parses `name=value,value,...`, e.g. `fast=5,10,20`.
+    pub fn parse(param: &str) -> Result<Self> {
+        let (name, values) = param
+            .split_once('=')
+            .ok_or_else(|| anyhow!("Expected name=value,... but got '{}'", param))?;
+        let values = values
+            .split(',')
+            .map(|value| {
+                value
+                    .trim()
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|value| value.is_finite())
+                    .ok_or_else(|| anyhow!("Invalid value '{}' for {}", value, name))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self {
+            name: name.trim().to_string(),
+            values,
+        })
+    }
+}
+
+/// Every combination of `grid`'s values, in order.
+fn combinations(grid: &[Param]) -> Vec<BTreeMap<String, f64>> {
+    grid.iter()
+        .fold(vec![BTreeMap::new()], |combinations, param| {
+            combinations
+                .iter()
+                .flat_map(|combination| {
+                    param.values.iter().map(move |value| {
+                        let mut combination = combination.clone();
+                        combination.insert(param.name.clone(), *value);
+                        combination
+                    })
+                })
+                .collect()
+        })
+}
+
+/// One step of the walk: parameters picked on the training candles, and
+/// how they fared on the test candles after them.
+#[derive(Debug, Clone)]
+pub struct Window {
+    pub train_from: DateTime<Utc>,
+    /// End of the training candles and start of the test ones.
+    pub test_from: DateTime<Utc>,
+    pub test_to: DateTime<Utc>,
+    /// The combination with the best training PnL.
+    pub params: BTreeMap<String, f64>,
+    pub train_pnl: f64,
+    pub test: Report,
+}
+
+impl Window {
+    pub fn to_json(&self) -> Value {
+        json!({
+            "train_from": self.train_from.to_rfc3339(),
+            "test_from": self.test_from.to_rfc3339(),
+            "test_to": self.test_to.to_rfc3339(),
+            "params": self.params,
+            "train_pnl": self.train_pnl,
+            "test_pnl": self.test.pnl(),
+            "test_trades": self.test.trades.len(),
+            "test_max_drawdown_percent": self.test.max_drawdown_percent,
+        })
+    }
+}
+
+/// The outcome of a walk-forward analysis.
+#[derive(Debug, Clone)]
+pub struct Analysis {
+    pub strategy: String,
+    pub starting_balance: f64,
+    /// Combinations tried on each training window.
+    pub combinations: usize,
+    pub train: Duration,
+    pub test: Duration,
+    pub windows: Vec<Window>,
+}
+
+impl Analysis {
+    /// PnL over every test window, each starting from the same balance.
+    pub fn pnl(&self) -> f64 {
+        self.windows.iter().map(|window| window.test.pnl()).sum()
+    }
+
+    pub fn trades(&self) -> usize {
+        self.windows
+            .iter()
+            .map(|window| window.test.trades.len())
+            .sum()
+    }
+
+    /// Percent of test trades that made money, or `None` without trades.
+    pub fn win_rate(&self) -> Option<f64> {
+        let trades = self.trades();
+        if trades == 0 {
+            return None;
+        }
+        let wins = self
+            .windows
+            .iter()
+            .flat_map(|window| &window.test.trades)
+            .filter(|trade| trade.pnl > 0.0)
+            .count();
+        Some(wins as f64 / trades as f64 * 100.0)
+    }
+
+    pub fn profitable_windows(&self) -> usize {
+        self.windows
+            .iter()
+            .filter(|window| window.test.pnl() > 0.0)
+            .count()
+    }
+
+    /// Largest drawdown within any test window, in percent.
+    pub fn max_drawdown_percent(&self) -> f64 {
+        self.windows
+            .iter()
+            .map(|window| window.test.max_drawdown_percent)
+            .fold(0.0, f64::max)
+    }
+
+    /// Test PnL per unit of time over training PnL per unit of time: near 1
+    /// when the optimized parameters hold up out of sample, near or below 0
+    /// when they were fitted to noise. `None` unless training made money.
+    pub fn efficiency(&self) -> Option<f64> {
+        let train_pnl: f64 = self.windows.iter().map(|window| window.train_pnl).sum();
+        if train_pnl <= 0.0 || self.windows.is_empty() {
+            return None;
+        }
+        let rate = |pnl: f64, length: Duration| pnl / length.num_seconds().max(1) as f64;
+        Some(rate(self.pnl(), self.test) / rate(train_pnl, self.train))
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "strategy": self.strategy,
+            "starting_balance": self.starting_balance,
+            "combinations": self.combinations,
+            "train_secs": self.train.num_seconds(),
+            "test_secs": self.test.num_seconds(),
+            "pnl": self.pnl(),
+            "trades": self.trades(),
+            "win_rate": self.win_rate(),
+            "profitable_windows": self.profitable_windows(),
+            "max_drawdown_percent": self.max_drawdown_percent(),
+            "efficiency": self.efficiency(),
+            "windows": self.windows.iter().map(Window::to_json).collect::<Vec<_>>(),
+        })
+    }
+}
+
+/// Walks `candles`, closed and ordered as for a backtest, in windows of
+/// `train` then `test`, rolled forward by `test` at a time. On each, the
+/// strategy named `strategy` is backtested alone with every combination of
+/// `grid` over the training candles, and the most profitable is backtested
+/// over the test candles, warmed up on the training ones. `pipeline` and
+/// `strategies` are forked for every run; alert orders are left out.
+pub fn run(
+    config: &Config,
+    candles: &[KlineData],
+    strategy: &str,
+    grid: &[Param],
+    (train, test): (Duration, Duration),
+    pipeline: &IndicatorPipeline,
+    strategies: &StrategySet,
+) -> Result<Analysis> {
+    let Some(base) = config.strategies.iter().find(|s| s.name() == strategy) else {
+        bail!("No strategy named '{}' is configured", strategy);
+    };
+    if train <= Duration::zero() || test <= Duration::zero() {
+        bail!("The training and test windows must be longer than zero");
+    }
+    let (Some(first), Some(last)) = (candles.first(), candles.last()) else {
+        bail!("No candles to walk through");
+    };
+    let end =
+        last.interval_start + interval_duration(&last.interval).unwrap_or_else(Duration::zero);
+
+    // Combinations the strategy rejects, such as a fast period not shorter
+    // than the slow one, are skipped.
+    let mut candidates = Vec::new();
+    let mut rejected = None;
+    for params in combinations(grid) {
+        let mut spec = base.clone();
+        spec.params
+            .extend(params.iter().map(|(name, value)| (name.clone(), *value)));
+        match strategies.with_specs(vec![spec.clone()]) {
+            Ok(_) => candidates.push((params, spec)),
+            Err(e) => rejected = Some(e),
+        }
+    }
+    if candidates.is_empty() {
+        return Err(rejected.unwrap_or_else(|| anyhow!("No parameter combinations to try")));
+    }
+
+    let backtest = |spec: &StrategyConfig, window: &[KlineData], warmup: usize| {
+        let mut config = config.clone();
+        config.strategies = vec![spec.clone()];
+        Ok::<_, anyhow::Error>(backtest::run_warmed(
+            &config,
+            window,
+            warmup,
+            pipeline.fork(),
+            AlertEngine::new(Vec::new(), &[])?,
+            strategies.with_specs(config.strategies.clone())?,
+        ))
+    };
+    let starting = |time: DateTime<Utc>| candles.partition_point(|c| c.interval_start < time);
+
+    let mut windows = Vec::new();
+    let mut train_from = first.interval_start;
+    while train_from + train + test <= end {
+        let test_from = train_from + train;
+        let test_to = test_from + test;
+        let (start, middle, stop) = (starting(train_from), starting(test_from), starting(test_to));
+        train_from += test;
+        if middle == start || stop == middle {
+            continue;
+        }
+
+        // Ties go to the earlier combination.
+        let mut best: Option<(f64, usize)> = None;
+        for (index, (_, spec)) in candidates.iter().enumerate() {
+            let pnl = backtest(spec, &candles[start..middle], 0)?.pnl();
+            if best.is_none_or(|(best, _)| pnl > best) {
+                best = Some((pnl, index));
+            }
+        }
+        let Some((train_pnl, index)) = best else {
+            continue;
+        };
+        let (params, spec) = &candidates[index];
+        windows.push(Window {
+            train_from: test_from - train,
+            test_from,
+            test_to,
+            params: params.clone(),
+            train_pnl,
+            test: backtest(spec, &candles[start..stop], middle - start)?,
+        });
+    }
+    Ok(Analysis {
+        strategy: strategy.to_string(),
+        starting_balance: config.paper.starting_balance,
+        combinations: candidates.len(),
+        train,
+        test,
+        windows,
+    })
+}
@@ -0,0 +1,220 @@
+//! Alert rules evaluated against hand-made candle histories.
+
+mod common;
+
+use common::{candle, minute};
+use crypto_kline_tracker::alerts::{Alert, AlertEngine, SeriesIndicators};
+use crypto_kline_tracker::config::AlertConfig;
+use crypto_kline_tracker::history::CandleHistory;
+#[cfg(feature = "indicators")]
+use crypto_kline_tracker::indicators::{IndicatorPipeline, IndicatorRegistry};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+
+fn rule(toml: &str) -> AlertConfig {
+    toml::from_str(toml).unwrap()
+}
+
+/// The alerts `rules` fire on flat `1m` candles closing at each price, a
+/// minute apart.
+fn fire(rules: Vec<AlertConfig>, closes: &[f64]) -> Vec<Alert> {
+    let mut engine = AlertEngine::new(rules, &["1m".to_string()]).unwrap();
+    let mut history = CandleHistory::new(100);
+    let mut fired = Vec::new();
+    for (n, close) in closes.iter().enumerate() {
+        let kline = candle("btcusdt", *close).minute(n as i64).build();
+        history.update(kline.clone());
+        fired.extend(engine.evaluate(
+            &kline,
+            &history,
+            SeriesIndicators::default(),
+            minute(n as i64),
+        ));
+    }
+    fired
+}
+
+#[test]
+fn price_alerts_fire_on_each_cross_but_not_on_a_price_already_past() {
+    let above = || {
+        rule(
+            r#"
+            kind = "price"
+            direction = "above"
+            price = 100.0
+            "#,
+        )
+    };
+    let fired = fire(vec![above()], &[99.0, 101.0, 102.0, 99.0, 101.0]);
+    assert_eq!(fired.len(), 2);
+    assert_eq!(fired[0].message, "btcusdt close crossed above 100");
+    assert_eq!(fired[0].price, 101.0);
+    assert_eq!(fired[1].triggered_at, minute(4));
+
+    assert!(fire(vec![above()], &[101.0, 102.0]).is_empty());
+}
+
+#[test]
+fn percent_change_catches_a_spike_that_retraces_within_the_window() {
+    let rules = vec![rule(
+        r#"
+        kind = "percent_change"
+        window = "5m"
+        threshold = 3.0
+        "#,
+    )];
+    let mut engine = AlertEngine::new(rules, &["1m".to_string()]).unwrap();
+    let mut history = CandleHistory::new(100);
+    let mut fired = Vec::new();
+    // Up 5% on a wick, closing where it opened.
+    for (n, high) in [(0, 100.0), (1, 100.0), (2, 105.0), (3, 100.0)] {
+        let kline = candle("btcusdt", 100.0)
+            .minute(n)
+            .range(high, 100.0)
+            .build();
+        history.update(kline.clone());
+        fired.extend(engine.evaluate(&kline, &history, SeriesIndicators::default(), minute(n)));
+    }
+    assert_eq!(fired.len(), 1);
+    assert!(fired[0].message.contains("moved -4.76% within 5m"));
+}
+
+#[test]
+fn price_alerts_rearm_past_their_hysteresis_and_respect_their_cooldown() {
+    let rules = vec![rule(
+        r#"
+        kind = "price"
+        direction = "above"
+        price = 100.0
+        rearm_pct = 1.0
+        cooldown_secs = 300
+        "#,
+    )];
+    // 99.5 is within 1% of the threshold, so the next cross is ignored; the
+    // cross after 98 comes 4 minutes after the first alert, inside the
+    // cooldown; the one after that, 6 minutes after, fires.
+    let closes = [99.0, 101.0, 99.5, 101.0, 98.0, 101.0, 98.0, 101.0];
+    let fired = fire(rules, &closes);
+    let times: Vec<_> = fired.iter().map(|alert| alert.triggered_at).collect();
+    assert_eq!(times, [minute(1), minute(7)]);
+}
+
+#[test]
+fn rules_sharing_a_dedup_key_fire_once_per_cooldown() {
+    let above = |price: f64| {
+        rule(&format!(
+            r#"
+            kind = "price"
+            direction = "above"
+            price = {price}
+            cooldown_secs = 60
+            dedup_key = "{{symbol}}"
+            "#
+        ))
+    };
+    let fired = fire(vec![above(100.0), above(100.5)], &[99.0, 101.0]);
+    assert_eq!(fired.len(), 1);
+    assert_eq!(fired[0].rule, "price above 100");
+}
+
+#[test]
+fn expression_rules_fire_when_they_start_to_hold_until_their_rearm_condition() {
+    let rules = vec![rule(
+        r#"
+        kind = "rule"
+        when = "close > 100 and change_pct >= 0"
+        rearm_when = "close < 95"
+        "#,
+    )];
+    let fired = fire(rules, &[99.0, 101.0, 99.0, 101.0, 94.0, 101.0]);
+    let times: Vec<_> = fired.iter().map(|alert| alert.triggered_at).collect();
+    assert_eq!(times, [minute(1), minute(5)]);
+    assert_eq!(
+        fired[0].message,
+        "btcusdt 1m: close > 100 and change_pct >= 0"
+    );
+}
+
+#[test]
+fn invalid_expression_rules_fail_up_front() {
+    let rules = vec![rule(
+        r#"
+        kind = "rule"
+        name = "broken"
+        when = "close >"
+        "#,
+    )];
+    let error = AlertEngine::new(rules, &["1m".to_string()]).err().unwrap();
+    assert_eq!(error.to_string(), "Invalid condition for alert 'broken'");
+}
+
+#[test]
+fn crossover_rules_fire_on_the_candle_where_one_indicator_crosses_another() {
+    let rules = vec![rule(
+        r#"
+        kind = "rule"
+        when = "crosses_above(fast, slow.value)"
+        "#,
+    )];
+    let mut engine = AlertEngine::new(rules, &["1m".to_string()]).unwrap();
+    let mut history = CandleHistory::new(100);
+    // Already above on the first candle, which has nothing to cross from.
+    let values: Vec<BTreeMap<String, Value>> = [3.0, 1.0, 3.0, 4.0]
+        .into_iter()
+        .map(|fast| {
+            BTreeMap::from([
+                ("fast".to_string(), json!(fast)),
+                ("slow".to_string(), json!({ "value": 2.0 })),
+            ])
+        })
+        .collect();
+    let mut fired = Vec::new();
+    for n in 0..values.len() {
+        let kline = candle("btcusdt", 100.0).minute(n as i64).build();
+        history.update(kline.clone());
+        let indicators = SeriesIndicators {
+            latest: Some(&values[n]),
+            previous: n.checked_sub(1).map(|previous| &values[previous]),
+        };
+        fired.extend(engine.evaluate(&kline, &history, indicators, minute(n as i64)));
+    }
+    assert_eq!(fired.len(), 1);
+    assert_eq!(fired[0].triggered_at, minute(2));
+}
+
+#[cfg(feature = "indicators")]
+#[test]
+fn volume_spikes_alert_against_each_symbol_s_own_threshold() {
+    let vol = |symbol: &str, threshold: f64| {
+        toml::from_str(&format!(
+            r#"
+            kind = "volume_spike"
+            name = "vol"
+            symbols = ["{symbol}"]
+            period = 2
+            threshold = {threshold}
+            "#
+        ))
+        .unwrap()
+    };
+    let specs = vec![vol("btcusdt", 3.0), vol("dogeusdt", 6.0)];
+    let mut pipeline = IndicatorPipeline::new(IndicatorRegistry::default(), specs).unwrap();
+    let rules = vec![rule(
+        r#"
+        kind = "indicator_event"
+        indicator = "vol"
+        event = "volume_spike"
+        "#,
+    )];
+    let mut engine = AlertEngine::new(rules, &["1m".to_string()]).unwrap();
+    let mut fired = Vec::new();
+    for symbol in ["btcusdt", "dogeusdt"] {
+        for (n, volume) in [1.0, 1.0, 4.0].into_iter().enumerate() {
+            let kline = candle(symbol, 1.0).minute(n as i64).volume(volume).build();
+            let (_, events) = pipeline.update(&kline);
+            fired.extend(engine.evaluate_events(&kline, &events, minute(n as i64)));
+        }
+    }
+    assert_eq!(fired.len(), 1);
+    assert_eq!(fired[0].message, "btcusdt 1m: vol volume_spike");
+}
@@ -0,0 +1,123 @@
+//! Spreads between venues and the alert on them.
+
+mod common;
+
+use common::{candle, minute};
+use crypto_kline_tracker::alerts::{AlertEngine, SeriesIndicators};
+use crypto_kline_tracker::arbitrage;
+use crypto_kline_tracker::config::{ArbitrageConfig, Config, VenueConfig};
+use crypto_kline_tracker::history::CandleHistory;
+
+#[test]
+fn spreads_pair_every_venue_and_keep_their_extremes() {
+    let config = ArbitrageConfig {
+        enabled: true,
+        ..ArbitrageConfig::default()
+    };
+    let venues = ["binanceus", "local"].map(|name| VenueConfig {
+        name: name.to_string(),
+        stream_url: "ws://127.0.0.1:9443".to_string(),
+        fee_pct: 0.2,
+    });
+    assert_eq!(
+        arbitrage::venue_pairs(&config, &venues),
+        [
+            ("binance", "binanceus", 0.1 + 0.2),
+            ("binance", "local", 0.1 + 0.2),
+            ("binanceus", "local", 0.2 + 0.2),
+        ]
+    );
+
+    let name = arbitrage::spread_name("binance", "binanceus", "btcusdt");
+    assert_eq!(
+        arbitrage::split_spread(&name),
+        Some(("binance", "binanceus", "btcusdt"))
+    );
+    let binance = candle("btcusdt", 100.0)
+        .minute(0)
+        .open(100.0)
+        .closed(false)
+        .build();
+    let first = arbitrage::compute_spread(
+        &name,
+        &binance,
+        &candle("binanceus:btcusdt", 101.0)
+            .minute(0)
+            .open(100.0)
+            .closed(false)
+            .build(),
+        None,
+    )
+    .unwrap();
+    assert_eq!((first.open, first.close), (0.0, 1.0));
+    assert!(!first.closed);
+    let second = arbitrage::compute_spread(
+        &name,
+        &candle("btcusdt", 100.0)
+            .minute(0)
+            .open(100.0)
+            .closed(true)
+            .build(),
+        &candle("binanceus:btcusdt", 99.5)
+            .minute(0)
+            .open(100.0)
+            .closed(true)
+            .build(),
+        Some(&first),
+    )
+    .unwrap();
+    assert_eq!((second.high, second.low, second.close), (1.0, -0.5, -0.5));
+    assert!(second.closed);
+    // The venues must be on the same candle.
+    assert!(arbitrage::compute_spread(
+        &name,
+        &binance,
+        &candle("binanceus:btcusdt", 101.0)
+            .minute(1)
+            .open(100.0)
+            .closed(false)
+            .build(),
+        None
+    )
+    .is_none());
+}
+
+#[test]
+fn fires_past_fees_and_threshold_either_way() {
+    let config: Config = toml::from_str(
+        r#"
+        intervals = ["1m", "5m"]
+
+        [[alerts]]
+        name = "arbitrage"
+        kind = "spread"
+        symbols = ["binance-binanceus:btcusdt"]
+        threshold_pct = 0.3
+        rearm_pct = 0.1
+        "#,
+    )
+    .unwrap();
+    let mut engine = AlertEngine::new(config.alerts, &config.intervals).unwrap();
+    let mut history = CandleHistory::new(10);
+    let mut fired = Vec::new();
+    for (n, spread) in [0.1, 0.35, 0.4, 0.25, 0.15, -0.5].into_iter().enumerate() {
+        let kline = candle("binance-binanceus:btcusdt", spread)
+            .minute(n as i64)
+            .open(0.0)
+            .build();
+        history.update(kline.clone());
+        let now = minute(n as i64);
+        for alert in engine.evaluate(&kline, &history, SeriesIndicators::default(), now) {
+            fired.push(alert.message);
+        }
+    }
+    // Still within 0.1 points of the threshold at 0.25, so not re-armed
+    // until 0.15.
+    assert_eq!(
+        fired,
+        [
+            "btcusdt is 0.35% dearer on binanceus than on binance, past the 0.30% of fees and threshold",
+            "btcusdt is 0.50% dearer on binance than on binanceus, past the 0.30% of fees and threshold",
+        ]
+    );
+}
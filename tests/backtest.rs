@@ -0,0 +1,52 @@
+//! Backtests of a strategy over hand-made candles.
+
+#![cfg(feature = "indicators")]
+
+mod common;
+
+use common::candle;
+use crypto_kline_tracker::alerts::AlertEngine;
+use crypto_kline_tracker::backtest;
+use crypto_kline_tracker::config::Config;
+use crypto_kline_tracker::indicators::{IndicatorPipeline, IndicatorRegistry};
+use crypto_kline_tracker::strategy::{StrategyRegistry, StrategySet};
+
+#[test]
+fn backtests_report_round_trips_equity_and_drawdown() {
+    let config: Config = toml::from_str(
+        r#"
+        [paper]
+        taker_fee_pct = 0.0
+
+        [[strategies]]
+        kind = "sma_cross"
+        fast = 1
+        slow = 2
+        quantity = 1.0
+        "#,
+    )
+    .unwrap();
+    let candles: Vec<_> = [100.0, 100.0, 110.0, 120.0, 100.0, 90.0]
+        .into_iter()
+        .enumerate()
+        .map(|(n, close)| candle("btcusdt", close).minute(n as i64).build())
+        .collect();
+    let pipeline = IndicatorPipeline::new(IndicatorRegistry::default(), Vec::new()).unwrap();
+    let alerts = AlertEngine::new(Vec::new(), &config.intervals).unwrap();
+    let strategies =
+        StrategySet::new(StrategyRegistry::default(), config.strategies.clone()).unwrap();
+
+    let report = backtest::run(&config, &candles, pipeline, alerts, strategies);
+    assert_eq!(report.candles, 6);
+    // Bought at 110 on the cross up, sold at 100 on the cross down.
+    assert_eq!(report.trades.len(), 1);
+    let trade = &report.trades[0];
+    assert_eq!((trade.entry_price, trade.exit_price), (110.0, 100.0));
+    assert_eq!(trade.pnl, -10.0);
+    assert_eq!(report.pnl(), -10.0);
+    assert_eq!(report.win_rate(), Some(0.0));
+    assert!(report.open_positions.is_empty());
+    // From the peak of 10 010 at 120 down to 9 990.
+    assert!((report.max_drawdown_percent - 20.0 / 10_010.0 * 100.0).abs() < 1e-9);
+    assert_eq!(report.equity.len(), 6);
+}
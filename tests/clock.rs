@@ -0,0 +1,46 @@
+//! Time-dependent limits driven by a virtual clock instead of the system's.
+
+use chrono::{Duration, TimeZone, Utc};
+use crypto_kline_tracker::clock::VirtualClock;
+use crypto_kline_tracker::config::{RiskConfig, Side};
+use crypto_kline_tracker::risk::{KillSwitch, RiskLimits};
+use std::sync::Arc;
+
+fn limits(clock: &Arc<VirtualClock>) -> RiskLimits {
+    let config = RiskConfig {
+        max_daily_loss: Some(50.0),
+        max_orders_per_minute: Some(2),
+        ..RiskConfig::default()
+    };
+    RiskLimits::new(&config, KillSwitch::default()).with_clock(clock.clone())
+}
+
+#[test]
+fn order_rate_counts_the_last_minute() {
+    let clock = Arc::new(VirtualClock::new(
+        Utc.with_ymd_and_hms(2024, 3, 1, 12, 0, 0).unwrap(),
+    ));
+    let mut limits = limits(&clock);
+    assert!(limits.approve("btcusdt", Side::Buy, 10.0, 0.0).is_ok());
+    clock.advance(Duration::seconds(30));
+    assert!(limits.approve("btcusdt", Side::Buy, 10.0, 0.0).is_ok());
+    assert!(limits.approve("btcusdt", Side::Sell, 10.0, 0.0).is_err());
+
+    // The first order drops out of the window a minute after it was placed.
+    clock.advance(Duration::seconds(30));
+    assert!(limits.approve("btcusdt", Side::Sell, 10.0, 0.0).is_ok());
+}
+
+#[test]
+fn daily_loss_resets_at_midnight() {
+    let clock = Arc::new(VirtualClock::new(
+        Utc.with_ymd_and_hms(2024, 3, 1, 23, 0, 0).unwrap(),
+    ));
+    let mut limits = limits(&clock);
+    limits.record_pnl(-60.0);
+    assert!(limits.approve("btcusdt", Side::Buy, 10.0, 0.0).is_err());
+    assert!(limits.approve("btcusdt", Side::Sell, 10.0, 0.0).is_ok());
+
+    clock.set(Utc.with_ymd_and_hms(2024, 3, 2, 0, 0, 0).unwrap());
+    assert!(limits.approve("btcusdt", Side::Buy, 10.0, 0.0).is_ok());
+}
@@ -0,0 +1,79 @@
+//! Fixtures shared by the integration tests.
+
+// Each test crate uses only some of them.
+#![allow(dead_code)]
+
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use crypto_kline_tracker::KlineData;
+use std::sync::Arc;
+
+/// When minute `n` of the fixtures starts, counting from
+/// 2023-11-14 22:13:20 UTC.
+pub fn minute(n: i64) -> DateTime<Utc> {
+    Utc.timestamp_opt(1_700_000_000, 0).unwrap() + Duration::minutes(n)
+}
+
+/// A closed `1m` candle of `symbol` starting at minute 0, flat at `close`
+/// with a volume of 1, to adjust with the [`Candle`] methods.
+pub fn candle(symbol: &str, close: f64) -> Candle {
+    Candle(KlineData {
+        symbol: Arc::from(symbol),
+        interval: Arc::from("1m"),
+        interval_start: minute(0),
+        open: close,
+        high: close,
+        low: close,
+        close,
+        volume: 1.0,
+        closed: true,
+        timing: None,
+    })
+}
+
+/// Builds a [`KlineData`] started by [`candle`].
+pub struct Candle(KlineData);
+
+impl Candle {
+    pub fn interval(mut self, interval: &str) -> Self {
+        self.0.interval = Arc::from(interval);
+        self
+    }
+
+    /// Starts at minute `n`.
+    pub fn minute(self, n: i64) -> Self {
+        self.at(minute(n))
+    }
+
+    pub fn at(mut self, start: DateTime<Utc>) -> Self {
+        self.0.interval_start = start;
+        self
+    }
+
+    /// Opens at `open`, widening the range to take it in.
+    pub fn open(mut self, open: f64) -> Self {
+        self.0.open = open;
+        self.0.high = self.0.high.max(open);
+        self.0.low = self.0.low.min(open);
+        self
+    }
+
+    pub fn range(mut self, high: f64, low: f64) -> Self {
+        self.0.high = high;
+        self.0.low = low;
+        self
+    }
+
+    pub fn volume(mut self, volume: f64) -> Self {
+        self.0.volume = volume;
+        self
+    }
+
+    pub fn closed(mut self, closed: bool) -> Self {
+        self.0.closed = closed;
+        self
+    }
+
+    pub fn build(self) -> KlineData {
+        self.0
+    }
+}
@@ -0,0 +1,88 @@
+//! The stablecoin depeg alert and summary.
+
+mod common;
+
+use common::{candle, minute};
+use crypto_kline_tracker::alerts::{AlertEngine, SeriesIndicators};
+use crypto_kline_tracker::config::{Config, DepegConfig};
+use crypto_kline_tracker::depeg;
+use crypto_kline_tracker::history::CandleHistory;
+use crypto_kline_tracker::session::SessionStats;
+use crypto_kline_tracker::state::MarketState;
+
+#[test]
+fn fires_past_the_threshold_and_rearms_inside_it() {
+    let config: Config = toml::from_str(
+        r#"
+        intervals = ["1m", "5m"]
+
+        [[alerts]]
+        name = "depeg"
+        kind = "depeg"
+        threshold_bps = 50
+        rearm_pct = 0.1
+        "#,
+    )
+    .unwrap();
+    let mut engine = AlertEngine::new(config.alerts, &config.intervals).unwrap();
+    let mut history = CandleHistory::new(10);
+    let mut fired = Vec::new();
+    for (n, close) in [0.9990, 0.9940, 0.9930, 0.9965, 0.9995, 1.0060]
+        .into_iter()
+        .enumerate()
+    {
+        let kline = candle("usdcusdt", close).open(1.0).minute(n as i64).build();
+        history.update(kline.clone());
+        let now = minute(n as i64);
+        for alert in engine.evaluate(&kline, &history, SeriesIndicators::default(), now) {
+            fired.push((alert.price, alert.message));
+        }
+        // Only the finest interval is watched.
+        let five = candle("usdcusdt", close).open(1.0).interval("5m").build();
+        assert!(engine
+            .evaluate(&five, &history, SeriesIndicators::default(), now)
+            .is_empty());
+    }
+    // Still 35 bps out at 0.9965, so not re-armed until 0.9995.
+    assert_eq!(
+        fired,
+        [
+            (
+                0.9940,
+                "usdcusdt is 60.0 bps below its peg at 0.9940".to_string()
+            ),
+            (
+                1.0060,
+                "usdcusdt is 60.0 bps above its peg at 1.0060".to_string()
+            ),
+        ]
+    );
+}
+
+#[test]
+fn summary_marks_depegged_pairs() {
+    let config = DepegConfig {
+        enabled: true,
+        symbols: vec!["usdcusdt".to_string(), "fdusdusdt".to_string()],
+        ..DepegConfig::default()
+    };
+    let state = MarketState::default();
+    assert_eq!(
+        depeg::summary_line(&config, &state).unwrap(),
+        "usdcusdt no price yet | fdusdusdt no price yet"
+    );
+    let kline = candle("usdcusdt", 0.9921).open(1.0).build();
+    state.sessions.insert(
+        kline.symbol.clone(),
+        SessionStats::new(&kline, kline.interval_start),
+    );
+    assert_eq!(
+        depeg::summary_line(&config, &state).unwrap(),
+        "usdcusdt 0.9921 (-79.0 bps, DEPEGGED) | fdusdusdt no price yet"
+    );
+    let off = DepegConfig {
+        enabled: false,
+        ..config
+    };
+    assert!(depeg::summary_line(&off, &state).is_none());
+}
@@ -0,0 +1,89 @@
+//! The periodic market digest, built from a hand-made market state.
+
+mod common;
+
+use common::{candle, minute};
+use crypto_kline_tracker::config::Config;
+use crypto_kline_tracker::digest::Digest;
+use crypto_kline_tracker::health::{Health, StreamState};
+use crypto_kline_tracker::session::SessionStats;
+use crypto_kline_tracker::state::SharedState;
+use crypto_kline_tracker::KlineData;
+
+fn update(state: &SharedState, kline: KlineData) {
+    state
+        .sessions
+        .entry(kline.symbol.clone())
+        .and_modify(|session| session.update(&kline))
+        .or_insert_with(|| SessionStats::new(&kline, kline.interval_start));
+    state.history_mut().update(kline, 100, None);
+}
+
+#[test]
+fn digest_covers_movers_volume_spikes_and_streams() {
+    let state = SharedState::default();
+    // The volume spike indicator compares with the 20 candles before.
+    for n in 0..30 {
+        let btc = candle("btcusdt", 100.0 + n as f64).minute(n);
+        update(&state, btc.volume(10.0).build());
+        let volume = if n == 29 { 50.0 } else { 10.0 };
+        let doge = candle("dogeusdt", 0.1 - 0.001 * n as f64).minute(n);
+        update(&state, doge.volume(volume).build());
+    }
+    let health = Health::new(std::time::Duration::from_secs(60));
+    health.set_stream_state("btcusdt", "1m", StreamState::Connected);
+    health.set_stream_state("dogeusdt", "1m", StreamState::Connecting);
+
+    let mut digest = Digest::new(&Config::default(), minute(25));
+    let lines = digest.summarize(&state, &health, minute(30));
+    assert!(lines[0].ends_with(": 2 symbol(s), traded 6356.49 USDT"));
+    assert_eq!(lines[1], "Top gainers: BTCUSDT +29.00% (129.00)");
+    assert_eq!(lines[2], "Top losers: DOGEUSDT -29.00% (0.0710)");
+    assert!(lines[3].starts_with("Volume spikes: DOGEUSDT 1m 5.0x at "));
+    assert_eq!(
+        lines[4],
+        "Streams: 1 connected, 1 connecting, 0 disconnected; silent: btcusdt 1m"
+    );
+
+    // Changes are measured from the previous digest, and volume is totalled
+    // by quote asset.
+    update(
+        &state,
+        candle("btcusdt", 150.0).minute(30).volume(10.0).build(),
+    );
+    update(
+        &state,
+        candle("ethbtc", 0.05).minute(30).volume(100.0).build(),
+    );
+    let lines = digest.summarize(&state, &health, minute(31));
+    assert!(lines[0].ends_with(": 3 symbol(s), traded 5.00 BTC, 1500.00 USDT"));
+    assert_eq!(lines[1], "Top gainers: BTCUSDT +16.28% (150.00)");
+    assert_eq!(lines[2], "Top losers: none");
+    assert_eq!(lines[3], "Volume spikes: none");
+}
+
+#[test]
+fn digest_spikes_follow_the_volume_spike_indicator() {
+    let state = SharedState::default();
+    for n in 0..10 {
+        let volume = if n == 9 { 25.0 } else { 10.0 };
+        update(
+            &state,
+            candle("btcusdt", 100.0).minute(n).volume(volume).build(),
+        );
+    }
+    let health = Health::new(std::time::Duration::from_secs(60));
+    let config: Config = toml::from_str(
+        r#"
+        [[indicators]]
+        kind = "volume_spike"
+        period = 5
+        threshold = 2.0
+        "#,
+    )
+    .unwrap();
+
+    let mut digest = Digest::new(&config, minute(5));
+    let lines = digest.summarize(&state, &health, minute(10));
+    assert!(lines[3].starts_with("Volume spikes: BTCUSDT 1m 2.5x at "));
+}
@@ -0,0 +1,200 @@
+//! The tracker's output formats, compared with the files under
+//! `tests/golden`, so that any change to what sinks, APIs and scrapers are
+//! sent shows up in review. Run with `UPDATE_GOLDEN=1` to rewrite the files
+//! after a deliberate change.
+
+mod common;
+
+use chrono::{TimeZone, Utc};
+use common::candle;
+use crypto_kline_tracker::alerts::Alert;
+use crypto_kline_tracker::config::Side;
+use crypto_kline_tracker::events::Event;
+use crypto_kline_tracker::journal::{Account, JournalEntry};
+use crypto_kline_tracker::metrics::Metrics;
+use crypto_kline_tracker::processor::{alert_line, indicators_line, kline_line, signal_line};
+use crypto_kline_tracker::strategy::TradeSignal;
+use crypto_kline_tracker::KlineData;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Compares `actual` with the golden file `name`, or rewrites the file when
+/// `UPDATE_GOLDEN` is set.
+fn assert_golden(name: &str, actual: &str) {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(name);
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        std::fs::write(&path, actual).unwrap();
+        return;
+    }
+    let expected = std::fs::read_to_string(&path)
+        .unwrap_or_else(|e| panic!("Failed to read {}: {}", path.display(), e));
+    assert!(
+        actual == expected,
+        "{} differs from what was produced:\n{}\nRerun with UPDATE_GOLDEN=1 if the change is deliberate.",
+        path.display(),
+        actual
+    );
+}
+
+fn kline() -> KlineData {
+    candle("btcusdt", 61388.12)
+        .at(Utc.with_ymd_and_hms(2024, 3, 1, 12, 30, 0).unwrap())
+        .open(61250.5)
+        .range(61410.0, 61190.25)
+        .volume(12.5)
+        .build()
+}
+
+fn indicators() -> BTreeMap<String, Value> {
+    BTreeMap::from([
+        ("rsi_14".to_string(), json!(63.25)),
+        (
+            "bollinger".to_string(),
+            json!({ "lower": 61012.5, "middle": 61240.0, "upper": 61467.5 }),
+        ),
+    ])
+}
+
+fn alert() -> Alert {
+    Alert {
+        rule: "btc breakout".to_string(),
+        symbol: "btcusdt".to_string(),
+        interval: "1m".to_string(),
+        price: 61388.12,
+        change_percent: 0.2247,
+        message: "btcusdt 1m: close > bollinger.middle".to_string(),
+        triggered_at: Utc.with_ymd_and_hms(2024, 3, 1, 12, 31, 0).unwrap(),
+    }
+}
+
+fn signal() -> TradeSignal {
+    TradeSignal {
+        strategy: "sma_cross".to_string(),
+        symbol: "btcusdt".to_string(),
+        interval: "1m".to_string(),
+        side: Side::Buy,
+        strength: 0.75,
+        reason: "sma_10 crossed above sma_30".to_string(),
+        price: 61388.12,
+        indicators: indicators(),
+        raised_at: Utc.with_ymd_and_hms(2024, 3, 1, 12, 31, 0).unwrap(),
+    }
+}
+
+fn trades() -> Vec<JournalEntry> {
+    let filled_at = Utc.with_ymd_and_hms(2024, 3, 1, 12, 31, 0).unwrap();
+    vec![
+        JournalEntry {
+            account: Account::Paper,
+            order_id: "paper-1".to_string(),
+            symbol: "btcusdt".to_string(),
+            side: Side::Buy,
+            quantity: 0.01,
+            price: 61388.12,
+            fee: 0.61388,
+            entry_price: None,
+            pnl: None,
+            reason: "sma_cross: sma_10 crossed above sma_30".to_string(),
+            indicators: json!({ "1m": { "rsi_14": 63.25 } }),
+            filled_at,
+        },
+        JournalEntry {
+            account: Account::Live,
+            order_id: "123456".to_string(),
+            symbol: "btcusdt".to_string(),
+            side: Side::Sell,
+            quantity: 0.01,
+            price: 62000.0,
+            fee: 0.0,
+            entry_price: Some(61388.12),
+            pnl: Some(6.1188),
+            reason: "take profit, \"manual\"\nafter review".to_string(),
+            indicators: json!({}),
+            filled_at: filled_at + chrono::Duration::hours(2),
+        },
+    ]
+}
+
+#[test]
+fn event_json() {
+    let events = [
+        Event::Kline(kline()),
+        Event::Alert(alert()),
+        Event::Signal(signal()),
+    ];
+    let mut lines = String::new();
+    for event in &events {
+        lines += &event.to_json().to_string();
+        lines.push('\n');
+    }
+    for trade in trades() {
+        lines += &trade.to_json().to_string();
+        lines.push('\n');
+    }
+    assert_golden("events.jsonl", &lines);
+}
+
+#[test]
+fn trades_csv() {
+    let mut csv = String::from(JournalEntry::CSV_HEADER);
+    csv.push('\n');
+    for trade in trades() {
+        csv += &trade.csv_row();
+        csv.push('\n');
+    }
+    assert_golden("trades.csv", &csv);
+}
+
+#[test]
+fn prometheus() {
+    let metrics = Metrics::default();
+    let key = (Arc::from("btcusdt"), Arc::from("1m"));
+    metrics.record_connected(&key);
+    metrics.record_connected(&key);
+    for _ in 0..3 {
+        metrics.record_message(&key, 420);
+    }
+    metrics.record_parse_error(&key);
+    metrics.record_dropped(&key);
+    metrics.record_coalesced(&key);
+    let channel = metrics.channel("klines-0", 100, || 7);
+    channel.record_send(Duration::ZERO);
+    channel.record_send(Duration::from_millis(250));
+    metrics.record_latency("parse", Duration::from_micros(40));
+    metrics.record_latency("parse", Duration::from_micros(60));
+    metrics.record_panic("notifier");
+
+    // Rates and the time of the last message depend on when the test runs.
+    let exposition: String = metrics
+        .to_prometheus()
+        .lines()
+        .map(|line| match line.split_once("} ") {
+            Some((series, _))
+                if series.starts_with("kline_stream_messages_per_second")
+                    || series.starts_with("kline_stream_bytes_per_second")
+                    || series.starts_with("kline_stream_last_message_timestamp_seconds") =>
+            {
+                format!("{}}} <varies>\n", series)
+            }
+            _ => format!("{}\n", line),
+        })
+        .collect();
+    assert_golden("metrics.prom", &exposition);
+}
+
+#[test]
+fn log_lines() {
+    let processed = Utc.with_ymd_and_hms(2024, 3, 1, 12, 31, 0).unwrap() + Duration::from_millis(5);
+    let lines = [
+        kline_line(&kline(), 2, processed),
+        indicators_line(&kline(), &indicators()),
+        alert_line(&alert(), 2, &Utc),
+        signal_line(&signal()),
+    ];
+    assert_golden("log.txt", &(lines.join("\n") + "\n"));
+}
@@ -0,0 +1,127 @@
+//! Halted and delisted symbols found in a local stand-in for the exchange
+//! info.
+
+use chrono::{TimeZone, Utc};
+use crypto_kline_tracker::actor::PanicReporter;
+use crypto_kline_tracker::clock::VirtualClock;
+use crypto_kline_tracker::config::{HaltsConfig, MultiplexConfig, QueueConfig, RestartConfig};
+use crypto_kline_tracker::events::Event;
+use crypto_kline_tracker::halts;
+use crypto_kline_tracker::health::Health;
+use crypto_kline_tracker::metrics::Metrics;
+use crypto_kline_tracker::shard::{Router, Shards};
+use crypto_kline_tracker::state::SharedState;
+use crypto_kline_tracker::streams::Streams;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio::sync::{broadcast, mpsc};
+
+fn pair(base: &str, status: &str) -> String {
+    format!(
+        r#"{{"symbol":"{base}USDT","status":"{status}","baseAsset":"{base}","quoteAsset":"USDT"}}"#
+    )
+}
+
+/// Has ETHUSDT in a break at first and trading after, and never lists
+/// DOGEUSDT.
+async fn serve() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        let mut polls = 0;
+        loop {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = vec![0; 4096];
+            let _ = socket.read(&mut request).await.unwrap();
+            polls += 1;
+            let eth = if polls == 1 { "BREAK" } else { "TRADING" };
+            let body = format!(
+                r#"{{"symbols":[{},{}]}}"#,
+                pair("BTC", "TRADING"),
+                pair("ETH", eth)
+            );
+            let response = format!(
+                "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            socket.write_all(response.as_bytes()).await.unwrap();
+        }
+    });
+    url
+}
+
+#[tokio::test]
+async fn halted_symbols_stop_streaming_until_they_trade() {
+    let url = serve().await;
+    let metrics = Metrics::default();
+    let health = Health::new(Duration::from_secs(60));
+    let (ops_tx, _ops_rx) = mpsc::channel(10);
+    let (router, _receivers) = Router::new(
+        &QueueConfig::default(),
+        Shards::new(1, &[]),
+        metrics.clone(),
+    );
+    let streams = Streams::new(
+        router,
+        RestartConfig::default(),
+        MultiplexConfig::default(),
+        ops_tx.clone(),
+        health.clone(),
+        metrics.clone(),
+        PanicReporter::new(metrics, ops_tx, false),
+    )
+    // Nothing listens here; only the subscriptions are checked.
+    .with_stream_url("ws://127.0.0.1:1");
+    for symbol in ["btcusdt", "ethusdt", "dogeusdt"] {
+        streams.add(symbol, "1m");
+    }
+    let state = SharedState::default();
+    let (events_tx, mut events) = broadcast::channel(10);
+    let (notify_tx, _notifications) = mpsc::channel(10);
+    let now = Utc.with_ymd_and_hms(2024, 3, 1, 12, 0, 0).unwrap();
+    let config = HaltsConfig {
+        enabled: true,
+        interval_secs: 1,
+        url,
+        ..HaltsConfig::default()
+    };
+    let watcher = tokio::spawn(halts::run(
+        config,
+        state.clone(),
+        streams.clone(),
+        health,
+        events_tx,
+        notify_tx,
+        Arc::new(VirtualClock::new(now)),
+    ));
+    tokio::time::sleep(Duration::from_millis(1500)).await;
+    watcher.abort();
+
+    let mut messages = Vec::new();
+    while let Ok(Event::Alert(alert)) = events.try_recv() {
+        assert_eq!((&*alert.rule, &*alert.interval), ("halt", "1m"));
+        messages.push(alert.message);
+    }
+    assert_eq!(
+        messages,
+        [
+            "Binance reports dogeusdt as DELISTED; stopped streaming it",
+            "Binance reports ethusdt as BREAK; stopped streaming it",
+            "Binance reports ethusdt trading again; streaming it again",
+        ]
+    );
+    assert_eq!(
+        streams.list(),
+        [
+            ("btcusdt".to_string(), "1m".to_string()),
+            ("ethusdt".to_string(), "1m".to_string()),
+        ]
+    );
+    let status = state.trading_status.get("dogeusdt").unwrap().clone();
+    assert_eq!((&*status.status, status.since), ("DELISTED", now));
+    assert!(state.trading_status.get("ethusdt").is_none());
+    streams.stop_all();
+}
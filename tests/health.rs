@@ -0,0 +1,56 @@
+//! What the health checks report about streams and sinks.
+
+use crypto_kline_tracker::health::{Health, StreamState};
+use serde_json::json;
+use std::time::Duration;
+
+#[test]
+fn probes_report_sink_status_without_errors() {
+    let health = Health::new(Duration::from_secs(60));
+    health.set_stream_state("btcusdt", "1m", StreamState::Connecting);
+    health.record_delivery(
+        "webhook",
+        Some("HTTP status server error (500)".to_string()),
+    );
+
+    // A failing sink fails neither check.
+    let liveness = health.liveness();
+    assert!(liveness.ok);
+    assert_eq!(
+        liveness.body["sinks"],
+        json!([{ "name": "webhook", "healthy": false }])
+    );
+    assert!(!health.readiness().ok);
+
+    let sinks = health.sinks();
+    assert_eq!(sinks[0]["last_error"], "HTTP status server error (500)");
+}
+
+#[test]
+fn streams_are_ready_once_connected_and_live_until_they_drop_or_go_quiet() {
+    let health = Health::new(Duration::from_secs(60));
+    let key = ("btcusdt".into(), "1m".into());
+    health.set_stream_state("btcusdt", "1m", StreamState::Connecting);
+    assert!(health.liveness().ok);
+    assert!(!health.readiness().ok);
+
+    health.set_stream_state("btcusdt", "1m", StreamState::Connected);
+    // Connected but yet to deliver anything.
+    assert!(health.liveness().ok);
+    assert!(!health.readiness().ok);
+    health.record_message(&key);
+    let readiness = health.readiness();
+    assert!(readiness.ok);
+    assert_eq!(readiness.body["status"], "ok");
+    assert_eq!(readiness.body["streams"][0]["state"], "connected");
+
+    health.set_stream_state("btcusdt", "1m", StreamState::Disconnected);
+    let liveness = health.liveness();
+    assert!(!liveness.ok);
+    assert_eq!(liveness.body["status"], "unavailable");
+
+    let quiet = Health::new(Duration::ZERO);
+    quiet.set_stream_state("btcusdt", "1m", StreamState::Connected);
+    quiet.record_message(&key);
+    assert!(!quiet.liveness().ok);
+}
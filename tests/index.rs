@@ -0,0 +1,135 @@
+//! Composite prices across venues, computed and replayed through the
+//! pipeline.
+
+mod common;
+
+use common::candle;
+use crypto_kline_tracker::alerts::AlertEngine;
+use crypto_kline_tracker::config::{Config, IndexMethod};
+use crypto_kline_tracker::events::Event;
+use crypto_kline_tracker::index;
+use crypto_kline_tracker::indicators::{IndicatorPipeline, IndicatorRegistry};
+use crypto_kline_tracker::mock::kline_message;
+use crypto_kline_tracker::replay;
+use crypto_kline_tracker::strategy::{StrategyRegistry, StrategySet};
+use std::sync::Arc;
+
+#[test]
+fn combines_the_venues_on_the_current_candle() {
+    let legs = [
+        candle("btcusdt", 100.0).open(100.0).volume(30.0).build(),
+        candle("binanceus:btcusdt", 101.0)
+            .open(100.0)
+            .volume(10.0)
+            .build(),
+        // Far off, and traded a lot.
+        candle("local:btcusdt", 110.0)
+            .open(100.0)
+            .volume(60.0)
+            .build(),
+    ];
+    let median =
+        index::compute_index("index:btcusdt", &legs, IndexMethod::Median, 2, None).unwrap();
+    assert_eq!((median.open, median.close), (100.0, 101.0));
+    assert_eq!((median.high, median.low), (101.0, 100.0));
+    assert_eq!(median.volume, 100.0);
+    assert!(median.closed);
+
+    let weighted =
+        index::compute_index("index:btcusdt", &legs, IndexMethod::VolumeWeighted, 2, None).unwrap();
+    assert!((weighted.close - (100.0 * 0.3 + 101.0 * 0.1 + 110.0 * 0.6)).abs() < 1e-9);
+
+    // A venue still on the previous candle is left out.
+    let behind = [
+        candle("btcusdt", 102.0)
+            .minute(1)
+            .open(100.0)
+            .volume(30.0)
+            .build(),
+        candle("binanceus:btcusdt", 101.0)
+            .open(100.0)
+            .volume(10.0)
+            .build(),
+        candle("local:btcusdt", 104.0)
+            .minute(1)
+            .open(100.0)
+            .volume(60.0)
+            .build(),
+    ];
+    let index =
+        index::compute_index("index:btcusdt", &behind, IndexMethod::Median, 2, None).unwrap();
+    assert_eq!((index.close, index.volume), (103.0, 90.0));
+    assert!(index::compute_index("index:btcusdt", &behind, IndexMethod::Median, 3, None).is_none());
+}
+
+#[tokio::test]
+async fn index_is_a_series_of_its_own() {
+    let config: Config = toml::from_str(
+        r#"
+        symbols = ["btcusdt"]
+
+        [[venues]]
+        name = "binanceus"
+        stream_url = "ws://127.0.0.1:9443"
+
+        [index]
+        enabled = true
+        symbols = ["btcusdt"]
+
+        [[alerts]]
+        name = "index above 101"
+        kind = "price"
+        symbols = ["index:btcusdt"]
+        direction = "above"
+        price = 101
+        "#,
+    )
+    .unwrap();
+    let messages = [
+        candle("btcusdt", 100.0).open(100.0).volume(5.0).build(),
+        candle("binanceus:btcusdt", 101.0)
+            .open(100.0)
+            .volume(5.0)
+            .build(),
+        candle("btcusdt", 101.0)
+            .minute(1)
+            .open(100.0)
+            .volume(5.0)
+            .build(),
+        candle("binanceus:btcusdt", 102.0)
+            .minute(1)
+            .open(100.0)
+            .volume(5.0)
+            .build(),
+    ]
+    .map(|kline| kline_message(&kline));
+    let pipeline = IndicatorPipeline::new(IndicatorRegistry::with_builtins(), Vec::new()).unwrap();
+    let alert_engine = AlertEngine::new(config.alerts.clone(), &config.intervals).unwrap();
+    let strategies = StrategySet::new(StrategyRegistry::with_builtins(), Vec::new()).unwrap();
+    let recording = replay::replay(messages, config, pipeline, alert_engine, strategies)
+        .await
+        .unwrap();
+
+    let closes: Vec<f64> = recording
+        .events
+        .iter()
+        .filter_map(|event| match event {
+            Event::Kline(kline) if &*kline.symbol == "index:btcusdt" => Some(kline.close),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(closes, [100.5, 101.5]);
+    let alerts: Vec<_> = recording
+        .events
+        .iter()
+        .filter_map(|event| match event {
+            Event::Alert(alert) => Some((&*alert.symbol, alert.price)),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(alerts, [("index:btcusdt", 101.5)]);
+    assert!(recording
+        .state
+        .latest
+        .contains_key(&(Arc::from("index:btcusdt"), Arc::from("1m"))));
+}
@@ -0,0 +1,288 @@
+//! Indicators run through the pipeline on hand-made candle series.
+
+mod common;
+
+use common::candle;
+use crypto_kline_tracker::config::IndicatorConfig;
+use crypto_kline_tracker::indicators::{Indicator, IndicatorPipeline, IndicatorRegistry};
+use crypto_kline_tracker::KlineData;
+use serde_json::{json, Value};
+
+fn spec(toml: &str) -> IndicatorConfig {
+    toml::from_str(toml).unwrap()
+}
+
+/// The output of the one indicator in `toml` after each candle, a minute
+/// apart.
+#[cfg(feature = "indicators")]
+fn outputs(toml: &str, candles: impl IntoIterator<Item = common::Candle>) -> Vec<Option<Value>> {
+    let config = spec(toml);
+    let name = config.name();
+    let mut pipeline = IndicatorPipeline::new(IndicatorRegistry::default(), vec![config]).unwrap();
+    candles
+        .into_iter()
+        .enumerate()
+        .map(|(n, candle)| {
+            let (values, _) = pipeline.update(&candle.minute(n as i64).build());
+            values.get(&name).cloned()
+        })
+        .collect()
+}
+
+#[cfg(feature = "indicators")]
+fn approx(value: &Value, expected: f64) -> bool {
+    (value.as_f64().unwrap() - expected).abs() < 1e-9
+}
+
+#[cfg(feature = "indicators")]
+#[test]
+fn indicators_run_only_on_their_series_under_their_names() {
+    let specs = vec![
+        spec(
+            r#"
+            kind = "sma"
+            period = 2
+            symbols = ["btcusdt"]
+            intervals = ["1m"]
+            "#,
+        ),
+        spec(
+            r#"
+            kind = "sma"
+            name = "slow"
+            period = 3
+            "#,
+        ),
+    ];
+    let mut pipeline = IndicatorPipeline::new(IndicatorRegistry::default(), specs).unwrap();
+    for (n, close) in [1.0, 2.0, 3.0].into_iter().enumerate() {
+        pipeline.update(&candle("btcusdt", close).minute(n as i64).build());
+        pipeline.update(&candle("ethusdt", close).minute(n as i64).build());
+    }
+
+    let btc = pipeline.values("btcusdt", "1m").unwrap();
+    assert_eq!(btc["sma_2"], 2.5);
+    assert_eq!(btc["slow"], 2.0);
+    assert_eq!(
+        pipeline.previous_values("btcusdt", "1m").unwrap()["sma_2"],
+        1.5
+    );
+    let eth = pipeline.values("ethusdt", "1m").unwrap();
+    assert!(!eth.contains_key("sma_2"));
+    assert_eq!(eth["slow"], 2.0);
+}
+
+#[cfg(feature = "indicators")]
+#[test]
+fn rate_of_change_and_momentum_compare_with_the_close_periods_back() {
+    let closes = || [100.0, 110.0, 121.0].map(|close| candle("btcusdt", close));
+    let roc = outputs("kind = \"roc\"\nperiod = 2", closes());
+    assert_eq!(roc[..2], [None, None]);
+    assert!(approx(roc[2].as_ref().unwrap(), 21.0));
+    let momentum = outputs("kind = \"momentum\"\nperiod = 2", closes());
+    assert!(approx(momentum[2].as_ref().unwrap(), 21.0));
+}
+
+#[cfg(feature = "indicators")]
+#[test]
+fn keltner_channels_surround_the_ema_by_the_atr_and_spot_the_squeeze() {
+    let values = outputs(
+        r#"
+        kind = "keltner"
+        period = 2
+        atr_period = 2
+        multiplier = 1
+        bollinger_period = 2
+        "#,
+        [
+            candle("btcusdt", 100.0).range(101.0, 99.0),
+            candle("btcusdt", 100.0).range(101.0, 99.0),
+            candle("btcusdt", 104.0).range(105.0, 103.0),
+        ],
+    );
+    assert_eq!(values[0], None);
+    let flat = values[1].as_ref().unwrap();
+    assert!(approx(&flat["upper"], 102.0));
+    assert!(approx(&flat["lower"], 98.0));
+    assert!(approx(&flat["position"], 0.5));
+    assert_eq!(flat["squeeze"], true);
+    // The EMA moves two thirds of the way to 104 and the ATR takes in the
+    // gap up, to (2 + 5) / 2.
+    let breakout = values[2].as_ref().unwrap();
+    assert!(approx(&breakout["middle"], 308.0 / 3.0));
+    assert!(approx(&breakout["upper"], 308.0 / 3.0 + 3.5));
+    assert!(approx(&breakout["lower"], 308.0 / 3.0 - 3.5));
+    assert_eq!(breakout["squeeze"], false);
+}
+
+#[cfg(feature = "indicators")]
+#[test]
+fn supertrend_trails_the_trend_and_flips_when_the_close_breaks_its_band() {
+    let values = outputs(
+        r#"
+        kind = "supertrend"
+        atr_period = 1
+        multiplier = 1
+        "#,
+        [
+            candle("btcusdt", 100.0).range(101.0, 99.0),
+            candle("btcusdt", 99.0).range(100.0, 98.0),
+            candle("btcusdt", 95.0).range(97.0, 94.0),
+            candle("btcusdt", 102.0).range(103.0, 101.0),
+        ],
+    );
+    let values: Vec<Value> = values.into_iter().map(Option::unwrap).collect();
+    let directions: Vec<&Value> = values.iter().map(|v| &v["direction"]).collect();
+    assert_eq!(directions, ["up", "up", "down", "up"]);
+    // The lower band holds at 98 while the trend is up, then the upper band
+    // tightens to 95.5 + 5 after the break.
+    assert!(approx(&values[1]["value"], 98.0));
+    assert!(values[1].get("event").is_none());
+    assert_eq!(values[2]["event"], "bearish_flip");
+    assert!(approx(&values[2]["value"], 100.5));
+    assert_eq!(values[3]["event"], "bullish_flip");
+    assert!(approx(&values[3]["value"], 94.0));
+}
+
+#[cfg(feature = "indicators")]
+#[test]
+fn parabolic_sar_accelerates_with_new_highs_and_flips_to_the_extreme() {
+    let values = outputs(
+        r#"
+        kind = "psar"
+        step = 0.1
+        max_step = 0.2
+        "#,
+        [
+            candle("btcusdt", 9.0).range(10.0, 8.0),
+            candle("btcusdt", 10.0).range(11.0, 9.0),
+            candle("btcusdt", 11.0).range(12.0, 10.0),
+            candle("btcusdt", 12.0).range(13.0, 11.0),
+            candle("btcusdt", 8.0).range(9.0, 7.0),
+        ],
+    );
+    assert_eq!(values[..2], [None, None]);
+    // Rising from the first low of 8, kept below the last two lows.
+    let rising = values[2].as_ref().unwrap();
+    assert!(approx(&rising["value"], 8.0));
+    assert_eq!(rising["direction"], "up");
+    // 8 + 0.2 × (12 - 8), the step having grown with the high of 12.
+    assert!(approx(&values[3].as_ref().unwrap()["value"], 8.8));
+    let flipped = values[4].as_ref().unwrap();
+    assert_eq!(flipped["event"], "bearish_flip");
+    assert_eq!(flipped["direction"], "down");
+    assert!(approx(&flipped["value"], 13.0));
+}
+
+#[cfg(feature = "indicators")]
+#[test]
+fn adx_rises_with_a_steady_trend_and_weakens_on_a_reversal() {
+    let values = outputs(
+        "kind = \"adx\"\nperiod = 2",
+        [
+            candle("btcusdt", 10.0).range(11.0, 9.0),
+            candle("btcusdt", 11.0).range(12.0, 10.0),
+            candle("btcusdt", 12.0).range(13.0, 11.0),
+            candle("btcusdt", 13.0).range(14.0, 12.0),
+            candle("btcusdt", 10.5).range(13.0, 10.0),
+        ],
+    );
+    assert_eq!(values[..3], [None, None, None]);
+    let trending = values[3].as_ref().unwrap();
+    assert!(approx(&trending["adx"], 100.0));
+    assert!(approx(&trending["plus_di"], 50.0));
+    assert!(approx(&trending["minus_di"], 0.0));
+    // +DI 20 and −DI 40 give a DX of 33.3, smoothed with the previous 100.
+    let reversal = values[4].as_ref().unwrap();
+    assert!(approx(&reversal["plus_di"], 20.0));
+    assert!(approx(&reversal["minus_di"], 40.0));
+    assert!(approx(&reversal["adx"], 200.0 / 3.0));
+}
+
+#[cfg(feature = "indicators")]
+#[test]
+fn cci_measures_the_typical_price_against_its_mean_deviation() {
+    let values = outputs(
+        "kind = \"cci\"\nperiod = 3",
+        [10.0, 10.0, 10.0, 13.0].map(|close| candle("btcusdt", close)),
+    );
+    assert_eq!(values[..2], [None, None]);
+    assert!(approx(values[2].as_ref().unwrap(), 0.0));
+    // (13 - 11) / (0.015 × 4/3)
+    assert!(approx(values[3].as_ref().unwrap(), 100.0));
+}
+
+#[cfg(feature = "indicators")]
+#[test]
+fn chandelier_exits_fire_when_the_close_crosses_a_level() {
+    let values = outputs(
+        r#"
+        kind = "chandelier"
+        period = 2
+        multiplier = 1
+        "#,
+        [
+            candle("btcusdt", 10.0).range(11.0, 9.0),
+            candle("btcusdt", 10.0).range(11.0, 9.0),
+            candle("btcusdt", 8.5).range(10.0, 8.0),
+            candle("btcusdt", 12.0).range(12.5, 10.5),
+        ],
+    );
+    assert_eq!(values[0], None);
+    let settled = values[1].as_ref().unwrap();
+    assert!(approx(&settled["long"], 9.0));
+    assert!(approx(&settled["short"], 11.0));
+    assert!(settled.get("event").is_none());
+    let dropped = values[2].as_ref().unwrap();
+    assert_eq!(dropped["event"], "long_exit");
+    assert!(approx(&dropped["short"], 10.0));
+    // The gap up widens the ATR to 3: 12.5 - 3 and 8 + 3.
+    let rallied = values[3].as_ref().unwrap();
+    assert_eq!(rallied["event"], "short_exit");
+    assert!(approx(&rallied["long"], 9.5));
+    assert!(approx(&rallied["short"], 11.0));
+}
+
+#[test]
+fn unknown_indicator_kinds_fail_up_front() {
+    let specs = vec![spec(r#"kind = "smaa""#)];
+    let error = IndicatorPipeline::new(IndicatorRegistry::default(), specs)
+        .err()
+        .unwrap();
+    assert_eq!(error.to_string(), "Unknown indicator kind 'smaa'");
+}
+
+/// Reports every close above `level` as an event.
+struct Above(f64);
+
+impl Indicator for Above {
+    fn update(&mut self, candle: &KlineData) -> Option<Value> {
+        if candle.close > self.0 {
+            Some(json!({ "close": candle.close, "event": "above" }))
+        } else {
+            Some(json!({ "close": candle.close }))
+        }
+    }
+}
+
+#[test]
+fn custom_indicators_raise_events_through_the_pipeline() {
+    let mut registry = IndicatorRegistry::empty();
+    registry.register("above", |c| Ok(Box::new(Above(c.param("level", 0.0)))));
+    let specs = vec![spec(
+        r#"
+        kind = "above"
+        level = 100
+        "#,
+    )];
+    let mut pipeline = IndicatorPipeline::new(registry, specs).unwrap();
+
+    let (values, events) = pipeline.update(&candle("btcusdt", 99.0).build());
+    assert_eq!(values["above_100"]["close"], 99.0);
+    assert!(events.is_empty());
+    let (_, events) = pipeline.update(&candle("btcusdt", 101.0).minute(1).build());
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].indicator, "above_100");
+    assert_eq!(events[0].event, "above");
+    assert_eq!(events[0].symbol, "btcusdt");
+}
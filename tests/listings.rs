@@ -0,0 +1,131 @@
+//! New listings found in a local stand-in for the exchange info.
+
+use chrono::{TimeZone, Utc};
+use crypto_kline_tracker::actor::PanicReporter;
+use crypto_kline_tracker::clock::VirtualClock;
+use crypto_kline_tracker::config::{ListingsConfig, MultiplexConfig, QueueConfig, RestartConfig};
+use crypto_kline_tracker::events::Event;
+use crypto_kline_tracker::health::Health;
+use crypto_kline_tracker::listings;
+use crypto_kline_tracker::metrics::Metrics;
+use crypto_kline_tracker::notify::Notification;
+use crypto_kline_tracker::shard::{Router, Shards};
+use crypto_kline_tracker::streams::Streams;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio::sync::{broadcast, mpsc};
+
+fn pair(base: &str, quote: &str, status: &str) -> String {
+    format!(
+        r#"{{"symbol":"{base}{quote}","status":"{status}","baseAsset":"{base}","quoteAsset":"{quote}"}}"#
+    )
+}
+
+/// Lists BTCUSDT at first, then two new pairs besides it.
+async fn serve() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        let mut polls = 0;
+        loop {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = vec![0; 4096];
+            let read = socket.read(&mut request).await.unwrap();
+            assert!(
+                String::from_utf8_lossy(&request[..read]).starts_with("GET /api/v3/exchangeInfo ")
+            );
+            polls += 1;
+            let mut pairs = vec![pair("BTC", "USDT", "TRADING")];
+            if polls > 1 {
+                pairs.push(pair("NEW", "USDT", "PRE_TRADING"));
+                pairs.push(pair("NEW", "BTC", "PRE_TRADING"));
+            }
+            let body = format!(r#"{{"timezone":"UTC","symbols":[{}]}}"#, pairs.join(","));
+            let response = format!(
+                "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            socket.write_all(response.as_bytes()).await.unwrap();
+        }
+    });
+    url
+}
+
+#[tokio::test]
+async fn new_pairs_are_reported_and_subscribed() {
+    let url = serve().await;
+    let metrics = Metrics::default();
+    let (ops_tx, _ops_rx) = mpsc::channel(10);
+    let (router, _receivers) = Router::new(
+        &QueueConfig::default(),
+        Shards::new(1, &[]),
+        metrics.clone(),
+    );
+    let streams = Streams::new(
+        router,
+        RestartConfig::default(),
+        MultiplexConfig::default(),
+        ops_tx.clone(),
+        Health::new(Duration::from_secs(60)),
+        metrics.clone(),
+        PanicReporter::new(metrics, ops_tx, false),
+    )
+    // Nothing listens here; the subscription is all that is checked.
+    .with_stream_url("ws://127.0.0.1:1");
+    let (events_tx, mut events) = broadcast::channel(10);
+    let (notify_tx, mut notifications) = mpsc::channel(10);
+    let now = Utc.with_ymd_and_hms(2024, 3, 1, 12, 0, 0).unwrap();
+    let config = ListingsConfig {
+        enabled: true,
+        interval_secs: 1,
+        subscribe: true,
+        url,
+        ..ListingsConfig::default()
+    };
+    let poller = tokio::spawn(listings::run(
+        config,
+        vec!["5m".to_string(), "1m".to_string()],
+        streams.clone(),
+        events_tx,
+        notify_tx,
+        Arc::new(VirtualClock::new(now)),
+    ));
+    tokio::time::sleep(Duration::from_millis(1500)).await;
+    poller.abort();
+
+    // Only the pair quoted in USDT, and only once it is new.
+    let Ok(Event::Alert(alert)) = events.try_recv() else {
+        panic!("no listing alert");
+    };
+    assert_eq!(
+        (
+            &*alert.rule,
+            &*alert.symbol,
+            &*alert.interval,
+            &*alert.message
+        ),
+        (
+            "listing",
+            "newusdt",
+            "1m",
+            "Binance listed NEW/USDT (PRE_TRADING)"
+        )
+    );
+    assert_eq!(alert.triggered_at, now);
+    assert!(events.try_recv().is_err());
+    assert!(matches!(
+        notifications.try_recv(),
+        Ok(Notification::Alert(alert)) if alert.symbol == "newusdt"
+    ));
+    assert_eq!(
+        streams.list(),
+        [
+            ("newusdt".to_string(), "1m".to_string()),
+            ("newusdt".to_string(), "5m".to_string()),
+        ]
+    );
+    streams.stop_all();
+}
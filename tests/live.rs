@@ -0,0 +1,253 @@
+//! Live orders placed against a local stand-in for Binance's REST API.
+#![cfg(feature = "live")]
+
+use crypto_kline_tracker::config::{LiveConfig, RiskConfig, Side};
+use crypto_kline_tracker::journal::Journal;
+use crypto_kline_tracker::live::{format_decimal, to_step, Amount, Live};
+use crypto_kline_tracker::risk::{KillSwitch, RiskLimits};
+use crypto_kline_tracker::state::SharedState;
+use crypto_kline_tracker::store::{Store, TradeQuery};
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+const EXCHANGE_INFO: &str = r#"{"symbols":[{"symbol":"BTCUSDT","status":"TRADING","baseAsset":"BTC","quoteAsset":"USDT",
+    "filters":[{"filterType":"PRICE_FILTER","minPrice":"0.01000000","maxPrice":"1000000.00000000","tickSize":"0.01000000"},
+               {"filterType":"LOT_SIZE","minQty":"0.00100000","maxQty":"9000.00000000","stepSize":"0.00100000"}]}]}"#;
+
+/// Serves the exchange info, and takes every order as a resting limit buy
+/// after a short delay, rejecting the first `rejected` of them.
+async fn serve(rejected: usize) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let orders = Arc::new(AtomicUsize::new(0));
+    tokio::spawn(async move {
+        loop {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let orders = orders.clone();
+            tokio::spawn(async move {
+                let mut request = vec![0; 8192];
+                let read = socket.read(&mut request).await.unwrap();
+                let request = String::from_utf8_lossy(&request[..read]).to_string();
+                let (status, body) = if request.starts_with("POST /api/v3/order") {
+                    tokio::time::sleep(Duration::from_millis(100)).await;
+                    let n = orders.fetch_add(1, Ordering::SeqCst);
+                    if n < rejected {
+                        (
+                            "400 Bad Request",
+                            r#"{"code":-2010,"msg":"Rejected"}"#.to_string(),
+                        )
+                    } else {
+                        (
+                            "200 OK",
+                            format!(
+                                r#"{{"orderId":{},"clientOrderId":"ckt-{}","symbol":"BTCUSDT","side":"BUY",
+                                    "type":"LIMIT","price":"100.00","origQty":"1.000","executedQty":"0.000",
+                                    "cummulativeQuoteQty":"0.00","status":"NEW","transactTime":1700000000000}}"#,
+                                n + 1,
+                                n + 1
+                            ),
+                        )
+                    }
+                } else {
+                    ("200 OK", EXCHANGE_INFO.to_string())
+                };
+                let response = format!(
+                    "HTTP/1.1 {status}\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+                socket.write_all(response.as_bytes()).await.unwrap();
+            });
+        }
+    });
+    url
+}
+
+/// An account trading through `rest_url`, journaling into `store`.
+fn account(risk: RiskConfig, rest_url: String, store: Arc<Store>) -> Live {
+    let config = LiveConfig {
+        api_key: "key".to_string(),
+        api_secret: "secret".to_string(),
+        rest_url,
+        ..LiveConfig::default()
+    };
+    let journal = Journal::new(store, SharedState::default());
+    Live::new(
+        &config,
+        RiskLimits::new(&risk, KillSwitch::default()),
+        journal,
+    )
+    .unwrap()
+}
+
+async fn live(risk: RiskConfig, rejected: usize) -> Live {
+    let store = Arc::new(Store::open(Path::new(":memory:")).unwrap());
+    account(risk, serve(rejected).await, store)
+}
+
+async fn buy(live: &Live) -> anyhow::Result<()> {
+    live.place(
+        "btcusdt",
+        Side::Buy,
+        Amount::Quantity(1.0),
+        Some(100.0),
+        Some(100.0),
+        "test",
+    )
+    .await
+    .map(drop)
+}
+
+#[tokio::test]
+async fn buys_in_flight_and_resting_count_towards_the_position_limit() {
+    let live = live(
+        RiskConfig {
+            max_position_value: Some(150.0),
+            ..RiskConfig::default()
+        },
+        0,
+    )
+    .await;
+    // Both are checked before Binance has answered either.
+    let (first, second) = tokio::join!(buy(&live), buy(&live));
+    assert!(first.is_ok() != second.is_ok());
+    let error = first.err().or(second.err()).unwrap();
+    assert!(error.to_string().contains("over the limit of 150.00"));
+
+    // The accepted buy rests unfilled, and still counts.
+    assert_eq!(live.held("btcusdt"), 0.0);
+    assert!(buy(&live).await.is_err());
+}
+
+#[tokio::test]
+async fn orders_in_flight_take_up_the_order_rate_until_rejected() {
+    let live = live(
+        RiskConfig {
+            max_orders_per_minute: Some(1),
+            ..RiskConfig::default()
+        },
+        1,
+    )
+    .await;
+    let (first, second) = tokio::join!(buy(&live), buy(&live));
+    assert!(first.is_err() && second.is_err());
+    let errors = [first, second].map(|result| result.unwrap_err().to_string());
+    assert!(errors.iter().any(|e| e.contains("Binance rejected")));
+    assert!(errors
+        .iter()
+        .any(|e| e.contains("orders were placed in the last minute")));
+
+    // The rejected order gave its slot back.
+    buy(&live).await.unwrap();
+    assert!(buy(&live).await.is_err());
+}
+
+#[test]
+fn quantities_and_prices_are_rounded_to_the_pair_step() {
+    let close = |a: f64, b: f64| (a - b).abs() < 1e-12;
+    assert!(close(
+        to_step(1.23456, Some("0.00100000"), f64::floor),
+        1.234
+    ));
+    assert!(close(
+        to_step(100.456, Some("0.01000000"), f64::round),
+        100.46
+    ));
+    // Three steps of 0.1, despite 0.3 / 0.1 falling just short of 3.
+    assert!(close(to_step(0.3, Some("0.10000000"), f64::floor), 0.3));
+    assert_eq!(to_step(1.5, None, f64::floor), 1.5);
+    assert_eq!(to_step(1.5, Some("0.00000000"), f64::floor), 1.5);
+    assert_eq!(to_step(1.5, Some("tick"), f64::floor), 1.5);
+}
+
+#[test]
+fn decimals_are_sent_without_exponent_or_trailing_zeros() {
+    assert_eq!(format_decimal(100.0), "100");
+    assert_eq!(format_decimal(1.5), "1.5");
+    assert_eq!(format_decimal(0.00000001), "0.00000001");
+    assert_eq!(format_decimal(1e-4), "0.0001");
+    assert_eq!(format_decimal(61388.12), "61388.12");
+}
+
+fn report(
+    id: i64,
+    side: &str,
+    status: &str,
+    at: i64,
+    filled: &str,
+    quote: &str,
+    fee: &str,
+) -> String {
+    format!(
+        r#"{{"e":"executionReport","E":{at},"s":"BTCUSDT","c":"ckt-{id}","C":"","S":"{side}","o":"LIMIT",
+            "q":"1.000","p":"100.00","X":"{status}","i":{id},"z":"{filled}","Z":"{quote}","n":"{fee}","N":"USDT"}}"#
+    )
+}
+
+#[tokio::test]
+async fn execution_reports_count_fills_and_commission_once_and_in_order() {
+    let store = Arc::new(Store::open(Path::new(":memory:")).unwrap());
+    let live = account(
+        RiskConfig::default(),
+        "http://127.0.0.1:9".to_string(),
+        store.clone(),
+    );
+    let apply = |text: String| live.on_stream_message(&text).unwrap().unwrap();
+
+    apply(report(7, "BUY", "NEW", 1000, "0.000", "0.00", "0"));
+    let partial = apply(report(
+        7,
+        "BUY",
+        "PARTIALLY_FILLED",
+        2000,
+        "0.500",
+        "50.00",
+        "0.05",
+    ));
+    assert_eq!(partial.commission, 0.05);
+    assert_eq!(partial.commission_asset, "USDT");
+    // A stale report changes nothing.
+    let stale = apply(report(7, "BUY", "NEW", 1500, "0.000", "0.00", "0"));
+    assert_eq!(stale.status, "PARTIALLY_FILLED");
+    assert_eq!(live.held("btcusdt"), 0.5);
+    let filled = apply(report(7, "BUY", "FILLED", 3000, "1.000", "102.00", "0.052"));
+    assert!((filled.commission - 0.102).abs() < 1e-9);
+    assert_eq!(filled.average_price(), Some(102.0));
+    assert_eq!(live.held("btcusdt"), 1.0);
+
+    apply(report(8, "SELL", "FILLED", 4000, "1.000", "110.00", "0.11"));
+    assert_eq!(live.held("btcusdt"), 0.0);
+    // 8 gained on the sell, less every fill's commission.
+    let daily_pnl = live.to_json()["risk"]["daily_pnl"].as_f64().unwrap();
+    assert!((daily_pnl - (8.0 - 0.05 - 0.052 - 0.11)).abs() < 1e-9);
+    assert!(live
+        .on_stream_message(r#"{"e":"outboundAccountPosition"}"#)
+        .unwrap()
+        .is_none());
+
+    let query = TradeQuery {
+        account: None,
+        symbol: None,
+        since: None,
+        until: None,
+    };
+    let mut trades = Vec::new();
+    for _ in 0..50 {
+        trades = store.trades(&query).unwrap();
+        if trades.len() == 3 {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    let fees: Vec<f64> = trades.iter().map(|trade| trade.fee).collect();
+    assert_eq!(fees.len(), 3);
+    assert!((fees[0] - 0.05).abs() < 1e-9);
+    assert!((fees[1] - 0.052).abs() < 1e-9);
+    assert!((fees[2] - 0.11).abs() < 1e-9);
+    assert_eq!(trades[1].price, 104.0);
+    assert_eq!(trades[2].pnl, Some(8.0));
+}
@@ -0,0 +1,228 @@
+//! Streams against a local mock of Binance's WebSocket endpoints.
+
+mod common;
+
+use common::candle;
+use crypto_kline_tracker::config::QueueConfig;
+use crypto_kline_tracker::health::Health;
+use crypto_kline_tracker::metrics::Metrics;
+use crypto_kline_tracker::mock::{kline_message, Chaos, MockServer};
+use crypto_kline_tracker::multiplex::{self, Route, Routes};
+use crypto_kline_tracker::queue::{self, KlineReceiver};
+use crypto_kline_tracker::{run_websocket, KlineData};
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::watch;
+use tokio::time::timeout;
+use tokio_util::sync::CancellationToken;
+
+async fn next_closes(rx: &mut KlineReceiver, count: usize) -> Vec<(String, f64)> {
+    let mut closes = Vec::new();
+    for _ in 0..count {
+        let kline = timeout(Duration::from_secs(5), rx.recv())
+            .await
+            .expect("timed out waiting for a kline")
+            .expect("queue closed");
+        closes.push((kline.symbol.to_string(), kline.close));
+    }
+    closes
+}
+
+/// Klines received until none arrive for a while.
+async fn drain(rx: &mut KlineReceiver) -> Vec<KlineData> {
+    let mut klines = Vec::new();
+    while let Ok(Some(kline)) = timeout(Duration::from_millis(500), rx.recv()).await {
+        klines.push(kline);
+    }
+    klines
+}
+
+fn stream_btcusdt(
+    server: &MockServer,
+    metrics: &Metrics,
+) -> (KlineReceiver, tokio::task::JoinHandle<anyhow::Result<()>>) {
+    let (tx, rx) = queue::channel("klines-0", &QueueConfig::default(), metrics.clone());
+    let (url, metrics) = (server.url(), metrics.clone());
+    let stream = tokio::spawn(async move {
+        run_websocket(
+            &url,
+            "btcusdt".to_string(),
+            "1m".to_string(),
+            tx,
+            Health::new(Duration::from_secs(60)),
+            metrics,
+            CancellationToken::new(),
+        )
+        .await
+    });
+    (rx, stream)
+}
+
+#[tokio::test]
+async fn single_stream_receives_its_candles_in_order() {
+    let server = MockServer::start([
+        kline_message(&candle("btcusdt", 101.0).build()),
+        kline_message(&candle("ethusdt", 50.0).build()),
+        kline_message(&candle("btcusdt", 102.5).minute(1).build()),
+    ])
+    .await
+    .unwrap();
+    let metrics = Metrics::default();
+    let (tx, mut rx) = queue::channel("klines-0", &QueueConfig::default(), metrics.clone());
+    let stopping = CancellationToken::new();
+    let url = server.url();
+    let stream = tokio::spawn({
+        let stopping = stopping.clone();
+        async move {
+            run_websocket(
+                &url,
+                "btcusdt".to_string(),
+                "1m".to_string(),
+                tx,
+                Health::new(Duration::from_secs(60)),
+                metrics,
+                stopping,
+            )
+            .await
+        }
+    });
+
+    let closes = next_closes(&mut rx, 2).await;
+    assert_eq!(
+        closes,
+        [
+            ("btcusdt".to_string(), 101.0),
+            ("btcusdt".to_string(), 102.5)
+        ]
+    );
+    assert_eq!(server.paths(), ["/ws/btcusdt@kline_1m"]);
+
+    stopping.cancel();
+    stream.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn shared_connection_subscribes_and_reconnects() {
+    let server = MockServer::start([
+        kline_message(&candle("btcusdt", 101.0).build()),
+        kline_message(&candle("ethusdt", 50.0).build()),
+    ])
+    .await
+    .unwrap();
+    let metrics = Metrics::default();
+    let (tx, mut rx) = queue::channel("klines-0", &QueueConfig::default(), metrics.clone());
+    let routes: Routes = ["btcusdt", "ethusdt"]
+        .into_iter()
+        .map(|symbol| {
+            let route = Route {
+                key: (Arc::from(symbol), Arc::from("1m")),
+                tx: tx.clone(),
+            };
+            (multiplex::stream_name(symbol, "1m"), route)
+        })
+        .collect();
+    let (_routes, watched) = watch::channel(routes);
+    let connect = || {
+        let (url, watched, metrics) = (server.url(), watched.clone(), metrics.clone());
+        tokio::spawn(async move {
+            multiplex::run_connection(
+                &url,
+                "connection 1".to_string(),
+                watched,
+                10,
+                Health::new(Duration::from_secs(60)),
+                metrics,
+                CancellationToken::new(),
+            )
+            .await
+        })
+    };
+
+    let first = connect();
+    let mut closes = next_closes(&mut rx, 2).await;
+    closes.sort_by(|a, b| a.0.cmp(&b.0));
+    assert_eq!(
+        closes,
+        [
+            ("btcusdt".to_string(), 101.0),
+            ("ethusdt".to_string(), 50.0)
+        ]
+    );
+    let requests = server.requests();
+    assert_eq!(requests.len(), 1);
+    assert!(requests[0].contains("SUBSCRIBE"));
+
+    // A connection Binance closes ends cleanly, and a new one is sent the
+    // candles again from the start.
+    server.disconnect();
+    first.await.unwrap().unwrap();
+    let second = connect();
+    assert_eq!(next_closes(&mut rx, 2).await.len(), 2);
+    assert_eq!(server.connections(), 2);
+    second.abort();
+}
+
+#[tokio::test]
+async fn stream_survives_mangled_messages() {
+    let server = MockServer::start(
+        (0..200).map(|minute| kline_message(&candle("btcusdt", 101.0).minute(minute).build())),
+    )
+    .await
+    .unwrap();
+    server.set_chaos(Chaos {
+        seed: 42,
+        truncate: 0.1,
+        reorder: 0.1,
+        duplicate: 0.1,
+        delay: 0.05,
+        max_delay: Duration::from_millis(5),
+        ..Chaos::default()
+    });
+    let metrics = Metrics::default();
+    let (mut rx, stream) = stream_btcusdt(&server, &metrics);
+
+    // Every message that was not cut short arrives, duplicates included, and
+    // the cut ones are skipped without dropping the connection.
+    let klines = drain(&mut rx).await;
+    let faults = server.faults();
+    let truncated = faults.truncated.load(Ordering::Relaxed);
+    assert!(truncated > 0 && faults.duplicated.load(Ordering::Relaxed) > 0);
+    assert_eq!(
+        klines.len(),
+        200 - truncated + faults.duplicated.load(Ordering::Relaxed)
+    );
+    assert!(klines
+        .windows(2)
+        .any(|pair| pair[1].interval_start < pair[0].interval_start));
+    assert_eq!(metrics.to_json()[0]["parse_errors"], truncated as u64);
+    assert_eq!(server.connections(), 1);
+    assert!(!stream.is_finished());
+    stream.abort();
+}
+
+#[tokio::test]
+async fn abrupt_disconnects_end_the_stream() {
+    let server = MockServer::start(
+        (0..200).map(|minute| kline_message(&candle("btcusdt", 101.0).minute(minute).build())),
+    )
+    .await
+    .unwrap();
+    server.set_chaos(Chaos {
+        seed: 7,
+        disconnect: 0.05,
+        ..Chaos::default()
+    });
+    let metrics = Metrics::default();
+    let (mut rx, stream) = stream_btcusdt(&server, &metrics);
+
+    let klines = drain(&mut rx).await;
+    assert!(klines.len() < 200);
+    assert_eq!(server.faults().disconnects.load(Ordering::Relaxed), 1);
+    // The connection task ends, for its supervisor to reconnect.
+    timeout(Duration::from_secs(5), stream)
+        .await
+        .unwrap()
+        .unwrap()
+        .ok();
+}
@@ -0,0 +1,79 @@
+//! Open interest polled from a local stand-in for the futures REST API.
+
+use crypto_kline_tracker::config::OpenInterestConfig;
+use crypto_kline_tracker::open_interest;
+use crypto_kline_tracker::state::SharedState;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+/// Answers BTCUSDT with 100 and then 110 contracts, and rejects any other
+/// symbol as Binance does. Returns the base URL and the number of requests
+/// for other symbols.
+async fn serve() -> (String, Arc<AtomicUsize>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let rejected = Arc::new(AtomicUsize::new(0));
+    let counter = rejected.clone();
+    tokio::spawn(async move {
+        let mut polls = 0;
+        loop {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = vec![0; 4096];
+            let read = socket.read(&mut request).await.unwrap();
+            let request = String::from_utf8_lossy(&request[..read]);
+            let (status, body) = if request.starts_with("GET /fapi/v1/openInterest?symbol=BTCUSDT ")
+            {
+                polls += 1;
+                let value = if polls == 1 { "100.000" } else { "110.000" };
+                (
+                    "200 OK",
+                    format!(
+                        r#"{{"openInterest":"{}","symbol":"BTCUSDT","time":1700000000000}}"#,
+                        value
+                    ),
+                )
+            } else {
+                counter.fetch_add(1, Ordering::Relaxed);
+                (
+                    "400 Bad Request",
+                    r#"{"code":-1121,"msg":"Invalid symbol."}"#.to_string(),
+                )
+            };
+            let response = format!(
+                "HTTP/1.1 {}\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            );
+            socket.write_all(response.as_bytes()).await.unwrap();
+        }
+    });
+    (url, rejected)
+}
+
+#[tokio::test]
+async fn polls_merge_into_the_state() {
+    let (url, rejected) = serve().await;
+    let state = SharedState::default();
+    let config = OpenInterestConfig {
+        enabled: true,
+        interval_secs: 1,
+        url,
+        ..OpenInterestConfig::default()
+    };
+    let symbols = vec!["btcusdt".to_string(), "dogeusdc".to_string()];
+    let poller = tokio::spawn(open_interest::run(state.clone(), config, symbols));
+    tokio::time::sleep(Duration::from_millis(1500)).await;
+    poller.abort();
+
+    let open_interest = *state.open_interest.get("btcusdt").unwrap();
+    assert_eq!(open_interest.value, 110.0);
+    assert_eq!(open_interest.first, 100.0);
+    assert!((open_interest.change_percent().unwrap() - 10.0).abs() < 1e-9);
+    assert!(state.open_interest.get("dogeusdc").is_none());
+    // A symbol without a perpetual future is only asked about once.
+    assert_eq!(rejected.load(Ordering::Relaxed), 1);
+}
@@ -0,0 +1,362 @@
+//! The paper trading account filling orders against hand-made candles.
+
+mod common;
+
+use chrono::Duration;
+use common::{candle, minute};
+use crypto_kline_tracker::config::{
+    DcaConfig, ExitConfig, FillModelConfig, GridConfig, PaperConfig, RiskConfig, Side,
+};
+use crypto_kline_tracker::paper::PaperAccount;
+use crypto_kline_tracker::risk::{KillSwitch, RiskLimits};
+
+fn account() -> PaperAccount {
+    PaperAccount::new(&PaperConfig {
+        starting_balance: 1000.0,
+        taker_fee_pct: 0.1,
+        maker_fee_pct: 0.0,
+        ..PaperConfig::default()
+    })
+}
+
+#[test]
+fn market_and_limit_orders_move_cash_positions_and_pnl() {
+    let mut account = account();
+    let buy = account
+        .market(
+            "btcusdt",
+            Side::Buy,
+            2.0,
+            100.0,
+            "test".to_string(),
+            ExitConfig::default(),
+        )
+        .unwrap();
+    assert!((buy.fee - 0.2).abs() < 1e-9);
+    assert!((account.cash() - 799.8).abs() < 1e-9);
+
+    account
+        .limit(
+            "btcusdt",
+            Side::Sell,
+            1.0,
+            110.0,
+            "test".to_string(),
+            ExitConfig::default(),
+        )
+        .unwrap();
+    assert!(account
+        .on_kline(&candle("btcusdt", 105.0).minute(1).build(), minute(2))
+        .is_empty());
+    assert_eq!(account.open_orders().len(), 1);
+    let fills = account.on_kline(&candle("btcusdt", 111.0).minute(2).build(), minute(3));
+    assert_eq!(fills.len(), 1);
+    assert_eq!(fills[0].price, 110.0);
+    assert_eq!(fills[0].entry_price, Some(100.0));
+    assert!(account.open_orders().is_empty());
+
+    let position = account.position("btcusdt").unwrap();
+    assert_eq!(position.quantity, 1.0);
+    assert_eq!(position.realized_pnl, 10.0);
+    // 10 realised and 10 unrealised at the fill's price, less the buy's fee.
+    assert!((account.pnl() - 19.8).abs() < 1e-9);
+
+    let error = account
+        .market(
+            "btcusdt",
+            Side::Buy,
+            10.0,
+            111.0,
+            "test".to_string(),
+            ExitConfig::default(),
+        )
+        .unwrap_err();
+    assert!(error.to_string().starts_with("Buying 10 btcusdt needs"));
+}
+
+/// An account holding 1 BTCUSDT bought at 100 at minute 0 with `exits`.
+fn bought(exits: ExitConfig) -> PaperAccount {
+    let mut account = account();
+    account.on_kline(&candle("btcusdt", 100.0).build(), minute(0));
+    account
+        .market("btcusdt", Side::Buy, 1.0, 100.0, "test".to_string(), exits)
+        .unwrap();
+    account
+}
+
+#[test]
+fn exits_sell_at_the_first_level_a_candle_reaches() {
+    let exits = ExitConfig {
+        stop_loss_pct: Some(5.0),
+        take_profit_pct: Some(10.0),
+        trailing_stop_pct: None,
+    };
+
+    let mut account = bought(exits);
+    let quiet = candle("btcusdt", 104.0).minute(1).range(108.0, 96.0);
+    assert!(account.on_kline(&quiet.build(), minute(2)).is_empty());
+    // Gapping down through the stop fills at the open.
+    let gap = candle("btcusdt", 94.0).minute(2).range(94.5, 93.0);
+    let fills = account.on_kline(&gap.build(), minute(3));
+    assert_eq!(fills[0].price, 94.0);
+    assert_eq!(fills[0].reason, "stop-loss of order 1 at 95.00");
+    assert_eq!(account.position("btcusdt").unwrap().quantity, 0.0);
+
+    let mut account = bought(exits);
+    let rally = candle("btcusdt", 105.0).minute(1).range(111.0, 99.0);
+    let fills = account.on_kline(&rally.build(), minute(2));
+    assert!((fills[0].price - 110.0).abs() < 1e-9);
+    assert_eq!(fills[0].reason, "take-profit of order 1 at 110.00");
+
+    // Reaching both, the stop is assumed to have come first.
+    let mut account = bought(exits);
+    let both = candle("btcusdt", 105.0).minute(1).range(111.0, 94.0);
+    let fills = account.on_kline(&both.build(), minute(2));
+    assert_eq!(fills[0].reason, "stop-loss of order 1 at 95.00");
+}
+
+#[test]
+fn trailing_stops_follow_the_high_of_earlier_candles() {
+    let mut account = bought(ExitConfig {
+        trailing_stop_pct: Some(5.0),
+        ..ExitConfig::default()
+    });
+    let rally = candle("btcusdt", 118.0).minute(1).range(120.0, 100.0);
+    assert!(account.on_kline(&rally.build(), minute(2)).is_empty());
+    let pullback = candle("btcusdt", 115.0)
+        .minute(2)
+        .open(118.0)
+        .range(119.0, 113.0);
+    let fills = account.on_kline(&pullback.build(), minute(3));
+    assert_eq!(fills[0].price, 114.0);
+    assert_eq!(fills[0].reason, "trailing stop of order 1 at 114.00");
+}
+
+#[test]
+fn grids_sell_each_buy_one_level_up_and_buy_back_below() {
+    let mut account = PaperAccount::new(&PaperConfig {
+        maker_fee_pct: 0.0,
+        grids: vec![GridConfig {
+            name: None,
+            symbol: "btcusdt".to_string(),
+            lower: 90.0,
+            upper: 110.0,
+            levels: 3,
+            quantity: 1.0,
+        }],
+        ..PaperConfig::default()
+    });
+    // Buys rest at 90 and 100, below the first price.
+    account.on_kline(&candle("btcusdt", 105.0).build(), minute(0));
+    let mut limits: Vec<f64> = account.open_orders().iter().map(|o| o.limit).collect();
+    limits.sort_by(f64::total_cmp);
+    assert_eq!(limits, [90.0, 100.0]);
+
+    account.on_kline(&candle("btcusdt", 99.0).minute(1).build(), minute(1));
+    assert!(account
+        .open_orders()
+        .iter()
+        .any(|o| o.side == Side::Sell && o.limit == 110.0));
+    account.on_kline(&candle("btcusdt", 111.0).minute(2).build(), minute(2));
+
+    let grid = &account.grids()[0];
+    assert_eq!((grid.buys, grid.sells), (1, 1));
+    assert_eq!(grid.realized_pnl, 10.0);
+    assert_eq!(grid.held(), 0.0);
+    let buys = account.open_orders().iter().filter(|o| o.side == Side::Buy);
+    assert_eq!(buys.count(), 2);
+}
+
+#[test]
+fn dca_buys_the_same_amount_on_schedule_skipping_missed_buys() {
+    let mut account = PaperAccount::new(&PaperConfig {
+        taker_fee_pct: 0.0,
+        dca: vec![DcaConfig {
+            name: None,
+            symbol: "btcusdt".to_string(),
+            quote_amount: 100.0,
+            every_secs: 120,
+        }],
+        ..PaperConfig::default()
+    });
+    // Prices stop between minutes 3 and 7: one buy at minute 7 stands in
+    // for those due at 4 and 6.
+    for (n, close) in [(0, 100.0), (1, 50.0), (2, 50.0), (3, 50.0), (7, 80.0)] {
+        account.on_kline(&candle("btcusdt", close).minute(n).build(), minute(n));
+    }
+    let dca = &account.dca()[0];
+    assert_eq!(dca.buys, 3);
+    assert_eq!(dca.quantity, 4.25);
+    assert_eq!(dca.invested(), 300.0);
+    assert_eq!(dca.pnl(), 40.0);
+    // 300 spent at 100 would be worth 240.
+    assert_eq!(dca.lump_sum_pnl(), -60.0);
+}
+
+#[test]
+fn orders_and_fills_are_stamped_with_the_account_time_not_the_clock() {
+    let mut account = bought(ExitConfig::default());
+    let order = account
+        .limit(
+            "btcusdt",
+            Side::Sell,
+            1.0,
+            110.0,
+            "test".to_string(),
+            ExitConfig::default(),
+        )
+        .unwrap();
+    assert_eq!(order.placed_at, minute(0));
+    let fills = account.on_kline(&candle("btcusdt", 111.0).minute(4).build(), minute(5));
+    assert_eq!(fills[0].filled_at, minute(5));
+    assert_eq!(account.position("btcusdt").unwrap().quantity, 0.0);
+}
+
+#[test]
+fn resting_and_delayed_buys_count_towards_the_position_limit() {
+    let risk = RiskConfig {
+        max_position_value: Some(250.0),
+        ..RiskConfig::default()
+    };
+    let mut account = PaperAccount::new(&PaperConfig {
+        starting_balance: 1000.0,
+        fills: FillModelConfig {
+            latency_ms: 500,
+            ..FillModelConfig::default()
+        },
+        ..PaperConfig::default()
+    })
+    .with_risk(RiskLimits::new(&risk, KillSwitch::default()));
+    let exits = ExitConfig::default;
+
+    account
+        .limit("btcusdt", Side::Buy, 1.0, 90.0, "test".to_string(), exits())
+        .unwrap();
+    account
+        .delayed(
+            "btcusdt",
+            Side::Buy,
+            1.0,
+            100.0,
+            "test".to_string(),
+            exits(),
+        )
+        .unwrap();
+    let error = account
+        .limit(
+            "btcusdt",
+            Side::Buy,
+            0.7,
+            100.0,
+            "test".to_string(),
+            exits(),
+        )
+        .unwrap_err();
+    assert!(error
+        .to_string()
+        .contains("worth 260.00, over the limit of 250.00"));
+    account
+        .limit(
+            "btcusdt",
+            Side::Buy,
+            0.6,
+            100.0,
+            "test".to_string(),
+            exits(),
+        )
+        .unwrap();
+}
+
+fn costly(fills: FillModelConfig) -> PaperAccount {
+    PaperAccount::new(&PaperConfig {
+        starting_balance: 1000.0,
+        fills,
+        ..PaperConfig::default()
+    })
+}
+
+#[test]
+fn market_orders_pay_half_the_spread_the_slippage_and_their_volume_impact() {
+    let mut account = costly(FillModelConfig {
+        spread_bps: 20.0,
+        slippage_bps: 5.0,
+        volume_impact_bps: 10.0,
+        ..FillModelConfig::default()
+    });
+    account.on_kline(&candle("btcusdt", 100.0).volume(100.0).build(), minute(0));
+    let exits = ExitConfig::default;
+
+    // 10 + 5 + 10 × 2% of the volume, in basis points.
+    let buy = account
+        .market(
+            "btcusdt",
+            Side::Buy,
+            2.0,
+            100.0,
+            "test".to_string(),
+            exits(),
+        )
+        .unwrap();
+    assert!((buy.price - 100.35).abs() < 1e-9);
+    let sell = account
+        .market(
+            "btcusdt",
+            Side::Sell,
+            1.0,
+            110.0,
+            "test".to_string(),
+            exits(),
+        )
+        .unwrap();
+    assert!((sell.price - 110.0 * (1.0 - 0.0025)).abs() < 1e-9);
+
+    // Limit orders fill at their limit.
+    account
+        .limit(
+            "btcusdt",
+            Side::Sell,
+            1.0,
+            120.0,
+            "test".to_string(),
+            exits(),
+        )
+        .unwrap();
+    let fills = account.on_kline(&candle("btcusdt", 125.0).minute(1).build(), minute(2));
+    assert_eq!(fills[0].price, 120.0);
+}
+
+#[test]
+fn delayed_orders_fill_at_the_first_price_of_their_symbol_once_due() {
+    let mut account = costly(FillModelConfig {
+        slippage_bps: 10.0,
+        latency_ms: 1500,
+        ..FillModelConfig::default()
+    });
+    account.on_kline(&candle("btcusdt", 100.0).build(), minute(0));
+    let order = account
+        .delayed(
+            "btcusdt",
+            Side::Buy,
+            1.0,
+            100.0,
+            "test".to_string(),
+            ExitConfig::default(),
+        )
+        .unwrap();
+    assert_eq!(order.due, minute(0) + Duration::milliseconds(1500));
+
+    let second = |ms| minute(0) + Duration::milliseconds(ms);
+    assert!(account
+        .on_kline(&candle("btcusdt", 101.0).build(), second(1000))
+        .is_empty());
+    assert!(account
+        .on_kline(&candle("ethusdt", 50.0).build(), second(2000))
+        .is_empty());
+    let fills = account.on_kline(&candle("btcusdt", 102.0).build(), second(2500));
+    assert_eq!(fills.len(), 1);
+    assert!((fills[0].price - 102.0 * 1.001).abs() < 1e-9);
+    assert_eq!(fills[0].filled_at, second(2500));
+    assert!(account
+        .on_kline(&candle("btcusdt", 103.0).build(), second(3000))
+        .is_empty());
+}
@@ -0,0 +1,134 @@
+//! The holdings' risk, measured on return series worked out by hand.
+
+mod common;
+
+use common::candle;
+use crypto_kline_tracker::config::Config;
+use crypto_kline_tracker::portfolio::Portfolio;
+use crypto_kline_tracker::session::SessionStats;
+use crypto_kline_tracker::state::SharedState;
+
+/// BTCUSDT returns +10%, -10%, +10%, and ETHUSDT +20%, 0, -10%, over the
+/// same three minutes; SOLUSDT is priced but has no candles.
+fn state() -> SharedState {
+    let state = SharedState::default();
+    for (symbol, closes) in [
+        ("btcusdt", [100.0, 110.0, 99.0, 108.9]),
+        ("ethusdt", [100.0, 120.0, 120.0, 108.0]),
+    ] {
+        for (n, close) in closes.into_iter().enumerate() {
+            let kline = candle(symbol, close).minute(n as i64).build();
+            state.sessions.insert(
+                kline.symbol.clone(),
+                SessionStats::new(&kline, kline.interval_start),
+            );
+            state.history_mut().update(kline, 100, None);
+        }
+    }
+    let sol = candle("solusdt", 20.0).build();
+    state.sessions.insert(
+        sol.symbol.clone(),
+        SessionStats::new(&sol, sol.interval_start),
+    );
+    state
+}
+
+fn portfolio(lookback: usize) -> Portfolio {
+    let config: Config = toml::from_str(&format!(
+        r#"
+        intervals = ["1m"]
+
+        [portfolio]
+        lookback = {lookback}
+
+        [[holdings]]
+        symbol = "btcusdt"
+        quantity = 10
+
+        [[holdings]]
+        symbol = "ethusdt"
+        quantity = 10
+
+        [[holdings]]
+        symbol = "solusdt"
+        quantity = 5
+        "#
+    ))
+    .unwrap();
+    Portfolio::new(&config)
+}
+
+fn assert_close(actual: f64, expected: f64) {
+    assert!(
+        (actual - expected).abs() < 1e-9,
+        "{actual} is not {expected}"
+    );
+}
+
+#[test]
+fn volatility_correlation_and_value_at_risk_follow_the_sample_covariance() {
+    let analysis = portfolio(100).analyze(&state());
+    let risk = analysis.risk.unwrap();
+    assert_eq!(risk.interval, "1m");
+    assert_eq!(risk.samples, 3);
+    // Without a return history, SOLUSDT is left out.
+    assert_eq!(risk.symbols, ["btcusdt", "ethusdt"]);
+
+    // Sample (co)variances over n - 1 = 2: 1/75 and 7/300, and 1/300.
+    let (btc, eth, covariance): (f64, f64, f64) = (1.0 / 75.0, 7.0 / 300.0, 1.0 / 300.0);
+    assert_eq!(risk.correlations.len(), 1);
+    assert_eq!(
+        risk.correlations[0].symbols,
+        ("btcusdt".to_string(), "ethusdt".to_string())
+    );
+    assert_close(
+        risk.correlations[0].value.unwrap(),
+        1.0 / (2.0 * 7f64.sqrt()),
+    );
+
+    // Weighted by the 1089 and 1080 held.
+    let covered = 1089.0 + 1080.0;
+    let (w_btc, w_eth) = (1089.0 / covered, 1080.0 / covered);
+    let volatility =
+        (w_btc * w_btc * btc + w_eth * w_eth * eth + 2.0 * w_btc * w_eth * covariance).sqrt();
+    let undiversified = w_btc * btc.sqrt() + w_eth * eth.sqrt();
+    assert_close(risk.volatility, volatility);
+    assert_close(risk.undiversified_volatility, undiversified);
+    assert_close(risk.value_at_risk, 1.645 * volatility * covered);
+    assert_close(
+        risk.diversification_ratio().unwrap(),
+        undiversified / volatility,
+    );
+    assert!(risk.volatility < risk.undiversified_volatility);
+}
+
+#[test]
+fn only_the_latest_lookback_returns_are_compared() {
+    let risk = portfolio(2).analyze(&state()).risk.unwrap();
+    assert_eq!(risk.samples, 2);
+    // -10%, +10% against 0, -10%: they move exactly against each other.
+    assert_close(risk.correlations[0].value.unwrap(), -1.0);
+}
+
+#[test]
+fn risk_needs_two_returns_in_common() {
+    let state = state();
+    // ETHUSDT's next candle lands after a gap BTCUSDT has no candle for.
+    let kline = candle("ethusdt", 110.0).minute(10).build();
+    state.history_mut().update(kline, 100, None);
+    assert_eq!(portfolio(100).analyze(&state).risk.unwrap().samples, 3);
+
+    // One return each is not enough.
+    let fresh = SharedState::default();
+    for n in 0..2 {
+        for symbol in ["btcusdt", "ethusdt"] {
+            let kline = candle(symbol, 100.0 + n as f64).minute(n).build();
+            fresh.sessions.insert(
+                kline.symbol.clone(),
+                SessionStats::new(&kline, kline.interval_start),
+            );
+            fresh.history_mut().update(kline, 100, None);
+        }
+    }
+    assert!(portfolio(100).analyze(&fresh).risk.is_none());
+}
@@ -0,0 +1,114 @@
+//! Prices shown with each symbol's tick size, fetched from a local stand-in
+//! for the exchange info.
+
+mod common;
+
+use chrono::{TimeZone, Utc};
+use common::candle;
+use crypto_kline_tracker::config::{ExitConfig, PaperConfig, PrecisionConfig, Side};
+use crypto_kline_tracker::holdings::{self, Mark};
+use crypto_kline_tracker::paper::PaperAccount;
+use crypto_kline_tracker::precision::{self, fallback_decimals, tick_decimals};
+use crypto_kline_tracker::processor::kline_line;
+use crypto_kline_tracker::state::SharedState;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+fn pair(base: &str, tick_size: &str) -> String {
+    format!(
+        r#"{{"symbol":"{base}USDT","status":"TRADING","baseAsset":"{base}","quoteAsset":"USDT",
+            "filters":[{{"filterType":"PRICE_FILTER","minPrice":"{tick_size}","maxPrice":"1000000.00000000","tickSize":"{tick_size}"}},
+                       {{"filterType":"LOT_SIZE","minQty":"1.00000000","maxQty":"9000000.00000000","stepSize":"1.00000000"}}]}}"#
+    )
+}
+
+async fn serve() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        let (mut socket, _) = listener.accept().await.unwrap();
+        let mut request = vec![0; 4096];
+        let _ = socket.read(&mut request).await.unwrap();
+        let body = format!(
+            r#"{{"symbols":[{},{}]}}"#,
+            pair("BTC", "0.01000000"),
+            pair("DOGE", "0.00001000")
+        );
+        let response = format!(
+            "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        );
+        socket.write_all(response.as_bytes()).await.unwrap();
+    });
+    url
+}
+
+#[test]
+fn decimals_follow_the_tick_size_or_the_price() {
+    assert_eq!(tick_decimals("0.00001000"), Some(5));
+    assert_eq!(tick_decimals("1.00000000"), Some(0));
+    assert_eq!(tick_decimals("0.00000000"), None);
+    assert_eq!(fallback_decimals(61388.12), 2);
+    assert_eq!(fallback_decimals(0.0812), 4);
+    assert_eq!(fallback_decimals(0.00001234), 7);
+    assert_eq!(fallback_decimals(0.0), 2);
+}
+
+#[tokio::test]
+async fn prices_are_shown_with_their_tick_size() {
+    let url = serve().await;
+    let state = SharedState::default();
+    precision::run(PrecisionConfig { enabled: true, url }, state.clone()).await;
+
+    assert_eq!(state.decimals("dogeusdt", 0.08123), 5);
+    assert_eq!(state.decimals("binanceus:dogeusdt", 0.08123), 5);
+    assert_eq!(state.decimals("btcusdt", 61388.12), 2);
+    // Neither a Binance pair nor a price.
+    assert_eq!(state.decimals("binance-kraken:dogeusdt", 0.0512), 4);
+    assert_eq!(state.decimals("shibusdt", 0.00001234), 7);
+
+    let kline = candle("dogeusdt", 0.08123)
+        .at(Utc.with_ymd_and_hms(2024, 3, 1, 12, 30, 0).unwrap())
+        .open(0.08112)
+        .range(0.08131, 0.08104)
+        .volume(152000.0)
+        .closed(false)
+        .build();
+    let processed = Utc.with_ymd_and_hms(2024, 3, 1, 12, 30, 20).unwrap();
+    assert_eq!(
+        kline_line(&kline, state.decimals("dogeusdt", kline.close), processed),
+        "Symbol: dogeusdt | Interval: 1m | Local time: 2024-03-01 12:30:20 | \
+         Interval start: 2024-03-01 12:30 | Open: 0.08112 | High: 0.08131 | \
+         Low: 0.08104 | Close: 0.08123 | Volume: 152000.00 | Change: 0.00011 (0.14%)"
+    );
+}
+
+#[test]
+fn paper_and_holdings_summaries_show_prices_with_their_tick_size() {
+    let state = SharedState::default();
+    state.price_decimals.insert("dogeusdt".into(), 5);
+
+    let mut account = PaperAccount::new(&PaperConfig::default());
+    account
+        .market(
+            "dogeusdt",
+            Side::Buy,
+            1000.0,
+            0.08123,
+            "test".to_string(),
+            ExitConfig::default(),
+        )
+        .unwrap();
+    let lines = account.summary_lines(&state);
+    assert!(lines[1].starts_with("  dogeusdt: 1000 @ 0.08123, marked 0.08123,"));
+
+    let marks = [Mark {
+        symbol: "dogeusdt".to_string(),
+        quantity: 1000.0,
+        cost_basis: Some(0.07512),
+        price: Some(0.08123),
+    }];
+    assert!(holdings::summary_lines(&marks, &state)[0]
+        .starts_with("dogeusdt 1000 at 0.08123 = 81.23 | Cost basis: 0.07512 |"));
+}
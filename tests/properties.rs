@@ -0,0 +1,242 @@
+//! Properties of the candle math that must hold for any prices: percent
+//! changes, the aggregates built from them, ratios and the indicators.
+
+mod common;
+
+use crypto_kline_tracker::config::RatioConfig;
+use crypto_kline_tracker::ratio::compute_ratio;
+use crypto_kline_tracker::session::SessionStats;
+use crypto_kline_tracker::stats::{market_stats, market_stats_by_interval};
+use crypto_kline_tracker::KlineData;
+use proptest::prelude::*;
+
+/// Whether `value` lies within `[low, high]`, give or take rounding.
+fn within(value: f64, low: f64, high: f64) -> bool {
+    let slack = 1e-9 * low.abs().max(high.abs()).max(1.0);
+    value >= low - slack && value <= high + slack
+}
+
+/// Prices as Binance sends them: zero for a pair that has not traded yet,
+/// otherwise anywhere from fractions of a cent to six figures.
+fn price() -> impl Strategy<Value = f64> {
+    prop_oneof![1 => Just(0.0), 20 => 1e-8..1e6f64]
+}
+
+/// A candle of `interval`, `minute` candles in, with wicks of `wick` beyond
+/// its open and close.
+fn kline(interval: &str, minute: i64, open: f64, close: f64, wick: f64, volume: f64) -> KlineData {
+    common::candle("btcusdt", close)
+        .interval(interval)
+        .minute(minute)
+        .range(open.max(close) + wick, (open.min(close) - wick).max(0.0))
+        .open(open)
+        .volume(volume)
+        .build()
+}
+
+/// A plausible candle of `interval`, `minute` candles in.
+fn candle(interval: &'static str, minute: i64) -> impl Strategy<Value = KlineData> {
+    (
+        price(),
+        price(),
+        0.0..1e3f64,
+        prop_oneof![Just(0.0), 0.0..1e6f64],
+    )
+        .prop_map(move |(open, close, wick, volume)| {
+            kline(interval, minute, open, close, wick, volume)
+        })
+}
+
+fn candles(len: usize) -> impl Strategy<Value = Vec<KlineData>> {
+    prop::collection::vec((price(), 0.0..1e3f64, 0.0..1e6f64), 1..len).prop_map(|prices| {
+        let mut open = prices[0].0;
+        prices
+            .into_iter()
+            .enumerate()
+            .map(|(minute, (close, wick, volume))| {
+                let kline = kline("1m", minute as i64, open, close, wick, volume);
+                open = close;
+                kline
+            })
+            .collect()
+    })
+}
+
+proptest! {
+    #[test]
+    fn price_change_percent_is_finite(kline in candle("1m", 0)) {
+        let change = kline.price_change_percent();
+        prop_assert!(change.is_finite());
+        if kline.open == 0.0 {
+            prop_assert_eq!(change, 0.0);
+        } else {
+            prop_assert_eq!(change > 0.0, kline.close > kline.open);
+            prop_assert_eq!(change < 0.0, kline.close < kline.open);
+            prop_assert!(change >= -100.0);
+        }
+    }
+
+    #[test]
+    fn price_change_percent_ignores_scale(kline in candle("1m", 0), scale in 1e-3..1e3f64) {
+        let mut scaled = kline.clone();
+        scaled.open *= scale;
+        scaled.close *= scale;
+        let (change, scaled) = (kline.price_change_percent(), scaled.price_change_percent());
+        prop_assert!((change - scaled).abs() <= 1e-6 * change.abs().max(1.0));
+    }
+
+    #[test]
+    fn market_stats_average_within_the_changes(candles in candles(50)) {
+        let changes: Vec<f64> = candles.iter().map(KlineData::price_change_percent).collect();
+        let low = changes.iter().copied().fold(f64::INFINITY, f64::min);
+        let high = changes.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+
+        let stats = market_stats(&candles);
+        prop_assert_eq!(stats.count, candles.len());
+        prop_assert!(within(stats.mean_change, low, high));
+        match stats.weighted_change {
+            Some(weighted) => prop_assert!(within(weighted, low, high)),
+            None => prop_assert!(candles.iter().all(|kline| kline.volume * kline.close <= 0.0)),
+        }
+    }
+
+    #[test]
+    fn market_stats_by_interval_partition_the_candles(
+        minutes in prop::collection::vec(candle("1m", 0), 0..20),
+        hours in prop::collection::vec(candle("1h", 0), 0..20),
+    ) {
+        let all: Vec<KlineData> = minutes.iter().chain(&hours).cloned().collect();
+        let stats = market_stats_by_interval(&all);
+        let intervals: Vec<&str> = stats.iter().map(|(interval, _)| interval.as_str()).collect();
+        let expected: Vec<&str> = [("1m", &minutes), ("1h", &hours)]
+            .into_iter()
+            .filter(|(_, candles)| !candles.is_empty())
+            .map(|(interval, _)| interval)
+            .collect();
+        prop_assert_eq!(intervals, expected);
+        prop_assert_eq!(stats.iter().map(|(_, stats)| stats.count).sum::<usize>(), all.len());
+    }
+
+    #[test]
+    fn ratio_candles_contain_their_open_and_close(
+        base in candle("1m", 0),
+        quote in candle("1m", 0),
+        previous in candle("1m", 0),
+    ) {
+        let ratio = RatioConfig { base: "btcusdt".into(), quote: "ethusdt".into() };
+        let Some(first) = compute_ratio(&ratio, &base, &quote, None) else {
+            prop_assert!(quote.open == 0.0 || quote.close == 0.0);
+            return Ok(());
+        };
+        for value in [first.open, first.high, first.low, first.close] {
+            prop_assert!(value.is_finite());
+        }
+        prop_assert!(first.low <= first.open.min(first.close));
+        prop_assert!(first.high >= first.open.max(first.close));
+
+        // Updates of the same candle only widen its range.
+        let previous = KlineData { high: previous.high / 1e3, low: previous.low / 1e3, ..previous };
+        let next = compute_ratio(&ratio, &base, &quote, Some(&previous)).unwrap();
+        prop_assert!(next.high >= first.high.max(previous.high));
+        prop_assert!(next.low <= first.low.min(previous.low));
+    }
+
+    #[test]
+    fn session_drawdown_is_a_percentage(candles in candles(50)) {
+        let mut session = SessionStats::new(&candles[0], candles[0].interval_start);
+        for kline in &candles[1..] {
+            session.update(kline);
+        }
+        prop_assert!(session.change_percent().is_finite());
+        prop_assert!(within(session.max_drawdown_percent, 0.0, 100.0));
+        prop_assert!(session.low <= session.high);
+    }
+}
+
+#[cfg(feature = "indicators")]
+mod indicators {
+    use super::*;
+    use crypto_kline_tracker::config::Config;
+    use crypto_kline_tracker::indicators::{IndicatorPipeline, IndicatorRegistry};
+    use serde_json::Value;
+    use std::collections::BTreeMap;
+
+    const PERIOD: usize = 5;
+
+    fn pipeline() -> IndicatorPipeline {
+        let config: Config = toml::from_str(&format!(
+            r#"
+            [[indicators]]
+            kind = "sma"
+            name = "sma"
+            period = {PERIOD}
+            [[indicators]]
+            kind = "ema"
+            name = "ema"
+            period = {PERIOD}
+            [[indicators]]
+            kind = "rsi"
+            name = "rsi"
+            period = {PERIOD}
+            [[indicators]]
+            kind = "bollinger"
+            name = "bollinger"
+            period = {PERIOD}
+            [[indicators]]
+            kind = "atr"
+            name = "atr"
+            period = {PERIOD}
+            [[indicators]]
+            kind = "roc"
+            name = "roc"
+            period = {PERIOD}
+            "#
+        ))
+        .unwrap();
+        IndicatorPipeline::new(IndicatorRegistry::with_builtins(), config.indicators).unwrap()
+    }
+
+    fn number(values: &BTreeMap<String, Value>, name: &str) -> Option<f64> {
+        values.get(name).and_then(Value::as_f64)
+    }
+
+    proptest! {
+        #[test]
+        fn indicators_stay_in_range(candles in candles(60)) {
+            let mut pipeline = pipeline();
+            let closes: Vec<f64> = candles.iter().map(|kline| kline.close).collect();
+            for (seen, kline) in candles.iter().enumerate() {
+                let (values, _) = pipeline.update(kline);
+                let window = &closes[(seen + 1).saturating_sub(PERIOD)..=seen];
+                let low = window.iter().copied().fold(f64::INFINITY, f64::min);
+                let high = window.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+                let all_low = closes[..=seen].iter().copied().fold(f64::INFINITY, f64::min);
+                let all_high = closes[..=seen].iter().copied().fold(f64::NEG_INFINITY, f64::max);
+
+                if let Some(sma) = number(values, "sma") {
+                    let mean = window.iter().sum::<f64>() / PERIOD as f64;
+                    prop_assert!((sma - mean).abs() <= 1e-6 * high.max(1.0));
+                }
+                if let Some(ema) = number(values, "ema") {
+                    prop_assert!(within(ema, all_low, all_high));
+                }
+                if let Some(rsi) = number(values, "rsi") {
+                    prop_assert!(within(rsi, 0.0, 100.0));
+                }
+                if let Some(bands) = values.get("bollinger") {
+                    let band = |name: &str| bands[name].as_f64().unwrap();
+                    let (lower, middle, upper) = (band("lower"), band("middle"), band("upper"));
+                    prop_assert!(lower <= middle && middle <= upper);
+                    prop_assert!(within(middle, low, high));
+                }
+                if let Some(atr) = number(values, "atr") {
+                    prop_assert!(atr.is_finite() && atr >= 0.0);
+                }
+                if let Some(roc) = values.get("roc") {
+                    // Past a close of 0 there is no rate of change to give.
+                    prop_assert!(roc.is_null() || roc.as_f64().is_some_and(f64::is_finite));
+                }
+            }
+        }
+    }
+}
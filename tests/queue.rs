@@ -0,0 +1,153 @@
+//! The kline queue's backpressure policies when the processor falls behind,
+//! and the priority lane closed candles take past them.
+
+mod common;
+
+use common::candle;
+use crypto_kline_tracker::config::{Backpressure, QueueConfig};
+use crypto_kline_tracker::metrics::Metrics;
+use crypto_kline_tracker::queue::{self, KlineReceiver, KlineSender};
+use crypto_kline_tracker::KlineData;
+use serde_json::Value;
+use std::time::Duration;
+
+fn channel(capacity: usize, backpressure: Backpressure) -> (KlineSender, KlineReceiver, Metrics) {
+    let metrics = Metrics::default();
+    let config = QueueConfig {
+        capacity,
+        backpressure,
+    };
+    let (tx, rx) = queue::channel("klines-0", &config, metrics.clone());
+    (tx, rx, metrics)
+}
+
+/// An update to the BTCUSDT candle of minute `n`, still open.
+fn update(n: i64, close: f64) -> KlineData {
+    candle("btcusdt", close).minute(n).closed(false).build()
+}
+
+/// Every update queued, as (symbol, close) in the order the receiver takes
+/// them.
+async fn drain(tx: KlineSender, mut rx: KlineReceiver) -> Vec<(String, f64)> {
+    drop(tx);
+    let mut received = Vec::new();
+    while let Some(kline) = rx.recv().await {
+        received.push((kline.symbol.to_string(), kline.close));
+    }
+    received
+}
+
+fn stream_count(metrics: &Metrics, field: &str) -> Value {
+    metrics.to_json()[0][field].clone()
+}
+
+#[tokio::test]
+async fn drop_oldest_makes_room_by_discarding_the_oldest_update() {
+    let (tx, rx, metrics) = channel(2, Backpressure::DropOldest);
+    for (n, close) in [(0, 100.0), (1, 101.0), (2, 102.0)] {
+        tx.send(update(n, close)).await.unwrap();
+    }
+    let closes: Vec<f64> = drain(tx, rx).await.into_iter().map(|(_, c)| c).collect();
+    assert_eq!(closes, [101.0, 102.0]);
+    assert_eq!(stream_count(&metrics, "dropped"), 1);
+}
+
+#[tokio::test]
+async fn coalesce_replaces_a_queued_update_to_the_same_candle_in_place() {
+    let (tx, rx, metrics) = channel(2, Backpressure::Coalesce);
+    tx.send(update(0, 100.0)).await.unwrap();
+    tx.send(candle("ethusdt", 50.0).closed(false).build())
+        .await
+        .unwrap();
+    tx.send(update(0, 100.5)).await.unwrap();
+    assert_eq!(
+        drain(tx, rx).await,
+        [
+            ("btcusdt".to_string(), 100.5),
+            ("ethusdt".to_string(), 50.0)
+        ]
+    );
+    assert_eq!(stream_count(&metrics, "coalesced"), 1);
+}
+
+#[tokio::test]
+async fn a_full_queue_holds_back_senders_until_there_is_room() {
+    for backpressure in [Backpressure::Block, Backpressure::Coalesce] {
+        let (tx, mut rx, _) = channel(1, backpressure);
+        tx.send(update(0, 100.0)).await.unwrap();
+        // Coalescing only waits for a distinct candle.
+        let send = tx.send(update(1, 101.0));
+        tokio::pin!(send);
+        assert!(
+            tokio::time::timeout(Duration::from_millis(50), send.as_mut())
+                .await
+                .is_err()
+        );
+        assert_eq!(rx.recv().await.unwrap().close, 100.0);
+        send.await.unwrap();
+        assert_eq!(rx.recv().await.unwrap().close, 101.0);
+    }
+}
+
+#[tokio::test]
+async fn sending_fails_once_the_receiver_is_gone() {
+    let (tx, rx, _) = channel(1, Backpressure::Block);
+    tx.send(update(0, 100.0)).await.unwrap();
+    // Would wait for room, but nobody will take the update.
+    let send = tokio::spawn(async move { tx.send(update(1, 101.0)).await });
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    drop(rx);
+    assert!(send.await.unwrap().is_err());
+}
+
+#[tokio::test]
+async fn closed_candles_skip_ahead_of_updates_and_are_never_dropped() {
+    let (tx, mut rx, metrics) = channel(1, Backpressure::DropOldest);
+    tx.send(candle("ethusdt", 50.0).closed(false).build())
+        .await
+        .unwrap();
+    for n in 0..3 {
+        tx.send(candle("btcusdt", 100.0 + n as f64).minute(n).build())
+            .await
+            .unwrap();
+    }
+    let mut batch = Vec::new();
+    assert_eq!(rx.recv_many(&mut batch, 10).await, 4);
+    let received: Vec<(&str, f64)> = batch.iter().map(|k| (&*k.symbol, k.close)).collect();
+    assert_eq!(
+        received,
+        [
+            ("btcusdt", 100.0),
+            ("btcusdt", 101.0),
+            ("btcusdt", 102.0),
+            ("ethusdt", 50.0)
+        ]
+    );
+    assert!(metrics.to_json().as_array().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn a_closed_candle_drops_the_queued_updates_it_supersedes() {
+    let (tx, rx, metrics) = channel(3, Backpressure::Block);
+    tx.send(update(0, 100.0)).await.unwrap();
+    tx.send(update(1, 101.0)).await.unwrap();
+    tx.send(update(0, 100.5)).await.unwrap();
+    tx.send(candle("btcusdt", 100.8).build()).await.unwrap();
+    let closes: Vec<f64> = drain(tx, rx).await.into_iter().map(|(_, c)| c).collect();
+    assert_eq!(closes, [100.8, 101.0]);
+    assert_eq!(stream_count(&metrics, "coalesced"), 2);
+}
+
+#[tokio::test]
+async fn a_closed_candle_frees_room_for_a_waiting_sender() {
+    let (tx, mut rx, _) = channel(1, Backpressure::Block);
+    tx.send(update(0, 100.0)).await.unwrap();
+    let waiting = tx.clone();
+    let send = tokio::spawn(async move { waiting.send(update(1, 101.0)).await });
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    assert!(!send.is_finished());
+    tx.send(candle("btcusdt", 100.2).build()).await.unwrap();
+    send.await.unwrap().unwrap();
+    assert_eq!(rx.recv().await.unwrap().close, 100.2);
+    assert_eq!(rx.recv().await.unwrap().close, 101.0);
+}
@@ -0,0 +1,60 @@
+//! Cross-pair ratios built from the candles of their two legs.
+
+mod common;
+
+use common::candle;
+use crypto_kline_tracker::config::RatioConfig;
+use crypto_kline_tracker::ratio::compute_ratio;
+
+fn eth_btc() -> RatioConfig {
+    RatioConfig {
+        base: "ethusdt".to_string(),
+        quote: "btcusdt".to_string(),
+    }
+}
+
+#[test]
+fn ratio_divides_the_legs_and_keeps_the_candles_extremes() {
+    let ratio = eth_btc();
+    let eth = candle("ethusdt", 3300.0).open(3000.0).closed(false).build();
+    let btc = candle("btcusdt", 60000.0).closed(false).build();
+
+    let first = compute_ratio(&ratio, &eth, &btc, None).unwrap();
+    assert_eq!(&*first.symbol, "ethusdt/btcusdt");
+    assert_eq!((first.open, first.close), (0.05, 0.055));
+    assert_eq!((first.high, first.low), (0.055, 0.05));
+    assert_eq!(first.volume, 0.0);
+    assert!(!first.closed);
+
+    // A later update of the same candle keeps the earlier high.
+    let eth = candle("ethusdt", 3150.0).open(3000.0).build();
+    let btc = candle("btcusdt", 60000.0).build();
+    let second = compute_ratio(&ratio, &eth, &btc, Some(&first)).unwrap();
+    assert_eq!(
+        (second.high, second.low, second.close),
+        (0.055, 0.05, 0.0525)
+    );
+    assert!(second.closed);
+}
+
+#[test]
+fn ratio_waits_for_both_legs_on_the_same_candle() {
+    let ratio = eth_btc();
+    let eth = candle("ethusdt", 3300.0).build();
+    assert!(compute_ratio(
+        &ratio,
+        &eth,
+        &candle("btcusdt", 60000.0).minute(1).build(),
+        None
+    )
+    .is_none());
+    assert!(compute_ratio(
+        &ratio,
+        &eth,
+        &candle("btcusdt", 60000.0).interval("5m").build(),
+        None
+    )
+    .is_none());
+    // A quote leg that has not traded has no ratio.
+    assert!(compute_ratio(&ratio, &eth, &candle("btcusdt", 0.0).build(), None).is_none());
+}
@@ -0,0 +1,98 @@
+//! The whole pipeline replayed over canned messages on a virtual clock.
+
+mod common;
+
+use chrono::Duration;
+use common::candle;
+use crypto_kline_tracker::alerts::AlertEngine;
+use crypto_kline_tracker::config::Config;
+use crypto_kline_tracker::events::Event;
+use crypto_kline_tracker::indicators::{IndicatorPipeline, IndicatorRegistry};
+use crypto_kline_tracker::mock::kline_message;
+use crypto_kline_tracker::notify::Notification;
+use crypto_kline_tracker::replay::{self, Recording};
+use crypto_kline_tracker::strategy::{StrategyRegistry, StrategySet};
+
+const CONFIG: &str = r#"
+symbols = ["btcusdt"]
+
+[[alerts]]
+name = "high close"
+kind = "rule"
+when = "close > 104"
+"#;
+
+fn messages() -> Vec<String> {
+    [102.0, 105.0, 101.0, 104.0, 106.0, 100.0, 107.0]
+        .into_iter()
+        .enumerate()
+        .map(|(minute, close)| {
+            let kline = candle("btcusdt", close)
+                .minute(minute as i64)
+                .open(100.0)
+                .range(110.0, 90.0)
+                .volume(5.0);
+            kline_message(&kline.build())
+        })
+        .collect()
+}
+
+async fn run() -> Recording {
+    let config: Config = toml::from_str(CONFIG).unwrap();
+    let pipeline = IndicatorPipeline::new(
+        IndicatorRegistry::with_builtins(),
+        config.indicators.clone(),
+    )
+    .unwrap();
+    let alert_engine = AlertEngine::new(config.alerts.clone(), &config.intervals).unwrap();
+    let strategies =
+        StrategySet::new(StrategyRegistry::with_builtins(), config.strategies.clone()).unwrap();
+    replay::replay(messages(), config, pipeline, alert_engine, strategies)
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn replays_are_identical() {
+    let (first, second) = (run().await, run().await);
+    assert_eq!(first.json_lines(), second.json_lines());
+    assert_eq!(first.json_lines().lines().count(), 7 + 3);
+}
+
+#[tokio::test]
+async fn alerts_are_stamped_with_the_event_time() {
+    let recording = run().await;
+    let alerts: Vec<_> = recording
+        .events
+        .iter()
+        .filter_map(|event| match event {
+            Event::Alert(alert) => Some(alert),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        alerts.iter().map(|alert| alert.price).collect::<Vec<_>>(),
+        [105.0, 106.0, 107.0]
+    );
+    for alert in alerts {
+        let kline = recording
+            .events
+            .iter()
+            .find_map(|event| match event {
+                Event::Kline(kline) if kline.close == alert.price => Some(kline),
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(
+            alert.triggered_at,
+            kline.interval_start + Duration::minutes(1)
+        );
+    }
+    let notified = recording
+        .notifications
+        .iter()
+        .filter(|notification| matches!(notification, Notification::Alert(_)))
+        .count();
+    assert_eq!(notified, 3);
+    assert_eq!(recording.state.latest.len(), 1);
+}
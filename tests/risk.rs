@@ -0,0 +1,92 @@
+//! Risk limits as paper and live trading check orders against them.
+
+use chrono::{Duration, TimeZone, Utc};
+use crypto_kline_tracker::clock::VirtualClock;
+use crypto_kline_tracker::config::{RiskConfig, Side};
+use crypto_kline_tracker::risk::{KillSwitch, RiskLimits};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+#[test]
+fn only_recorded_orders_count_towards_the_order_rate() {
+    let config = RiskConfig {
+        max_orders_per_minute: Some(2),
+        ..RiskConfig::default()
+    };
+    let mut risk = RiskLimits::new(&config, KillSwitch::default());
+
+    // Checked orders that were never placed leave the rate alone.
+    for _ in 0..5 {
+        risk.check("btcusdt", Side::Buy, 100.0, 0.0).unwrap();
+    }
+    risk.check("btcusdt", Side::Buy, 100.0, 0.0).unwrap();
+    risk.record_order();
+    risk.approve("btcusdt", Side::Sell, 100.0, 0.0).unwrap();
+    assert!(risk.check("btcusdt", Side::Buy, 100.0, 0.0).is_err());
+}
+
+#[test]
+fn the_kill_switch_refuses_every_order_until_released() {
+    let kill_switch = KillSwitch::new(true);
+    let mut risk = RiskLimits::new(&RiskConfig::default(), kill_switch.clone());
+    assert!(risk.approve("btcusdt", Side::Buy, 100.0, 0.0).is_err());
+    let error = risk.approve("btcusdt", Side::Sell, 100.0, 0.0).unwrap_err();
+    assert_eq!(error.to_string(), "The kill switch is engaged");
+
+    kill_switch.release();
+    risk.approve("btcusdt", Side::Buy, 100.0, 0.0).unwrap();
+    kill_switch.engage();
+    assert!(risk.approve("btcusdt", Side::Sell, 100.0, 0.0).is_err());
+}
+
+#[test]
+fn the_daily_loss_stops_buys_until_the_next_utc_day() {
+    let config = RiskConfig {
+        max_daily_loss: Some(50.0),
+        ..RiskConfig::default()
+    };
+    let clock = Arc::new(VirtualClock::new(
+        Utc.with_ymd_and_hms(2024, 3, 1, 23, 0, 0).unwrap(),
+    ));
+    let mut risk = RiskLimits::new(&config, KillSwitch::default()).with_clock(clock.clone());
+
+    risk.record_pnl(-30.0);
+    risk.approve("btcusdt", Side::Buy, 100.0, 0.0).unwrap();
+    risk.record_pnl(20.0);
+    risk.record_pnl(-40.0);
+    let error = risk.approve("btcusdt", Side::Buy, 100.0, 0.0).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "Today's realized loss of 50.00 has reached the limit of 50.00"
+    );
+    // Selling can only cut the position.
+    risk.approve("btcusdt", Side::Sell, 100.0, 0.0).unwrap();
+
+    clock.advance(Duration::minutes(59));
+    assert!(risk.approve("btcusdt", Side::Buy, 100.0, 0.0).is_err());
+    clock.advance(Duration::minutes(1));
+    risk.approve("btcusdt", Side::Buy, 100.0, 0.0).unwrap();
+    assert_eq!(risk.to_json()["daily_pnl"], 0.0);
+}
+
+#[test]
+fn the_position_limit_caps_what_buys_add_to_a_symbol() {
+    let config = RiskConfig {
+        max_position_value: Some(500.0),
+        position_limits: HashMap::from([("ethusdt".to_string(), 200.0)]),
+        ..RiskConfig::default()
+    };
+    let mut risk = RiskLimits::new(&config, KillSwitch::default());
+
+    risk.approve("btcusdt", Side::Buy, 200.0, 300.0).unwrap();
+    let error = risk
+        .approve("btcusdt", Side::Buy, 200.1, 300.0)
+        .unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "Buying 200.10 more btcusdt would make the position worth 500.10, over the limit of 500.00"
+    );
+    // The symbol's own limit replaces the default.
+    assert!(risk.approve("ethusdt", Side::Buy, 150.0, 100.0).is_err());
+    risk.approve("ethusdt", Side::Sell, 1000.0, 1000.0).unwrap();
+}
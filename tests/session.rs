@@ -0,0 +1,49 @@
+//! Session statistics fed updates on several intervals.
+
+mod common;
+
+use chrono::Duration;
+use common::{candle, minute};
+use crypto_kline_tracker::session::SessionStats;
+
+#[test]
+fn sessions_follow_the_shortest_interval_from_their_first_close() {
+    let started = minute(0) + Duration::seconds(30);
+    let hour_candle = |high, low, close| {
+        candle("btcusdt", close)
+            .interval("1h")
+            .range(high, low)
+            .closed(false)
+            .build()
+    };
+    let minute_candle = |n, high, low, close| {
+        candle("btcusdt", close)
+            .minute(n)
+            .range(high, low)
+            .closed(false)
+            .build()
+    };
+    // The hour began before the session, so its range is not the session's.
+    let mut session = SessionStats::new(&hour_candle(110.0, 90.0, 100.0), started);
+    assert_eq!(
+        (session.first_price, session.high, session.low),
+        (100.0, 100.0, 100.0)
+    );
+
+    // A shorter interval takes over, again from its close.
+    session.update(&minute_candle(0, 105.0, 95.0, 101.0));
+    assert_eq!(&*session.interval, "1m");
+    assert_eq!(
+        (session.first_price, session.high, session.low),
+        (101.0, 101.0, 101.0)
+    );
+
+    session.update(&minute_candle(1, 104.0, 100.0, 102.0));
+    // The hour's low is not compared with the minute's high.
+    session.update(&hour_candle(110.0, 80.0, 102.0));
+    assert_eq!(
+        (session.high, session.low, session.last_price),
+        (104.0, 100.0, 102.0)
+    );
+    assert!((session.max_drawdown_percent - 0.990099).abs() < 1e-6);
+}
@@ -0,0 +1,41 @@
+//! How symbols are partitioned across the processor workers.
+
+use crypto_kline_tracker::config::RatioConfig;
+use crypto_kline_tracker::shard::Shards;
+
+fn ratio(base: &str, quote: &str) -> RatioConfig {
+    RatioConfig {
+        base: base.to_string(),
+        quote: quote.to_string(),
+    }
+}
+
+#[test]
+fn ratio_legs_and_venues_of_a_symbol_share_a_worker() {
+    let shards = Shards::new(
+        8,
+        &[ratio("btcusdt", "ethusdt"), ratio("ethusdt", "solusdt")],
+    );
+    // Chained ratios put all three legs together.
+    let btc = shards.of("btcusdt");
+    assert_eq!(shards.of("ethusdt"), btc);
+    assert_eq!(shards.of("solusdt"), btc);
+    assert_eq!(shards.of("bybit:solusdt"), btc);
+    assert_eq!(shards.of("binance-bybit:ethusdt"), btc);
+
+    let symbols: Vec<String> = (0..100).map(|n| format!("coin{}usdt", n)).collect();
+    let workers: Vec<usize> = symbols.iter().map(|symbol| shards.of(symbol)).collect();
+    assert!(workers.iter().all(|worker| *worker < 8));
+    // Spread over more than a couple of workers.
+    let mut used = workers;
+    used.sort();
+    used.dedup();
+    assert!(used.len() > 4, "{:?}", used);
+}
+
+#[test]
+fn zero_workers_means_one() {
+    let shards = Shards::new(0, &[]);
+    assert_eq!(shards.count(), 1);
+    assert_eq!(shards.of("btcusdt"), 0);
+}
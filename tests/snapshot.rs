@@ -0,0 +1,96 @@
+//! Market state saved to disk and restored into a fresh one.
+
+mod common;
+
+use common::candle;
+use crypto_kline_tracker::config::Config;
+use crypto_kline_tracker::session::SessionStats;
+use crypto_kline_tracker::snapshot;
+use crypto_kline_tracker::state::MarketState;
+use crypto_kline_tracker::KlineData;
+
+#[test]
+fn snapshots_restore_history_latest_candles_and_sessions() {
+    let path = std::env::temp_dir().join(format!("kline-snapshot-{}.json", std::process::id()));
+    let config = Config::default();
+    let state = MarketState::default();
+    let klines: Vec<KlineData> = [(0, 100.0, true), (1, 104.0, true), (2, 98.5, false)]
+        .into_iter()
+        .map(|(n, close, closed)| {
+            candle("btcusdt", close)
+                .minute(n)
+                .open(101.0)
+                .volume(2.5)
+                .closed(closed)
+                .build()
+        })
+        .collect();
+    let mut session = SessionStats::new(&klines[0], klines[0].interval_start);
+    for kline in &klines {
+        state.history_mut().update(kline.clone(), 100, None);
+        state.latest.insert(kline.key(), kline.clone());
+        session.update(kline);
+    }
+    state.sessions.insert("btcusdt".into(), session.clone());
+
+    assert_eq!(snapshot::save(&state, &path).unwrap(), 1);
+    let restored = MarketState::default();
+    let (series, _) = snapshot::restore(&restored, &path, &config)
+        .unwrap()
+        .unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(series, 1);
+
+    let history = restored.history();
+    let candles: Vec<_> = history
+        .get(&("btcusdt".into(), "1m".into()))
+        .unwrap()
+        .iter()
+        .map(|k| {
+            (
+                k.interval_start,
+                k.open,
+                k.high,
+                k.low,
+                k.close,
+                k.volume,
+                k.closed,
+            )
+        })
+        .collect();
+    let expected: Vec<_> = klines
+        .iter()
+        .map(|k| {
+            (
+                k.interval_start,
+                k.open,
+                k.high,
+                k.low,
+                k.close,
+                k.volume,
+                k.closed,
+            )
+        })
+        .collect();
+    assert_eq!(candles, expected);
+    assert_eq!(restored.latest_klines()[0].close, 98.5);
+
+    let sessions = restored.sorted_sessions();
+    let (symbol, stats) = &sessions[0];
+    assert_eq!(&**symbol, "btcusdt");
+    assert_eq!(stats.first_price, session.first_price);
+    assert_eq!(stats.last_price, 98.5);
+    assert_eq!(stats.high, session.high);
+    assert_eq!(stats.max_drawdown_percent, session.max_drawdown_percent);
+    assert_eq!(stats.started, session.started);
+}
+
+#[test]
+fn a_missing_snapshot_restores_nothing() {
+    let path = std::env::temp_dir().join("kline-snapshot-that-does-not-exist.json");
+    let state = MarketState::default();
+    assert!(snapshot::restore(&state, &path, &Config::default())
+        .unwrap()
+        .is_none());
+    assert!(state.latest_klines().is_empty());
+}
@@ -0,0 +1,44 @@
+//! Market-wide price change statistics over hand-made candles.
+
+mod common;
+
+use common::candle;
+use crypto_kline_tracker::stats::{market_stats, market_stats_by_interval};
+
+#[test]
+fn weighted_change_follows_the_quote_volume() {
+    let klines = [
+        // +2% on 1020 of quote volume.
+        candle("btcusdt", 102.0).open(100.0).volume(10.0).build(),
+        // -10% on 90.
+        candle("dogeusdt", 0.9).open(1.0).volume(100.0).build(),
+    ];
+    let stats = market_stats(&klines);
+    assert_eq!(stats.count, 2);
+    assert!((stats.mean_change + 4.0).abs() < 1e-9);
+    let weighted = stats.weighted_change.unwrap();
+    assert!((weighted - 1140.0 / 1110.0).abs() < 1e-9, "{}", weighted);
+
+    let ratio = candle("btcusdt/ethusdt", 20.0)
+        .open(19.0)
+        .volume(0.0)
+        .build();
+    assert_eq!(market_stats([&ratio]).weighted_change, None);
+}
+
+#[test]
+fn stats_by_interval_never_mix_horizons() {
+    let klines = [
+        candle("btcusdt", 101.0).open(100.0).interval("1h").build(),
+        candle("btcusdt", 99.0).open(100.0).build(),
+        candle("ethusdt", 97.0).open(100.0).build(),
+    ];
+    let stats = market_stats_by_interval(&klines);
+    let summary: Vec<(&str, usize)> = stats
+        .iter()
+        .map(|(interval, stats)| (interval.as_str(), stats.count))
+        .collect();
+    assert_eq!(summary, [("1m", 2), ("1h", 1)]);
+    assert!((stats[0].1.mean_change + 2.0).abs() < 1e-9);
+    assert!((stats[1].1.mean_change - 1.0).abs() < 1e-9);
+}
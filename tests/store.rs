@@ -0,0 +1,123 @@
+//! Records round-tripped through an in-memory store.
+
+mod common;
+
+use common::{candle, minute};
+use crypto_kline_tracker::alerts::Alert;
+use crypto_kline_tracker::store::{AlertQuery, CandleRange, Store};
+use std::path::Path;
+
+fn alert(rule: &str, symbol: &str, n: i64) -> Alert {
+    Alert {
+        rule: rule.to_string(),
+        symbol: symbol.to_string(),
+        interval: "1m".to_string(),
+        price: 100.0 + n as f64,
+        change_percent: -0.5,
+        message: format!("{} fired", rule),
+        triggered_at: minute(n),
+    }
+}
+
+fn query() -> AlertQuery {
+    AlertQuery {
+        symbol: None,
+        rule: None,
+        since: None,
+        until: None,
+        offset: 0,
+        limit: 100,
+    }
+}
+
+#[test]
+fn alerts_come_back_with_their_deliveries_newest_first() {
+    let store = Store::open(Path::new(":memory:")).unwrap();
+    let first = store.record_alert(&alert("dip", "btcusdt", 0)).unwrap();
+    store.record_delivery(first, "webhook", None).unwrap();
+    store
+        .record_delivery(first, "telegram", Some("HTTP status 502"))
+        .unwrap();
+    store.record_alert(&alert("dip", "ethusdt", 1)).unwrap();
+    store.record_alert(&alert("rip", "btcusdt", 2)).unwrap();
+
+    let records = store.alerts(&query()).unwrap();
+    let order: Vec<_> = records.iter().map(|r| r.alert.triggered_at).collect();
+    assert_eq!(order, [minute(2), minute(1), minute(0)]);
+
+    let record = &records[2];
+    assert_eq!(record.id, first);
+    assert_eq!(record.alert.rule, "dip");
+    assert_eq!(record.alert.price, 100.0);
+    assert_eq!(record.alert.change_percent, -0.5);
+    assert_eq!(record.alert.message, "dip fired");
+    let deliveries: Vec<_> = record
+        .deliveries
+        .iter()
+        .map(|d| (d.notifier.as_str(), d.error.as_deref()))
+        .collect();
+    assert_eq!(
+        deliveries,
+        [("webhook", None), ("telegram", Some("HTTP status 502"))]
+    );
+    assert_eq!(record.to_json()["deliveries"][1]["delivered"], false);
+
+    let btc_dips = store
+        .alerts(&AlertQuery {
+            symbol: Some("btcusdt".to_string()),
+            rule: Some("dip".to_string()),
+            ..query()
+        })
+        .unwrap();
+    assert_eq!(btc_dips.len(), 1);
+    assert_eq!(btc_dips[0].id, first);
+}
+
+#[test]
+fn alert_and_candle_queries_page_through_a_time_range() {
+    let store = Store::open(Path::new(":memory:")).unwrap();
+    for n in 0..5 {
+        store.record_alert(&alert("dip", "btcusdt", n)).unwrap();
+    }
+    let page = store
+        .alerts(&AlertQuery {
+            since: Some(minute(1)),
+            until: Some(minute(4)),
+            offset: 1,
+            limit: 1,
+            ..query()
+        })
+        .unwrap();
+    let times: Vec<_> = page.iter().map(|r| r.alert.triggered_at).collect();
+    assert_eq!(times, [minute(2)]);
+
+    let klines: Vec<_> = (0..5)
+        .map(|n| candle("btcusdt", 100.0 + n as f64).minute(n).build())
+        .collect();
+    store.record_candles(&klines).unwrap();
+    // Recording a candle again replaces it.
+    store
+        .record_candles(&[candle("btcusdt", 110.0).minute(4).build()])
+        .unwrap();
+    assert_eq!(
+        store.series().unwrap(),
+        [("btcusdt".to_string(), "1m".to_string())]
+    );
+
+    let closes = |range: CandleRange| -> Vec<f64> {
+        store
+            .candles("btcusdt", "1m", &range)
+            .unwrap()
+            .iter()
+            .map(|kline| kline.close)
+            .collect()
+    };
+    assert_eq!(closes(CandleRange::latest(2)), [103.0, 110.0]);
+    let range = CandleRange {
+        from: Some(minute(1)),
+        to: Some(minute(4)),
+        offset: 1,
+        limit: 10,
+    };
+    assert_eq!(closes(range), [101.0, 102.0]);
+}
@@ -0,0 +1,65 @@
+//! Built-in strategies signalling on hand-made candle series.
+
+#![cfg(feature = "indicators")]
+
+mod common;
+
+use common::{candle, minute};
+use crypto_kline_tracker::config::{Side, StrategyConfig};
+use crypto_kline_tracker::strategy::{StrategyRegistry, StrategySet};
+
+#[test]
+fn sma_cross_buys_and_sells_on_each_cross_only() {
+    let spec: StrategyConfig = toml::from_str(
+        r#"
+        kind = "sma_cross"
+        fast = 1
+        slow = 2
+        symbols = ["btcusdt"]
+        "#,
+    )
+    .unwrap();
+    let mut strategies = StrategySet::new(StrategyRegistry::default(), vec![spec]).unwrap();
+    let mut signals = Vec::new();
+    for (n, close) in [100.0, 100.0, 110.0, 120.0, 100.0, 90.0]
+        .into_iter()
+        .enumerate()
+    {
+        let n = n as i64;
+        signals.extend(strategies.update(
+            &candle("btcusdt", close).minute(n).build(),
+            None,
+            minute(n),
+        ));
+        // Scoped to BTCUSDT alone.
+        let eth = candle("ethusdt", close).minute(n).build();
+        assert!(strategies.update(&eth, None, minute(n)).is_empty());
+    }
+    let sides: Vec<(Side, f64)> = signals.iter().map(|s| (s.side, s.price)).collect();
+    assert_eq!(sides, [(Side::Buy, 110.0), (Side::Sell, 100.0)]);
+    assert_eq!(signals[0].strategy, "sma_cross");
+    assert_eq!(
+        signals[0].reason,
+        "fast SMA 110.0000 crossed above slow SMA 105.0000"
+    );
+    assert_eq!(signals[0].raised_at, minute(2));
+}
+
+#[test]
+fn sma_cross_needs_fast_shorter_than_slow() {
+    let spec: StrategyConfig = toml::from_str(
+        r#"
+        kind = "sma_cross"
+        fast = 30
+        slow = 10
+        "#,
+    )
+    .unwrap();
+    let error = StrategySet::new(StrategyRegistry::default(), vec![spec])
+        .err()
+        .unwrap();
+    assert_eq!(
+        error.to_string(),
+        "Strategy 'sma_cross' needs fast shorter than slow"
+    );
+}
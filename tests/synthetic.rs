@@ -0,0 +1,83 @@
+//! The synthetic market's candles: reproducible from their seed, and
+//! consistent across intervals.
+
+use crypto_kline_tracker::config::SyntheticConfig;
+use crypto_kline_tracker::source::{Source, SyntheticSource};
+use crypto_kline_tracker::KlineData;
+use futures_util::TryStreamExt;
+use std::collections::HashMap;
+
+async fn generate(seed: u64) -> Vec<KlineData> {
+    let config = SyntheticConfig {
+        prices: HashMap::from([("btcusdt".to_string(), 65000.0)]),
+        speed: 0.0,
+        candles: Some(15),
+        seed: Some(seed),
+        ..SyntheticConfig::default()
+    };
+    let symbols = ["btcusdt".to_string(), "ethusdt".to_string()];
+    let intervals = ["1m".to_string(), "5m".to_string()];
+    let source = SyntheticSource::new(&symbols, &intervals, config).unwrap();
+    Box::new(source).klines().try_collect().await.unwrap()
+}
+
+fn closed<'a>(klines: &'a [KlineData], symbol: &str, interval: &str) -> Vec<&'a KlineData> {
+    klines
+        .iter()
+        .filter(|kline| kline.closed && &*kline.symbol == symbol && &*kline.interval == interval)
+        .collect()
+}
+
+#[tokio::test]
+async fn seeded_runs_repeat() {
+    let (first, second) = (generate(7).await, generate(7).await);
+    let closes = |klines: &[KlineData]| klines.iter().map(|kline| kline.close).collect::<Vec<_>>();
+    assert_eq!(closes(&first), closes(&second));
+    assert_ne!(closes(&first), closes(&generate(8).await));
+    // Four updates per candle, the last closing it.
+    assert_eq!(first.len(), 2 * 2 * 15 * 4);
+}
+
+#[tokio::test]
+async fn longer_intervals_aggregate_the_shortest() {
+    let klines = generate(7).await;
+    for symbol in ["btcusdt", "ethusdt"] {
+        let minutes = closed(&klines, symbol, "1m");
+        let fives = closed(&klines, symbol, "5m");
+        assert_eq!(minutes.len(), 15);
+        assert!(!fives.is_empty());
+        for kline in minutes.iter().chain(&fives) {
+            assert!(kline.low > 0.0 && kline.volume > 0.0);
+            assert!(kline.low <= kline.open.min(kline.close));
+            assert!(kline.high >= kline.open.max(kline.close));
+        }
+        for five in fives {
+            let parts: Vec<_> = minutes
+                .iter()
+                .filter(|minute| {
+                    minute.interval_start >= five.interval_start
+                        && minute.interval_start
+                            < five.interval_start + chrono::Duration::minutes(5)
+                })
+                .collect();
+            // The walk starts at the current minute, which may be partway
+            // through the first five.
+            assert!(parts.len() == 5 || five.interval_start < minutes[0].interval_start);
+            assert_eq!(five.open, parts[0].open);
+            assert_eq!(five.close, parts[parts.len() - 1].close);
+            let high = parts
+                .iter()
+                .map(|kline| kline.high)
+                .fold(f64::MIN, f64::max);
+            let low = parts.iter().map(|kline| kline.low).fold(f64::MAX, f64::min);
+            assert_eq!((five.high, five.low), (high, low));
+            let volume: f64 = parts.iter().map(|kline| kline.volume).sum();
+            assert!((five.volume - volume).abs() < 1e-9 * volume);
+        }
+    }
+    let first = klines
+        .iter()
+        .find(|kline| &*kline.symbol == "btcusdt")
+        .unwrap();
+    assert!((first.open - 65000.0).abs() < 1e-9);
+}
@@ -0,0 +1,114 @@
+//! Walk-forward analysis of a strategy over hand-made candles.
+
+#![cfg(feature = "indicators")]
+
+mod common;
+
+use chrono::Duration;
+use common::{candle, minute};
+use crypto_kline_tracker::config::Config;
+use crypto_kline_tracker::indicators::{IndicatorPipeline, IndicatorRegistry};
+use crypto_kline_tracker::strategy::{StrategyRegistry, StrategySet};
+use crypto_kline_tracker::walkforward::{self, Param};
+
+#[test]
+fn each_window_trains_on_its_own_candles_and_tests_on_the_next() {
+    let config: Config = toml::from_str(
+        r#"
+        [paper]
+        taker_fee_pct = 0.0
+
+        [[strategies]]
+        kind = "sma_cross"
+        quantity = 1.0
+        "#,
+    )
+    .unwrap();
+    let candles: Vec<_> = [
+        100.0, 90.0, 80.0, 100.0, 95.0, 120.0, 110.0, 130.0, 140.0, 100.0, 110.0, 100.0,
+    ]
+    .into_iter()
+    .enumerate()
+    .map(|(n, close)| candle("btcusdt", close).minute(n as i64).build())
+    .collect();
+    // Fast 3 is not shorter than slow 3, and never tried.
+    let grid = [
+        Param::parse("fast=1,2,3").unwrap(),
+        Param::parse("slow=3").unwrap(),
+    ];
+    let pipeline = IndicatorPipeline::new(IndicatorRegistry::default(), Vec::new()).unwrap();
+    let strategies =
+        StrategySet::new(StrategyRegistry::default(), config.strategies.clone()).unwrap();
+
+    let analysis = walkforward::run(
+        &config,
+        &candles,
+        "sma_cross",
+        &grid,
+        (Duration::minutes(6), Duration::minutes(3)),
+        &pipeline,
+        &strategies,
+    )
+    .unwrap();
+    assert_eq!(analysis.combinations, 2);
+    let bounds: Vec<_> = analysis
+        .windows
+        .iter()
+        .map(|window| (window.train_from, window.test_from, window.test_to))
+        .collect();
+    assert_eq!(
+        bounds,
+        [
+            (minute(0), minute(6), minute(9)),
+            (minute(3), minute(9), minute(12)),
+        ]
+    );
+
+    // Fast 2 makes 25 on the first six candles to fast 1's 20, though fast
+    // 1 would have won had the three test candles counted too.
+    let first = &analysis.windows[0];
+    assert_eq!(first.params["fast"], 2.0);
+    assert_eq!(first.train_pnl, 25.0);
+    assert_eq!(first.test.candles, 3);
+    assert_eq!(first.test.equity[0].0, minute(6));
+    // Neither trades on the second window's training candles.
+    let second = &analysis.windows[1];
+    assert_eq!(second.train_pnl, 0.0);
+    assert_eq!(second.params["fast"], 1.0);
+}
+
+#[test]
+fn walks_need_a_configured_strategy_and_a_combination_it_accepts() {
+    let config: Config = toml::from_str(
+        r#"
+        [[strategies]]
+        kind = "sma_cross"
+        "#,
+    )
+    .unwrap();
+    let candles = vec![candle("btcusdt", 100.0).build()];
+    let pipeline = IndicatorPipeline::new(IndicatorRegistry::default(), Vec::new()).unwrap();
+    let strategies =
+        StrategySet::new(StrategyRegistry::default(), config.strategies.clone()).unwrap();
+    let walk = |strategy: &str, grid: &[Param]| {
+        walkforward::run(
+            &config,
+            &candles,
+            strategy,
+            grid,
+            (Duration::minutes(1), Duration::minutes(1)),
+            &pipeline,
+            &strategies,
+        )
+        .unwrap_err()
+        .to_string()
+    };
+
+    assert_eq!(walk("rsi", &[]), "No strategy named 'rsi' is configured");
+    let grid = [Param::parse("fast=30,40").unwrap()];
+    assert_eq!(
+        walk("sma_cross", &grid),
+        "Strategy 'sma_cross' needs fast shorter than slow"
+    );
+    assert!(Param::parse("fast=1,x").is_err());
+}